@@ -0,0 +1,63 @@
+-- Reglas de alerta por usuario y su historial de disparos (alertas.rs).
+-- `umbral` y `periodo` dependen del tipo de regla; `estado` guarda el último
+-- trimestre o aviso de dividendo visto, para disparar sólo cuando cambia.
+CREATE TABLE IF NOT EXISTS public.alert_rules
+(
+    id serial NOT NULL,
+    usuario_id integer NOT NULL,
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    serie text COLLATE pg_catalog."default" NOT NULL,
+    tipo text COLLATE pg_catalog."default" NOT NULL,
+    umbral double precision,
+    periodo integer,
+    activa boolean NOT NULL DEFAULT true,
+    estado text COLLATE pg_catalog."default",
+    ultima_sesion date,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT alert_rules_pkey PRIMARY KEY (id),
+    CONSTRAINT alert_rules_tipo_check CHECK (tipo IN (
+        'precio_arriba', 'precio_abajo', 'cambio_dia', 'cruce_media',
+        'volumen_pico', 'reporte_trimestral', 'dividendo'
+    )),
+    CONSTRAINT alert_rules_usuario_id_fkey FOREIGN KEY (usuario_id)
+        REFERENCES public.usuarios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE,
+    CONSTRAINT alert_rules_emisora_serie_fkey FOREIGN KEY (emisoras, serie)
+        REFERENCES public.emisoras (emisoras, serie) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_alert_rules_usuario
+    ON public.alert_rules USING btree
+    (usuario_id ASC NULLS LAST)
+    TABLESPACE pg_default;
+
+CREATE TABLE IF NOT EXISTS public.alert_history
+(
+    id serial NOT NULL,
+    regla_id integer NOT NULL,
+    usuario_id integer NOT NULL,
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    serie text COLLATE pg_catalog."default" NOT NULL,
+    tipo text COLLATE pg_catalog."default" NOT NULL,
+    mensaje text COLLATE pg_catalog."default" NOT NULL,
+    valor double precision,
+    disparada timestamp with time zone NOT NULL DEFAULT now(),
+    leida boolean NOT NULL DEFAULT false,
+    CONSTRAINT alert_history_pkey PRIMARY KEY (id),
+    CONSTRAINT alert_history_regla_id_fkey FOREIGN KEY (regla_id)
+        REFERENCES public.alert_rules (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_alert_history_usuario
+    ON public.alert_history USING btree
+    (usuario_id ASC NULLS LAST, disparada DESC NULLS LAST)
+    TABLESPACE pg_default;
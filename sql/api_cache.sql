@@ -0,0 +1,17 @@
+-- Caché persistente de respuestas de DataBursatil (emisoras, tasas y financieros)
+CREATE TABLE IF NOT EXISTS public.api_cache
+(
+    clave text COLLATE pg_catalog."default" NOT NULL,
+    endpoint text COLLATE pg_catalog."default" NOT NULL,
+    respuesta text COLLATE pg_catalog."default" NOT NULL,
+    guardado timestamp with time zone NOT NULL DEFAULT now(),
+    expira timestamp with time zone NOT NULL,
+    CONSTRAINT api_cache_pkey PRIMARY KEY (clave)
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_api_cache_endpoint
+    ON public.api_cache USING btree
+    (endpoint COLLATE pg_catalog."default" ASC NULLS LAST)
+    TABLESPACE pg_default;
@@ -0,0 +1,14 @@
+-- Cierres extraordinarios y sesiones reducidas de la BMV/BIVA que no siguen
+-- una regla fija (los feriados de ley se calculan en calendario.rs)
+CREATE TABLE IF NOT EXISTS public.calendario_excepciones
+(
+    fecha date NOT NULL,
+    tipo text COLLATE pg_catalog."default" NOT NULL,
+    cierre time without time zone,
+    descripcion text COLLATE pg_catalog."default",
+    CONSTRAINT calendario_excepciones_pkey PRIMARY KEY (fecha),
+    CONSTRAINT calendario_excepciones_tipo_check CHECK (tipo IN ('feriado', 'media_sesion')),
+    CONSTRAINT calendario_excepciones_cierre_check CHECK (tipo <> 'media_sesion' OR cierre IS NOT NULL)
+)
+
+TABLESPACE pg_default;
@@ -0,0 +1,26 @@
+-- Hallazgos de las validaciones que se corren al guardar los estados financieros.
+-- Se reemplazan completos en cada validación de la emisora/trimestre.
+CREATE TABLE IF NOT EXISTS public.calidad_datos
+(
+    id serial NOT NULL,
+    emisora text COLLATE pg_catalog."default" NOT NULL,
+    trimestre text COLLATE pg_catalog."default" NOT NULL,
+    estado text COLLATE pg_catalog."default" NOT NULL,
+    regla text COLLATE pg_catalog."default" NOT NULL,
+    partida text COLLATE pg_catalog."default",
+    severidad text COLLATE pg_catalog."default" NOT NULL,
+    mensaje text COLLATE pg_catalog."default" NOT NULL,
+    valor double precision,
+    esperado double precision,
+    detectado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT calidad_datos_pkey PRIMARY KEY (id),
+    CONSTRAINT calidad_datos_estado_check CHECK (estado IN ('flujos', 'resultados', 'posicion')),
+    CONSTRAINT calidad_datos_severidad_check CHECK (severidad IN ('advertencia', 'error'))
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_calidad_datos_emisora_trimestre
+    ON public.calidad_datos USING btree
+    (emisora ASC NULLS LAST, trimestre ASC NULLS LAST)
+    TABLESPACE pg_default;
@@ -1,19 +1,19 @@
+-- Movimientos de efectivo de cada portafolio (`portfolio_management`): depósitos
+-- y retiros, costo de compras, producto de ventas y dividendos. Los cargos van
+-- con monto negativo.
 CREATE TABLE IF NOT EXISTS public.cashflow
 (
     id integer NOT NULL DEFAULT nextval('cashflow_id_seq'::regclass),
-    portafolio_id integer,
-    monto double precision NOT NULL,
-    tipo text COLLATE pg_catalog."default" NOT NULL,
-    fecha timestamp without time zone NOT NULL DEFAULT now(),
-    descripcion text COLLATE pg_catalog."default",
+    portfolio_id integer NOT NULL,
+    flow_type text COLLATE pg_catalog."default" NOT NULL,
+    amount double precision NOT NULL,
+    flow_date date NOT NULL DEFAULT CURRENT_DATE,
+    description text COLLATE pg_catalog."default",
     CONSTRAINT cashflow_pkey PRIMARY KEY (id),
-    CONSTRAINT cashflow_portafolio_id_fkey FOREIGN KEY (portafolio_id)
+    CONSTRAINT cashflow_portfolio_id_fkey FOREIGN KEY (portfolio_id)
         REFERENCES public.portafolios (id) MATCH SIMPLE
         ON UPDATE NO ACTION
         ON DELETE CASCADE
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.cashflow
-    OWNER to garden_admin;
@@ -0,0 +1,22 @@
+-- Conceptos que reporta el proveedor en `/financieros` y que no llenaron ninguna
+-- columna según `mapeo_xbrl.json`, para no perder datos reportados. Se
+-- reemplazan completos cada vez que se guarda el estado de la emisora/trimestre.
+CREATE TABLE IF NOT EXISTS public.conceptos_no_mapeados
+(
+    emisora text COLLATE pg_catalog."default" NOT NULL,
+    trimestre text COLLATE pg_catalog."default" NOT NULL,
+    estado text COLLATE pg_catalog."default" NOT NULL,
+    concepto text COLLATE pg_catalog."default" NOT NULL,
+    valor double precision,
+    descripcion text COLLATE pg_catalog."default",
+    guardado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT conceptos_no_mapeados_pkey PRIMARY KEY (emisora, trimestre, estado, concepto),
+    CONSTRAINT conceptos_no_mapeados_estado_check CHECK (estado IN ('flujos', 'resultados', 'posicion'))
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_conceptos_no_mapeados_concepto
+    ON public.conceptos_no_mapeados USING btree
+    (concepto ASC NULLS LAST)
+    TABLESPACE pg_default;
@@ -0,0 +1,23 @@
+-- Supuestos del modelo de flujos descontados que cada usuario guarda por emisora.
+-- `etapas` es un arreglo JSON de {anios, crecimiento}; las tasas van como
+-- fracción. `fcf_base` y `deuda_neta` vacíos significan usar los de los estados.
+CREATE TABLE IF NOT EXISTS public.dcf_supuestos
+(
+    id serial NOT NULL,
+    usuario_id integer NOT NULL,
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    etapas text COLLATE pg_catalog."default" NOT NULL,
+    wacc double precision NOT NULL,
+    crecimiento_terminal double precision NOT NULL,
+    fcf_base double precision,
+    deuda_neta double precision,
+    updated_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT dcf_supuestos_pkey PRIMARY KEY (id),
+    CONSTRAINT dcf_supuestos_usuario_emisora_key UNIQUE (usuario_id, emisoras),
+    CONSTRAINT dcf_supuestos_usuario_id_fkey FOREIGN KEY (usuario_id)
+        REFERENCES public.usuarios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
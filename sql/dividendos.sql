@@ -12,6 +12,3 @@ CREATE TABLE IF NOT EXISTS public.dividendos
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.dividendos
-    OWNER to garden_admin;
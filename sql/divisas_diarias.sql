@@ -0,0 +1,12 @@
+-- Foto diaria de los tipos de cambio al cierre de la sesión
+CREATE TABLE IF NOT EXISTS public.divisas_diarias
+(
+    par text COLLATE pg_catalog."default" NOT NULL,
+    fecha date NOT NULL,
+    ultimo double precision NOT NULL,
+    cambio_pct double precision,
+    guardado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT divisas_diarias_pkey PRIMARY KEY (par, fecha)
+)
+
+TABLESPACE pg_default;
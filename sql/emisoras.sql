@@ -16,6 +16,3 @@ CREATE TABLE IF NOT EXISTS public.emisoras
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.emisoras
-    OWNER to garden_admin;
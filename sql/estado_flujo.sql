@@ -36,6 +36,3 @@ CREATE TABLE IF NOT EXISTS public.estado_flujos
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.estado_flujos
-    OWNER to garden_admin;
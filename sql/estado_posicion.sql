@@ -37,6 +37,3 @@ CREATE TABLE IF NOT EXISTS public.estado_posicion
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.estado_posicion
-    OWNER to garden_admin;
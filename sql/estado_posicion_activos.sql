@@ -0,0 +1,3 @@
+-- Activo total reportado, para validar activo = pasivo + capital (ver `calidad_datos`).
+ALTER TABLE public.estado_posicion
+    ADD COLUMN IF NOT EXISTS assets double precision;
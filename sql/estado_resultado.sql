@@ -33,6 +33,3 @@ CREATE TABLE IF NOT EXISTS public.estado_resultado_trimestral
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.estado_resultado_trimestral
-    OWNER to garden_admin;
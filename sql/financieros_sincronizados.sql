@@ -0,0 +1,17 @@
+-- Último `rangos_financieros` de cada emisora cuyos estados ya se descargaron;
+-- cuando el catálogo trae un rango distinto se piden los estados del nuevo trimestre
+CREATE TABLE IF NOT EXISTS public.financieros_sincronizados
+(
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    serie text COLLATE pg_catalog."default" NOT NULL,
+    rango text COLLATE pg_catalog."default" NOT NULL,
+    trimestre text COLLATE pg_catalog."default" NOT NULL,
+    actualizado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT financieros_sincronizados_pkey PRIMARY KEY (emisoras, serie),
+    CONSTRAINT financieros_sincronizados_emisora_serie_fkey FOREIGN KEY (emisoras, serie)
+        REFERENCES public.emisoras (emisoras, serie) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
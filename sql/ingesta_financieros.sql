@@ -0,0 +1,17 @@
+-- Avance de la ingesta masiva de estados financieros, un renglón por emisora y
+-- trimestre pedido. Los trimestres 'ok' no se vuelven a pedir; los 'vacio' y
+-- 'error' se reintentan al reanudar hasta agotar los intentos (ver `ingesta.rs`).
+CREATE TABLE IF NOT EXISTS public.ingesta_financieros
+(
+    emisora text COLLATE pg_catalog."default" NOT NULL,
+    trimestre text COLLATE pg_catalog."default" NOT NULL,
+    estado text COLLATE pg_catalog."default" NOT NULL,
+    intentos integer NOT NULL DEFAULT 1,
+    periodos integer NOT NULL DEFAULT 0,
+    mensaje text COLLATE pg_catalog."default",
+    actualizado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT ingesta_financieros_pkey PRIMARY KEY (emisora, trimestre),
+    CONSTRAINT ingesta_financieros_estado_check CHECK (estado IN ('ok', 'vacio', 'error'))
+)
+
+TABLESPACE pg_default;
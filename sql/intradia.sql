@@ -16,11 +16,8 @@ CREATE TABLE IF NOT EXISTS public.intradia_data
 
 TABLESPACE pg_default;
 
-ALTER TABLE public.intradia_data
-    OWNER to garden_admin;
-
 -- Index: public.idx_intradia_emisora_fecha
 CREATE INDEX IF NOT EXISTS idx_intradia_emisora_fecha
     ON public.intradia_data USING btree
     (emisora COLLATE pg_catalog."default" ASC NULLS LAST, fecha_hora ASC NULLS LAST)
-    TABLESPACE pg_default;
\ No newline at end of file
+    TABLESPACE pg_default;
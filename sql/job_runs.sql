@@ -0,0 +1,22 @@
+-- Bitácora de ejecuciones del programador de tareas (scheduler.rs). La última
+-- fila de cada job es su estado vigente; `intentos` cuenta los reintentos
+-- hechos dentro de la misma ejecución.
+CREATE TABLE IF NOT EXISTS public.job_runs
+(
+    id serial NOT NULL,
+    job text COLLATE pg_catalog."default" NOT NULL,
+    inicio timestamp with time zone NOT NULL DEFAULT now(),
+    fin timestamp with time zone,
+    estado text COLLATE pg_catalog."default" NOT NULL DEFAULT 'en_curso',
+    intentos integer NOT NULL DEFAULT 1,
+    mensaje text COLLATE pg_catalog."default",
+    CONSTRAINT job_runs_pkey PRIMARY KEY (id),
+    CONSTRAINT job_runs_estado_check CHECK (estado IN ('en_curso', 'exito', 'error'))
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_job_runs_job_inicio
+    ON public.job_runs USING btree
+    (job COLLATE pg_catalog."default" ASC NULLS LAST, inicio DESC NULLS LAST)
+    TABLESPACE pg_default;
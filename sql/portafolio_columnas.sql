@@ -0,0 +1,48 @@
+-- Las primeras versiones de cashflow.sql y portfolio_transactions.sql no
+-- correspondían a las columnas que usa `portfolio_management`. Lleva al esquema
+-- actual las bases que ya corrieron esos scripts; en las demás no hace nada.
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_schema = 'public' AND table_name = 'cashflow'
+                 AND column_name = 'portafolio_id') THEN
+        ALTER TABLE public.cashflow RENAME COLUMN portafolio_id TO portfolio_id;
+        ALTER TABLE public.cashflow RENAME COLUMN monto TO amount;
+        ALTER TABLE public.cashflow RENAME COLUMN tipo TO flow_type;
+        ALTER TABLE public.cashflow RENAME COLUMN fecha TO flow_date;
+        ALTER TABLE public.cashflow RENAME COLUMN descripcion TO description;
+        ALTER TABLE public.cashflow
+            ALTER COLUMN flow_date TYPE date,
+            ALTER COLUMN flow_date SET DEFAULT CURRENT_DATE;
+        ALTER TABLE public.cashflow
+            RENAME CONSTRAINT cashflow_portafolio_id_fkey TO cashflow_portfolio_id_fkey;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_schema = 'public' AND table_name = 'portfolio_transactions'
+                 AND column_name = 'transaction_id') THEN
+        ALTER TABLE public.portfolio_transactions RENAME COLUMN transaction_id TO id;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM information_schema.columns
+               WHERE table_schema = 'public' AND table_name = 'portfolio_transactions'
+                 AND column_name = 'quantity' AND data_type = 'numeric') THEN
+        -- La app lee price como obligatorio; se deduce del total si falta.
+        UPDATE public.portfolio_transactions
+            SET price = COALESCE(total_amount / NULLIF(quantity, 0), 0)
+            WHERE price IS NULL;
+        ALTER TABLE public.portfolio_transactions
+            ALTER COLUMN user_id DROP NOT NULL,
+            ALTER COLUMN quantity TYPE double precision,
+            ALTER COLUMN price TYPE double precision,
+            ALTER COLUMN price SET NOT NULL,
+            ALTER COLUMN transaction_date TYPE date,
+            ALTER COLUMN total_amount TYPE double precision,
+            ALTER COLUMN total_amount DROP NOT NULL;
+        ALTER TABLE public.portfolio_transactions
+            DROP CONSTRAINT IF EXISTS portfolio_transactions_transaction_type_check;
+        ALTER TABLE public.portfolio_transactions
+            ADD CONSTRAINT portfolio_transactions_transaction_type_check
+            CHECK (UPPER(transaction_type) IN ('BUY', 'SELL', 'DIVIDEND', 'DEPOSIT', 'WITHDRAWAL'));
+    END IF;
+END $$;
@@ -0,0 +1,30 @@
+-- Miembros de cada portafolio y su rol (viewer, editor u owner)
+CREATE TABLE IF NOT EXISTS public.portfolio_members
+(
+    portafolio_id integer NOT NULL,
+    usuario_id integer NOT NULL,
+    rol text COLLATE pg_catalog."default" NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT portfolio_members_pkey PRIMARY KEY (portafolio_id, usuario_id),
+    CONSTRAINT portfolio_members_rol_check CHECK (rol IN ('viewer', 'editor', 'owner')),
+    CONSTRAINT portfolio_members_portafolio_id_fkey FOREIGN KEY (portafolio_id)
+        REFERENCES public.portafolios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE,
+    CONSTRAINT portfolio_members_usuario_id_fkey FOREIGN KEY (usuario_id)
+        REFERENCES public.usuarios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_portfolio_members_usuario
+    ON public.portfolio_members USING btree
+    (usuario_id ASC NULLS LAST)
+    TABLESPACE pg_default;
+
+-- El creador de cada portafolio existente pasa a ser su owner
+INSERT INTO public.portfolio_members (portafolio_id, usuario_id, rol)
+SELECT id, usuario_id, 'owner' FROM public.portafolios WHERE usuario_id IS NOT NULL
+ON CONFLICT (portafolio_id, usuario_id) DO NOTHING;
@@ -18,6 +18,3 @@ CREATE TABLE IF NOT EXISTS public.portafolio_ticker
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.portafolio_ticker
-    OWNER to garden_admin;
\ No newline at end of file
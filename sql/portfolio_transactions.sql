@@ -1,18 +1,15 @@
--- Elimina la tabla vieja si existe para evitar conflictos
-DROP TABLE IF EXISTS portfolio_transactions;
-DROP TABLE IF EXISTS portfolio_cash;
-
 -- Tabla de transacciones del portafolio
 CREATE TABLE IF NOT EXISTS portfolio_transactions (
-    transaction_id SERIAL PRIMARY KEY,
+    id SERIAL PRIMARY KEY,
     portfolio_id INTEGER NOT NULL,                        -- FK al portafolio
-    user_id INTEGER NOT NULL,                             -- FK redundante para consultas rápidas
+    user_id INTEGER,                                      -- quien la registró, si se conoce
     ticker VARCHAR(20) NOT NULL,
-    transaction_type VARCHAR(12) NOT NULL CHECK (transaction_type IN ('BUY', 'SELL', 'DIVIDEND', 'DEPOSIT', 'WITHDRAWAL')),
-    quantity DECIMAL(18, 8) NOT NULL,
-    price DECIMAL(18, 4),
-    transaction_date TIMESTAMPTZ NOT NULL,
-    total_amount DECIMAL(18, 4) NOT NULL,
+    -- `portfolio_management` usa buy/sell y `portfolio` BUY/SELL
+    transaction_type VARCHAR(12) NOT NULL CHECK (UPPER(transaction_type) IN ('BUY', 'SELL', 'DIVIDEND', 'DEPOSIT', 'WITHDRAWAL')),
+    quantity DOUBLE PRECISION NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    transaction_date DATE NOT NULL,
+    total_amount DOUBLE PRECISION,
     currency VARCHAR(10) NOT NULL DEFAULT 'MXN',
     notes TEXT,
     created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
@@ -16,6 +16,3 @@ CREATE TABLE IF NOT EXISTS public.portafolios
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.portafolios
-    OWNER to garden_admin;
@@ -0,0 +1,26 @@
+-- Barras diarias (OHLCV) por emisora/serie, llenadas desde el endpoint de históricos
+CREATE TABLE IF NOT EXISTS public.precios_diarios
+(
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    serie text COLLATE pg_catalog."default" NOT NULL,
+    fecha date NOT NULL,
+    apertura double precision,
+    maximo double precision,
+    minimo double precision,
+    cierre double precision NOT NULL,
+    volumen bigint,
+    operaciones integer,
+    actualizado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT precios_diarios_pkey PRIMARY KEY (emisoras, serie, fecha),
+    CONSTRAINT precios_diarios_emisora_serie_fkey FOREIGN KEY (emisoras, serie)
+        REFERENCES public.emisoras (emisoras, serie) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
+
+CREATE INDEX IF NOT EXISTS idx_precios_diarios_fecha
+    ON public.precios_diarios USING btree
+    (fecha ASC NULLS LAST)
+    TABLESPACE pg_default;
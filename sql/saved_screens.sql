@@ -0,0 +1,21 @@
+-- Filtros del screener guardados por usuario: la expresión tal como se escribió
+-- y la columna por la que se ordena el resultado.
+CREATE TABLE IF NOT EXISTS public.saved_screens
+(
+    id serial NOT NULL,
+    usuario_id integer NOT NULL,
+    nombre text COLLATE pg_catalog."default" NOT NULL,
+    expresion text COLLATE pg_catalog."default" NOT NULL,
+    orden text COLLATE pg_catalog."default",
+    descendente boolean NOT NULL DEFAULT true,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    updated_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT saved_screens_pkey PRIMARY KEY (id),
+    CONSTRAINT saved_screens_usuario_nombre_key UNIQUE (usuario_id, nombre),
+    CONSTRAINT saved_screens_usuario_id_fkey FOREIGN KEY (usuario_id)
+        REFERENCES public.usuarios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
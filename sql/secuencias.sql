@@ -0,0 +1,9 @@
+-- Secuencias de las columnas id que usan nextval(); deben existir antes que las tablas
+CREATE SEQUENCE IF NOT EXISTS public.usuarios_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.portafolios_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.portafolio_ticker_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.transacciones_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.cashflow_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.dividendos_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.intradia_data_id_seq;
+CREATE SEQUENCE IF NOT EXISTS public.estado_resultado_acumulado_id_seq;
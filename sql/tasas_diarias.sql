@@ -0,0 +1,11 @@
+-- Foto diaria de las tasas de referencia (CETES, TIIE, objetivo de Banxico)
+CREATE TABLE IF NOT EXISTS public.tasas_diarias
+(
+    tasa text COLLATE pg_catalog."default" NOT NULL,
+    fecha date NOT NULL,
+    valor double precision NOT NULL,
+    guardado timestamp with time zone NOT NULL DEFAULT now(),
+    CONSTRAINT tasas_diarias_pkey PRIMARY KEY (tasa, fecha)
+)
+
+TABLESPACE pg_default;
@@ -14,6 +14,3 @@ CREATE TABLE IF NOT EXISTS public.transacciones
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.transacciones
-    OWNER to garden_admin;
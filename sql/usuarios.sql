@@ -8,6 +8,3 @@ CREATE TABLE IF NOT EXISTS public.usuarios
 )
 
 TABLESPACE pg_default;
-
-ALTER TABLE public.usuarios
-    OWNER to garden_admin;
@@ -0,0 +1,36 @@
+-- Listas de seguimiento por usuario: emisoras que se siguen sin tenerlas en un
+-- portafolio. Sus emisoras también entran a la descarga diaria de precios.
+CREATE TABLE IF NOT EXISTS public.watchlists
+(
+    id serial NOT NULL,
+    usuario_id integer NOT NULL,
+    nombre text COLLATE pg_catalog."default" NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT watchlists_pkey PRIMARY KEY (id),
+    CONSTRAINT watchlists_usuario_nombre_key UNIQUE (usuario_id, nombre),
+    CONSTRAINT watchlists_usuario_id_fkey FOREIGN KEY (usuario_id)
+        REFERENCES public.usuarios (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
+
+CREATE TABLE IF NOT EXISTS public.watchlist_items
+(
+    watchlist_id integer NOT NULL,
+    emisoras text COLLATE pg_catalog."default" NOT NULL,
+    serie text COLLATE pg_catalog."default" NOT NULL,
+    created_at timestamp without time zone NOT NULL DEFAULT now(),
+    CONSTRAINT watchlist_items_pkey PRIMARY KEY (watchlist_id, emisoras, serie),
+    CONSTRAINT watchlist_items_watchlist_id_fkey FOREIGN KEY (watchlist_id)
+        REFERENCES public.watchlists (id) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE,
+    CONSTRAINT watchlist_items_emisora_serie_fkey FOREIGN KEY (emisoras, serie)
+        REFERENCES public.emisoras (emisoras, serie) MATCH SIMPLE
+        ON UPDATE NO ACTION
+        ON DELETE CASCADE
+)
+
+TABLESPACE pg_default;
@@ -1,19 +1,47 @@
 use tauri::command;
 use crate::get_data;
 use crate::get_data::EmisoraBusqueda;
-use postgres::{Client, Error};
-use chrono::{Datelike, NaiveDate, Local, Duration};
-use chrono::NaiveDateTime;
+use crate::db;
+use crate::calendario;
+use sqlx::{PgPool, Row};
+use chrono::Utc;
 use std::collections::HashMap;
 
 
+/// Columnas de `estado_flujos` que regresa `get_finantial_flow`.
+pub const COLUMNAS_FLUJO: [&str; 23] = [
+    "flujo_operacion", "utilidad_neta", "depreciacion", "cambio_inventarios",
+    "cambio_cxc", "cambio_cxp", "impuestos_pagados", "intereses_pagados", "flujo_inversion",
+    "capex", "venta_activos", "compra_intangibles", "flujo_financiamiento", "prestamos_obtenidos",
+    "pago_deuda", "dividendos_pagados", "recompras", "cambio_efectivo", "efectivo_final", "efecto_tc", "deterioros",
+    "partidas_no_monetarias", "costos_financieros"
+];
+
+/// Columnas de `estado_posicion` que regresa `get_finantial_position`.
+pub const COLUMNAS_POSICION: [&str; 24] = [
+    "currentassets", "currentliabilities", "cashandcashequivalents", "inventories",
+    "tradeandothercurrentreceivables", "tradeandothercurrentpayables", "equity", "liabilities",
+    "noncurrentliabilities", "equityattributabletoownersofparent", "noncontrollinginterests",
+    "propertyplantandequipment", "intangibleassetsotherthangoodwill", "goodwill",
+    "rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty", "deferredtaxassets",
+    "deferredtaxliabilities", "noncurrentassetsordisposalgroupsclassifiedasheldforsale",
+    "retainedearnings", "issuedcapital", "otherreserves", "noncurrentleaseliabilities",
+    "othernoncurrentfinancialliabilities", "noncurrentprovisionsforemployeebenefits"
+];
+
+/// Columnas de `estado_resultado_trimestral` que regresa `get_quarterly_income_statement`.
+pub const COLUMNAS_RESULTADO: [&str; 18] = [
+    "revenue", "grossprofit", "profitlossfromoperatingactivities", "profitloss", "profitlossbeforetax",
+    "costofsales", "distributioncosts", "administrativeexpense", "financecosts", "financeincome",
+    "incometaxexpensecontinuingoperations", "profitlossattributabletoownersofparent",
+    "basicearningslosspershare", "dilutedearningslosspershare", "otherincome",
+    "shareofprofitlossofassociatesandjointventuresaccountedforusinge",
+    "profitlossfromdiscontinuedoperations", "depreciacion"
+];
+
 #[command]
-pub fn get_emisora_query(query: String) -> Result<Vec<EmisoraBusqueda>, String> {
-    use postgres::{Client, NoTls};
-    let mut client = Client::connect(
-        "host=localhost user=garden_admin password=password dbname=dalia_db",
-        NoTls,
-    ).map_err(|e| e.to_string())?;
+pub async fn get_emisora_query(query: String) -> Result<Vec<EmisoraBusqueda>, String> {
+    let pool = db::pool().await?;
     let sql = r#"
         SELECT razon_social, emisoras, serie
         FROM emisoras
@@ -23,31 +51,32 @@ pub fn get_emisora_query(query: String) -> Result<Vec<EmisoraBusqueda>, String>
         LIMIT 20
     "#;
     let pattern = format!("%{}%", query.to_lowercase());
-    let rows = client.query(sql, &[&pattern]).map_err(|e| e.to_string())?;
-    let results = rows
-        .into_iter()
-        .map(|row| EmisoraBusqueda {
-            razon_social: row.get(0),
-            emisoras: row.get(1),
-            serie: row.get(2),
-        })
-        .collect();
-    Ok(results)
+    let rows = sqlx::query(sql).bind(pattern).fetch_all(pool).await.map_err(|e| e.to_string())?;
+    rows.into_iter()
+        .map(|row| Ok(EmisoraBusqueda {
+            razon_social: row.try_get(0)?,
+            emisoras: row.try_get(1)?,
+            serie: row.try_get(2)?,
+        }))
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
 }
 
-pub fn list_dividendos_by_activo(
-    pg_client: &mut Client,
+pub async fn list_dividendos_by_activo(
+    pool: &PgPool,
     portafolio_ticker_id: i32,
-) -> Result<Vec<(i32, f64, chrono::NaiveDateTime)>, Box<dyn std::error::Error>> {
-    let rows = pg_client.query(
+) -> Result<Vec<(i32, f64, chrono::NaiveDateTime)>, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = sqlx::query(
         "SELECT id, monto, fecha FROM dividendos WHERE portafolio_ticker_id = $1 ORDER BY fecha DESC",
-        &[&portafolio_ticker_id],
-    )?;
+    )
+    .bind(portafolio_ticker_id)
+    .fetch_all(pool)
+    .await?;
     let mut dividendos = Vec::new();
     for row in rows {
-        let id: i32 = row.get(0);
-        let monto: f64 = row.get(1);
-        let fecha: chrono::NaiveDateTime = row.get(2);
+        let id: i32 = row.try_get(0)?;
+        let monto: f64 = row.try_get(1)?;
+        let fecha: chrono::NaiveDateTime = row.try_get(2)?;
         dividendos.push((id, monto, fecha));
     }
     Ok(dividendos)
@@ -60,7 +89,7 @@ pub fn list_dividendos_by_activo(
 ///
 /// # Arguments
 ///
-/// * `pg_client` - A mutable reference to an active PostgreSQL database connection.
+/// * `pool` - The shared PostgreSQL connection pool (see `db::pool`).
 /// * `emisora` - The name of the company (case-insensitive).
 /// * `trimestre` - The quarter to query, e.g., `"2024Q4"`.
 ///
@@ -70,42 +99,49 @@ pub fn list_dividendos_by_activo(
 /// (such as `"flujo_operacion"`, `"utilidad_neta"`, etc.) and the corresponding value
 /// is the associated amount.
 ///
-/// If a column is `NULL`, it will default to `0.0`.
+/// Columns that are `NULL` (not reported) are left out of the map.
 ///
 /// # Errors
 ///
-/// Returns a `Box<dyn std::error::Error>` if any error occurs while querying the database
+/// Returns a `Box<dyn std::error::Error + Send + Sync>` if any error occurs while querying the database
 /// or fetching external data.
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// let mut client = connect_to_db()?; // Assume this is implemented
-/// let cash_flow = get_finantial_flow(&mut client, "WALMEX", "2024Q1")?;
-/// println!("Operating cash flow: {}", cash_flow["flujo_operacion"]);
+/// # use dalia_trac_lib::{activos::get_finantial_flow, db};
+/// # async fn ejemplo() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let pool = db::pool().await?;
+/// let cash_flow = get_finantial_flow(pool, "WALMEX", "2024Q1").await?;
+/// println!("Operating cash flow: {:?}", cash_flow.get("flujo_operacion"));
+/// # Ok(())
+/// # }
 /// ```
-pub fn get_finantial_flow(
-    pg_client: &mut Client, 
+pub async fn get_finantial_flow(
+    pool: &PgPool,
     emisora: &str, 
     trimestre: &str
-) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<String, f64>, Box<dyn std::error::Error + Send + Sync>> {
 
     // 1. Verificar existencia
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT EXISTS (
             SELECT 1 FROM public.estado_flujos
             WHERE LOWER(emisora) = LOWER($1)
               AND LOWER(trimestre) = LOWER($2)
         ) AS existe",
-        &[&emisora, &trimestre],
-    )?;
-    let existe: bool = row.get("existe");
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
+    let existe: bool = row.try_get("existe")?;
     if !existe {
-        get_data::get_flujos_financieros(pg_client, emisora, trimestre)?;
+        get_data::get_flujos_financieros(pool, emisora, trimestre).await?;
     }
 
     // 2. Solicitar los datos del flujo financiero
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT flujo_operacion, utilidad_neta, depreciacion, cambio_inventarios,
             cambio_cxc, cambio_cxp, impuestos_pagados, intereses_pagados, flujo_inversion,
             capex, venta_activos, compra_intangibles, flujo_financiamiento, prestamos_obtenidos, 
@@ -115,21 +151,20 @@ pub fn get_finantial_flow(
         WHERE LOWER(emisora) = LOWER($1)
           AND LOWER(trimestre) = LOWER($2)
         LIMIT 1",
-        &[&emisora, &trimestre],
-    )?;
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
 
     // 3. Organizar en un HashMap
-    let columnas = [
-        "flujo_operacion", "utilidad_neta", "depreciacion", "cambio_inventarios",
-        "cambio_cxc", "cambio_cxp", "impuestos_pagados", "intereses_pagados", "flujo_inversion",
-        "capex", "venta_activos", "compra_intangibles", "flujo_financiamiento", "prestamos_obtenidos", 
-        "pago_deuda", "dividendos_pagados", "recompras", "cambio_efectivo", "efectivo_final", "efecto_tc", "deterioros",
-        "partidas_no_monetarias", "costos_financieros"
-    ];
+    let columnas = COLUMNAS_FLUJO;
     let mut estados_financieros = HashMap::new();
     for (i, col) in columnas.iter().enumerate() {
-        let valor: Option<f64> = row.get(i);
-        estados_financieros.insert(col.to_string(), valor.unwrap_or(0.0));
+        // Una partida en NULL no se reportó: se omite en lugar de tomarla como cero
+        if let Some(valor) = row.try_get::<Option<f64>, _>(i)? {
+            estados_financieros.insert(col.to_string(), valor);
+        }
     }
 
     Ok(estados_financieros)
@@ -137,28 +172,31 @@ pub fn get_finantial_flow(
 
 
 
-pub fn get_finantial_position(
-    pg_client: &mut Client, 
+pub async fn get_finantial_position(
+    pool: &PgPool,
     emisora: &str,
     trimestre: &str
-) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<String, f64>, Box<dyn std::error::Error + Send + Sync>> {
 
     // 1. Verificar existencia
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT EXISTS (
             SELECT 1 FROM public.estado_posicion
             WHERE LOWER(emisora) = LOWER($1)
               AND LOWER(trimestre) = LOWER($2)
         ) AS existe",
-        &[&emisora, &trimestre],
-    )?;
-    let existe: bool = row.get("existe");
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
+    let existe: bool = row.try_get("existe")?;
     if !existe {
-        get_data::get_posicion_financiera(pg_client, emisora, trimestre)?;
+        get_data::get_posicion_financiera(pool, emisora, trimestre).await?;
     }
 
     // 2. Solicitar los datos de la posición financiera
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT currentassets, currentliabilities, cashandcashequivalents, inventories,
             tradeandothercurrentreceivables, tradeandothercurrentpayables, equity, liabilities,
             noncurrentliabilities, equityattributabletoownersofparent, noncontrollinginterests,
@@ -171,24 +209,20 @@ pub fn get_finantial_position(
         WHERE LOWER(emisora) = LOWER($1)
           AND LOWER(trimestre) = LOWER($2)
         LIMIT 1",
-        &[&emisora, &trimestre],
-    )?;
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
 
     // 3. Organizar en un HashMap
-    let columnas = [
-        "currentassets", "currentliabilities", "cashandcashequivalents", "inventories",
-        "tradeandothercurrentreceivables", "tradeandothercurrentpayables", "equity", "liabilities",
-        "noncurrentliabilities", "equityattributabletoownersofparent", "noncontrollinginterests",
-        "propertyplantandequipment", "intangibleassetsotherthangoodwill", "goodwill",
-        "rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty", "deferredtaxassets",
-        "deferredtaxliabilities", "noncurrentassetsordisposalgroupsclassifiedasheldforsale",
-        "retainedearnings", "issuedcapital", "otherreserves", "noncurrentleaseliabilities",
-        "othernoncurrentfinancialliabilities", "noncurrentprovisionsforemployeebenefits"
-    ];
+    let columnas = COLUMNAS_POSICION;
     let mut estados_posicion = HashMap::new();
     for (i, col) in columnas.iter().enumerate() {
-        let valor: Option<f64> = row.get(i);
-        estados_posicion.insert(col.to_string(), valor.unwrap_or(0.0));
+        // Una partida en NULL no se reportó: se omite en lugar de tomarla como cero
+        if let Some(valor) = row.try_get::<Option<f64>, _>(i)? {
+            estados_posicion.insert(col.to_string(), valor);
+        }
     }
 
     Ok(estados_posicion)
@@ -196,23 +230,26 @@ pub fn get_finantial_position(
 
 
 
-pub fn get_quarterly_income_statement(
-    pg_client: &mut Client,
+pub async fn get_quarterly_income_statement(
+    pool: &PgPool,
     emisora: &str,
     trimestre: &str
-) -> Result<HashMap<String, f64>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<String, f64>, Box<dyn std::error::Error + Send + Sync>> {
     // 1. Verificar existencia
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT EXISTS (
             SELECT 1 FROM public.estado_resultado_trimestral
             WHERE LOWER(emisora) = LOWER($1)
               AND LOWER(trimestre) = LOWER($2)
         ) AS existe",
-        &[&emisora, &trimestre],
-    )?;
-    let existe: bool = row.get("existe");
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
+    let existe: bool = row.try_get("existe")?;
     if !existe {
-        get_data::get_estado_resultado_trimestral(pg_client, emisora, trimestre)?;
+        get_data::get_estado_resultado_trimestral(pool, emisora, trimestre).await?;
     }
 
     // 2. Solicitar los datos del estado de resultados trimesal
@@ -222,7 +259,7 @@ pub fn get_quarterly_income_statement(
     // gross profit, operating activities profit/loss, net profit/loss, cost of sales, expenses,
     // finance costs/income, tax expense, earnings per share, other income, share of profit/loss
     // from associates and joint ventures, discontinued operations profit/loss, y depreciación.
-    let row = pg_client.query_one(
+    let row = sqlx::query(
         "SELECT revenue, grossprofit, profitlossfromoperatingactivities, profitloss, profitlossbeforetax, \
             costofsales, distributioncosts, administrativeexpense, financecosts, financeincome, \
             incometaxexpensecontinuingoperations, profitlossattributabletoownersofparent, \
@@ -233,41 +270,35 @@ pub fn get_quarterly_income_statement(
         WHERE LOWER(emisora) = LOWER($1)
           AND LOWER(trimestre) = LOWER($2)
         LIMIT 1",
-        &[&emisora, &trimestre],
-    )?;
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
 
     // 3. Organizar en un HashMap
-    let columnas = [
-        "revenue", "grossprofit", "profitlossfromoperatingactivities", "profitloss", "profitlossbeforetax",
-        "costofsales", "distributioncosts", "administrativeexpense", "financecosts", "financeincome",
-        "incometaxexpensecontinuingoperations", "profitlossattributabletoownersofparent",
-        "basicearningslosspershare", "dilutedearningslosspershare", "otherincome",
-        "shareofprofitlossofassociatesandjointventuresaccountedforusinge",
-        "profitlossfromdiscontinuedoperations", "depreciacion"
-    ];
+    let columnas = COLUMNAS_RESULTADO;
     let mut estado_resultado = HashMap::new();
     for (i, col) in columnas.iter().enumerate() {
-        let valor: Option<f64> = row.get(i);
-        estado_resultado.insert(col.to_string(), valor.unwrap_or(0.0));
+        // Una partida en NULL no se reportó: se omite en lugar de tomarla como cero
+        if let Some(valor) = row.try_get::<Option<f64>, _>(i)? {
+            estado_resultado.insert(col.to_string(), valor);
+        }
     }
 
     Ok(estado_resultado)
 }
 
 #[tauri::command]
-pub fn get_trimestres_disponibles(emisora: String) -> Result<Vec<String>, String> {
-    use postgres::{Client, NoTls};
-    let mut client = Client::connect(
-        "host=localhost user=garden_admin password=password dbname=dalia_db",
-        NoTls,
-    ).map_err(|e| e.to_string())?;
+pub async fn get_trimestres_disponibles(emisora: String) -> Result<Vec<String>, String> {
+    let pool = db::pool().await?;
     // Buscar en las tres tablas y unir los trimestres únicos
     let mut trimestres = std::collections::HashSet::new();
     for tabla in ["estado_flujos", "estado_posicion", "estado_resultado_trimestral"] {
         let sql = format!("SELECT DISTINCT trimestre FROM public.{} WHERE LOWER(emisora) = LOWER($1)", tabla);
-        let rows = client.query(&sql, &[&emisora]).map_err(|e| e.to_string())?;
+        let rows = sqlx::query(&sql).bind(&emisora).fetch_all(pool).await.map_err(|e| e.to_string())?;
         for row in rows {
-            let t: String = row.get(0);
+            let t: String = row.try_get(0).map_err(|e| e.to_string())?;
             trimestres.insert(t);
         }
     }
@@ -277,48 +308,43 @@ pub fn get_trimestres_disponibles(emisora: String) -> Result<Vec<String>, String
 }
 
 #[tauri::command]
-pub fn get_emisora_info(emisora: String, trimestre: Option<String>) -> Result<String, String> {
+pub async fn get_emisora_info(emisora: String, trimestre: Option<String>) -> Result<String, String> {
     use serde_json::json;
-    let mut client = match postgres::Client::connect(
-        "host=localhost user=garden_admin password=password dbname=dalia_db",
-        postgres::NoTls,
-    ) {
-        Ok(c) => c,
-        Err(e) => return Err(format!("DB connection error: {}", e)),
-    };
+    let pool = db::pool().await?;
 
     // Obtener los 4 trimestres más recientes si no se especifica
     let trimestres: Vec<String> = if let Some(t) = trimestre {
         vec![t]
     } else {
         let sql = "SELECT DISTINCT trimestre FROM public.estado_flujos WHERE LOWER(emisora) = LOWER($1) ORDER BY trimestre DESC LIMIT 4";
-        let rows = client.query(sql, &[&emisora]).map_err(|e| e.to_string())?;
-        let mut ts: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+        let rows = sqlx::query(sql).bind(&emisora).fetch_all(pool).await.map_err(|e| e.to_string())?;
+        let mut ts: Vec<String> = rows
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
         ts.sort();
         ts
     };
 
-    let mut resultados = Vec::new();
-    for t in &trimestres {
-        let cashflow = match get_finantial_flow(&mut client, &emisora, t) {
-            Ok(data) => data,
-            Err(e) => return Err(format!("Error getting financial flow for {t}: {}", e)),
-        };
-        let position = match get_finantial_position(&mut client, &emisora, t) {
-            Ok(data) => data,
-            Err(e) => return Err(format!("Error getting financial position for {t}: {}", e)),
-        };
-        let income = match get_quarterly_income_statement(&mut client, &emisora, t) {
-            Ok(data) => data,
-            Err(e) => return Err(format!("Error getting income statement for {t}: {}", e)),
-        };
-        resultados.push(json!({
-            "trimestre": t,
-            "cashflow": cashflow,
-            "position": position,
-            "income": income
-        }));
-    }
+    // Los tres estados de cada trimestre se piden en paralelo
+    let consultas = trimestres.iter().map(|t| {
+        let emisora = &emisora;
+        async move {
+            let (cashflow, position, income) = tokio::try_join!(
+                async { get_finantial_flow(pool, emisora, t).await.map_err(|e| format!("Error getting financial flow for {t}: {}", e)) },
+                async { get_finantial_position(pool, emisora, t).await.map_err(|e| format!("Error getting financial position for {t}: {}", e)) },
+                async { get_quarterly_income_statement(pool, emisora, t).await.map_err(|e| format!("Error getting income statement for {t}: {}", e)) },
+            )?;
+            Ok::<_, String>(json!({
+                "trimestre": t,
+                "cashflow": cashflow,
+                "position": position,
+                "income": income
+            }))
+        }
+    });
+    let resultados = futures::future::try_join_all(consultas).await?;
     let result = json!({
         "emisora": emisora,
         "trimestres": trimestres,
@@ -362,23 +388,19 @@ pub struct AssetDetails {
 }
 
 #[tauri::command]
-pub fn get_asset_details(ticker: String) -> Result<AssetDetails, String> {
-    use postgres::{Client, NoTls};
-    use chrono::{Local};
-    let mut client = Client::connect(
-        "host=localhost user=garden_admin password=password dbname=dalia_db",
-        NoTls,
-    ).map_err(|e| e.to_string())?;
+pub async fn get_asset_details(ticker: String) -> Result<AssetDetails, String> {
+    let pool = db::pool().await?;
 
     // 1. Info básica y API
-    let row = client.query_one(
-        "SELECT razon_social, emisoras, serie FROM emisoras WHERE emisoras = $1",
-        &[&ticker],
-    ).map_err(|e| format!("Error fetching basic info for {}: {}", ticker, e))?;
-    let emisora_db: String = row.get("emisoras");
-    let serie_db: String = row.get("serie");
+    let row = sqlx::query("SELECT razon_social, emisoras, serie FROM emisoras WHERE emisoras = $1")
+        .bind(&ticker)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Error fetching basic info for {}: {}", ticker, e))?;
+    let emisora_db: String = row.try_get("emisoras").map_err(|e| e.to_string())?;
+    let serie_db: String = row.try_get("serie").map_err(|e| e.to_string())?;
     let ticker_key = format!("{}{}", emisora_db, serie_db);
-    let cot_actual = crate::get_data::get_cotizaciones(&ticker_key).ok().flatten();
+    let cot_actual = crate::get_data::get_cotizaciones(&ticker_key).await.ok().flatten();
     let mut price = 0.0;
     let mut open = 0.0;
     let mut high = 0.0;
@@ -387,21 +409,67 @@ pub fn get_asset_details(ticker: String) -> Result<AssetDetails, String> {
     if let Some(cot) = &cot_actual {
         price = cot.ultimo_precio.unwrap_or(0.0);
         open = cot.precio_promedio.unwrap_or(0.0); // Usar precio_promedio como apertura
-        // No hay campos high/low, los dejamos en 0.0
         volume = cot.volumen.unwrap_or(0.0) as i64;
     }
 
-    // 2. Precio de cierre anterior (día hábil anterior)
+    // Sesión vigente según el calendario de la BMV: antes de la apertura o en día
+    // inhábil, los datos "del día" son los de la última sesión
+    let sesion = calendario::sesion_vigente(Utc::now());
+
+    // Apertura, máximo y mínimo de la sesión: su barra diaria o, si aún no existe,
+    // los extremos de los precios intradía de esa sesión
+    let rango_dia = r#"
+        SELECT apertura, maximo, minimo
+        FROM precios_diarios
+        WHERE emisoras = $1 AND serie = $2 AND fecha = $4
+        UNION ALL
+        SELECT (array_agg(precio ORDER BY fecha_hora))[1], MAX(precio), MIN(precio)
+        FROM intradia_data
+        WHERE emisora = $3 AND fecha_hora >= $4::timestamp AND fecha_hora < ($4 + 1)::timestamp
+        HAVING COUNT(*) > 0
+        LIMIT 1;
+    "#;
+    if let Ok(Some(dia)) = sqlx::query(rango_dia)
+        .bind(&emisora_db)
+        .bind(&serie_db)
+        .bind(&ticker_key)
+        .bind(sesion)
+        .fetch_optional(pool)
+        .await
+    {
+        if let Ok(Some(apertura)) = dia.try_get::<Option<f64>, _>(0) {
+            open = apertura;
+        }
+        high = dia.try_get::<Option<f64>, _>(1).ok().flatten().unwrap_or(0.0);
+        low = dia.try_get::<Option<f64>, _>(2).ok().flatten().unwrap_or(0.0);
+    }
+
+    // 2. Precio de cierre anterior (día hábil anterior): barra diaria y, si no hay, intradía
     let previous_close_price_query = r#"
-        SELECT precio
+        -- El cierre oficial del día pesa más que cualquier precio intradía de ese día
+        SELECT cierre AS precio, (fecha + 1)::timestamp AS momento
+        FROM precios_diarios
+        WHERE emisoras = $1 AND serie = $2 AND fecha < $4
+        UNION ALL
+        SELECT precio, fecha_hora AS momento
         FROM intradia_data
-        WHERE emisora = $1 AND fecha_hora < current_date::timestamp
-        ORDER BY fecha_hora DESC
+        WHERE emisora = $3 AND fecha_hora < $4::timestamp
+        ORDER BY momento DESC
         LIMIT 1;
     "#;
-    let previous_close_price: f64 = match client.query_one(previous_close_price_query, &[&ticker_key]) {
-        Ok(row) => row.get("precio"),
-        Err(_) => {
+    let previous_close_price: f64 = match sqlx::query(previous_close_price_query)
+        .bind(&emisora_db)
+        .bind(&serie_db)
+        .bind(&ticker_key)
+        .bind(sesion)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|row| row.try_get::<f64, _>("precio").ok())
+    {
+        Some(precio) => precio,
+        None => {
             println!("[INFO] No se encontró cierre anterior para {}. Usando precio de apertura.", ticker_key);
             open
         }
@@ -426,26 +494,31 @@ pub fn get_asset_details(ticker: String) -> Result<AssetDetails, String> {
     };
     // 3. Últimos 4 trimestres financieros (sin cambios)
     let mut quarterly_financials = Vec::new();
-    for row in client.query(
+    let filas = sqlx::query(
         "SELECT trimestre, flujo_operacion, utilidad_neta, depreciacion, cambio_inventarios, impuestos_pagados FROM estado_flujos WHERE emisora = $1 ORDER BY trimestre DESC LIMIT 4",
-        &[&ticker],
-    ).map_err(|e| format!("Error fetching financials: {}", e))? {
-        let trimestre: String = row.get("trimestre");
+    )
+    .bind(&ticker)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Error fetching financials: {}", e))?;
+    for fila in filas {
+        let trimestre: String = fila.try_get("trimestre").map_err(|e| e.to_string())?;
         let anio = trimestre.get(0..4).and_then(|s| s.parse::<i32>().ok()).unwrap_or(0);
+        let valor = |col: &str| fila.try_get::<Option<f64>, _>(col).ok().flatten().unwrap_or(0.0);
         quarterly_financials.push(FinancialStatement {
             anio,
             trimestre: trimestre.clone(),
-            utilidad_neta: row.get("utilidad_neta"),
-            flujo_operativo: row.get("flujo_operacion"),
-            depreciacion: row.get("depreciacion"),
-            cambio_inventarios: row.get("cambio_inventarios"),
-            impuestos_pagados: row.get("impuestos_pagados"),
+            utilidad_neta: valor("utilidad_neta"),
+            flujo_operativo: valor("flujo_operacion"),
+            depreciacion: valor("depreciacion"),
+            cambio_inventarios: valor("cambio_inventarios"),
+            impuestos_pagados: valor("impuestos_pagados"),
         });
     }
     Ok(AssetDetails {
-        razon_social: row.get("razon_social"),
-        emisoras: row.get("emisoras"),
-        serie: row.get("serie"),
+        razon_social: row.try_get("razon_social").map_err(|e| e.to_string())?,
+        emisoras: emisora_db,
+        serie: serie_db,
         intradia,
         quarterly_financials,
     })
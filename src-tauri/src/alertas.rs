@@ -0,0 +1,596 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::command;
+use crate::get_data::{self, Cotizacion};
+use crate::{calendario, db, scheduler};
+
+// --- Alertas de precio y fundamentales ---
+// Cada usuario define reglas por emisora. Un ciclo en segundo plano las evalúa:
+// las de mercado (precio, cambio, media móvil, volumen) cada minuto mientras la
+// BMV está abierta, contra la cotización de la caché; las fundamentales (reporte
+// trimestral, dividendo) cada 15 minutos, contra el catálogo que sincroniza el
+// programador. Una regla de mercado dispara como máximo una vez por sesión; una
+// fundamental, cuando cambia el último trimestre o aviso de dividendo visto.
+// Cada disparo queda en `alert_history` y se avisa por el `Notificador` (en la
+// app, un evento de Tauri y una notificación del sistema).
+
+const REVISION_SEGUNDOS: u64 = 60;
+/// Las reglas fundamentales se revisan una vez cada tantos ciclos.
+const CICLOS_FUNDAMENTALES: u64 = 15;
+const PERIODO_MEDIA_POR_DEFECTO: i32 = 50;
+const MULTIPLO_VOLUMEN_POR_DEFECTO: f64 = 2.0;
+/// Sesiones con las que se calcula el volumen promedio.
+const SESIONES_VOLUMEN: i64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoAlerta {
+    PrecioArriba,
+    PrecioAbajo,
+    CambioDia,
+    CruceMedia,
+    VolumenPico,
+    ReporteTrimestral,
+    Dividendo,
+}
+
+impl TipoAlerta {
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            TipoAlerta::PrecioArriba => "precio_arriba",
+            TipoAlerta::PrecioAbajo => "precio_abajo",
+            TipoAlerta::CambioDia => "cambio_dia",
+            TipoAlerta::CruceMedia => "cruce_media",
+            TipoAlerta::VolumenPico => "volumen_pico",
+            TipoAlerta::ReporteTrimestral => "reporte_trimestral",
+            TipoAlerta::Dividendo => "dividendo",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<TipoAlerta, String> {
+        match s.trim().to_lowercase().as_str() {
+            "precio_arriba" => Ok(TipoAlerta::PrecioArriba),
+            "precio_abajo" => Ok(TipoAlerta::PrecioAbajo),
+            "cambio_dia" => Ok(TipoAlerta::CambioDia),
+            "cruce_media" => Ok(TipoAlerta::CruceMedia),
+            "volumen_pico" => Ok(TipoAlerta::VolumenPico),
+            "reporte_trimestral" => Ok(TipoAlerta::ReporteTrimestral),
+            "dividendo" => Ok(TipoAlerta::Dividendo),
+            otro => Err(format!(
+                "Tipo de alerta inválido '{}'. Usa precio_arriba, precio_abajo, cambio_dia, cruce_media, volumen_pico, reporte_trimestral o dividendo",
+                otro
+            )),
+        }
+    }
+
+    /// Si la regla se evalúa contra cotizaciones (y sólo con el mercado abierto).
+    pub fn de_mercado(&self) -> bool {
+        !matches!(self, TipoAlerta::ReporteTrimestral | TipoAlerta::Dividendo)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReglaAlerta {
+    pub id: i32,
+    pub usuario_id: i32,
+    pub emisora: String,
+    pub serie: String,
+    pub tipo: TipoAlerta,
+    /// precio_*: precio; cambio_dia: % absoluto; volumen_pico: múltiplo del volumen promedio
+    pub umbral: Option<f64>,
+    /// cruce_media: sesiones de la media móvil
+    pub periodo: Option<i32>,
+    pub activa: bool,
+    #[serde(skip)]
+    estado: Option<String>,
+    #[serde(skip)]
+    ultima_sesion: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertaDisparada {
+    pub id: i32,
+    pub regla_id: i32,
+    pub usuario_id: i32,
+    pub emisora: String,
+    pub serie: String,
+    pub tipo: String,
+    pub mensaje: String,
+    pub valor: Option<f64>,
+    pub disparada: DateTime<Utc>,
+    pub leida: bool,
+}
+
+pub type Notificador = Arc<dyn Fn(&AlertaDisparada) + Send + Sync>;
+
+type AlertaError = Box<dyn std::error::Error + Send + Sync>;
+
+fn regla_desde_fila(row: &sqlx::postgres::PgRow) -> Result<ReglaAlerta, String> {
+    let tipo: String = row.try_get("tipo").map_err(|e| e.to_string())?;
+    Ok(ReglaAlerta {
+        id: row.try_get("id").map_err(|e| e.to_string())?,
+        usuario_id: row.try_get("usuario_id").map_err(|e| e.to_string())?,
+        emisora: row.try_get("emisoras").map_err(|e| e.to_string())?,
+        serie: row.try_get("serie").map_err(|e| e.to_string())?,
+        tipo: TipoAlerta::parse(&tipo)?,
+        umbral: row.try_get("umbral").map_err(|e| e.to_string())?,
+        periodo: row.try_get("periodo").map_err(|e| e.to_string())?,
+        activa: row.try_get("activa").map_err(|e| e.to_string())?,
+        estado: row.try_get("estado").map_err(|e| e.to_string())?,
+        ultima_sesion: row.try_get("ultima_sesion").map_err(|e| e.to_string())?,
+    })
+}
+
+const COLUMNAS_REGLA: &str = "id, usuario_id, emisoras, serie, tipo, umbral, periodo, activa, estado, ultima_sesion";
+
+/// Último trimestre reportado o aviso de dividendo del catálogo, según el tipo.
+async fn estado_fundamental(pool: &PgPool, tipo: TipoAlerta, emisora: &str, serie: &str) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query("SELECT rangos_financieros, dividendos FROM emisoras WHERE emisoras = $1 AND serie = $2")
+        .bind(emisora)
+        .bind(serie)
+        .fetch_optional(pool)
+        .await?;
+    let row = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    Ok(match tipo {
+        TipoAlerta::ReporteTrimestral => row
+            .try_get::<Option<String>, _>("rangos_financieros")?
+            .as_deref()
+            .and_then(scheduler::ultimo_periodo),
+        TipoAlerta::Dividendo => row
+            .try_get::<Option<String>, _>("dividendos")?
+            .filter(|d| !d.is_empty() && d != "null" && d != "[]" && d != "{}"),
+        _ => None,
+    })
+}
+
+/// Guarda el disparo en el historial y marca la regla para no repetirlo.
+async fn registrar(
+    pool: &PgPool,
+    regla: &ReglaAlerta,
+    mensaje: String,
+    valor: Option<f64>,
+    sesion: Option<NaiveDate>,
+    estado: Option<&str>,
+) -> Result<AlertaDisparada, AlertaError> {
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query(
+        "INSERT INTO alert_history (regla_id, usuario_id, emisoras, serie, tipo, mensaje, valor)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING id, disparada",
+    )
+    .bind(regla.id)
+    .bind(regla.usuario_id)
+    .bind(&regla.emisora)
+    .bind(&regla.serie)
+    .bind(regla.tipo.nombre())
+    .bind(&mensaje)
+    .bind(valor)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE alert_rules SET ultima_sesion = COALESCE($2, ultima_sesion), estado = COALESCE($3, estado)
+         WHERE id = $1",
+    )
+    .bind(regla.id)
+    .bind(sesion)
+    .bind(estado)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(AlertaDisparada {
+        id: row.try_get("id")?,
+        regla_id: regla.id,
+        usuario_id: regla.usuario_id,
+        emisora: regla.emisora.clone(),
+        serie: regla.serie.clone(),
+        tipo: regla.tipo.nombre().to_string(),
+        mensaje,
+        valor,
+        disparada: row.try_get("disparada")?,
+        leida: false,
+    })
+}
+
+/// Cierre anterior, media de `periodo` cierres y volumen promedio, todos previos a la sesión.
+async fn contexto_historico(
+    pool: &PgPool,
+    emisora: &str,
+    serie: &str,
+    sesion: NaiveDate,
+    periodo: i64,
+) -> Result<(Option<f64>, Option<f64>, Option<f64>), sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT
+            (SELECT cierre FROM precios_diarios
+             WHERE emisoras = $1 AND serie = $2 AND fecha < $3
+             ORDER BY fecha DESC LIMIT 1) AS cierre_anterior,
+            (SELECT AVG(cierre) FROM (
+                SELECT cierre FROM precios_diarios
+                WHERE emisoras = $1 AND serie = $2 AND fecha < $3
+                ORDER BY fecha DESC LIMIT $4
+             ) c HAVING COUNT(*) = $4) AS media,
+            (SELECT AVG(volumen)::double precision FROM (
+                SELECT volumen FROM precios_diarios
+                WHERE emisoras = $1 AND serie = $2 AND fecha < $3 AND volumen IS NOT NULL
+                ORDER BY fecha DESC LIMIT $5
+             ) v) AS volumen_medio",
+    )
+    .bind(emisora)
+    .bind(serie)
+    .bind(sesion)
+    .bind(periodo)
+    .bind(SESIONES_VOLUMEN)
+    .fetch_one(pool)
+    .await?;
+    Ok((row.try_get("cierre_anterior")?, row.try_get("media")?, row.try_get("volumen_medio")?))
+}
+
+/// Mensaje y valor si la regla de mercado se cumple con esta cotización.
+async fn evaluar_mercado(
+    pool: &PgPool,
+    regla: &ReglaAlerta,
+    cotizacion: &Cotizacion,
+    sesion: NaiveDate,
+) -> Result<Option<(String, Option<f64>)>, AlertaError> {
+    let ticker = format!("{}{}", regla.emisora, regla.serie);
+    let precio = match cotizacion.ultimo_precio.filter(|p| *p > 0.0) {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    let periodo = regla.periodo.unwrap_or(PERIODO_MEDIA_POR_DEFECTO) as i64;
+    let disparo = match regla.tipo {
+        TipoAlerta::PrecioArriba => regla
+            .umbral
+            .filter(|u| precio >= *u)
+            .map(|u| (format!("{} superó {:.2} (último {:.2})", ticker, u, precio), Some(precio))),
+        TipoAlerta::PrecioAbajo => regla
+            .umbral
+            .filter(|u| precio <= *u)
+            .map(|u| (format!("{} bajó de {:.2} (último {:.2})", ticker, u, precio), Some(precio))),
+        TipoAlerta::CambioDia => {
+            let (anterior, _, _) = contexto_historico(pool, &regla.emisora, &regla.serie, sesion, periodo).await?;
+            match (anterior.filter(|a| *a > 0.0), regla.umbral) {
+                (Some(anterior), Some(umbral)) => {
+                    let cambio = (precio - anterior) / anterior * 100.0;
+                    (cambio.abs() >= umbral)
+                        .then(|| (format!("{} se mueve {:+.2}% en el día (último {:.2})", ticker, cambio, precio), Some(cambio)))
+                }
+                _ => None,
+            }
+        }
+        TipoAlerta::CruceMedia => {
+            // Cruce entre el cierre anterior y el precio actual respecto a la media de los cierres previos
+            let (anterior, media, _) = contexto_historico(pool, &regla.emisora, &regla.serie, sesion, periodo).await?;
+            match (anterior, media) {
+                (Some(anterior), Some(media)) if anterior <= media && precio > media => Some((
+                    format!("{} cruzó hacia arriba su media de {} sesiones ({:.2})", ticker, periodo, media),
+                    Some(precio),
+                )),
+                (Some(anterior), Some(media)) if anterior >= media && precio < media => Some((
+                    format!("{} cruzó hacia abajo su media de {} sesiones ({:.2})", ticker, periodo, media),
+                    Some(precio),
+                )),
+                _ => None,
+            }
+        }
+        TipoAlerta::VolumenPico => {
+            let (_, _, promedio) = contexto_historico(pool, &regla.emisora, &regla.serie, sesion, periodo).await?;
+            let multiplo = regla.umbral.unwrap_or(MULTIPLO_VOLUMEN_POR_DEFECTO);
+            match (cotizacion.volumen, promedio.filter(|p| *p > 0.0)) {
+                (Some(volumen), Some(promedio)) if volumen >= promedio * multiplo => Some((
+                    format!(
+                        "{} opera {:.0} títulos, {:.1} veces su promedio de {} sesiones",
+                        ticker,
+                        volumen,
+                        volumen / promedio,
+                        SESIONES_VOLUMEN
+                    ),
+                    Some(volumen),
+                )),
+                _ => None,
+            }
+        }
+        TipoAlerta::ReporteTrimestral | TipoAlerta::Dividendo => None,
+    };
+    Ok(disparo)
+}
+
+async fn reglas_activas(pool: &PgPool) -> Result<Vec<ReglaAlerta>, AlertaError> {
+    let rows = sqlx::query(&format!("SELECT {} FROM alert_rules WHERE activa ORDER BY id", COLUMNAS_REGLA))
+        .fetch_all(pool)
+        .await?;
+    let reglas = rows.iter().map(regla_desde_fila).collect::<Result<Vec<_>, String>>()?;
+    Ok(reglas)
+}
+
+/// Evalúa las reglas activas y regresa las que dispararon.
+/// `mercado` y `fundamentales` eligen qué grupo de reglas se revisa.
+pub async fn evaluar(
+    pool: &PgPool,
+    mercado: bool,
+    fundamentales: bool,
+    notificar: &Notificador,
+) -> Result<Vec<AlertaDisparada>, AlertaError> {
+    let sesion = calendario::sesion_vigente(Utc::now());
+    let reglas = reglas_activas(pool).await?;
+    // Una sola cotización por emisora aunque tenga varias reglas
+    let mut cotizaciones: HashMap<String, Option<Cotizacion>> = HashMap::new();
+    let mut disparadas = Vec::new();
+    for regla in &reglas {
+        let resultado = if regla.tipo.de_mercado() {
+            if !mercado || regla.ultima_sesion == Some(sesion) {
+                continue;
+            }
+            let ticker = format!("{}{}", regla.emisora, regla.serie);
+            if !cotizaciones.contains_key(&ticker) {
+                let cotizacion = get_data::get_cotizaciones(&ticker).await.ok().flatten();
+                cotizaciones.insert(ticker.clone(), cotizacion);
+            }
+            match &cotizaciones[&ticker] {
+                Some(cotizacion) => match evaluar_mercado(pool, regla, cotizacion, sesion).await {
+                    Ok(Some((mensaje, valor))) => registrar(pool, regla, mensaje, valor, Some(sesion), None).await.map(Some),
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(e),
+                },
+                None => Ok(None),
+            }
+        } else {
+            if !fundamentales {
+                continue;
+            }
+            match estado_fundamental(pool, regla.tipo, &regla.emisora, &regla.serie).await {
+                Ok(Some(actual)) if regla.estado.as_deref() != Some(actual.as_str()) => {
+                    // Sin estado previo sólo se toma la referencia; no es un aviso nuevo
+                    if regla.estado.is_none() {
+                        sqlx::query("UPDATE alert_rules SET estado = $2 WHERE id = $1")
+                            .bind(regla.id)
+                            .bind(&actual)
+                            .execute(pool)
+                            .await
+                            .map(|_| None)
+                            .map_err(Into::into)
+                    } else {
+                        let mensaje = match regla.tipo {
+                            TipoAlerta::ReporteTrimestral => {
+                                format!("{}{} publicó su reporte {}", regla.emisora, regla.serie, actual)
+                            }
+                            _ => format!("{}{} tiene un nuevo aviso de dividendo", regla.emisora, regla.serie),
+                        };
+                        registrar(pool, regla, mensaje, None, None, Some(&actual)).await.map(Some)
+                    }
+                }
+                Ok(_) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        };
+        match resultado {
+            Ok(Some(alerta)) => {
+                notificar(&alerta);
+                disparadas.push(alerta);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[alertas] Regla {} ({}{}): {}", regla.id, regla.emisora, regla.serie, e),
+        }
+    }
+    Ok(disparadas)
+}
+
+/// Ciclo del evaluador. No regresa; pensado para `tokio::spawn` o para un demonio.
+pub async fn correr(notificar: Notificador) {
+    let mut intervalo = tokio::time::interval(std::time::Duration::from_secs(REVISION_SEGUNDOS));
+    let mut ciclo: u64 = 0;
+    loop {
+        intervalo.tick().await;
+        let fundamentales = ciclo.is_multiple_of(CICLOS_FUNDAMENTALES);
+        ciclo += 1;
+        let mercado = calendario::is_open(Utc::now()) && get_data::get_api_key().is_ok();
+        if !mercado && !fundamentales {
+            continue;
+        }
+        let pool = match db::pool().await {
+            Ok(pool) => pool,
+            Err(e) => {
+                eprintln!("[alertas] {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = evaluar(pool, mercado, fundamentales, &notificar).await {
+            eprintln!("[alertas] {}", e);
+        }
+    }
+}
+
+/// Arranca el evaluador en segundo plano.
+pub fn iniciar(notificar: Notificador) {
+    tauri::async_runtime::spawn(correr(notificar));
+}
+
+pub const EVENTO_ALERTA: &str = "alert-triggered";
+
+/// Notificador de la app: evento para el frontend y notificación del sistema.
+pub fn notificador_app(app: tauri::AppHandle) -> Notificador {
+    use tauri::Emitter;
+    use tauri_plugin_notification::NotificationExt;
+    Arc::new(move |alerta| {
+        let _ = app.emit(EVENTO_ALERTA, alerta.clone());
+        let _ = app
+            .notification()
+            .builder()
+            .title(format!("{}{}", alerta.emisora, alerta.serie))
+            .body(&alerta.mensaje)
+            .show();
+    })
+}
+
+#[command]
+pub async fn get_alert_rules(usuario_id: i32) -> Result<Vec<ReglaAlerta>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM alert_rules WHERE usuario_id = $1 ORDER BY emisoras, serie, id",
+        COLUMNAS_REGLA
+    ))
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter().map(regla_desde_fila).collect()
+}
+
+#[command]
+pub async fn create_alert_rule(
+    usuario_id: i32,
+    emisora: String,
+    serie: String,
+    tipo: String,
+    umbral: Option<f64>,
+    periodo: Option<i32>,
+) -> Result<ReglaAlerta, String> {
+    let tipo = TipoAlerta::parse(&tipo)?;
+    let (umbral, periodo) = match tipo {
+        TipoAlerta::PrecioArriba | TipoAlerta::PrecioAbajo | TipoAlerta::CambioDia => match umbral {
+            Some(u) if u > 0.0 => (Some(u), None),
+            _ => return Err("Esta alerta requiere un umbral mayor a cero".to_string()),
+        },
+        TipoAlerta::CruceMedia => match periodo.unwrap_or(PERIODO_MEDIA_POR_DEFECTO) {
+            p @ 2..=250 => (None, Some(p)),
+            _ => return Err("El periodo de la media debe estar entre 2 y 250 sesiones".to_string()),
+        },
+        TipoAlerta::VolumenPico => match umbral.unwrap_or(MULTIPLO_VOLUMEN_POR_DEFECTO) {
+            u if u > 1.0 => (Some(u), None),
+            _ => return Err("El múltiplo de volumen debe ser mayor a 1".to_string()),
+        },
+        TipoAlerta::ReporteTrimestral | TipoAlerta::Dividendo => (None, None),
+    };
+    let pool = db::pool().await?;
+    let row = sqlx::query(
+        "SELECT emisoras, serie FROM emisoras
+         WHERE UPPER(emisoras) = UPPER($1) AND UPPER(serie) = UPPER($2)
+         LIMIT 1",
+    )
+    .bind(emisora.trim())
+    .bind(serie.trim())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No existe la emisora {} serie {} en el catálogo", emisora.trim(), serie.trim()))?;
+    let emisora: String = row.try_get("emisoras").map_err(|e| e.to_string())?;
+    let serie: String = row.try_get("serie").map_err(|e| e.to_string())?;
+    // Las fundamentales parten del estado actual: sólo avisan de lo que llegue después
+    let estado = estado_fundamental(pool, tipo, &emisora, &serie).await.map_err(|e| e.to_string())?;
+    let row = sqlx::query(&format!(
+        "INSERT INTO alert_rules (usuario_id, emisoras, serie, tipo, umbral, periodo, estado)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING {}",
+        COLUMNAS_REGLA
+    ))
+    .bind(usuario_id)
+    .bind(&emisora)
+    .bind(&serie)
+    .bind(tipo.nombre())
+    .bind(umbral)
+    .bind(periodo)
+    .bind(estado)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    regla_desde_fila(&row)
+}
+
+async fn regla_del_usuario(pool: &PgPool, regla_id: i32, usuario_id: i32) -> Result<(), String> {
+    let row = sqlx::query("SELECT 1 FROM alert_rules WHERE id = $1 AND usuario_id = $2")
+        .bind(regla_id)
+        .bind(usuario_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(_) => Ok(()),
+        None => Err(format!("No se encontró la alerta {}", regla_id)),
+    }
+}
+
+#[command]
+pub async fn set_alert_rule_active(regla_id: i32, usuario_id: i32, activa: bool) -> Result<(), String> {
+    let pool = db::pool().await?;
+    regla_del_usuario(pool, regla_id, usuario_id).await?;
+    sqlx::query("UPDATE alert_rules SET activa = $2 WHERE id = $1")
+        .bind(regla_id)
+        .bind(activa)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[command]
+pub async fn delete_alert_rule(regla_id: i32, usuario_id: i32) -> Result<(), String> {
+    let pool = db::pool().await?;
+    regla_del_usuario(pool, regla_id, usuario_id).await?;
+    // El historial de la regla se borra en cascada
+    sqlx::query("DELETE FROM alert_rules WHERE id = $1")
+        .bind(regla_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[command]
+pub async fn get_alert_history(usuario_id: i32, solo_no_leidas: Option<bool>) -> Result<Vec<AlertaDisparada>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT id, regla_id, usuario_id, emisoras, serie, tipo, mensaje, valor, disparada, leida
+         FROM alert_history
+         WHERE usuario_id = $1 AND (NOT $2 OR NOT leida)
+         ORDER BY disparada DESC
+         LIMIT 200",
+    )
+    .bind(usuario_id)
+    .bind(solo_no_leidas.unwrap_or(false))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter()
+        .map(|row| {
+            Ok(AlertaDisparada {
+                id: row.try_get("id")?,
+                regla_id: row.try_get("regla_id")?,
+                usuario_id: row.try_get("usuario_id")?,
+                emisora: row.try_get("emisoras")?,
+                serie: row.try_get("serie")?,
+                tipo: row.try_get("tipo")?,
+                mensaje: row.try_get("mensaje")?,
+                valor: row.try_get("valor")?,
+                disparada: row.try_get("disparada")?,
+                leida: row.try_get("leida")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn mark_alerts_read(usuario_id: i32) -> Result<u64, String> {
+    let pool = db::pool().await?;
+    let resultado = sqlx::query("UPDATE alert_history SET leida = true WHERE usuario_id = $1 AND NOT leida")
+        .bind(usuario_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(resultado.rows_affected())
+}
+
+/// Evalúa las reglas activas en este momento, sin esperar al ciclo. Las de
+/// mercado sólo si la sesión está abierta, como en `correr`.
+#[command]
+pub async fn evaluate_alerts(app: tauri::AppHandle) -> Result<Vec<AlertaDisparada>, String> {
+    let pool = db::pool().await?;
+    let mercado = calendario::is_open(Utc::now());
+    evaluar(pool, mercado, true, &notificador_app(app)).await.map_err(|e| e.to_string())
+}
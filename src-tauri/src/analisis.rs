@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+use crate::periodos::{self, Estado, Periodo, Trimestre};
+use crate::ratios::{dividir, dividir_o, Estados};
+use crate::db;
+
+// --- Análisis: DuPont y crecimiento ---
+// La descomposición DuPont del ROE se hace por trimestre con cifras de doce
+// meses (`periodos::historia_ttm`), para que el ROE sea anual y no dependa de la
+// estacionalidad. Se usa la utilidad neta total y el capital total, de modo que
+// el producto de los factores es exactamente utilidad / capital.
+// Los crecimientos trimestre contra trimestre (QoQ) y contra el mismo trimestre
+// del año anterior (YoY) usan los trimestres discretos; las tasas compuestas
+// (CAGR) usan los ejercicios completos. Todo va como fracción (0.12 = 12%).
+
+/// Partidas a las que se les calcula crecimiento: (clave, estado, columna).
+const METRICAS: [(&str, Estado, &str); 5] = [
+    ("ingresos", Estado::Resultados, "revenue"),
+    ("utilidad_operativa", Estado::Resultados, "profitlossfromoperatingactivities"),
+    ("utilidad_neta", Estado::Resultados, "profitloss"),
+    ("upa", Estado::Resultados, "basicearningslosspershare"),
+    ("flujo_operacion", Estado::Flujos, "flujo_operacion"),
+];
+/// Horizontes de las tasas compuestas, en años.
+const HORIZONTES_CAGR: [i32; 3] = [3, 5, 10];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DupontTres {
+    pub margen_neto: Option<f64>,
+    pub rotacion_activos: Option<f64>,
+    pub multiplicador_capital: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DupontCinco {
+    /// Utilidad neta / utilidad antes de impuestos
+    pub carga_fiscal: Option<f64>,
+    /// Utilidad antes de impuestos / utilidad de operación
+    pub carga_intereses: Option<f64>,
+    pub margen_operativo: Option<f64>,
+    pub rotacion_activos: Option<f64>,
+    pub multiplicador_capital: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DupontTrimestre {
+    pub trimestre: String,
+    pub roe: Option<f64>,
+    pub tres_pasos: DupontTres,
+    pub cinco_pasos: DupontCinco,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Crecimiento {
+    pub valor: Option<f64>,
+    pub qoq: Option<f64>,
+    pub yoy: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CrecimientoTrimestre {
+    pub trimestre: String,
+    pub metricas: BTreeMap<String, Crecimiento>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Cagr {
+    pub metrica: String,
+    pub anios: i32,
+    pub desde: i32,
+    pub hasta: i32,
+    /// Vacío si alguno de los extremos no es positivo
+    pub valor: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnalisisEmisora {
+    pub emisora: String,
+    pub dupont: Vec<DupontTrimestre>,
+    pub crecimiento: Vec<CrecimientoTrimestre>,
+    pub cagr: Vec<Cagr>,
+}
+
+pub fn dupont(trimestre: String, e: &Estados) -> DupontTrimestre {
+    let utilidad = e.r("profitloss");
+    let antes_impuestos = e.r("profitlossbeforetax");
+    let operativa = e.r("profitlossfromoperatingactivities");
+    let ingresos = e.r("revenue");
+    let activos = e.activos_totales();
+    let capital = e.p("equity");
+    let rotacion_activos = dividir_o(ingresos, activos);
+    let multiplicador_capital = dividir_o(activos, capital);
+    DupontTrimestre {
+        trimestre,
+        roe: dividir_o(utilidad, capital),
+        tres_pasos: DupontTres {
+            margen_neto: dividir_o(utilidad, ingresos),
+            rotacion_activos,
+            multiplicador_capital,
+        },
+        cinco_pasos: DupontCinco {
+            carga_fiscal: dividir_o(utilidad, antes_impuestos),
+            carga_intereses: dividir_o(antes_impuestos, operativa),
+            margen_operativo: dividir_o(operativa, ingresos),
+            rotacion_activos,
+            multiplicador_capital,
+        },
+    }
+}
+
+/// Cambio relativo contra una base; con base negativa el signo indica si mejoró.
+fn cambio(actual: f64, base: f64) -> Option<f64> {
+    dividir(actual - base, base.abs())
+}
+
+fn crecimientos(resultados: &[Periodo], flujos: &[Periodo]) -> Vec<CrecimientoTrimestre> {
+    let indice = |serie: &[Periodo]| -> HashMap<Trimestre, HashMap<String, f64>> {
+        serie.iter().map(|p| (p.periodo, p.valores.clone())).collect()
+    };
+    let (por_resultado, por_flujo) = (indice(resultados), indice(flujos));
+    let mut etiquetas: BTreeMap<Trimestre, String> = BTreeMap::new();
+    for p in resultados.iter().chain(flujos.iter()) {
+        etiquetas.entry(p.periodo).or_insert_with(|| p.trimestre.clone());
+    }
+    etiquetas
+        .into_iter()
+        .map(|(t, trimestre)| {
+            let hace_un_anio = Trimestre { anio: t.anio - 1, q: t.q };
+            let metricas = METRICAS
+                .iter()
+                .map(|(clave, estado, columna)| {
+                    let serie = if *estado == Estado::Resultados { &por_resultado } else { &por_flujo };
+                    let valor_en = |t: Trimestre| serie.get(&t).and_then(|v| v.get(*columna)).copied();
+                    let valor = valor_en(t);
+                    let contra = |base: Option<f64>| valor.zip(base).and_then(|(a, b)| cambio(a, b));
+                    (
+                        clave.to_string(),
+                        Crecimiento {
+                            valor,
+                            qoq: contra(valor_en(t.anterior())),
+                            yoy: contra(valor_en(hace_un_anio)),
+                        },
+                    )
+                })
+                .collect();
+            CrecimientoTrimestre { trimestre, metricas }
+        })
+        .collect()
+}
+
+fn cagr(resultados: &[Periodo], flujos: &[Periodo]) -> Vec<Cagr> {
+    let anuales_resultado = periodos::anuales(Estado::Resultados, resultados);
+    let anuales_flujo = periodos::anuales(Estado::Flujos, flujos);
+    let mut tasas = Vec::new();
+    for (clave, estado, columna) in METRICAS {
+        let anuales = if estado == Estado::Resultados { &anuales_resultado } else { &anuales_flujo };
+        let hasta = match anuales.keys().next_back() {
+            Some(anio) => *anio,
+            None => continue,
+        };
+        for anios in HORIZONTES_CAGR {
+            let desde = hasta - anios;
+            let (inicio, fin) = match (anuales.get(&desde).and_then(|v| v.get(columna)), anuales[&hasta].get(columna)) {
+                (Some(inicio), Some(fin)) => (*inicio, *fin),
+                _ => continue,
+            };
+            let valor = (inicio > 0.0 && fin > 0.0).then(|| (fin / inicio).powf(1.0 / anios as f64) - 1.0);
+            tasas.push(Cagr { metrica: clave.to_string(), anios, desde, hasta, valor });
+        }
+    }
+    tasas
+}
+
+/// DuPont de tres y cinco pasos por trimestre (TTM), crecimientos QoQ/YoY de los
+/// trimestres discretos y CAGR de 3, 5 y 10 años. `trimestres` limita la historia
+/// a los más recientes.
+#[command]
+pub async fn get_analysis(emisora: String, trimestres: Option<usize>) -> Result<AnalisisEmisora, String> {
+    let pool = db::pool().await?;
+    let (historia, (_, resultados), (_, flujos)) = tokio::try_join!(
+        periodos::historia_ttm(pool, &emisora),
+        periodos::serie_discreta(pool, &emisora, Estado::Resultados),
+        periodos::serie_discreta(pool, &emisora, Estado::Flujos),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut dupont: Vec<DupontTrimestre> = historia.into_iter().map(|(t, _, e)| self::dupont(t, &e)).collect();
+    let mut crecimiento = crecimientos(&resultados, &flujos);
+    let cagr = cagr(&resultados, &flujos);
+    if let Some(n) = trimestres {
+        dupont.drain(..dupont.len().saturating_sub(n));
+        crecimiento.drain(..crecimiento.len().saturating_sub(n));
+    }
+    Ok(AnalisisEmisora { emisora, dupont, crecimiento, cagr })
+}
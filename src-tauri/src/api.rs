@@ -0,0 +1,550 @@
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::command;
+use tokio::sync::oneshot;
+use crate::{activos, analisis, calendario, calidad_datos, get_data, multiplos, portfolio, portfolio_management, puntajes, ratios, screener, settings, velas};
+
+// --- API HTTP local ---
+// Expone las mismas operaciones que los comandos de Tauri para notebooks y
+// otras herramientas. Sólo escucha en 127.0.0.1 y exige `Authorization: Bearer
+// <token>`; el token se genera al primer uso y se guarda con los demás secretos.
+// Los handlers llaman a los comandos existentes, no tienen SQL propio.
+// Arranca sola si existe `DALIA_API_PORT`; si no, con `start_api_server` o con
+// `dalia-cli serve`.
+
+pub const PUERTO_POR_DEFECTO: u16 = 8765;
+const TOKEN_SECRET: &str = "api_token";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EstadoApi {
+    pub activo: bool,
+    pub puerto: Option<u16>,
+    pub url: Option<String>,
+}
+
+struct Servidor {
+    puerto: u16,
+    apagar: oneshot::Sender<()>,
+}
+
+fn servidor() -> &'static Mutex<Option<Servidor>> {
+    static SERVIDOR: OnceLock<Mutex<Option<Servidor>>> = OnceLock::new();
+    SERVIDOR.get_or_init(|| Mutex::new(None))
+}
+
+fn nuevo_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Token de acceso; se crea y guarda si todavía no existe.
+pub fn token() -> Result<String, String> {
+    match settings::get_secret(TOKEN_SECRET)? {
+        Some(t) if !t.is_empty() => Ok(t),
+        _ => {
+            let t = nuevo_token();
+            settings::set_secret(TOKEN_SECRET, &t)?;
+            Ok(t)
+        }
+    }
+}
+
+/// Comparación en tiempo constante para no filtrar el token por tiempos de respuesta.
+fn mismo_token(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn autenticar(State(token): State<Arc<String>>, req: Request, next: Next) -> Response {
+    let recibido = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match recibido {
+        Some(t) if mismo_token(t, &token) => next.run(req).await,
+        _ => ApiError(StatusCode::UNAUTHORIZED, "Token inválido o ausente".to_string()).into_response(),
+    }
+}
+
+pub struct ApiError(StatusCode, String);
+
+impl From<String> for ApiError {
+    /// Los comandos sólo regresan texto; el código HTTP se deduce del mensaje.
+    fn from(mensaje: String) -> Self {
+        let status = if mensaje.starts_with("Permiso denegado") {
+            StatusCode::FORBIDDEN
+        } else if mensaje.starts_with("No se encontró") || mensaje.starts_with("No existe") {
+            StatusCode::NOT_FOUND
+        } else if mensaje.starts_with("DB connection error") || mensaje == settings::PROVIDER_NOT_CONFIGURED {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        ApiError(status, mensaje)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type Resultado<T> = Result<Json<T>, ApiError>;
+
+#[derive(Deserialize)]
+struct Usuario {
+    usuario_id: i32,
+}
+
+#[derive(Deserialize)]
+struct NuevoPortafolio {
+    usuario_id: i32,
+    nombre: String,
+}
+
+#[derive(Deserialize)]
+struct NuevaTransaccion {
+    usuario_id: i32,
+    #[serde(flatten)]
+    transaccion: portfolio_management::NuevaTransaccion,
+}
+
+#[derive(Deserialize)]
+struct NuevoMovimiento {
+    usuario_id: i32,
+    flow_type: String,
+    amount: f64,
+    flow_date: NaiveDate,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct Busqueda {
+    q: String,
+}
+
+#[derive(Deserialize)]
+struct Financieros {
+    trimestre: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Razones {
+    trimestre: Option<String>,
+    base: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Multiplos {
+    serie: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Analisis {
+    trimestres: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct Screener {
+    expresion: String,
+    orden: Option<String>,
+    descendente: Option<bool>,
+    limite: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct RangoVelas {
+    temporalidad: String,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+struct Efectivo {
+    saldo: f64,
+    movimientos: Vec<portfolio_management::CashFlow>,
+}
+
+async fn portafolios(Query(u): Query<Usuario>) -> Resultado<Vec<portfolio::Portfolio>> {
+    Ok(Json(portfolio::get_portfolios(u.usuario_id).await?))
+}
+
+async fn crear_portafolio(Json(p): Json<NuevoPortafolio>) -> Resultado<portfolio::Portfolio> {
+    Ok(Json(portfolio::create_portfolio(p.usuario_id, p.nombre).await?))
+}
+
+async fn transacciones(Path(id): Path<i32>, Query(u): Query<Usuario>) -> Resultado<Vec<portfolio_management::AssetTransaction>> {
+    Ok(Json(portfolio_management::get_asset_transactions(id, u.usuario_id).await?))
+}
+
+async fn crear_transaccion(Path(id): Path<i32>, Json(t): Json<NuevaTransaccion>) -> Resultado<portfolio_management::AssetTransaction> {
+    Ok(Json(
+        portfolio_management::add_asset_transaction(id, t.usuario_id, t.transaccion).await?,
+    ))
+}
+
+async fn borrar_transaccion(Path(id): Path<i32>, Query(u): Query<Usuario>) -> Resultado<serde_json::Value> {
+    let mensaje = portfolio_management::delete_asset_transaction(id, u.usuario_id).await?;
+    Ok(Json(json!({ "mensaje": mensaje })))
+}
+
+async fn efectivo(Path(id): Path<i32>, Query(u): Query<Usuario>) -> Resultado<Efectivo> {
+    let (saldo, movimientos) = tokio::try_join!(
+        portfolio_management::get_cash_balance(id, u.usuario_id),
+        portfolio_management::get_cash_flow_history(id, u.usuario_id),
+    )?;
+    Ok(Json(Efectivo { saldo, movimientos }))
+}
+
+async fn crear_movimiento(Path(id): Path<i32>, Json(m): Json<NuevoMovimiento>) -> Resultado<portfolio_management::CashFlow> {
+    Ok(Json(
+        portfolio_management::add_cash_movement(id, m.usuario_id, m.flow_type, m.amount, m.flow_date, m.description).await?,
+    ))
+}
+
+async fn posiciones(Path(id): Path<i32>, Query(u): Query<Usuario>) -> Resultado<Vec<portfolio_management::PositionSlot>> {
+    Ok(Json(portfolio_management::get_portfolio_slots(id, u.usuario_id).await?))
+}
+
+async fn pl(Path(id): Path<i32>, Query(u): Query<Usuario>) -> Resultado<Vec<portfolio_management::ProfitLoss>> {
+    Ok(Json(portfolio_management::calculate_portfolio_pl(id, u.usuario_id).await?))
+}
+
+async fn buscar(Query(b): Query<Busqueda>) -> Resultado<Vec<get_data::EmisoraBusqueda>> {
+    Ok(Json(get_data::buscar_emisoras(b.q).await?))
+}
+
+async fn trimestres(Path(emisora): Path<String>) -> Resultado<Vec<String>> {
+    Ok(Json(activos::get_trimestres_disponibles(emisora).await?))
+}
+
+async fn financieros(Path(emisora): Path<String>, Query(f): Query<Financieros>) -> Resultado<serde_json::Value> {
+    let texto = activos::get_emisora_info(emisora, f.trimestre).await?;
+    let valor = serde_json::from_str(&texto).map_err(|e| e.to_string())?;
+    Ok(Json(valor))
+}
+
+async fn razones(Path(emisora): Path<String>, Query(r): Query<Razones>) -> Resultado<Vec<ratios::RatiosTrimestre>> {
+    Ok(Json(ratios::get_ratios(emisora, r.trimestre, r.base).await?))
+}
+
+async fn valuacion(Path(emisora): Path<String>, Query(m): Query<Multiplos>) -> Resultado<multiplos::ValuacionEmisora> {
+    Ok(Json(multiplos::get_multiples(emisora, m.serie).await?))
+}
+
+async fn analisis_emisora(Path(emisora): Path<String>, Query(a): Query<Analisis>) -> Resultado<analisis::AnalisisEmisora> {
+    Ok(Json(analisis::get_analysis(emisora, a.trimestres).await?))
+}
+
+async fn calidad(Path(emisora): Path<String>) -> Resultado<puntajes::PuntajesEmisora> {
+    Ok(Json(puntajes::get_scores(emisora).await?))
+}
+
+async fn hallazgos(Path(emisora): Path<String>, Query(f): Query<Financieros>) -> Resultado<Vec<calidad_datos::Hallazgo>> {
+    Ok(Json(calidad_datos::get_data_quality(emisora, f.trimestre).await?))
+}
+
+async fn filtrar(Query(f): Query<Screener>) -> Resultado<screener::ResultadoScreener> {
+    Ok(Json(screener::run_screen(f.expresion, f.orden, f.descendente, f.limite).await?))
+}
+
+async fn cotizacion(Path(ticker): Path<String>) -> Resultado<activos::AssetDetails> {
+    Ok(Json(activos::get_asset_details(ticker).await?))
+}
+
+async fn velas_ticker(Path(ticker): Path<String>, Query(r): Query<RangoVelas>) -> Resultado<Vec<velas::Vela>> {
+    Ok(Json(velas::get_velas(ticker, r.temporalidad, r.inicio, r.fin).await?))
+}
+
+async fn mercado() -> Json<calendario::EstadoMercado> {
+    Json(calendario::get_market_status())
+}
+
+async fn openapi_json() -> Json<serde_json::Value> {
+    Json(openapi())
+}
+
+pub fn router(token: String) -> Router {
+    let protegidas = Router::new()
+        .route("/portfolios", get(portafolios).post(crear_portafolio))
+        .route("/portfolios/{id}/transactions", get(transacciones).post(crear_transaccion))
+        .route("/transactions/{id}", delete(borrar_transaccion))
+        .route("/portfolios/{id}/cash", get(efectivo).post(crear_movimiento))
+        .route("/portfolios/{id}/holdings", get(posiciones))
+        .route("/portfolios/{id}/pl", get(pl))
+        .route("/emisoras", get(buscar))
+        .route("/emisoras/{emisora}/trimestres", get(trimestres))
+        .route("/emisoras/{emisora}/financials", get(financieros))
+        .route("/emisoras/{emisora}/ratios", get(razones))
+        .route("/emisoras/{emisora}/multiples", get(valuacion))
+        .route("/emisoras/{emisora}/scores", get(calidad))
+        .route("/emisoras/{emisora}/analysis", get(analisis_emisora))
+        .route("/emisoras/{emisora}/quality", get(hallazgos))
+        .route("/screener", get(filtrar))
+        .route("/quotes/{ticker}", get(cotizacion))
+        .route("/candles/{ticker}", get(velas_ticker))
+        .route("/market/status", get(mercado))
+        .layer(middleware::from_fn_with_state(Arc::new(token), autenticar));
+    Router::new()
+        .nest("/api/v1", protegidas)
+        .route("/openapi.json", get(openapi_json))
+}
+
+/// Descripción OpenAPI 3 de las rutas de `router`.
+pub fn openapi() -> serde_json::Value {
+    let usuario = json!({ "name": "usuario_id", "in": "query", "required": true, "schema": { "type": "integer" } });
+    let id = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } });
+    let texto = |nombre: &str, donde: &str, requerido: bool| {
+        json!({ "name": nombre, "in": donde, "required": requerido, "schema": { "type": "string" } })
+    };
+    let ok = |descripcion: &str| json!({ "200": { "description": descripcion, "content": { "application/json": {} } } });
+    let cuerpo = |propiedades: serde_json::Value, requeridas: &[&str]| {
+        json!({ "required": true, "content": { "application/json": { "schema": {
+            "type": "object", "properties": propiedades, "required": requeridas
+        } } } })
+    };
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "DALIA-TRAC API local",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Mismas operaciones que la app de escritorio. Todas las rutas bajo /api/v1 requieren `Authorization: Bearer <token>`."
+        },
+        "servers": [{ "url": format!("http://127.0.0.1:{}", PUERTO_POR_DEFECTO) }],
+        "components": { "securitySchemes": { "token": { "type": "http", "scheme": "bearer" } } },
+        "security": [{ "token": [] }],
+        "paths": {
+            "/api/v1/portfolios": {
+                "get": { "summary": "Portafolios propios y compartidos", "parameters": [usuario], "responses": ok("Lista de portafolios") },
+                "post": {
+                    "summary": "Crea un portafolio",
+                    "requestBody": cuerpo(json!({ "usuario_id": { "type": "integer" }, "nombre": { "type": "string" } }), &["usuario_id", "nombre"]),
+                    "responses": ok("Portafolio creado")
+                }
+            },
+            "/api/v1/portfolios/{id}/transactions": {
+                "get": { "summary": "Transacciones del portafolio", "parameters": [id, usuario], "responses": ok("Transacciones") },
+                "post": {
+                    "summary": "Registra una compra o venta",
+                    "parameters": [id],
+                    "requestBody": cuerpo(json!({
+                        "usuario_id": { "type": "integer" },
+                        "ticker": { "type": "string" },
+                        "transaction_type": { "type": "string", "enum": ["buy", "sell"] },
+                        "quantity": { "type": "number" },
+                        "price": { "type": "number" },
+                        "transaction_date": { "type": "string", "format": "date" },
+                        "use_cash_from_portfolio": { "type": "boolean" }
+                    }), &["usuario_id", "ticker", "transaction_type", "quantity", "price", "transaction_date"]),
+                    "responses": ok("Transacción registrada")
+                }
+            },
+            "/api/v1/transactions/{id}": {
+                "delete": { "summary": "Elimina una transacción", "parameters": [id, usuario], "responses": ok("Mensaje de confirmación") }
+            },
+            "/api/v1/portfolios/{id}/cash": {
+                "get": { "summary": "Saldo y movimientos de efectivo", "parameters": [id, usuario], "responses": ok("Saldo y movimientos") },
+                "post": {
+                    "summary": "Registra un depósito o retiro",
+                    "parameters": [id],
+                    "requestBody": cuerpo(json!({
+                        "usuario_id": { "type": "integer" },
+                        "flow_type": { "type": "string", "enum": ["deposit", "withdrawal"] },
+                        "amount": { "type": "number" },
+                        "flow_date": { "type": "string", "format": "date" },
+                        "description": { "type": "string" }
+                    }), &["usuario_id", "flow_type", "amount", "flow_date"]),
+                    "responses": ok("Movimiento registrado")
+                }
+            },
+            "/api/v1/portfolios/{id}/holdings": {
+                "get": { "summary": "Posiciones abiertas y costo promedio", "parameters": [id, usuario], "responses": ok("Posiciones") }
+            },
+            "/api/v1/portfolios/{id}/pl": {
+                "get": { "summary": "Ganancia/pérdida no realizada", "parameters": [id, usuario], "responses": ok("P&L por posición") }
+            },
+            "/api/v1/emisoras": {
+                "get": { "summary": "Busca emisoras activas por nombre o clave", "parameters": [texto("q", "query", true)], "responses": ok("Coincidencias") }
+            },
+            "/api/v1/emisoras/{emisora}/trimestres": {
+                "get": { "summary": "Trimestres con estados financieros guardados", "parameters": [texto("emisora", "path", true)], "responses": ok("Trimestres") }
+            },
+            "/api/v1/emisoras/{emisora}/financials": {
+                "get": {
+                    "summary": "Estados financieros (los 4 trimestres más recientes si no se indica uno)",
+                    "parameters": [texto("emisora", "path", true), texto("trimestre", "query", false)],
+                    "responses": ok("Estados financieros")
+                }
+            },
+            "/api/v1/emisoras/{emisora}/ratios": {
+                "get": {
+                    "summary": "Razones financieras con fórmula y entradas (los 4 trimestres más recientes si no se indica uno)",
+                    "parameters": [
+                        texto("emisora", "path", true),
+                        texto("trimestre", "query", false),
+                        { "name": "base", "in": "query", "required": false,
+                          "schema": { "type": "string", "enum": ["ttm", "trimestre"], "default": "ttm" } }
+                    ],
+                    "responses": ok("Razones por trimestre")
+                }
+            },
+            "/api/v1/emisoras/{emisora}/multiples": {
+                "get": {
+                    "summary": "Múltiplos de valuación actuales e históricos al cierre de cada trimestre",
+                    "parameters": [texto("emisora", "path", true), texto("serie", "query", false)],
+                    "responses": ok("Múltiplos de valuación")
+                }
+            },
+            "/api/v1/emisoras/{emisora}/scores": {
+                "get": {
+                    "summary": "Piotroski, Altman y Beneish por ejercicio, con componentes y entradas omitidas",
+                    "parameters": [texto("emisora", "path", true)],
+                    "responses": ok("Puntajes por ejercicio")
+                }
+            },
+            "/api/v1/emisoras/{emisora}/analysis": {
+                "get": {
+                    "summary": "DuPont de tres y cinco pasos, crecimientos QoQ/YoY y CAGR",
+                    "parameters": [
+                        texto("emisora", "path", true),
+                        { "name": "trimestres", "in": "query", "required": false, "schema": { "type": "integer" } }
+                    ],
+                    "responses": ok("Análisis de la emisora")
+                }
+            },
+            "/api/v1/emisoras/{emisora}/quality": {
+                "get": {
+                    "summary": "Hallazgos de calidad de datos de los estados financieros",
+                    "parameters": [texto("emisora", "path", true), texto("trimestre", "query", false)],
+                    "responses": ok("Hallazgos por trimestre")
+                }
+            },
+            "/api/v1/screener": {
+                "get": {
+                    "summary": "Emisoras que cumplen una expresión de filtro, p. ej. roe > 15 AND pe < 12",
+                    "parameters": [
+                        texto("expresion", "query", true),
+                        texto("orden", "query", false),
+                        { "name": "descendente", "in": "query", "required": false, "schema": { "type": "boolean", "default": true } },
+                        { "name": "limite", "in": "query", "required": false, "schema": { "type": "integer", "default": 200 } }
+                    ],
+                    "responses": ok("Tabla del screener")
+                }
+            },
+            "/api/v1/quotes/{ticker}": {
+                "get": { "summary": "Cotización y datos del día", "parameters": [texto("ticker", "path", true)], "responses": ok("Cotización") }
+            },
+            "/api/v1/candles/{ticker}": {
+                "get": {
+                    "summary": "Velas OHLC",
+                    "parameters": [
+                        texto("ticker", "path", true),
+                        { "name": "temporalidad", "in": "query", "required": true,
+                          "schema": { "type": "string", "enum": ["1m", "5m", "15m", "1h", "1d", "1w", "1M"] } },
+                        texto("inicio", "query", false),
+                        texto("fin", "query", false)
+                    ],
+                    "responses": ok("Velas")
+                }
+            },
+            "/api/v1/market/status": {
+                "get": { "summary": "Estado del mercado según el calendario de la BMV", "responses": ok("Estado del mercado") }
+            },
+            "/openapi.json": {
+                "get": { "summary": "Esta descripción", "security": [], "responses": ok("Documento OpenAPI") }
+            }
+        }
+    })
+}
+
+/// Levanta el servidor en 127.0.0.1:`puerto` en segundo plano.
+pub async fn iniciar(puerto: u16) -> Result<EstadoApi, String> {
+    if servidor().lock().unwrap().is_some() {
+        return Err("La API local ya está activa".to_string());
+    }
+    let app = router(token()?);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", puerto))
+        .await
+        .map_err(|e| format!("No se pudo abrir el puerto {}: {}", puerto, e))?;
+    let (apagar, apagado) = oneshot::channel::<()>();
+    *servidor().lock().unwrap() = Some(Servidor { puerto, apagar });
+    tokio::spawn(async move {
+        let resultado = axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = apagado.await;
+            })
+            .await;
+        if let Err(e) = resultado {
+            eprintln!("[api] {}", e);
+        }
+    });
+    println!("[api] Escuchando en http://127.0.0.1:{}", puerto);
+    Ok(estado())
+}
+
+pub fn detener() -> EstadoApi {
+    if let Some(s) = servidor().lock().unwrap().take() {
+        let _ = s.apagar.send(());
+    }
+    estado()
+}
+
+pub fn estado() -> EstadoApi {
+    let puerto = servidor().lock().unwrap().as_ref().map(|s| s.puerto);
+    EstadoApi {
+        activo: puerto.is_some(),
+        puerto,
+        url: puerto.map(|p| format!("http://127.0.0.1:{}/api/v1", p)),
+    }
+}
+
+/// Puerto de `DALIA_API_PORT`, si está definido.
+pub fn puerto_configurado() -> Option<u16> {
+    std::env::var("DALIA_API_PORT").ok()?.trim().parse().ok()
+}
+
+#[command]
+pub fn get_api_status() -> EstadoApi {
+    estado()
+}
+
+#[command]
+pub async fn start_api_server(puerto: Option<u16>) -> Result<EstadoApi, String> {
+    iniciar(puerto.unwrap_or(PUERTO_POR_DEFECTO)).await
+}
+
+#[command]
+pub fn stop_api_server() -> EstadoApi {
+    detener()
+}
+
+/// Token para configurar los clientes (se muestra en Configuración).
+#[command]
+pub fn get_api_token() -> Result<String, String> {
+    token()
+}
+
+/// Invalida el token actual. El servidor activo se reinicia para usar el nuevo.
+#[command]
+pub async fn regenerate_api_token() -> Result<String, String> {
+    let t = nuevo_token();
+    settings::set_secret(TOKEN_SECRET, &t)?;
+    let puerto = estado().puerto;
+    if let Some(puerto) = puerto {
+        detener();
+        // Dar tiempo a que el servidor anterior suelte el puerto
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        iniciar(puerto).await?;
+    }
+    Ok(t)
+}
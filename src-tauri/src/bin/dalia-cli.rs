@@ -0,0 +1,343 @@
+// Línea de comandos de administración: las mismas funciones que usa la app,
+// sin la interfaz gráfica, para scripts de operación y tareas por lotes.
+//
+//   dalia-cli migrate [--dry-run]
+//   dalia-cli sync emisoras
+//   dalia-cli backfill prices [--emisora AMX --serie B] [--from 2020-01-01] [--to 2024-12-31]
+//   dalia-cli backfill financials [--emisora WALMEX --emisora AMX]
+//   dalia-cli fetch financials WALMEX 2024Q4
+//   dalia-cli dedupe emisoras [--isin] [--dry-run]
+//   dalia-cli portfolio export 3 [--format csv] [--output cartera.csv]
+//   dalia-cli portfolio clear 3
+//   dalia-cli daemon
+//   dalia-cli serve [--port 8765]
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand, ValueEnum};
+use dalia_trac_lib::clean_emisoras::{self, Criterio};
+use dalia_trac_lib::portfolio_management::{self, PortfolioExport};
+use dalia_trac_lib::{alertas, api, calendario, db, get_data, historicos, ingesta, migraciones, portfolio, scheduler};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+type CliError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Parser)]
+#[command(name = "dalia-cli", about = "Administración y tareas por lotes de dalia-trac")]
+struct Cli {
+    #[command(subcommand)]
+    comando: Comando,
+}
+
+#[derive(Subcommand)]
+enum Comando {
+    /// Aplica las migraciones pendientes del esquema
+    Migrate {
+        /// Sólo lista las migraciones pendientes
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Sincroniza catálogos con el proveedor
+    Sync {
+        #[command(subcommand)]
+        que: Sincronizar,
+    },
+    /// Descarga históricos
+    Backfill {
+        #[command(subcommand)]
+        que: Backfill,
+    },
+    /// Descarga datos puntuales del proveedor
+    Fetch {
+        #[command(subcommand)]
+        que: Fetch,
+    },
+    /// Limpia registros duplicados
+    Dedupe {
+        #[command(subcommand)]
+        que: Dedupe,
+    },
+    /// Operaciones sobre portafolios
+    Portfolio {
+        #[command(subcommand)]
+        que: Portfolio,
+    },
+    /// Corre el programador de tareas y las alertas en primer plano (sin la app)
+    Daemon,
+    /// Sirve la API HTTP local (127.0.0.1) hasta recibir Ctrl+C
+    Serve {
+        #[arg(long, default_value_t = api::PUERTO_POR_DEFECTO)]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand)]
+enum Sincronizar {
+    /// Catálogo de emisoras (`get_ticker`)
+    Emisoras,
+}
+
+#[derive(Subcommand)]
+enum Backfill {
+    /// Barras diarias OHLCV
+    Prices {
+        /// Emisora; sin ella se usan todas las que están en algún portafolio o lista de seguimiento
+        #[arg(long, requires = "serie")]
+        emisora: Option<String>,
+        #[arg(long, requires = "emisora")]
+        serie: Option<String>,
+        /// Fecha inicial (YYYY-MM-DD); sin ella se usa `rangos_historicos`
+        #[arg(long = "from")]
+        desde: Option<NaiveDate>,
+        /// Fecha final (YYYY-MM-DD); por defecto, hoy
+        #[arg(long = "to", requires = "desde")]
+        hasta: Option<NaiveDate>,
+    },
+    /// Estados financieros de todos los trimestres de `rangos_financieros`;
+    /// retoma lo pendiente de la ejecución anterior
+    Financials {
+        /// Emisora (se puede repetir); sin ella se usan todas las activas
+        #[arg(long)]
+        emisora: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Fetch {
+    /// Flujos, resultados y posición financiera de un trimestre
+    Financials {
+        emisora: String,
+        /// Trimestre como lo nombra el proveedor, p. ej. 2024Q4
+        trimestre: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum Dedupe {
+    /// Filas repetidas en `emisoras`
+    Emisoras {
+        /// Duplicados por ISIN en lugar de emisora/serie/ISIN exactos
+        #[arg(long)]
+        isin: bool,
+        /// Sólo lista lo que se borraría
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum Portfolio {
+    /// Transacciones, flujos de efectivo y posiciones
+    Export {
+        portfolio_id: i32,
+        #[arg(long, value_enum, default_value_t = Formato::Json)]
+        format: Formato,
+        /// Archivo de salida; por defecto, la salida estándar
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Borra todos los tickers (y sus transacciones) de un portafolio, sin
+    /// revisar roles: la línea de comandos es sólo para administración
+    Clear { portfolio_id: i32 },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Formato {
+    Json,
+    Csv,
+}
+
+fn campo_csv(valor: &str) -> String {
+    if valor.contains([',', '"', '\n']) {
+        format!("\"{}\"", valor.replace('"', "\"\""))
+    } else {
+        valor.to_string()
+    }
+}
+
+/// Un solo libro en CSV: transacciones y movimientos de efectivo ordenados por fecha.
+fn a_csv(export: &PortfolioExport) -> String {
+    let mut filas: Vec<(NaiveDate, String)> = Vec::new();
+    for t in &export.transacciones {
+        filas.push((
+            t.transaction_date,
+            format!(
+                "{},transaccion,{},{},{},{},{},",
+                t.transaction_date,
+                campo_csv(&t.ticker),
+                t.transaction_type,
+                t.quantity,
+                t.price,
+                t.quantity * t.price
+            ),
+        ));
+    }
+    for f in &export.flujos {
+        filas.push((
+            f.flow_date,
+            format!(
+                "{},efectivo,,{},,,{},{}",
+                f.flow_date,
+                f.flow_type,
+                f.amount,
+                campo_csv(f.description.as_deref().unwrap_or(""))
+            ),
+        ));
+    }
+    filas.sort_by_key(|(fecha, _)| *fecha);
+    let mut csv = String::from("fecha,registro,ticker,tipo,cantidad,precio,monto,descripcion\n");
+    for (_, fila) in filas {
+        csv.push_str(&fila);
+        csv.push('\n');
+    }
+    csv
+}
+
+async fn backfill_prices(
+    pool: &PgPool,
+    emisora: Option<String>,
+    serie: Option<String>,
+    desde: Option<NaiveDate>,
+    hasta: Option<NaiveDate>,
+) -> Result<(), CliError> {
+    let emisoras = match (emisora, serie) {
+        (Some(e), Some(s)) => vec![(e.to_uppercase(), s.to_uppercase())],
+        _ => scheduler::emisoras_seguidas(pool).await?,
+    };
+    let mut total = 0;
+    let mut errores = 0;
+    for (emisora, serie) in &emisoras {
+        let resultado = match desde {
+            Some(desde) => {
+                let hasta = hasta.unwrap_or_else(calendario::hoy);
+                historicos::backfill_rango(pool, emisora, serie, desde, hasta).await
+            }
+            None => historicos::backfill_emisora(pool, emisora, serie).await,
+        };
+        match resultado {
+            Ok(n) => {
+                println!("{}{}: {} barras", emisora, serie, n);
+                total += n;
+            }
+            Err(e) => {
+                eprintln!("{}{}: {}", emisora, serie, e);
+                errores += 1;
+            }
+        }
+    }
+    println!("{} emisoras, {} barras, {} con error", emisoras.len(), total, errores);
+    if errores > 0 {
+        return Err(format!("{} emisoras fallaron", errores).into());
+    }
+    Ok(())
+}
+
+async fn ejecutar(comando: Comando) -> Result<(), CliError> {
+    let pool = db::pool().await?;
+    match comando {
+        Comando::Migrate { dry_run: true } => {
+            let pendientes = migraciones::pendientes(pool).await?;
+            if pendientes.is_empty() {
+                println!("El esquema está al día.");
+            }
+            for v in pendientes {
+                println!("pendiente: {}", v);
+            }
+        }
+        Comando::Migrate { dry_run: false } => {
+            let aplicadas = migraciones::migrar(pool).await?;
+            if aplicadas.is_empty() {
+                println!("El esquema está al día.");
+            }
+            for v in aplicadas {
+                println!("aplicada: {}", v);
+            }
+        }
+        Comando::Sync { que: Sincronizar::Emisoras } => get_data::get_ticker(pool).await?,
+        Comando::Backfill { que: Backfill::Prices { emisora, serie, desde, hasta } } => {
+            calendario::cargar_excepciones(pool).await?;
+            backfill_prices(pool, emisora, serie, desde, hasta).await?;
+        }
+        Comando::Backfill { que: Backfill::Financials { emisora } } => {
+            let notificar: scheduler::Notificador = Arc::new(|p| {
+                println!("{}/{} {} {}", p.actual, p.total, p.estado, p.mensaje.unwrap_or_default());
+            });
+            let emisoras = (!emisora.is_empty()).then_some(emisora);
+            let resumen = ingesta::ingerir(pool, emisoras, &notificar).await?;
+            if resumen.errores > 0 {
+                return Err(format!("{} trimestres fallaron; vuelve a correr el comando para reintentarlos", resumen.errores).into());
+            }
+        }
+        Comando::Fetch { que: Fetch::Financials { emisora, trimestre } } => {
+            get_data::get_flujos_financieros(pool, &emisora, &trimestre).await?;
+            get_data::get_estado_resultado_trimestral(pool, &emisora, &trimestre).await?;
+            get_data::get_posicion_financiera(pool, &emisora, &trimestre).await?;
+            println!("Estados financieros de {} {} guardados.", emisora, trimestre);
+        }
+        Comando::Dedupe { que: Dedupe::Emisoras { isin, dry_run } } => {
+            let criterio = if isin { Criterio::Isin } else { Criterio::Exactos };
+            let duplicados = clean_emisoras::eliminar_duplicados(pool, criterio, dry_run).await?;
+            for d in &duplicados {
+                println!(
+                    "{} {} {} {} (ctid {})",
+                    if dry_run { "se borraría" } else { "borrado" },
+                    d.emisoras.as_deref().unwrap_or("-"),
+                    d.serie.as_deref().unwrap_or("-"),
+                    d.isin.as_deref().unwrap_or("-"),
+                    d.ctid
+                );
+            }
+            println!("{} duplicados{}", duplicados.len(), if dry_run { " (sin cambios)" } else { "" });
+        }
+        Comando::Portfolio { que: Portfolio::Export { portfolio_id, format, output } } => {
+            let mut conn = pool.acquire().await?;
+            let export = portfolio_management::exportar_portafolio(&mut conn, portfolio_id).await?;
+            let contenido = match format {
+                Formato::Json => serde_json::to_string_pretty(&export)?,
+                Formato::Csv => a_csv(&export),
+            };
+            match output {
+                Some(ruta) => std::fs::write(ruta, contenido)?,
+                None => println!("{}", contenido),
+            }
+        }
+        Comando::Portfolio { que: Portfolio::Clear { portfolio_id } } => {
+            let borrados = portfolio::borrar_todos_los_tickers_y_transacciones(pool, portfolio_id).await?;
+            println!("{} tickers del portafolio {} eliminados con sus transacciones.", borrados, portfolio_id);
+        }
+        Comando::Daemon => {
+            calendario::cargar_excepciones(pool).await?;
+            let notificar: scheduler::Notificador = Arc::new(|p| {
+                println!(
+                    "[{}] {} {}/{} {}",
+                    p.job,
+                    p.estado,
+                    p.actual,
+                    p.total,
+                    p.mensaje.unwrap_or_default()
+                );
+            });
+            let avisar: alertas::Notificador = Arc::new(|a| println!("[alerta] usuario {}: {}", a.usuario_id, a.mensaje));
+            tokio::spawn(alertas::correr(avisar));
+            scheduler::correr(notificar).await;
+        }
+        Comando::Serve { port } => {
+            calendario::cargar_excepciones(pool).await?;
+            let estado = api::iniciar(port).await?;
+            println!("API en {} (token: {})", estado.url.unwrap_or_default(), api::token()?);
+            tokio::signal::ctrl_c().await?;
+            api::detener();
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+    if let Err(e) = ejecutar(cli.comando).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
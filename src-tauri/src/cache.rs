@@ -0,0 +1,228 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use dashmap::DashMap;
+use sqlx::Row;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+// --- Caché de respuestas de DataBursatil ---
+// Guarda el texto crudo de cada respuesta, indexado por la URL sin el token.
+// Vive en memoria (DashMap) y, para los endpoints que cambian poco, también en
+// la tabla `api_cache` para sobrevivir reinicios. Una entrada vencida pero
+// dentro de su ventana de "stale" se regresa de inmediato y se refresca en una
+// tarea aparte (stale-while-revalidate).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Cotizaciones,
+    Indices,
+    Divisas,
+    Top,
+    Intradia,
+    Tasas,
+    Emisoras,
+    Financieros,
+}
+
+impl Endpoint {
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            Endpoint::Cotizaciones => "cotizaciones",
+            Endpoint::Indices => "indices",
+            Endpoint::Divisas => "divisas",
+            Endpoint::Top => "top",
+            Endpoint::Intradia => "intradia",
+            Endpoint::Tasas => "tasas",
+            Endpoint::Emisoras => "emisoras",
+            Endpoint::Financieros => "financieros",
+        }
+    }
+
+    /// Momento en que vence una respuesta obtenida en `ahora`.
+    pub fn expira(&self, ahora: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Endpoint::Cotizaciones | Endpoint::Indices | Endpoint::Divisas => ahora + Duration::seconds(30),
+            Endpoint::Top => ahora + Duration::seconds(60),
+            Endpoint::Intradia => ahora + Duration::minutes(5),
+            Endpoint::Tasas => ahora + Duration::hours(1),
+            Endpoint::Emisoras => ahora + Duration::hours(24),
+            // Los estados financieros no cambian hasta que se reporta el siguiente trimestre
+            Endpoint::Financieros => inicio_siguiente_trimestre(ahora),
+        }
+    }
+
+    /// Tiempo después de vencida durante el cual una entrada todavía se sirve
+    /// mientras se refresca en segundo plano.
+    pub fn ventana_stale(&self) -> Duration {
+        match self {
+            Endpoint::Cotizaciones | Endpoint::Indices | Endpoint::Divisas | Endpoint::Top => Duration::minutes(10),
+            Endpoint::Intradia => Duration::minutes(30),
+            Endpoint::Tasas => Duration::hours(12),
+            Endpoint::Emisoras => Duration::days(7),
+            // Sólo cubre unos días tras el cambio de trimestre: después, los
+            // reportes nuevos ya no deben esperar a una revalidación.
+            Endpoint::Financieros => Duration::days(3),
+        }
+    }
+
+    /// Si la respuesta también se guarda en la tabla `api_cache`.
+    pub fn persistente(&self) -> bool {
+        matches!(self, Endpoint::Tasas | Endpoint::Emisoras | Endpoint::Financieros)
+    }
+}
+
+fn inicio_siguiente_trimestre(ahora: DateTime<Utc>) -> DateTime<Utc> {
+    let (anio, mes) = match ahora.month() {
+        1..=3 => (ahora.year(), 4),
+        4..=6 => (ahora.year(), 7),
+        7..=9 => (ahora.year(), 10),
+        _ => (ahora.year() + 1, 1),
+    };
+    let fecha = NaiveDate::from_ymd_opt(anio, mes, 1).expect("fecha de trimestre válida");
+    Utc.from_utc_datetime(&fecha.and_hms_opt(0, 0, 0).expect("medianoche válida"))
+}
+
+#[derive(Debug, Clone)]
+struct Entrada {
+    respuesta: String,
+    expira: DateTime<Utc>,
+}
+
+fn memoria() -> &'static DashMap<String, Entrada> {
+    static CACHE: OnceLock<DashMap<String, Entrada>> = OnceLock::new();
+    CACHE.get_or_init(DashMap::new)
+}
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static STALE_HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// (aciertos frescos, aciertos vencidos servidos, fallos) desde que arrancó la app.
+pub fn estadisticas() -> (u64, u64, u64) {
+    (HITS.load(Ordering::Relaxed), STALE_HITS.load(Ordering::Relaxed), MISSES.load(Ordering::Relaxed))
+}
+
+fn revalidando() -> &'static DashMap<String, ()> {
+    static EN_CURSO: OnceLock<DashMap<String, ()>> = OnceLock::new();
+    EN_CURSO.get_or_init(DashMap::new)
+}
+
+/// Clave de caché: la URL sin el parámetro `token`.
+pub fn clave_de_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => {
+            let params: Vec<&str> = query.split('&').filter(|p| !p.starts_with("token=")).collect();
+            format!("{}?{}", base, params.join("&"))
+        }
+        None => url.to_string(),
+    }
+}
+
+async fn leer_db(clave: &str) -> Option<Entrada> {
+    let pool = crate::db::pool().await.ok()?;
+    let row = sqlx::query("SELECT respuesta, expira FROM api_cache WHERE clave = $1")
+        .bind(clave)
+        .fetch_optional(pool)
+        .await
+        .ok()??;
+    Some(Entrada { respuesta: row.try_get(0).ok()?, expira: row.try_get(1).ok()? })
+}
+
+async fn guardar_db(endpoint: Endpoint, clave: &str, entrada: &Entrada) {
+    let pool = match crate::db::pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            println!("[cache] No se pudo persistir {}: {}", clave, e);
+            return;
+        }
+    };
+    let resultado = sqlx::query(
+        "INSERT INTO api_cache (clave, endpoint, respuesta, guardado, expira) VALUES ($1, $2, $3, now(), $4)
+         ON CONFLICT (clave) DO UPDATE SET respuesta = EXCLUDED.respuesta, guardado = EXCLUDED.guardado, expira = EXCLUDED.expira",
+    )
+    .bind(clave)
+    .bind(endpoint.nombre())
+    .bind(&entrada.respuesta)
+    .bind(entrada.expira)
+    .execute(pool)
+    .await;
+    if let Err(e) = resultado {
+        println!("[cache] No se pudo persistir {}: {}", clave, e);
+    }
+}
+
+/// Sólo se guardan respuestas con datos. Vacías, `[]`, `{}`, `null`, las que no
+/// son JSON y los objetos con `error` (así avisa el proveedor de un token
+/// inválido o una consulta sin datos) se regresan sin guardar, para volver a
+/// pedirlas la siguiente vez.
+fn guardable(respuesta: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(respuesta) {
+        Ok(serde_json::Value::Array(a)) => !a.is_empty(),
+        Ok(serde_json::Value::Object(o)) => !o.is_empty() && !o.contains_key("error"),
+        Ok(serde_json::Value::Null) | Err(_) => false,
+        Ok(_) => true,
+    }
+}
+
+async fn guardar(endpoint: Endpoint, clave: &str, respuesta: String) {
+    if !guardable(&respuesta) {
+        println!("[cache] Respuesta vacía o con error, no se guarda: {}", clave);
+        return;
+    }
+    let entrada = Entrada { respuesta, expira: endpoint.expira(Utc::now()) };
+    if endpoint.persistente() {
+        guardar_db(endpoint, clave, &entrada).await;
+    }
+    memoria().insert(clave.to_string(), entrada);
+}
+
+/// Regresa la respuesta de `url` desde la caché o, si no hay una utilizable,
+/// la obtiene con `fetch` y la guarda.
+pub async fn get_or_fetch<F, Fut>(
+    endpoint: Endpoint,
+    url: &str,
+    fetch: F,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, Box<dyn std::error::Error + Send + Sync>>> + Send + 'static,
+{
+    let clave = clave_de_url(url);
+    let ahora = Utc::now();
+
+    let mut entrada = memoria().get(&clave).map(|e| e.clone());
+    if entrada.is_none() && endpoint.persistente() {
+        entrada = leer_db(&clave).await;
+        if let Some(e) = &entrada {
+            memoria().insert(clave.clone(), e.clone());
+        }
+    }
+
+    if let Some(entrada) = entrada {
+        if entrada.expira > ahora {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(entrada.respuesta);
+        }
+        if entrada.expira + endpoint.ventana_stale() > ahora {
+            // Servir la copia vieja y refrescar en segundo plano (una sola vez por clave)
+            if revalidando().insert(clave.clone(), ()).is_none() {
+                let clave_tarea = clave.clone();
+                tokio::spawn(async move {
+                    match fetch().await {
+                        Ok(respuesta) => guardar(endpoint, &clave_tarea, respuesta).await,
+                        Err(e) => println!("[cache] Error refrescando {}: {}", clave_tarea, e),
+                    }
+                    revalidando().remove(&clave_tarea);
+                });
+            }
+            STALE_HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(entrada.respuesta);
+        }
+    }
+
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let respuesta = fetch().await?;
+    guardar(endpoint, &clave, respuesta.clone()).await;
+    Ok(respuesta)
+}
+
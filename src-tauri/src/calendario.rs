@@ -0,0 +1,200 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::America::Mexico_City;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use tauri::command;
+
+// --- Calendario de la BMV/BIVA ---
+// Ambas bolsas siguen el mismo calendario: sesión de 8:30 a 15:00 hora de la
+// Ciudad de México, sin operación en fines de semana ni en los días inhábiles
+// que publica la CNBV. Los feriados con regla fija se calculan aquí; los cierres
+// extraordinarios y las sesiones reducidas se cargan de `calendario_excepciones`.
+
+const APERTURA: (u32, u32) = (8, 30);
+const CIERRE: (u32, u32) = (15, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Excepcion {
+    Feriado,
+    /// Sesión reducida que cierra a la hora indicada.
+    MediaSesion(NaiveTime),
+}
+
+fn excepciones() -> &'static RwLock<HashMap<NaiveDate, Excepcion>> {
+    static EXCEPCIONES: OnceLock<RwLock<HashMap<NaiveDate, Excepcion>>> = OnceLock::new();
+    EXCEPCIONES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Carga los cierres extraordinarios y medias sesiones de la base de datos.
+pub async fn cargar_excepciones(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let rows = sqlx::query("SELECT fecha, tipo, cierre FROM calendario_excepciones")
+        .fetch_all(pool)
+        .await?;
+    let mut mapa = HashMap::new();
+    for row in rows {
+        let fecha: NaiveDate = row.try_get("fecha")?;
+        let tipo: String = row.try_get("tipo")?;
+        let cierre: Option<NaiveTime> = row.try_get("cierre")?;
+        let excepcion = match (tipo.as_str(), cierre) {
+            ("media_sesion", Some(cierre)) => Excepcion::MediaSesion(cierre),
+            _ => Excepcion::Feriado,
+        };
+        mapa.insert(fecha, excepcion);
+    }
+    let n = mapa.len();
+    *excepciones().write().unwrap() = mapa;
+    Ok(n)
+}
+
+/// Domingo de Pascua (algoritmo anónimo gregoriano).
+fn domingo_de_pascua(anio: i32) -> NaiveDate {
+    let a = anio % 19;
+    let b = anio / 100;
+    let c = anio % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let mes = (h + l - 7 * m + 114) / 31;
+    let dia = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(anio, mes as u32, dia as u32).expect("fecha de Pascua válida")
+}
+
+fn enesimo_lunes(anio: i32, mes: u32, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(anio, mes, Weekday::Mon, n).expect("lunes válido")
+}
+
+/// Días inhábiles con regla fija (LFT y calendario de la CNBV).
+pub fn feriados(anio: i32) -> Vec<NaiveDate> {
+    let fecha = |mes, dia| NaiveDate::from_ymd_opt(anio, mes, dia).expect("feriado válido");
+    let pascua = domingo_de_pascua(anio);
+    let mut dias = vec![
+        fecha(1, 1),                      // Año Nuevo
+        enesimo_lunes(anio, 2, 1),        // Día de la Constitución
+        enesimo_lunes(anio, 3, 3),        // Natalicio de Benito Juárez
+        pascua - Duration::days(3),       // Jueves Santo
+        pascua - Duration::days(2),       // Viernes Santo
+        fecha(5, 1),                      // Día del Trabajo
+        fecha(9, 16),                     // Independencia
+        fecha(11, 2),                     // Día de Muertos
+        enesimo_lunes(anio, 11, 3),       // Revolución Mexicana
+        fecha(12, 12),                    // Día de la Virgen de Guadalupe
+        fecha(12, 25),                    // Navidad
+    ];
+    // Transmisión del Poder Ejecutivo Federal, cada seis años desde 2024
+    if anio >= 2024 && (anio - 2024) % 6 == 0 {
+        dias.push(fecha(10, 1));
+    }
+    dias
+}
+
+fn es_feriado(fecha: NaiveDate) -> bool {
+    if let Some(e) = excepciones().read().unwrap().get(&fecha) {
+        return *e == Excepcion::Feriado;
+    }
+    feriados(fecha.year()).contains(&fecha)
+}
+
+pub fn is_trading_day(fecha: NaiveDate) -> bool {
+    !matches!(fecha.weekday(), Weekday::Sat | Weekday::Sun) && !es_feriado(fecha)
+}
+
+/// Apertura y cierre (hora de la Ciudad de México) de la sesión de `fecha`, si hay.
+pub fn sesion(fecha: NaiveDate) -> Option<(NaiveTime, NaiveTime)> {
+    if !is_trading_day(fecha) {
+        return None;
+    }
+    let apertura = NaiveTime::from_hms_opt(APERTURA.0, APERTURA.1, 0).expect("hora válida");
+    let cierre = match excepciones().read().unwrap().get(&fecha) {
+        Some(Excepcion::MediaSesion(cierre)) => *cierre,
+        _ => NaiveTime::from_hms_opt(CIERRE.0, CIERRE.1, 0).expect("hora válida"),
+    };
+    Some((apertura, cierre))
+}
+
+/// Último día hábil estrictamente anterior a `fecha`.
+pub fn previous_trading_day(fecha: NaiveDate) -> NaiveDate {
+    let mut dia = fecha - Duration::days(1);
+    while !is_trading_day(dia) {
+        dia -= Duration::days(1);
+    }
+    dia
+}
+
+/// `fecha` si es hábil; si no, el día hábil anterior.
+pub fn last_trading_day_on_or_before(fecha: NaiveDate) -> NaiveDate {
+    if is_trading_day(fecha) { fecha } else { previous_trading_day(fecha) }
+}
+
+pub fn next_trading_day(fecha: NaiveDate) -> NaiveDate {
+    let mut dia = fecha + Duration::days(1);
+    while !is_trading_day(dia) {
+        dia += Duration::days(1);
+    }
+    dia
+}
+
+/// Días hábiles entre `inicio` y `fin`, ambos incluidos.
+pub fn trading_days_between(inicio: NaiveDate, fin: NaiveDate) -> Vec<NaiveDate> {
+    inicio
+        .iter_days()
+        .take_while(|d| *d <= fin)
+        .filter(|d| is_trading_day(*d))
+        .collect()
+}
+
+/// Fecha de hoy en la Ciudad de México.
+pub fn hoy() -> NaiveDate {
+    Utc::now().with_timezone(&Mexico_City).date_naive()
+}
+
+pub fn is_open(momento: DateTime<Utc>) -> bool {
+    let local = momento.with_timezone(&Mexico_City);
+    match sesion(local.date_naive()) {
+        Some((apertura, cierre)) => local.time() >= apertura && local.time() < cierre,
+        None => false,
+    }
+}
+
+/// Sesión cuyos datos son los más recientes en `momento`: hoy si ya abrió el
+/// mercado; si no, el día hábil anterior.
+pub fn sesion_vigente(momento: DateTime<Utc>) -> NaiveDate {
+    let local = momento.with_timezone(&Mexico_City);
+    let fecha = local.date_naive();
+    match sesion(fecha) {
+        Some((apertura, _)) if local.time() >= apertura => fecha,
+        _ => previous_trading_day(fecha),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EstadoMercado {
+    pub abierto: bool,
+    pub sesion_vigente: NaiveDate,
+    pub dia_habil_anterior: NaiveDate,
+    pub siguiente_dia_habil: NaiveDate,
+    pub apertura: Option<NaiveTime>,
+    pub cierre: Option<NaiveTime>,
+}
+
+#[command]
+pub fn get_market_status() -> EstadoMercado {
+    let ahora = Utc::now();
+    let vigente = sesion_vigente(ahora);
+    let horario = sesion(hoy());
+    EstadoMercado {
+        abierto: is_open(ahora),
+        sesion_vigente: vigente,
+        dia_habil_anterior: previous_trading_day(vigente),
+        siguiente_dia_habil: next_trading_day(hoy()),
+        apertura: horario.map(|h| h.0),
+        cierre: horario.map(|h| h.1),
+    }
+}
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tauri::command;
+use crate::periodos::{self, Estado, Periodo, Trimestre};
+use crate::db;
+
+// --- Calidad de los estados financieros ---
+// Cada vez que se guarda un estado (`get_data::get_flujos_financieros`,
+// `get_estado_resultado_trimestral`, `get_posicion_financiera`) se validan los
+// tres estados del trimestre con lo que haya guardado y los hallazgos reemplazan
+// a los anteriores en `calidad_datos`. Las reglas:
+// - identidades: activo = pasivo + capital; operación + inversión +
+//   financiamiento (+ efecto cambiario) = cambio en efectivo; utilidad bruta =
+//   ingresos - costo de ventas;
+// - cruzadas: el efectivo final del flujo contra el efectivo de la posición;
+// - signos: partidas que no pueden ser negativas (ingresos del trimestre,
+//   activos, pasivos);
+// - atípicos: una partida estable que se aleja más de `FACTOR_ATIPICO` veces de
+//   la mediana de los trimestres anteriores.
+// Resultados y flujos se comparan en trimestres discretos (ver `periodos`).
+// Una regla sin las partidas que necesita no se evalúa.
+
+/// Diferencia relativa que se tolera en las identidades contables.
+const TOLERANCIA: f64 = 0.01;
+const FACTOR_ATIPICO: f64 = 5.0;
+/// Trimestres anteriores con los que se compara y mínimo para hacerlo.
+const VENTANA_ATIPICOS: usize = 8;
+const MINIMO_ATIPICOS: usize = 4;
+
+/// Partidas que no pueden ser negativas.
+const NO_NEGATIVAS_RESULTADO: [&str; 1] = ["revenue"];
+const NO_NEGATIVAS_POSICION: [&str; 7] = [
+    "currentassets", "currentliabilities", "cashandcashequivalents", "inventories", "liabilities",
+    "propertyplantandequipment", "goodwill",
+];
+/// Partidas que suelen moverse poco de un trimestre a otro.
+const ESTABLES_RESULTADO: [&str; 2] = ["revenue", "costofsales"];
+const ESTABLES_FLUJO: [&str; 1] = ["depreciacion"];
+const ESTABLES_POSICION: [&str; 4] = ["currentassets", "liabilities", "equity", "propertyplantandequipment"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severidad {
+    Advertencia,
+    Error,
+}
+
+impl Severidad {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severidad::Advertencia => "advertencia",
+            Severidad::Error => "error",
+        }
+    }
+
+    fn parse(s: &str) -> Severidad {
+        if s == "error" { Severidad::Error } else { Severidad::Advertencia }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hallazgo {
+    pub emisora: String,
+    pub trimestre: String,
+    /// flujos, resultados o posicion
+    pub estado: String,
+    pub regla: String,
+    /// Columna junto a la que se muestra la advertencia
+    pub partida: Option<String>,
+    pub severidad: Severidad,
+    pub mensaje: String,
+    pub valor: Option<f64>,
+    pub esperado: Option<f64>,
+}
+
+/// Hallazgos que se juntan al validar un trimestre.
+struct Contexto<'a> {
+    emisora: &'a str,
+    trimestre: &'a str,
+    hallazgos: Vec<Hallazgo>,
+}
+
+impl Contexto<'_> {
+    #[allow(clippy::too_many_arguments)]
+    fn agregar(
+        &mut self,
+        estado: &str,
+        regla: &str,
+        partida: Option<&str>,
+        severidad: Severidad,
+        mensaje: String,
+        valor: Option<f64>,
+        esperado: Option<f64>,
+    ) {
+        self.hallazgos.push(Hallazgo {
+            emisora: self.emisora.to_string(),
+            trimestre: self.trimestre.to_string(),
+            estado: estado.to_string(),
+            regla: regla.to_string(),
+            partida: partida.map(|p| p.to_string()),
+            severidad,
+            mensaje,
+            valor,
+            esperado,
+        });
+    }
+
+    /// Identidad `valor ≈ esperado` dentro de `TOLERANCIA`.
+    fn identidad(&mut self, estado: &str, regla: &str, partida: &str, valor: f64, esperado: f64, descripcion: &str) {
+        let escala = valor.abs().max(esperado.abs()).max(1.0);
+        if (valor - esperado).abs() > TOLERANCIA * escala {
+            self.agregar(
+                estado,
+                regla,
+                Some(partida),
+                Severidad::Error,
+                format!("{} no cuadra: {:.0} contra {:.0} esperado", descripcion, valor, esperado),
+                Some(valor),
+                Some(esperado),
+            );
+        }
+    }
+
+    fn no_negativas(&mut self, estado: &str, periodo: &Periodo, partidas: &[&str]) {
+        for partida in partidas {
+            if let Some(v) = periodo.valores.get(*partida).copied().filter(|v| *v < 0.0) {
+                self.agregar(
+                    estado,
+                    "signo",
+                    Some(partida),
+                    Severidad::Advertencia,
+                    format!("{} es negativo ({:.0})", partida, v),
+                    Some(v),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Compara contra la mediana (en valor absoluto) de los trimestres anteriores.
+    fn atipicos(&mut self, estado: &str, serie: &[Periodo], t: Trimestre, partidas: &[&str]) {
+        let actual = match buscar(serie, t) {
+            Some(p) => p,
+            None => return,
+        };
+        for partida in partidas {
+            let valor = match actual.valores.get(*partida) {
+                Some(v) => *v,
+                None => continue,
+            };
+            let mut previos: Vec<f64> = serie
+                .iter()
+                .filter(|p| p.periodo < t)
+                .rev()
+                .filter_map(|p| p.valores.get(*partida).map(|v| v.abs()))
+                .take(VENTANA_ATIPICOS)
+                .collect();
+            if previos.len() < MINIMO_ATIPICOS {
+                continue;
+            }
+            previos.sort_by(|a, b| a.total_cmp(b));
+            let mediana = previos[previos.len() / 2];
+            if mediana <= 0.0 {
+                continue;
+            }
+            let veces = valor.abs() / mediana;
+            if !(1.0 / FACTOR_ATIPICO..=FACTOR_ATIPICO).contains(&veces) {
+                self.agregar(
+                    estado,
+                    "atipico",
+                    Some(partida),
+                    Severidad::Advertencia,
+                    format!("{} es {:.1} veces la mediana de los {} trimestres anteriores", partida, veces, previos.len()),
+                    Some(valor),
+                    Some(mediana),
+                );
+            }
+        }
+    }
+}
+
+fn buscar(serie: &[Periodo], t: Trimestre) -> Option<&Periodo> {
+    serie.iter().find(|p| p.periodo == t)
+}
+
+/// Corre las reglas sobre los estados guardados del trimestre. No escribe nada.
+pub async fn validar(pool: &PgPool, emisora: &str, trimestre: &str) -> Result<Vec<Hallazgo>, sqlx::Error> {
+    let mut ctx = Contexto { emisora, trimestre, hallazgos: Vec::new() };
+    let t = match Trimestre::parse(trimestre) {
+        Some(t) => t,
+        None => return Ok(ctx.hallazgos),
+    };
+    let ((_, resultados), (_, flujos), posiciones) = tokio::try_join!(
+        periodos::serie_discreta(pool, emisora, Estado::Resultados),
+        periodos::serie_discreta(pool, emisora, Estado::Flujos),
+        periodos::posiciones(pool, emisora),
+    )?;
+    // Las identidades y el efectivo final se revisan con las cifras tal como se reportaron
+    let (flujos_reportados, activos) = tokio::try_join!(periodos::serie(pool, emisora, Estado::Flujos), activos_reportados(pool, emisora, trimestre))?;
+
+    if let Some(posicion) = buscar(&posiciones, t) {
+        let p = |c: &str| posicion.valores.get(c).copied();
+        if let (Some(activos), Some(pasivo), Some(capital)) = (activos, p("liabilities"), p("equity")) {
+            ctx.identidad("posicion", "balance", "liabilities", pasivo + capital, activos, "Pasivo + capital contra activo total");
+        }
+        ctx.no_negativas("posicion", posicion, &NO_NEGATIVAS_POSICION);
+    }
+
+    if let Some(flujo) = buscar(&flujos_reportados, t) {
+        let f = |c: &str| flujo.valores.get(c).copied();
+        if let (Some(operacion), Some(inversion), Some(financiamiento), Some(cambio)) =
+            (f("flujo_operacion"), f("flujo_inversion"), f("flujo_financiamiento"), f("cambio_efectivo"))
+        {
+            let suma = operacion + inversion + financiamiento + f("efecto_tc").unwrap_or(0.0);
+            ctx.identidad("flujos", "secciones_flujo", "cambio_efectivo", suma, cambio, "Operación + inversión + financiamiento contra el cambio en efectivo");
+        }
+        let efectivo = buscar(&posiciones, t).and_then(|p| p.valores.get("cashandcashequivalents").copied());
+        if let (Some(final_flujo), Some(efectivo)) = (f("efectivo_final"), efectivo) {
+            ctx.identidad("flujos", "efectivo_final", "efectivo_final", final_flujo, efectivo, "Efectivo final del flujo contra el efectivo de la posición");
+        }
+    }
+
+    if let Some(resultado) = buscar(&resultados, t) {
+        let r = |c: &str| resultado.valores.get(c).copied();
+        if let (Some(ingresos), Some(costo), Some(bruta)) = (r("revenue"), r("costofsales"), r("grossprofit")) {
+            ctx.identidad("resultados", "utilidad_bruta", "grossprofit", bruta, ingresos - costo.abs(), "Utilidad bruta contra ingresos - costo de ventas");
+        }
+        ctx.no_negativas("resultados", resultado, &NO_NEGATIVAS_RESULTADO);
+    }
+
+    ctx.atipicos("resultados", &resultados, t, &ESTABLES_RESULTADO);
+    ctx.atipicos("flujos", &flujos, t, &ESTABLES_FLUJO);
+    ctx.atipicos("posicion", &posiciones, t, &ESTABLES_POSICION);
+    Ok(ctx.hallazgos)
+}
+
+async fn activos_reportados(pool: &PgPool, emisora: &str, trimestre: &str) -> Result<Option<f64>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT assets FROM public.estado_posicion WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2) LIMIT 1",
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| row.try_get::<Option<f64>, _>(0)).transpose()?.flatten())
+}
+
+/// Reemplaza los hallazgos guardados del trimestre.
+pub async fn registrar(pool: &PgPool, emisora: &str, trimestre: &str, hallazgos: &[Hallazgo]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM calidad_datos WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2)")
+        .bind(emisora)
+        .bind(trimestre)
+        .execute(&mut *tx)
+        .await?;
+    for h in hallazgos {
+        sqlx::query(
+            "INSERT INTO calidad_datos (emisora, trimestre, estado, regla, partida, severidad, mensaje, valor, esperado)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(&h.emisora)
+        .bind(&h.trimestre)
+        .bind(&h.estado)
+        .bind(&h.regla)
+        .bind(&h.partida)
+        .bind(h.severidad.as_str())
+        .bind(&h.mensaje)
+        .bind(h.valor)
+        .bind(h.esperado)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Valida y guarda; pensado para llamarse al final de cada ingesta. Un error aquí
+/// sólo se reporta: no debe tirar la ingesta que ya se guardó.
+pub async fn validar_y_registrar(pool: &PgPool, emisora: &str, trimestre: &str) {
+    let resultado = async {
+        let hallazgos = validar(pool, emisora, trimestre).await?;
+        registrar(pool, emisora, trimestre, &hallazgos).await
+    };
+    if let Err(e) = resultado.await {
+        eprintln!("[calidad_datos] Error validando {} {}: {}", emisora, trimestre, e);
+    }
+}
+
+fn desde_fila(row: &sqlx::postgres::PgRow) -> Result<Hallazgo, sqlx::Error> {
+    let severidad: String = row.try_get("severidad")?;
+    Ok(Hallazgo {
+        emisora: row.try_get("emisora")?,
+        trimestre: row.try_get("trimestre")?,
+        estado: row.try_get("estado")?,
+        regla: row.try_get("regla")?,
+        partida: row.try_get("partida")?,
+        severidad: Severidad::parse(&severidad),
+        mensaje: row.try_get("mensaje")?,
+        valor: row.try_get("valor")?,
+        esperado: row.try_get("esperado")?,
+    })
+}
+
+/// Hallazgos guardados de la emisora (de un trimestre si se indica).
+#[command]
+pub async fn get_data_quality(emisora: String, trimestre: Option<String>) -> Result<Vec<Hallazgo>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT emisora, trimestre, estado, regla, partida, severidad, mensaje, valor, esperado
+         FROM calidad_datos
+         WHERE LOWER(emisora) = LOWER($1) AND ($2::text IS NULL OR LOWER(trimestre) = LOWER($2))
+         ORDER BY trimestre, estado, regla, partida",
+    )
+    .bind(&emisora)
+    .bind(&trimestre)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter().map(desde_fila).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Trimestres con al menos uno de los tres estados guardado.
+async fn trimestres_guardados(pool: &PgPool, emisora: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT trimestre FROM public.estado_flujos WHERE LOWER(emisora) = LOWER($1)
+         UNION
+         SELECT trimestre FROM public.estado_resultado_trimestral WHERE LOWER(emisora) = LOWER($1)
+         UNION
+         SELECT trimestre FROM public.estado_posicion WHERE LOWER(emisora) = LOWER($1)
+         ORDER BY trimestre",
+    )
+    .bind(emisora)
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(|row| row.try_get(0)).collect()
+}
+
+/// Vuelve a validar los estados guardados (un trimestre o todos los de la
+/// emisora), por ejemplo después de cambiar las reglas.
+#[command]
+pub async fn validate_statements(emisora: String, trimestre: Option<String>) -> Result<Vec<Hallazgo>, String> {
+    let pool = db::pool().await?;
+    let trimestres = match trimestre {
+        Some(t) => vec![t],
+        None => trimestres_guardados(pool, &emisora).await.map_err(|e| e.to_string())?,
+    };
+    let mut hallazgos = Vec::new();
+    for t in trimestres {
+        let encontrados = validar(pool, &emisora, &t).await.map_err(|e| e.to_string())?;
+        registrar(pool, &emisora, &t, &encontrados).await.map_err(|e| e.to_string())?;
+        hallazgos.extend(encontrados);
+    }
+    Ok(hallazgos)
+}
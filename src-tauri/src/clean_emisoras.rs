@@ -1,28 +1,27 @@
 use std::collections::HashSet;
-use postgres::{Client, NoTls};
+use sqlx::{PgPool, Row};
 
-pub fn eliminar_duplicados_isin(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn eliminar_duplicados_isin(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // 1. Buscar todos los isins duplicados
-    let rows = client.query(
-        "SELECT isin FROM emisoras WHERE isin IS NOT NULL GROUP BY isin HAVING COUNT(*) > 1",
-        &[],
-    )?;
+    let rows = sqlx::query("SELECT isin FROM emisoras WHERE isin IS NOT NULL GROUP BY isin HAVING COUNT(*) > 1")
+        .fetch_all(pool)
+        .await?;
 
     for row in rows {
-        let isin: String = row.get(0);
+        let isin: String = row.try_get(0)?;
         // 2. Obtener los ctid de todas las filas con ese isin
-        let ctids = client.query(
-            "SELECT ctid FROM emisoras WHERE isin = $1",
-            &[&isin],
-        )?;
+        let ctids = sqlx::query("SELECT ctid::text FROM emisoras WHERE isin = $1")
+            .bind(&isin)
+            .fetch_all(pool)
+            .await?;
         // 3. Mantener solo el primer ctid, borrar los demás
         if ctids.len() > 1 {
             for ctid_row in ctids.iter().skip(1) {
-                let ctid: String = ctid_row.get(0);
-                client.execute(
-                    &format!("DELETE FROM emisoras WHERE ctid = '{}'", ctid),
-                    &[],
-                )?;
+                let ctid: String = ctid_row.try_get(0)?;
+                sqlx::query("DELETE FROM emisoras WHERE ctid::text = $1")
+                    .bind(&ctid)
+                    .execute(pool)
+                    .await?;
             }
         }
     }
@@ -30,24 +29,26 @@ pub fn eliminar_duplicados_isin(client: &mut Client) -> Result<(), Box<dyn std::
     Ok(())
 }
 
-pub fn eliminar_duplicados_emisoras_iterativo(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn eliminar_duplicados_emisoras_iterativo(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Obtener todos los registros ordenados por emisoras, serie, isin
-    let rows = client.query(
-        "SELECT ctid, emisoras, serie, isin FROM emisoras ORDER BY emisoras, serie, isin",
-        &[],
-    )?;
+    let rows = sqlx::query("SELECT ctid::text, emisoras, serie, isin FROM emisoras ORDER BY emisoras, serie, isin")
+        .fetch_all(pool)
+        .await?;
     let mut prev_emisoras = String::new();
     let mut prev_serie = String::new();
     let mut prev_isin = String::new();
     let mut first = true;
     for row in &rows {
-        let ctid: String = row.get(0);
-        let emisoras: String = row.get(1);
-        let serie: String = row.get(2);
-        let isin: String = row.get(3);
+        let ctid: String = row.try_get(0)?;
+        let emisoras: String = row.try_get(1)?;
+        let serie: String = row.try_get(2)?;
+        let isin: String = row.try_get(3)?;
         if !first && emisoras == prev_emisoras && serie == prev_serie && isin == prev_isin {
             // Duplicado, borrar
-            client.execute(&format!("DELETE FROM emisoras WHERE ctid = '{}'", ctid), &[])?;
+            sqlx::query("DELETE FROM emisoras WHERE ctid::text = $1")
+                .bind(&ctid)
+                .execute(pool)
+                .await?;
         } else {
             prev_emisoras = emisoras;
             prev_serie = serie;
@@ -59,57 +60,85 @@ pub fn eliminar_duplicados_emisoras_iterativo(client: &mut Client) -> Result<(),
     Ok(())
 }
 
-pub fn eliminar_duplicados_emisoras_hashset(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
-    let rows = client.query(
-        "SELECT ctid, emisoras, serie, isin FROM emisoras",
-        &[],
-    )?;
-    let mut vistos = HashSet::new();
-    for row in &rows {
-        let ctid: String = row.get(0);
-        let emisoras: String = row.get(1);
-        let serie: String = row.get(2);
-        let isin: String = row.get(3);
-        let clave = format!("{}|{}|{}", emisoras.trim(), serie.trim(), isin.trim());
-        if vistos.contains(&clave) {
-            client.execute(
-                &format!("DELETE FROM emisoras WHERE ctid = '{}'", ctid),
-                &[],
-            )?;
-        } else {
-            vistos.insert(clave);
-        }
-    }
+pub async fn eliminar_duplicados_emisoras_hashset(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    eliminar_duplicados(pool, Criterio::Exactos, false).await?;
     println!("Duplicados exactos de emisoras/serie/isin eliminados (HashSet).");
     Ok(())
 }
 
-pub fn eliminar_duplicados_isin_hashset(client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
-    loop {
-        let rows = client.query(
-            "SELECT ctid::text, isin FROM emisoras WHERE isin IS NOT NULL AND TRIM(isin) <> ''",
-            &[],
-        )?;
-        let mut vistos = HashSet::new();
-        let mut eliminados = 0;
-        for row in &rows {
-            let ctid: String = row.get(0);
-            let isin: String = row.get(1);
-            let clave = isin.trim().to_uppercase();
-            if vistos.contains(&clave) {
-                client.execute(
-                    "DELETE FROM emisoras WHERE ctid::text = $1",
-                    &[&ctid],
-                )?;
-                eliminados += 1;
-            } else {
-                vistos.insert(clave);
+pub async fn eliminar_duplicados_isin_hashset(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    eliminar_duplicados(pool, Criterio::Isin, false).await?;
+    println!("Duplicados exactos de ISIN eliminados (HashSet, robusto). Solo se borra si ISIN es igual y no nulo.");
+    Ok(())
+}
+
+/// Qué filas de `emisoras` se consideran la misma.
+#[derive(Debug, Clone, Copy)]
+pub enum Criterio {
+    /// Misma emisora, serie e ISIN (sin espacios de más).
+    Exactos,
+    /// Mismo ISIN no vacío, sin importar mayúsculas.
+    Isin,
+}
+
+#[derive(Debug, Clone)]
+pub struct Duplicado {
+    pub ctid: String,
+    pub emisoras: Option<String>,
+    pub serie: Option<String>,
+    pub isin: Option<String>,
+}
+
+/// Filas sobrantes según `criterio`: de cada grupo se conserva la primera en el
+/// orden físico de la tabla y se regresan las demás.
+pub async fn buscar_duplicados(pool: &PgPool, criterio: Criterio) -> Result<Vec<Duplicado>, sqlx::Error> {
+    let rows = sqlx::query("SELECT ctid::text, emisoras, serie, isin FROM emisoras ORDER BY ctid")
+        .fetch_all(pool)
+        .await?;
+    let mut vistos = HashSet::new();
+    let mut duplicados = Vec::new();
+    for row in &rows {
+        let fila = Duplicado {
+            ctid: row.try_get(0)?,
+            emisoras: row.try_get(1)?,
+            serie: row.try_get(2)?,
+            isin: row.try_get(3)?,
+        };
+        let texto = |v: &Option<String>| v.as_deref().unwrap_or("").trim().to_string();
+        let clave = match criterio {
+            Criterio::Exactos => format!("{}|{}|{}", texto(&fila.emisoras), texto(&fila.serie), texto(&fila.isin)),
+            Criterio::Isin => {
+                let isin = texto(&fila.isin).to_uppercase();
+                if isin.is_empty() {
+                    continue;
+                }
+                isin
             }
-        }
-        if eliminados == 0 {
-            break;
+        };
+        if !vistos.insert(clave) {
+            duplicados.push(fila);
         }
     }
-    println!("Duplicados exactos de ISIN eliminados (HashSet, robusto). Solo se borra si ISIN es igual y no nulo.");
-    Ok(())
+    Ok(duplicados)
+}
+
+/// Borra los duplicados en una sola transacción. Con `dry_run` sólo los regresa.
+pub async fn eliminar_duplicados(
+    pool: &PgPool,
+    criterio: Criterio,
+    dry_run: bool,
+) -> Result<Vec<Duplicado>, Box<dyn std::error::Error + Send + Sync>> {
+    let duplicados = buscar_duplicados(pool, criterio).await?;
+    if dry_run || duplicados.is_empty() {
+        return Ok(duplicados);
+    }
+    let mut tx = pool.begin().await?;
+    for d in &duplicados {
+        sqlx::query("DELETE FROM emisoras WHERE ctid::text = $1")
+            .bind(&d.ctid)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(duplicados)
 }
@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tauri::command;
+use crate::periodos::{self, Estado};
+use crate::ratios::dividir;
+use crate::{db, multiplos};
+
+// --- Flujos descontados (DCF) ---
+// El flujo libre histórico sale de `estado_flujos` (flujo_operacion - |capex|),
+// por ejercicio y de los doce meses más recientes. A partir del flujo base se
+// proyectan una o varias etapas de crecimiento, se agrega un valor terminal de
+// Gordon y todo se descuenta al WACC. Al valor empresa se le resta la deuda
+// neta (deuda financiera menos efectivo) y el interés minoritario de la última
+// posición, y se divide entre las acciones en circulación del catálogo.
+// Todas las tasas van como fracción (0.11 = 11%). Los supuestos se guardan por
+// usuario y emisora.
+
+const WACC_POR_DEFECTO: f64 = 0.11;
+const CRECIMIENTO_TERMINAL_POR_DEFECTO: f64 = 0.03;
+/// Crecimiento de la primera etapa cuando no hay historia con la cual estimarlo.
+const CRECIMIENTO_POR_DEFECTO: f64 = 0.05;
+const CRECIMIENTO_MAXIMO_SEMBRADO: f64 = 0.15;
+const ANIOS_MAXIMOS: u32 = 50;
+/// Desplazamientos de la tabla de sensibilidad alrededor del supuesto base.
+const PASOS_WACC: [f64; 5] = [-0.02, -0.01, 0.0, 0.01, 0.02];
+const PASOS_CRECIMIENTO: [f64; 5] = [-0.01, -0.005, 0.0, 0.005, 0.01];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Etapa {
+    pub anios: u32,
+    pub crecimiento: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SupuestosDcf {
+    pub etapas: Vec<Etapa>,
+    pub wacc: f64,
+    pub crecimiento_terminal: f64,
+    /// Flujo libre del año base; si falta se usa el de los últimos doce meses
+    pub fcf_base: Option<f64>,
+    /// Deuda neta; si falta se toma de la última posición financiera
+    pub deuda_neta: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FcfPeriodo {
+    pub periodo: String,
+    pub flujo_operacion: f64,
+    pub capex: f64,
+    pub fcf: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HistoriaFcf {
+    pub anuales: Vec<FcfPeriodo>,
+    pub ttm: Option<FcfPeriodo>,
+    /// Crecimiento anual compuesto del flujo libre entre el primer y el último ejercicio
+    pub crecimiento_historico: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnioProyectado {
+    pub anio: u32,
+    pub crecimiento: f64,
+    pub fcf: f64,
+    pub factor_descuento: f64,
+    pub valor_presente: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Sensibilidad {
+    pub wacc: Vec<f64>,
+    pub crecimiento_terminal: Vec<f64>,
+    /// Valor por acción; una fila por WACC y una columna por crecimiento terminal.
+    /// Vacío cuando el WACC no supera al crecimiento.
+    pub valores: Vec<Vec<Option<f64>>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValuacionDcf {
+    pub emisora: String,
+    pub serie: String,
+    /// indicados, guardados o por_defecto
+    pub origen_supuestos: String,
+    pub supuestos: SupuestosDcf,
+    pub historia: HistoriaFcf,
+    pub fcf_base: f64,
+    pub proyeccion: Vec<AnioProyectado>,
+    pub valor_terminal: f64,
+    pub valor_presente_terminal: f64,
+    pub valor_empresa: f64,
+    pub deuda_neta: f64,
+    pub interes_minoritario: f64,
+    pub valor_capital: f64,
+    pub acciones: i64,
+    pub valor_por_accion: f64,
+    pub precio: Option<f64>,
+    /// Diferencia entre el valor por acción y el precio, como fracción del precio
+    pub potencial: Option<f64>,
+    pub sensibilidad: Sensibilidad,
+}
+
+/// Resultado de descontar una proyección.
+struct Descuento {
+    anios: Vec<AnioProyectado>,
+    valor_terminal: f64,
+    valor_presente_terminal: f64,
+    valor_empresa: f64,
+}
+
+fn fcf_periodo(periodo: String, valores: &std::collections::HashMap<String, f64>) -> FcfPeriodo {
+    let flujo_operacion = valores.get("flujo_operacion").copied().unwrap_or(0.0);
+    let capex = valores.get("capex").copied().unwrap_or(0.0).abs();
+    FcfPeriodo { periodo, flujo_operacion, capex, fcf: flujo_operacion - capex }
+}
+
+/// Flujo libre por ejercicio completo y de los últimos doce meses.
+pub async fn historia(pool: &PgPool, emisora: &str) -> Result<HistoriaFcf, sqlx::Error> {
+    let (_, discretos) = periodos::serie_discreta(pool, emisora, Estado::Flujos).await?;
+    let anuales: Vec<FcfPeriodo> = periodos::anuales(Estado::Flujos, &discretos)
+        .into_iter()
+        .map(|(anio, valores)| fcf_periodo(anio.to_string(), &valores))
+        .collect();
+    let ttm = discretos.last().and_then(|ultimo| {
+        periodos::ttm(Estado::Flujos, &discretos, ultimo.periodo).map(|valores| fcf_periodo(format!("TTM {}", ultimo.periodo.etiqueta()), &valores))
+    });
+    let crecimiento_historico = match (anuales.first(), anuales.last()) {
+        (Some(primero), Some(ultimo)) if anuales.len() > 1 && primero.fcf > 0.0 && ultimo.fcf > 0.0 => {
+            Some((ultimo.fcf / primero.fcf).powf(1.0 / (anuales.len() - 1) as f64) - 1.0)
+        }
+        _ => None,
+    };
+    Ok(HistoriaFcf { anuales, ttm, crecimiento_historico })
+}
+
+/// Supuestos iniciales: una etapa de cinco años al crecimiento histórico (acotado)
+/// y otra de cinco a la mitad.
+fn supuestos_por_defecto(historia: &HistoriaFcf) -> SupuestosDcf {
+    let crecimiento = historia
+        .crecimiento_historico
+        .map(|c| c.clamp(0.0, CRECIMIENTO_MAXIMO_SEMBRADO))
+        .unwrap_or(CRECIMIENTO_POR_DEFECTO);
+    SupuestosDcf {
+        etapas: vec![
+            Etapa { anios: 5, crecimiento },
+            Etapa { anios: 5, crecimiento: crecimiento / 2.0 },
+        ],
+        wacc: WACC_POR_DEFECTO,
+        crecimiento_terminal: CRECIMIENTO_TERMINAL_POR_DEFECTO,
+        fcf_base: None,
+        deuda_neta: None,
+    }
+}
+
+fn validar(s: &SupuestosDcf) -> Result<(), String> {
+    if s.etapas.is_empty() {
+        return Err("Se necesita al menos una etapa de crecimiento".to_string());
+    }
+    if s.etapas.iter().any(|e| e.anios == 0 || !e.crecimiento.is_finite() || e.crecimiento <= -1.0) {
+        return Err("Cada etapa necesita al menos un año y un crecimiento mayor a -100%".to_string());
+    }
+    let anios: u32 = s.etapas.iter().map(|e| e.anios).sum();
+    if anios > ANIOS_MAXIMOS {
+        return Err(format!("La proyección no puede pasar de {} años", ANIOS_MAXIMOS));
+    }
+    if !(s.wacc > 0.0 && s.wacc < 1.0) {
+        return Err("El WACC debe estar entre 0 y 1 (0.11 = 11%)".to_string());
+    }
+    if !(s.crecimiento_terminal > -1.0 && s.crecimiento_terminal < s.wacc) {
+        return Err("El crecimiento terminal debe ser menor al WACC".to_string());
+    }
+    Ok(())
+}
+
+/// Proyecta el flujo por etapas y lo descuenta. `None` si el WACC no supera al
+/// crecimiento terminal.
+fn descontar(fcf_base: f64, etapas: &[Etapa], wacc: f64, crecimiento_terminal: f64) -> Option<Descuento> {
+    if wacc <= crecimiento_terminal || wacc <= -1.0 {
+        return None;
+    }
+    let mut anios = Vec::new();
+    let mut fcf = fcf_base;
+    let mut anio = 0;
+    for etapa in etapas {
+        for _ in 0..etapa.anios {
+            anio += 1;
+            fcf *= 1.0 + etapa.crecimiento;
+            let factor_descuento = 1.0 / (1.0 + wacc).powi(anio as i32);
+            anios.push(AnioProyectado {
+                anio,
+                crecimiento: etapa.crecimiento,
+                fcf,
+                factor_descuento,
+                valor_presente: fcf * factor_descuento,
+            });
+        }
+    }
+    let valor_terminal = fcf * (1.0 + crecimiento_terminal) / (wacc - crecimiento_terminal);
+    let valor_presente_terminal = valor_terminal * anios.last().map(|a| a.factor_descuento).unwrap_or(1.0);
+    let valor_empresa = anios.iter().map(|a| a.valor_presente).sum::<f64>() + valor_presente_terminal;
+    Some(Descuento { anios, valor_terminal, valor_presente_terminal, valor_empresa })
+}
+
+/// Valor por acción para cada combinación de WACC y crecimiento terminal.
+fn sensibilidad(fcf_base: f64, s: &SupuestosDcf, ajuste: f64, acciones: f64, wacc: Vec<f64>, crecimiento: Vec<f64>) -> Sensibilidad {
+    let valores = wacc
+        .iter()
+        .map(|w| {
+            crecimiento
+                .iter()
+                .map(|g| descontar(fcf_base, &s.etapas, *w, *g).and_then(|d| dividir(d.valor_empresa - ajuste, acciones)))
+                .collect()
+        })
+        .collect();
+    Sensibilidad { wacc, crecimiento_terminal: crecimiento, valores }
+}
+
+/// Deuda financiera menos efectivo, e interés minoritario, de la última posición guardada.
+async fn deuda_y_minoritario(pool: &PgPool, emisora: &str) -> Result<(f64, f64), sqlx::Error> {
+    let posiciones = periodos::posiciones(pool, emisora).await?;
+    let ultima = match posiciones.last() {
+        Some(p) => &p.valores,
+        None => return Ok((0.0, 0.0)),
+    };
+    let p = |columna: &str| ultima.get(columna).copied().unwrap_or(0.0);
+    let deuda = p("othernoncurrentfinancialliabilities") + p("noncurrentleaseliabilities");
+    Ok((deuda - p("cashandcashequivalents"), p("noncontrollinginterests")))
+}
+
+fn desde_fila(row: &sqlx::postgres::PgRow) -> Result<SupuestosDcf, String> {
+    let etapas: String = row.try_get("etapas").map_err(|e| e.to_string())?;
+    Ok(SupuestosDcf {
+        etapas: serde_json::from_str(&etapas).map_err(|e| format!("Etapas guardadas inválidas: {}", e))?,
+        wacc: row.try_get("wacc").map_err(|e| e.to_string())?,
+        crecimiento_terminal: row.try_get("crecimiento_terminal").map_err(|e| e.to_string())?,
+        fcf_base: row.try_get("fcf_base").map_err(|e| e.to_string())?,
+        deuda_neta: row.try_get("deuda_neta").map_err(|e| e.to_string())?,
+    })
+}
+
+async fn supuestos_guardados(pool: &PgPool, usuario_id: i32, emisora: &str) -> Result<Option<SupuestosDcf>, String> {
+    let row = sqlx::query(
+        "SELECT etapas, wacc, crecimiento_terminal, fcf_base, deuda_neta FROM dcf_supuestos
+         WHERE usuario_id = $1 AND UPPER(emisoras) = UPPER($2)",
+    )
+    .bind(usuario_id)
+    .bind(emisora)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    row.as_ref().map(desde_fila).transpose()
+}
+
+#[command]
+pub async fn get_dcf_history(emisora: String) -> Result<HistoriaFcf, String> {
+    let pool = db::pool().await?;
+    historia(pool, &emisora).await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_dcf_assumptions(usuario_id: i32, emisora: String) -> Result<Option<SupuestosDcf>, String> {
+    let pool = db::pool().await?;
+    supuestos_guardados(pool, usuario_id, emisora.trim()).await
+}
+
+/// Guarda los supuestos del usuario para la emisora, reemplazando los anteriores.
+#[command]
+pub async fn save_dcf_assumptions(usuario_id: i32, emisora: String, supuestos: SupuestosDcf) -> Result<SupuestosDcf, String> {
+    validar(&supuestos)?;
+    let pool = db::pool().await?;
+    let (emisora, _, _) = multiplos::acciones(pool, &emisora, None).await?;
+    let etapas = serde_json::to_string(&supuestos.etapas).map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO dcf_supuestos (usuario_id, emisoras, etapas, wacc, crecimiento_terminal, fcf_base, deuda_neta)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (usuario_id, emisoras) DO UPDATE
+             SET etapas = EXCLUDED.etapas, wacc = EXCLUDED.wacc,
+                 crecimiento_terminal = EXCLUDED.crecimiento_terminal,
+                 fcf_base = EXCLUDED.fcf_base, deuda_neta = EXCLUDED.deuda_neta, updated_at = now()",
+    )
+    .bind(usuario_id)
+    .bind(&emisora)
+    .bind(&etapas)
+    .bind(supuestos.wacc)
+    .bind(supuestos.crecimiento_terminal)
+    .bind(supuestos.fcf_base)
+    .bind(supuestos.deuda_neta)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(supuestos)
+}
+
+#[command]
+pub async fn delete_dcf_assumptions(usuario_id: i32, emisora: String) -> Result<(), String> {
+    let pool = db::pool().await?;
+    sqlx::query("DELETE FROM dcf_supuestos WHERE usuario_id = $1 AND UPPER(emisoras) = UPPER($2)")
+        .bind(usuario_id)
+        .bind(emisora.trim())
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Valor intrínseco por acción. Usa los supuestos indicados; si no vienen, los
+/// guardados por el usuario y, si no hay, unos sembrados con la historia.
+/// `wacc` y `crecimiento` fijan los ejes de la tabla de sensibilidad; por
+/// defecto son pasos alrededor del supuesto base.
+#[command]
+pub async fn run_dcf(
+    usuario_id: i32,
+    emisora: String,
+    serie: Option<String>,
+    supuestos: Option<SupuestosDcf>,
+    wacc: Option<Vec<f64>>,
+    crecimiento: Option<Vec<f64>>,
+) -> Result<ValuacionDcf, String> {
+    let pool = db::pool().await?;
+    let (emisora, serie, acciones) = multiplos::acciones(pool, &emisora, serie.as_deref()).await?;
+    let historia = historia(pool, &emisora).await.map_err(|e| e.to_string())?;
+
+    let (supuestos, origen) = match supuestos {
+        Some(s) => (s, "indicados"),
+        None => match supuestos_guardados(pool, usuario_id, &emisora).await? {
+            Some(s) => (s, "guardados"),
+            None => (supuestos_por_defecto(&historia), "por_defecto"),
+        },
+    };
+    validar(&supuestos)?;
+
+    let fcf_base = supuestos
+        .fcf_base
+        .or_else(|| historia.ttm.as_ref().map(|t| t.fcf))
+        .or_else(|| historia.anuales.last().map(|a| a.fcf))
+        .ok_or_else(|| format!("No hay flujos de {} para estimar el flujo libre base; indícalo en los supuestos", emisora))?;
+    let (deuda_guardada, interes_minoritario) = deuda_y_minoritario(pool, &emisora).await.map_err(|e| e.to_string())?;
+    let deuda_neta = supuestos.deuda_neta.unwrap_or(deuda_guardada);
+    let ajuste = deuda_neta + interes_minoritario;
+
+    let descuento = descontar(fcf_base, &supuestos.etapas, supuestos.wacc, supuestos.crecimiento_terminal)
+        .ok_or_else(|| "El crecimiento terminal debe ser menor al WACC".to_string())?;
+    let valor_capital = descuento.valor_empresa - ajuste;
+    let valor_por_accion = valor_capital / acciones as f64;
+
+    let precio = multiplos::precio_actual(pool, &emisora, &serie).await.map_err(|e| e.to_string())?.map(|(_, p)| p);
+    let potencial = precio.and_then(|p| dividir(valor_por_accion - p, p));
+
+    let ejes_wacc = wacc.unwrap_or_else(|| {
+        PASOS_WACC.iter().map(|d| supuestos.wacc + d).filter(|w| *w > 0.0).collect()
+    });
+    let ejes_crecimiento = crecimiento.unwrap_or_else(|| {
+        PASOS_CRECIMIENTO.iter().map(|d| supuestos.crecimiento_terminal + d).collect()
+    });
+    let sensibilidad = sensibilidad(fcf_base, &supuestos, ajuste, acciones as f64, ejes_wacc, ejes_crecimiento);
+
+    Ok(ValuacionDcf {
+        emisora,
+        serie,
+        origen_supuestos: origen.to_string(),
+        supuestos,
+        historia,
+        fcf_base,
+        proyeccion: descuento.anios,
+        valor_terminal: descuento.valor_terminal,
+        valor_presente_terminal: descuento.valor_presente_terminal,
+        valor_empresa: descuento.valor_empresa,
+        deuda_neta,
+        interes_minoritario,
+        valor_capital,
+        acciones,
+        valor_por_accion,
+        precio,
+        potencial,
+        sensibilidad,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cerca(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn descuenta_la_proyeccion_y_el_valor_terminal() {
+        let etapas = [Etapa { anios: 2, crecimiento: 0.10 }];
+        let d = descontar(100.0, &etapas, 0.10, 0.02).unwrap();
+        assert_eq!(d.anios.len(), 2);
+        // Crecer y descontar al 10 % deja el valor presente de cada año en 100
+        for (anio, fcf) in d.anios.iter().zip([110.0, 121.0]) {
+            assert!(cerca(anio.fcf, fcf));
+            assert!(cerca(anio.valor_presente, 100.0));
+        }
+        // Gordon: 121 × 1.02 / (0.10 - 0.02), descontado dos años
+        assert!(cerca(d.valor_terminal, 1542.75));
+        assert!(cerca(d.valor_presente_terminal, 1275.0));
+        assert!(cerca(d.valor_empresa, 1475.0));
+    }
+
+    #[test]
+    fn encadena_las_etapas() {
+        let etapas = [Etapa { anios: 1, crecimiento: 0.5 }, Etapa { anios: 2, crecimiento: -0.1 }];
+        let d = descontar(100.0, &etapas, 0.08, 0.0).unwrap();
+        let crecimientos: Vec<f64> = d.anios.iter().map(|a| a.crecimiento).collect();
+        assert_eq!(crecimientos, [0.5, -0.1, -0.1]);
+        assert_eq!(d.anios.iter().map(|a| a.anio).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(cerca(d.anios[2].fcf, 121.5));
+        assert!(cerca(d.anios[2].factor_descuento, 1.0 / 1.08f64.powi(3)));
+        assert!(cerca(d.valor_terminal, 121.5 / 0.08));
+    }
+
+    #[test]
+    fn sin_etapas_solo_hay_valor_terminal() {
+        let d = descontar(100.0, &[], 0.10, 0.0).unwrap();
+        assert!(d.anios.is_empty());
+        assert!(cerca(d.valor_empresa, 1000.0));
+    }
+
+    #[test]
+    fn wacc_no_mayor_al_crecimiento_no_tiene_valor() {
+        assert!(descontar(100.0, &[], 0.05, 0.05).is_none());
+        assert!(descontar(100.0, &[], 0.03, 0.05).is_none());
+        assert!(descontar(100.0, &[], -1.5, -2.0).is_none());
+    }
+}
@@ -1,13 +1,19 @@
-use chrono::{Local, Datelike, Timelike};
-use postgres::{Client, NoTls};
-use reqwest::blocking::Client as HttpClient;
-use std::collections::HashMap;
+use sqlx::postgres::PgPool;
+use sqlx::Row;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDateTime;
 use tauri::command;
-use chrono::NaiveDate; 
-use dotenv::dotenv;
-use std::env;
+use chrono::{Datelike, NaiveDate};
+use crate::settings;
+use crate::cache::{self, Endpoint};
+use crate::provider_client::{ProviderClient, ProviderDiagnostics};
+use std::sync::OnceLock;
+use crate::db;
+use crate::calendario;
+use crate::calidad_datos;
+use crate::mapeo_xbrl::{self, EstadoXbrl};
+use crate::periodos::Trimestre;
 
 
 
@@ -156,20 +162,30 @@ fn parse_json(s: Option<String>) -> Option<serde_json::Value> {
     s.and_then(|json_str| serde_json::from_str(&json_str).ok())
 }
 
-fn get_api_key() -> String {
-    dotenv().ok();
-    env::var("API_KEY").expect("API_KEY not set in .env")
+/// API key de DataBursatil. Si no hay ninguna configurada regresa un error
+/// "proveedor no configurado" en lugar de tumbar la app.
+pub(crate) fn get_api_key() -> Result<String, String> {
+    settings::load_api_key()
+        .map(|(key, _)| key)
+        .ok_or_else(|| settings::PROVIDER_NOT_CONFIGURED.to_string())
 }
 
-pub fn get_ticker(pg_client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+/// Cliente HTTP único que comparten todos los endpoints del proveedor.
+pub fn provider() -> &'static ProviderClient {
+    static PROVIDER: OnceLock<ProviderClient> = OnceLock::new();
+    PROVIDER.get_or_init(ProviderClient::new)
+}
+
+/// Pide `url` pasando por la caché de respuestas (ver `cache.rs`).
+async fn fetch_cached(endpoint: Endpoint, url: String) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let clave = url.clone();
+    cache::get_or_fetch(endpoint, &clave, move || async move { provider().get_text(&url).await }).await
+}
+
+pub async fn get_ticker(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!("https://api.databursatil.com/v2/emisoras?token={}", api_key);
-    let client = HttpClient::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Emisoras, url).await?;
 
     let map: HashMap<String, serde_json::Value> = serde_json::from_str(&response)?;
     println!("Total tickers recibidos: {}", map.len());
@@ -179,161 +195,16 @@ pub fn get_ticker(pg_client: &mut Client) -> Result<(), Box<dyn std::error::Erro
         println!("Procesando ticker: {}", ticker);
         if let Some(obj) = inner_obj.as_object() {
             for (serie, emisora_val) in obj {
-                if emisora_val.is_array() {
-                    if let Some(arr) = emisora_val.as_array() {
-                        for sub_emisora_val in arr {
-                            if let Some(emisora_obj) = sub_emisora_val.as_object() {
-                                let razon_social = emisora_obj.get("razon_social").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                let isin = emisora_obj.get("isin").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                let bolsa = emisora_obj.get("bolsa").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                let tipo_valor = emisora_obj.get("tipo_valor_descripcion").and_then(|v| v.as_str()).map(|s| s.to_string());
-                                let tipo_valor_id = emisora_obj.get("tipo_valor_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                let estatus = emisora_obj.get("estatus").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                                let acciones_circulacion = emisora_obj.get("acciones_en_circulacion").and_then(|v| v.as_i64());
-                                let rangos_historicos = emisora_obj.get("rango_historicos").and_then(|v| v.as_str()).map(|s| s.to_string());
-                                let rangos_financieros = emisora_obj.get("rango_financieros").and_then(|v| v.as_str()).map(|s| s.to_string());
-                                let dividendos = emisora_obj.get("dividendos").cloned();
-                                let rangos_historicos_str = rangos_historicos.clone();
-                                let rangos_financieros_str = rangos_financieros.clone();
-                                let dividendos_str = dividendos.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default());
-                                let rows = pg_client.query(
-                                    "SELECT 1 FROM emisoras WHERE emisoras = $1 AND serie = $2",
-                                    &[&ticker, &serie],
-                                )?;
-                                if rows.is_empty() {
-                                    match pg_client.execute(
-                                        "INSERT INTO emisoras (
-                                            emisoras, serie, razon_social, isin, bolsa, tipo_valor, tipo_valor_id, estatus,
-                                            acciones_circulacion, rangos_historicos, rangos_financieros, dividendos
-                                        ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12)",
-                                        &[
-                                            &ticker,
-                                            &serie,
-                                            &razon_social,
-                                            &isin,
-                                            &bolsa,
-                                            &tipo_valor,
-                                            &tipo_valor_id,
-                                            &estatus,
-                                            &acciones_circulacion,
-                                            &rangos_historicos_str,
-                                            &rangos_financieros_str,
-                                            &dividendos_str,
-                                        ],
-                                    ) {
-                                        Ok(_) => { println!("Insertado: {} ({})", ticker, serie); guardados += 1; },
-                                        Err(e) => { println!("Error insertando {} ({}): {}\nDatos: {}", ticker, serie, e, serde_json::to_string(&emisora_obj).unwrap_or_default()); errores += 1; },
-                                    }
-                                } else {
-                                    match pg_client.execute(
-                                        "UPDATE emisoras SET
-                                            razon_social = $3,
-                                            isin = $4,
-                                            bolsa = $5,
-                                            tipo_valor = $6,
-                                            tipo_valor_id = $7,
-                                            estatus = $8,
-                                            acciones_circulacion = $9,
-                                            rangos_historicos = $10,
-                                            rangos_financieros = $11,
-                                            dividendos = $12
-                                         WHERE emisoras = $1 AND serie = $2",
-                                        &[
-                                            &ticker,
-                                            &serie,
-                                            &razon_social,
-                                            &isin,
-                                            &bolsa,
-                                            &tipo_valor,
-                                            &tipo_valor_id,
-                                            &estatus,
-                                            &acciones_circulacion,
-                                            &rangos_historicos_str,
-                                            &rangos_financieros_str,
-                                            &dividendos_str,
-                                        ],
-                                    ) {
-                                        Ok(_) => { println!("Actualizado: {} ({})", ticker, serie); guardados += 1; },
-                                        Err(e) => { println!("Error actualizando {} ({}): {}\nDatos: {}", ticker, serie, e, serde_json::to_string(&emisora_obj).unwrap_or_default()); errores += 1; },
-                                    }
-                                }
-                            }
-                        }
-                    }
-                } else if let Some(emisora_obj) = emisora_val.as_object() {
-                    let razon_social = emisora_obj.get("razon_social").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let isin = emisora_obj.get("isin").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let bolsa = emisora_obj.get("bolsa").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let tipo_valor = emisora_obj.get("tipo_valor_descripcion").and_then(|v| v.as_str()).map(|s| s.to_string());
-                    let tipo_valor_id = emisora_obj.get("tipo_valor_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let estatus = emisora_obj.get("estatus").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                    let acciones_circulacion = emisora_obj.get("acciones_en_circulacion").and_then(|v| v.as_i64());
-                    let rangos_historicos = emisora_obj.get("rango_historicos").and_then(|v| v.as_str()).map(|s| s.to_string());
-                    let rangos_financieros = emisora_obj.get("rango_financieros").and_then(|v| v.as_str()).map(|s| s.to_string());
-                    let dividendos = emisora_obj.get("dividendos").cloned();
-                    let rangos_historicos_str = rangos_historicos.clone();
-                    let rangos_financieros_str = rangos_financieros.clone();
-                    let dividendos_str = dividendos.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default());
-                    let rows = pg_client.query(
-                        "SELECT 1 FROM emisoras WHERE emisoras = $1 AND serie = $2",
-                        &[&ticker, &serie],
-                    )?;
-                    if rows.is_empty() {
-                        match pg_client.execute(
-                            "INSERT INTO emisoras (
-                                emisoras, serie, razon_social, isin, bolsa, tipo_valor, tipo_valor_id, estatus,
-                                acciones_circulacion, rangos_historicos, rangos_financieros, dividendos
-                            ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12)",
-                            &[
-                                &ticker,
-                                &serie,
-                                &razon_social,
-                                &isin,
-                                &bolsa,
-                                &tipo_valor,
-                                &tipo_valor_id,
-                                &estatus,
-                                &acciones_circulacion,
-                                &rangos_historicos_str,
-                                &rangos_financieros_str,
-                                &dividendos_str,
-                            ],
-                        ) {
-                            Ok(_) => { println!("Insertado: {} ({})", ticker, serie); guardados += 1; },
-                            Err(e) => { println!("Error insertando {} ({}): {}\nDatos: {}", ticker, serie, e, serde_json::to_string(&emisora_obj).unwrap_or_default()); errores += 1; },
-                        }
-                    } else {
-                        match pg_client.execute(
-                            "UPDATE emisoras SET
-                                razon_social = $3,
-                                isin = $4,
-                                bolsa = $5,
-                                tipo_valor = $6,
-                                tipo_valor_id = $7,
-                                estatus = $8,
-                                acciones_circulacion = $9,
-                                rangos_historicos = $10,
-                                rangos_financieros = $11,
-                                dividendos = $12
-                             WHERE emisoras = $1 AND serie = $2",
-                            &[
-                                &ticker,
-                                &serie,
-                                &razon_social,
-                                &isin,
-                                &bolsa,
-                                &tipo_valor,
-                                &tipo_valor_id,
-                                &estatus,
-                                &acciones_circulacion,
-                                &rangos_historicos_str,
-                                &rangos_financieros_str,
-                                &dividendos_str,
-                            ],
-                        ) {
-                            Ok(_) => { println!("Actualizado: {} ({})", ticker, serie); guardados += 1; },
-                            Err(e) => { println!("Error actualizando {} ({}): {}\nDatos: {}", ticker, serie, e, serde_json::to_string(&emisora_obj).unwrap_or_default()); errores += 1; },
-                        }
+                // Cada serie trae un objeto o una lista de objetos
+                let emisora_objs: Vec<&serde_json::Map<String, serde_json::Value>> = match emisora_val {
+                    serde_json::Value::Array(arr) => arr.iter().filter_map(|v| v.as_object()).collect(),
+                    serde_json::Value::Object(emisora_obj) => vec![emisora_obj],
+                    _ => vec![],
+                };
+                for emisora_obj in emisora_objs {
+                    match guardar_emisora(pool, &ticker, serie, emisora_obj).await {
+                        Ok(accion) => { println!("{}: {} ({})", accion, ticker, serie); guardados += 1; },
+                        Err(e) => { println!("Error guardando {} ({}): {}\nDatos: {}", ticker, serie, e, serde_json::to_string(&emisora_obj).unwrap_or_default()); errores += 1; },
                     }
                 }
             }
@@ -346,8 +217,69 @@ pub fn get_ticker(pg_client: &mut Client) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
-pub fn show_data(pg_client: &mut Client)-> Result<(), Box<dyn std::error::Error>> {
-    for row in pg_client.query(
+async fn guardar_emisora(
+    pool: &PgPool,
+    ticker: &str,
+    serie: &str,
+    emisora_obj: &serde_json::Map<String, serde_json::Value>,
+) -> Result<&'static str, sqlx::Error> {
+    let razon_social = emisora_obj.get("razon_social").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let isin = emisora_obj.get("isin").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let bolsa = emisora_obj.get("bolsa").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let tipo_valor = emisora_obj.get("tipo_valor_descripcion").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let tipo_valor_id = emisora_obj.get("tipo_valor_id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let estatus = emisora_obj.get("estatus").and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let acciones_circulacion = emisora_obj.get("acciones_en_circulacion").and_then(|v| v.as_i64());
+    let rangos_historicos = emisora_obj.get("rango_historicos").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let rangos_financieros = emisora_obj.get("rango_financieros").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let dividendos = emisora_obj.get("dividendos").cloned();
+    let dividendos_str = dividendos.as_ref().map(|v| serde_json::to_string(v).unwrap_or_default());
+
+    let existe = sqlx::query("SELECT 1 FROM emisoras WHERE emisoras = $1 AND serie = $2")
+        .bind(ticker)
+        .bind(serie)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+    let (sql, accion) = if existe {
+        ("UPDATE emisoras SET
+            razon_social = $3,
+            isin = $4,
+            bolsa = $5,
+            tipo_valor = $6,
+            tipo_valor_id = $7,
+            estatus = $8,
+            acciones_circulacion = $9,
+            rangos_historicos = $10,
+            rangos_financieros = $11,
+            dividendos = $12
+         WHERE emisoras = $1 AND serie = $2", "Actualizado")
+    } else {
+        ("INSERT INTO emisoras (
+            emisoras, serie, razon_social, isin, bolsa, tipo_valor, tipo_valor_id, estatus,
+            acciones_circulacion, rangos_historicos, rangos_financieros, dividendos
+        ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12)", "Insertado")
+    };
+    sqlx::query(sql)
+        .bind(ticker)
+        .bind(serie)
+        .bind(razon_social)
+        .bind(isin)
+        .bind(bolsa)
+        .bind(tipo_valor)
+        .bind(tipo_valor_id)
+        .bind(estatus)
+        .bind(acciones_circulacion)
+        .bind(rangos_historicos)
+        .bind(rangos_financieros)
+        .bind(dividendos_str)
+        .execute(pool)
+        .await?;
+    Ok(accion)
+}
+
+pub async fn show_data(pool: &PgPool)-> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for row in sqlx::query(
         "SELECT \
             emisoras, \
             serie, \
@@ -361,20 +293,19 @@ pub fn show_data(pg_client: &mut Client)-> Result<(), Box<dyn std::error::Error>
             rangos_historicos, \
             rangos_financieros, \
             dividendos \
-        FROM emisoras",
-        &[]
-    )? {
+        FROM emisoras"
+    ).fetch_all(pool).await? {
         let emisora = Emisora {
-            razon_social: row.get(2),
-            isin: row.get(3),
-            bolsa: row.get(4),
-            tipo_valor: row.get(5),
-            tipo_valor_id: row.get(6),
-            estatus: row.get(7),
-            acciones_circulacion: row.get(8),
-            rangos_historicos: row.get(9),
-            rangos_financieros: row.get(10),
-            dividendos: parse_json(row.get(11)),
+            razon_social: row.try_get(2)?,
+            isin: row.try_get(3)?,
+            bolsa: row.try_get(4)?,
+            tipo_valor: row.try_get(5)?,
+            tipo_valor_id: row.try_get(6)?,
+            estatus: row.try_get(7)?,
+            acciones_circulacion: row.try_get(8)?,
+            rangos_historicos: row.try_get(9)?,
+            rangos_financieros: row.try_get(10)?,
+            dividendos: parse_json(row.try_get(11)?),
         };
         println!("{:#?}", emisora);
     }
@@ -382,27 +313,28 @@ pub fn show_data(pg_client: &mut Client)-> Result<(), Box<dyn std::error::Error>
     Ok(())
 }
 
-fn construir_url_intradia(emisoras: &[&str], inicio: &str, final_: &str, api_key: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Intervalos que acepta el endpoint de intradía del proveedor.
+pub const INTERVALOS_INTRADIA: [&str; 5] = ["1m", "5m", "15m", "30m", "1h"];
+
+fn construir_url_intradia(emisoras: &[&str], inicio: &str, final_: &str, intervalo: &str, api_key: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     if emisoras.is_empty() {
         return Err("Lista de emisoras vacía".into());
     }
+    if !INTERVALOS_INTRADIA.contains(&intervalo) {
+        return Err(format!("Intervalo inválido '{}'. Usa uno de {:?}", intervalo, INTERVALOS_INTRADIA).into());
+    }
     let emisoras_str = emisoras.join(",");
     let url = format!(
-        "https://api.databursatil.com/v2/intradia?token={}&emisora_serie={}&bolsa=BMV&intervalo=1h&inicio={}&final={}",
-        api_key, emisoras_str, inicio, final_
+        "https://api.databursatil.com/v2/intradia?token={}&emisora_serie={}&bolsa=BMV&intervalo={}&inicio={}&final={}",
+        api_key, emisoras_str, intervalo, inicio, final_
     );
     Ok(url)
 }
 
-pub fn get_intradia(emi: &[&str], ini: &str, fin: &str, pg_client: &mut Client) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
-    let url = construir_url_intradia(emi, ini, fin, &api_key)?;
-    let client = HttpClient::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+pub async fn get_intradia(emi: &[&str], ini: &str, fin: &str, intervalo: &str, pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
+    let url = construir_url_intradia(emi, ini, fin, intervalo, &api_key)?;
+    let response = fetch_cached(Endpoint::Intradia, url).await?;
     
     let map: HashMap<String, serde_json::Value> = serde_json::from_str(&response)?;
     println!("Intradía: {:#?}", map);
@@ -417,13 +349,17 @@ pub fn get_intradia(emi: &[&str], ini: &str, fin: &str, pg_client: &mut Client)
                             "%Y-%m-%d %H:%M:%S"
                         )?;
 
-                        match pg_client.execute(
+                        match sqlx::query(
                             "INSERT INTO intradia_data (emisora, fecha_hora, precio)
                              VALUES ($1, $2, $3)
                              ON CONFLICT (emisora, fecha_hora)
                              DO UPDATE SET precio = EXCLUDED.precio",
-                            &[&ticker, &fecha_hora, &precio],
-                        ) {
+                        )
+                        .bind(&ticker)
+                        .bind(fecha_hora)
+                        .bind(precio)
+                        .execute(pool)
+                        .await {
                             Ok(_) => println!("Datos insertados/actualizados para {} a las {}", ticker, fecha_hora),
                             Err(e) => println!("Error en BD para {}: {}", ticker, e),
                         }
@@ -435,26 +371,16 @@ pub fn get_intradia(emi: &[&str], ini: &str, fin: &str, pg_client: &mut Client)
     Ok(())
 }
 
-pub fn get_cotizaciones(emisora: &str) -> Result<Option<Cotizacion>, Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+pub async fn get_cotizaciones(emisora: &str) -> Result<Option<Cotizacion>, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/cotizaciones?token={}&emisora_serie={}&concepto=p,v,u&bolsa=bmv",
         api_key, emisora
     );
     println!("[get_cotizaciones] Solicitando cotización para ticker: {}", emisora);
-    let client = HttpClient::new();
-
-    let response = match client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send() {
-        Ok(resp) => match resp.text() {
-            Ok(text) => text,
-            Err(e) => {
-                println!("[get_cotizaciones] Error leyendo texto de respuesta para {}: {}", emisora, e);
-                return Ok(None);
-            }
-        },
+
+    let response = match fetch_cached(Endpoint::Cotizaciones, url).await {
+        Ok(text) => text,
         Err(e) => {
             println!("[get_cotizaciones] Error en la petición HTTP para {}: {}", emisora, e);
             return Ok(None);
@@ -521,24 +447,16 @@ pub fn get_cotizaciones(emisora: &str) -> Result<Option<Cotizacion>, Box<dyn std
     Ok(None)
 }
 
-pub fn get_top() -> Result<TopResponse, Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
-    let now = Local::now();
-    let mut fecha = now.date_naive();
-    if now.hour() < 7 {
-        fecha = fecha.pred_opt().expect("No previous date");
-    }
+pub async fn get_top() -> Result<TopResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
+    // Antes de la apertura (o en día inhábil) el top es el de la última sesión
+    let fecha = calendario::sesion_vigente(chrono::Utc::now());
     let fecha_str = fecha.format("%Y-%m-%d").to_string();
     let url = format!(
         "https://api.databursatil.com/v2/top?token={}&variables=suben,bajan,importe,volumen,operaciones&bolsa=BMV&cantidad=5&mercado=local&inicio={fecha}&final={fecha}",
         api_key, fecha = fecha_str
     );
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Top, url).await?;
 
     let map: serde_json::Value = serde_json::from_str(&response)?;
 
@@ -565,142 +483,91 @@ pub fn get_top() -> Result<TopResponse, Box<dyn std::error::Error>> {
 }
 
 #[command]
-pub fn get_top_tauri() -> Result<TopResponse, String> {
-    get_top().map_err(|e| e.to_string())
+pub async fn get_top_tauri() -> Result<TopResponse, String> {
+    get_top().await.map_err(|e| e.to_string())
 }
 
 
 
-pub fn get_flujos_financieros(pg_client: &mut Client, emisora: &str, trimestre: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+/// Trimestre de una llave de periodo de `/financieros`: por la fecha de cierre
+/// que sigue al último `_` o, si no la trae, por la llave misma.
+fn trimestre_de_periodo(periodo: &str) -> Option<Trimestre> {
+    let fecha = periodo.rsplit('_').next().unwrap_or(periodo);
+    match NaiveDate::parse_from_str(fecha, "%Y-%m-%d") {
+        Ok(f) => Some(Trimestre { anio: f.year(), q: (f.month() - 1) / 3 + 1 }),
+        Err(_) => Trimestre::parse(periodo),
+    }
+}
+
+/// Periodos de una respuesta de `/financieros` con la etiqueta con la que se
+/// guarda cada uno. Además del periodo pedido, el proveedor regresa periodos
+/// comparativos; se guardan todos, cada uno bajo su propio trimestre y en el
+/// formato de `trimestre`. Las llaves sin trimestre reconocible se descartan:
+/// si la respuesta no trae el pedido, éste no se guarda (ver `ingesta`).
+fn periodos_respuesta<'a>(
+    valores: &'a serde_json::Map<String, serde_json::Value>,
+    trimestre: &str,
+) -> Vec<(String, &'a str, &'a serde_json::Value)> {
+    let mut llaves: Vec<(&'a String, &'a serde_json::Value)> = valores.iter().collect();
+    llaves.sort_by_key(|(k, _)| *k);
+    // Con llaves repetidas para un trimestre se queda la última en orden
+    let mut por_trimestre: BTreeMap<Trimestre, (&'a str, &'a serde_json::Value)> = BTreeMap::new();
+    for (k, v) in llaves {
+        if let Some(t) = trimestre_de_periodo(k) {
+            por_trimestre.insert(t, (k.as_str(), v));
+        }
+    }
+    por_trimestre
+        .into_iter()
+        .map(|(t, (k, v))| (t.etiqueta_como(trimestre), k, v))
+        .collect()
+}
+
+pub async fn get_flujos_financieros(pool: &PgPool, emisora: &str, trimestre: &str) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/financieros?token={}&emisora={}&periodo={}&financieros=flujos",
         api_key, emisora, trimestre
     );
-    let client = HttpClient::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Financieros, url).await?;
     let map: HashMap<String, serde_json::Value> = serde_json::from_str(&response)?;
-    println!("{:#?}", map);
+    let mut guardados = 0;
     if let Some(serde_json::Value::Object(valores)) = map.get("flujos") {
-        if let Some((periodo, datos)) = valores.iter().max_by_key(|(k, _)| *k) {
-            let get_num = |key: &str| datos.get(key).and_then(|v| v.get(1)).and_then(|v| v.as_f64());
-            let _fecha_sql = Some(trimestre.to_string());
-            let trimestre_sql = trimestre;
-            let flujo_operacion = get_num("cashflowsfromusedinoperatingactivities");
-            let utilidad_neta = get_num("profitloss");
-            let depreciacion = get_num("adjustmentsfordepreciationandamortisationexpense");
-            let cambio_inventarios = get_num("adjustmentsfordecreaseincreaseininventories");
-            let cambio_cxc = get_num("adjustmentsfordecreaseincreaseintradeaccountreceivable");
-            let cambio_cxp = get_num("adjustmentsforincreasedecreaseintradeaccountpayable");
-            let impuestos_pagados = get_num("incometaxespaidrefundclassifiedasoperatingactivities");
-            let intereses_pagados = get_num("interestpaidclassifiedasoperatingactivities");
-            let flujo_inversion = get_num("cashflowsfromusedininvestingactivities");
-            let capex = get_num("purchaseofpropertyplantandequipmentclassifiedasinvestingactivities");
-            let venta_activos = get_num("proceedsfromsalesofpropertyplantandequipmentclassifiedasinvestingactivities");
-            let compra_intangibles = get_num("purchaseofintangibleassetsclassifiedasinvestingactivities");
-            let flujo_financiamiento = get_num("cashflowsfromusedinfinancingactivities");
-            let prestamos_obtenidos = get_num("proceedsfromborrowingsclassifiedasfinancingactivities");
-            let pago_deuda = get_num("repaymentsofborrowingsclassifiedasfinancingactivities");
-            let dividendos_pagados = get_num("dividendspaidclassifiedasfinancingactivities");
-            let recompras = get_num("paymentstoacquireorredeementitysshares");
-            let cambio_efectivo = get_num("increasedecreaseincashandcashequivalents");
-            let efectivo_final = get_num("cashandcashequivalents_ending");
-            let efecto_tc = get_num("effectofexchangeratechangesoncashandcashequivalents");
-            let deterioros = get_num("adjustmentsforimpairmentlossreversalofimpairmentlossrecognisedinprofitorloss");
-            let partidas_no_monetarias = get_num("otheradjustmentsfornoncashitems");
-            let costos_financieros = get_num("adjustmentsforfinancecosts");
-            let fecha = periodo.split('_').last().unwrap_or("");
+        for (etiqueta, periodo, datos) in periodos_respuesta(valores, trimestre) {
+            let trimestre = etiqueta.as_str();
+            let fecha = periodo.rsplit('_').next().unwrap_or("");
             let fecha_sql = match chrono::NaiveDate::parse_from_str(fecha, "%Y-%m-%d") {
                 Ok(date) => Some(date),
-                Err(_) => { return Ok(()); }
+                Err(_) => continue,
             };
-            let query = "INSERT INTO estado_flujos (
-                emisora, trimestre, fecha, flujo_operacion, utilidad_neta, depreciacion, cambio_inventarios, cambio_cxc, cambio_cxp, impuestos_pagados, intereses_pagados,
-                flujo_inversion, capex, venta_activos, compra_intangibles,
-                flujo_financiamiento, prestamos_obtenidos, pago_deuda, dividendos_pagados, recompras,
-                cambio_efectivo, efectivo_final, efecto_tc,
-                deterioros, partidas_no_monetarias, costos_financieros
-            ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
-                $12, $13, $14, $15,
-                $16, $17, $18, $19, $20,
-                $21, $22, $23,
-                $24, $25, $26
-            ) ON CONFLICT (emisora, trimestre) DO UPDATE SET
-                fecha=EXCLUDED.fecha, flujo_operacion=EXCLUDED.flujo_operacion, utilidad_neta=EXCLUDED.utilidad_neta, depreciacion=EXCLUDED.depreciacion, cambio_inventarios=EXCLUDED.cambio_inventarios, cambio_cxc=EXCLUDED.cambio_cxc, cambio_cxp=EXCLUDED.cambio_cxp, impuestos_pagados=EXCLUDED.impuestos_pagados, intereses_pagados=EXCLUDED.intereses_pagados,
-                flujo_inversion=EXCLUDED.flujo_inversion, capex=EXCLUDED.capex, venta_activos=EXCLUDED.venta_activos, compra_intangibles=EXCLUDED.compra_intangibles,
-                flujo_financiamiento=EXCLUDED.flujo_financiamiento, prestamos_obtenidos=EXCLUDED.prestamos_obtenidos, pago_deuda=EXCLUDED.pago_deuda, dividendos_pagados=EXCLUDED.dividendos_pagados, recompras=EXCLUDED.recompras,
-                cambio_efectivo=EXCLUDED.cambio_efectivo, efectivo_final=EXCLUDED.efectivo_final, efecto_tc=EXCLUDED.efecto_tc,
-                deterioros=EXCLUDED.deterioros, partidas_no_monetarias=EXCLUDED.partidas_no_monetarias, costos_financieros=EXCLUDED.costos_financieros";
-            let params: [&(dyn postgres::types::ToSql + Sync); 26] = [
-                &emisora,
-                &trimestre_sql,
-                &fecha_sql,
-                &flujo_operacion,
-                &utilidad_neta,
-                &depreciacion,
-                &cambio_inventarios,
-                &cambio_cxc,
-                &cambio_cxp,
-                &impuestos_pagados,
-                &intereses_pagados,
-                &flujo_inversion,
-                &capex,
-                &venta_activos,
-                &compra_intangibles,
-                &flujo_financiamiento,
-                &prestamos_obtenidos,
-                &pago_deuda,
-                &dividendos_pagados,
-                &recompras,
-                &cambio_efectivo,
-                &efectivo_final,
-                &efecto_tc,
-                &deterioros,
-                &partidas_no_monetarias,
-                &costos_financieros
-            ];
-            let _ = pg_client.execute(query, &params);
+            if !mapeo_xbrl::guardar(pool, EstadoXbrl::Flujos, emisora, trimestre, fecha_sql, datos).await? {
+                continue;
+            }
+            calidad_datos::validar_y_registrar(pool, emisora, trimestre).await;
+            guardados += 1;
         }
     }
-    Ok(())
+    Ok(guardados)
 }
 
 
-pub fn get_estado_resultado_trimestral(
-    pg_client: &mut Client,
+pub async fn get_estado_resultado_trimestral(
+    pool: &PgPool,
     emisora: &str,
     trimestre: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/financieros?token={}&emisora={}&periodo={}&financieros=resultado_trimestre",
         api_key, emisora, trimestre
     );
-    let client = HttpClient::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
-    
+    let response = fetch_cached(Endpoint::Financieros, url).await?;
     let map: HashMap<String, serde_json::Value> = serde_json::from_str(&response)?;
-    println!("{:#?}",map);
-
-    
+    let mut guardados = 0;
     if let Some(serde_json::Value::Object(valores)) = map.get("resultado_trimestre") {
-        if let Some((periodo, datos)) = valores.iter().max_by_key(|(k, _)| *k) {
-            let get_num = |key: &str| -> Option<f64> {
-                datos.get(key)
-                    .and_then(|v| v.get(1))
-                    .and_then(|v| v.as_f64())
-            };
-            
-            let fecha = periodo.split('_').last()
+        for (etiqueta, periodo, datos) in periodos_respuesta(valores, trimestre) {
+            let trimestre = etiqueta.as_str();
+            let fecha = periodo.rsplit('_').next()
                 .and_then(|fecha_str| {
                     if fecha_str.is_empty() {
                         None
@@ -710,123 +577,34 @@ pub fn get_estado_resultado_trimestral(
                         })
                     }
                 });
-            let revenue = get_num("revenue");
-            let grossprofit = get_num("grossprofit");
-            let profitlossfromoperatingactivities = get_num("profitlossfromoperatingactivities");
-            let profitloss = get_num("profitloss");
-            let profitlossbeforetax = get_num("profitlossbeforetax");
-            let costofsales = get_num("costofsales");
-            let distributioncosts = get_num("distributioncost");
-            let administrativeexpense = get_num("administrativeexpense");
-            let financecosts = get_num("financecosts");
-            let financeincome = get_num("financeincome");
-            let incometaxexpensecontinuingoperations = get_num("incometaxexpensecontinuingoperations");
-            let profitlossattributabletoownersofparent = get_num("profitlossattributabletoownersofparent");
-            let basicearningslosspershare = get_num("basicearningslosspershare");
-            let dilutedearningslosspershare = get_num("dilutedearningslosspershare");
-            let otherincome = get_num("otherincome");
-            let shareofprofitlossofassociatesandjointventuresaccountedforusinge = get_num("shareofprofitlossofassociatesandjointventuresaccountedforusinge");
-            let profitlossfromdiscontinuedoperations = get_num("profitlossfromdiscontinuedoperations");
-            let depreciacion = get_num("depreciacion");
-            let fecha_sql = fecha;
-            let query = r#"
-                INSERT INTO estado_resultado_trimestral (
-                    emisora, trimestre, fecha, revenue, grossprofit, 
-                    profitlossfromoperatingactivities, profitloss, profitlossbeforetax, 
-                    costofsales, distributioncosts, administrativeexpense, financecosts, 
-                    financeincome, incometaxexpensecontinuingoperations, 
-                    profitlossattributabletoownersofparent, basicearningslosspershare, 
-                    dilutedearningslosspershare, otherincome, 
-                    shareofprofitlossofassociatesandjointventuresaccountedforusinge,
-                    profitlossfromdiscontinuedoperations, depreciacion
-                ) VALUES (
-                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, 
-                    $16, $17, $18, $19, $20, $21
-                ) 
-                ON CONFLICT (emisora, trimestre) 
-                DO UPDATE SET
-                    fecha = EXCLUDED.fecha,
-                    revenue = EXCLUDED.revenue,
-                    grossprofit = EXCLUDED.grossprofit,
-                    profitlossfromoperatingactivities = EXCLUDED.profitlossfromoperatingactivities,
-                    profitloss = EXCLUDED.profitloss,
-                    profitlossbeforetax = EXCLUDED.profitlossbeforetax,
-                    costofsales = EXCLUDED.costofsales,
-                    distributioncosts = EXCLUDED.distributioncosts,
-                    administrativeexpense = EXCLUDED.administrativeexpense,
-                    financecosts = EXCLUDED.financecosts,
-                    financeincome = EXCLUDED.financeincome,
-                    incometaxexpensecontinuingoperations = EXCLUDED.incometaxexpensecontinuingoperations,
-                    profitlossattributabletoownersofparent = EXCLUDED.profitlossattributabletoownersofparent,
-                    basicearningslosspershare = EXCLUDED.basicearningslosspershare,
-                    dilutedearningslosspershare = EXCLUDED.dilutedearningslosspershare,
-                    otherincome = EXCLUDED.otherincome,
-                    shareofprofitlossofassociatesandjointventuresaccountedforusinge = EXCLUDED.shareofprofitlossofassociatesandjointventuresaccountedforusinge,
-                    profitlossfromdiscontinuedoperations = EXCLUDED.profitlossfromdiscontinuedoperations,
-                    depreciacion = EXCLUDED.depreciacion
-            "#;
-
-            pg_client.execute(
-                query,
-                &[
-                    &emisora,
-                    &trimestre,
-                    &fecha_sql, 
-                    &revenue,
-                    &grossprofit,
-                    &profitlossfromoperatingactivities,
-                    &profitloss,
-                    &profitlossbeforetax,
-                    &costofsales,
-                    &distributioncosts,
-                    &administrativeexpense,
-                    &financecosts,
-                    &financeincome,
-                    &incometaxexpensecontinuingoperations,
-                    &profitlossattributabletoownersofparent,
-                    &basicearningslosspershare,
-                    &dilutedearningslosspershare,
-                    &otherincome,
-                    &shareofprofitlossofassociatesandjointventuresaccountedforusinge,
-                    &profitlossfromdiscontinuedoperations,
-                    &depreciacion,
-                ],
-            )?;
+            if !mapeo_xbrl::guardar(pool, EstadoXbrl::Resultados, emisora, trimestre, fecha, datos).await? {
+                continue;
+            }
+            calidad_datos::validar_y_registrar(pool, emisora, trimestre).await;
+            guardados += 1;
         }
     }
-    Ok(())
+    Ok(guardados)
 }
 
 
-pub fn get_posicion_financiera(
-    pg_client: &mut Client,
+pub async fn get_posicion_financiera(
+    pool: &PgPool,
     emisora: &str,
     trimestre: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/financieros?token={}&emisora={}&periodo={}&financieros=posicion",
         api_key, emisora, trimestre
     );
-    let client = HttpClient::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
-    
+    let response = fetch_cached(Endpoint::Financieros, url).await?;
     let map: HashMap<String, serde_json::Value> = serde_json::from_str(&response)?;
-    println!("{:#?}",map);
-
+    let mut guardados = 0;
     if let Some(serde_json::Value::Object(valores)) = map.get("posicion") {
-        if let Some((periodo, datos)) = valores.iter().max_by_key(|(k, _)| *k) {
-            let get_num = |key: &str| -> Option<f64> {
-                datos.get(key)
-                    .and_then(|v| v.get(1))
-                    .and_then(|v| v.as_f64())
-            };
-            
-            let fecha = periodo.split('_').last()
+        for (etiqueta, periodo, datos) in periodos_respuesta(valores, trimestre) {
+            let trimestre = etiqueta.as_str();
+            let fecha = periodo.rsplit('_').next()
                 .and_then(|fecha_str| {
                     if fecha_str.is_empty() {
                         None
@@ -834,167 +612,70 @@ pub fn get_posicion_financiera(
                         NaiveDate::parse_from_str(fecha_str, "%Y-%m-%d").ok()
                     }
                 });
-            let currentassets = get_num("currentassets");
-            let currentliabilities = get_num("currentliabilities");
-            let cashandcashequivalents = get_num("cashandcashequivalents");
-            let inventories = get_num("inventories");
-            let tradeandothercurrentreceivables = get_num("tradeandothercurrentreceivables");
-            let tradeandothercurrentpayables = get_num("tradeandothercurrentpayables");
-            let equity = get_num("equity");
-            let liabilities = get_num("liabilities");
-            let noncurrentliabilities = get_num("noncurrentliabilities");
-            let equityattributabletoownersofparent = get_num("equityattributabletoownersofparent");
-            let noncontrollinginterests = get_num("noncontrollinginterests");
-            let propertyplantandequipment = get_num("propertyplantandequipment");
-            let intangibleassetsotherthangoodwill = get_num("intangibleassetsotherthangoodwill");
-            let goodwill = get_num("goodwill");
-            let rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty = get_num("rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty");
-            let deferredtaxassets = get_num("deferredtaxassets");
-            let deferredtaxliabilities = get_num("deferredtaxliabilities");
-            let noncurrentassetsordisposalgroupsclassifiedasheldforsale = get_num("noncurrentassetsordisposalgroupsclassifiedasheldforsale");
-            let retainedearnings = get_num("retainedearnings");
-            let issuedcapital = get_num("issuedcapital");
-            let otherreserves = get_num("otherreserves");
-            let noncurrentleaseliabilities = get_num("noncurrentleaseliabilities");
-            let othernoncurrentfinancialliabilities = get_num("othernoncurrentfinancialliabilities");
-            let noncurrentprovisionsforemployeebenefits = get_num("noncurrentprovisionsforemployeebenefits");
-            let query = r#"
-                INSERT INTO estado_posicion (
-                    emisora, trimestre, fecha, currentassets, currentliabilities, cashandcashequivalents, inventories, tradeandothercurrentreceivables, tradeandothercurrentpayables, equity, liabilities, noncurrentliabilities, equityattributabletoownersofparent, noncontrollinginterests, propertyplantandequipment, intangibleassetsotherthangoodwill, goodwill, rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty, deferredtaxassets, deferredtaxliabilities, noncurrentassetsordisposalgroupsclassifiedasheldforsale, retainedearnings, issuedcapital, otherreserves, noncurrentleaseliabilities, othernoncurrentfinancialliabilities, noncurrentprovisionsforemployeebenefits
-                ) VALUES (
-                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27
-                )
-                ON CONFLICT (emisora, trimestre)
-                DO UPDATE SET
-                    fecha = EXCLUDED.fecha,
-                    currentassets = EXCLUDED.currentassets,
-                    currentliabilities = EXCLUDED.currentliabilities,
-                    cashandcashequivalents = EXCLUDED.cashandcashequivalents,
-                    inventories = EXCLUDED.inventories,
-                    tradeandothercurrentreceivables = EXCLUDED.tradeandothercurrentreceivables,
-                    tradeandothercurrentpayables = EXCLUDED.tradeandothercurrentpayables,
-                    equity = EXCLUDED.equity,
-                    liabilities = EXCLUDED.liabilities,
-                    noncurrentliabilities = EXCLUDED.noncurrentliabilities,
-                    equityattributabletoownersofparent = EXCLUDED.equityattributabletoownersofparent,
-                    noncontrollinginterests = EXCLUDED.noncontrollinginterests,
-                    propertyplantandequipment = EXCLUDED.propertyplantandequipment,
-                    intangibleassetsotherthangoodwill = EXCLUDED.intangibleassetsotherthangoodwill,
-                    goodwill = EXCLUDED.goodwill,
-                    rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty = EXCLUDED.rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty,
-                    deferredtaxassets = EXCLUDED.deferredtaxassets,
-                    deferredtaxliabilities = EXCLUDED.deferredtaxliabilities,
-                    noncurrentassetsordisposalgroupsclassifiedasheldforsale = EXCLUDED.noncurrentassetsordisposalgroupsclassifiedasheldforsale,
-                    retainedearnings = EXCLUDED.retainedearnings,
-                    issuedcapital = EXCLUDED.issuedcapital,
-                    otherreserves = EXCLUDED.otherreserves,
-                    noncurrentleaseliabilities = EXCLUDED.noncurrentleaseliabilities,
-                    othernoncurrentfinancialliabilities = EXCLUDED.othernoncurrentfinancialliabilities,
-                    noncurrentprovisionsforemployeebenefits = EXCLUDED.noncurrentprovisionsforemployeebenefits
-            "#;
-
-            pg_client.execute(
-                query,
-                &[&emisora, 
-                &trimestre,
-                 &fecha, 
-                 &currentassets, 
-                 &currentliabilities, 
-                 &cashandcashequivalents, 
-                 &inventories, &tradeandothercurrentreceivables, 
-                 &tradeandothercurrentpayables, 
-                 &equity, 
-                 &liabilities, 
-                 &noncurrentliabilities, 
-                 &equityattributabletoownersofparent, 
-                 &noncontrollinginterests, 
-                 &propertyplantandequipment, 
-                 &intangibleassetsotherthangoodwill, 
-                 &goodwill, 
-                 &rightofuseassetsthatdonotmeetdefinitionofinvestmentproperty, 
-                 &deferredtaxassets, 
-                 &deferredtaxliabilities, 
-                 &noncurrentassetsordisposalgroupsclassifiedasheldforsale, 
-                 &retainedearnings, 
-                 &issuedcapital, 
-                 &otherreserves, 
-                 &noncurrentleaseliabilities, 
-                 &othernoncurrentfinancialliabilities, 
-                 &noncurrentprovisionsforemployeebenefits],
-            )?;
+            if !mapeo_xbrl::guardar(pool, EstadoXbrl::Posicion, emisora, trimestre, fecha, datos).await? {
+                continue;
+            }
+            calidad_datos::validar_y_registrar(pool, emisora, trimestre).await;
+            guardados += 1;
         }
     }
-    Ok(())
+    Ok(guardados)
 }
 
 
-pub fn get_indices() -> Result<IndicesResponse, Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+pub async fn get_indices() -> Result<IndicesResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/indices?token={}&ticker=IPC,FTSEBIVA,SP500,DJIA",
         api_key
     );
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Indices, url).await?;
     let indices: IndicesResponse = serde_json::from_str(&response)?;
     Ok(indices)
 }
 
 #[command]
-pub fn get_indices_tauri() -> Result<IndicesResponse, String> {
-    get_indices().map_err(|e| e.to_string())
+pub async fn get_indices_tauri() -> Result<IndicesResponse, String> {
+    get_indices().await.map_err(|e| e.to_string())
 }
 
-pub fn get_tasas_struct() -> Result<TasasResponse, Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+pub async fn get_tasas_struct() -> Result<TasasResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/tasas?token={}",
         api_key
     );
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Tasas, url).await?;
     let tasas: TasasResponse = serde_json::from_str(&response)?;
     Ok(tasas)
 }
 
 
 
-pub fn get_forex() -> Result<ForexResponse, Box<dyn std::error::Error>> {
-    let api_key = get_api_key();
+pub async fn get_forex() -> Result<ForexResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_api_key()?;
     let url = format!(
         "https://api.databursatil.com/v2/divisas?token={}&ticker=USDMXN,EURMXN",
         api_key
     );
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (X11; Linux x86_64)")
-        .send()?
-        .text()?;
+    let response = fetch_cached(Endpoint::Divisas, url).await?;
     let forex: ForexResponse = serde_json::from_str(&response)?;
     Ok(forex)
 }
 
 #[command]
-pub fn get_forex_tauri() -> Result<ForexResponse, String> {
-    get_forex().map_err(|e| e.to_string())
+pub fn get_provider_diagnostics() -> ProviderDiagnostics {
+    provider().diagnostics()
 }
 
 #[command]
-pub fn buscar_emisoras(query: String) -> Result<Vec<EmisoraBusqueda>, String> {
-    use postgres::{Client, NoTls};
-    let mut client = Client::connect(
-        "host=localhost user=garden_admin password=password dbname=dalia_db",
-        NoTls,
-    ).map_err(|e| e.to_string())?;
+pub async fn get_forex_tauri() -> Result<ForexResponse, String> {
+    get_forex().await.map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn buscar_emisoras(query: String) -> Result<Vec<EmisoraBusqueda>, String> {
+    let pool = db::pool().await?;
     let sql = r#"
         SELECT razon_social, emisoras, serie
         FROM emisoras
@@ -1004,14 +685,13 @@ pub fn buscar_emisoras(query: String) -> Result<Vec<EmisoraBusqueda>, String> {
         LIMIT 20
     "#;
     let pattern = format!("%{}%", query.to_lowercase());
-    let rows = client.query(sql, &[&pattern]).map_err(|e| e.to_string())?;
-    let results = rows
-        .into_iter()
-        .map(|row| EmisoraBusqueda {
-            razon_social: row.get(0),
-            emisoras: row.get(1),
-            serie: row.get(2),
-        })
-        .collect();
-    Ok(results)
+    let rows = sqlx::query(sql).bind(pattern).fetch_all(pool).await.map_err(|e| e.to_string())?;
+    rows.into_iter()
+        .map(|row| Ok(EmisoraBusqueda {
+            razon_social: row.try_get(0)?,
+            emisoras: row.try_get(1)?,
+            serie: row.try_get(2)?,
+        }))
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
 }
\ No newline at end of file
@@ -0,0 +1,357 @@
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use tauri::command;
+use crate::calendario;
+use crate::db;
+use crate::get_data::{self, provider};
+
+// --- Históricos diarios (OHLCV) ---
+// Llena `precios_diarios` desde el endpoint de históricos de DataBursatil.
+// El backfill cubre el rango de `emisoras.rangos_historicos`; la actualización
+// incremental pide los días posteriores a la última barra guardada y los huecos
+// de las últimas `DIAS_REVISION` jornadas. En ambos casos sólo se piden los días
+// hábiles de `calendario` que no tienen barra.
+// Las respuestas no pasan por la caché: cada tramo se pide una sola vez y
+// después vive en la tabla.
+
+/// Días por petición; los rangos largos se parten en tramos de este tamaño.
+const DIAS_POR_TRAMO: i64 = 365;
+/// Rango por defecto si la emisora no tiene `rangos_historicos`.
+const ANIOS_POR_DEFECTO: i64 = 5;
+/// Días hacia atrás en los que la actualización diaria busca huecos. Los más
+/// viejos los llena el backfill: una emisora poco operada tiene días sin barra
+/// que no vale la pena volver a pedir todos los días.
+const DIAS_REVISION: i64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BarraDiaria {
+    pub emisora: String,
+    pub serie: String,
+    pub fecha: NaiveDate,
+    pub apertura: Option<f64>,
+    pub maximo: Option<f64>,
+    pub minimo: Option<f64>,
+    pub cierre: f64,
+    pub volumen: Option<i64>,
+    pub operaciones: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ResumenHistoricos {
+    pub emisoras: usize,
+    pub barras: u64,
+    pub errores: Vec<String>,
+}
+
+/// Toma la primera y la última fecha `YYYY-MM-DD` que aparezcan en `rangos_historicos`.
+pub fn parse_rango(rango: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let fechas: Vec<NaiveDate> = rango
+        .split(|c: char| !(c.is_ascii_digit() || c == '-'))
+        .filter_map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+        .collect();
+    Some((*fechas.iter().min()?, *fechas.iter().max()?))
+}
+
+fn numero(obj: &serde_json::Map<String, serde_json::Value>, claves: &[&str]) -> Option<f64> {
+    claves.iter().find_map(|c| match obj.get(*c)? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Convierte la respuesta del proveedor en barras. Acepta el mapa fecha → barra
+/// directamente o anidado bajo la emisora/bolsa, y barras que sólo traen el
+/// precio de cierre como número.
+fn parse_barras(emisora: &str, serie: &str, valor: &serde_json::Value, barras: &mut Vec<BarraDiaria>) {
+    let obj = match valor.as_object() {
+        Some(o) => o,
+        None => return,
+    };
+    for (clave, v) in obj {
+        let fecha = match NaiveDate::parse_from_str(clave.get(0..10).unwrap_or(clave), "%Y-%m-%d") {
+            Ok(f) => f,
+            Err(_) => {
+                parse_barras(emisora, serie, v, barras);
+                continue;
+            }
+        };
+        let barra = match v {
+            serde_json::Value::Number(n) => n.as_f64().map(|cierre| BarraDiaria {
+                emisora: emisora.to_string(),
+                serie: serie.to_string(),
+                fecha,
+                apertura: None,
+                maximo: None,
+                minimo: None,
+                cierre,
+                volumen: None,
+                operaciones: None,
+            }),
+            // `u` es el último precio; `c` es el cambio porcentual, no el cierre
+            serde_json::Value::Object(b) => numero(b, &["u", "cierre", "precio", "close"]).map(|cierre| BarraDiaria {
+                emisora: emisora.to_string(),
+                serie: serie.to_string(),
+                fecha,
+                apertura: numero(b, &["a", "apertura", "open"]),
+                maximo: numero(b, &["m", "maximo", "high"]),
+                minimo: numero(b, &["n", "minimo", "low"]),
+                cierre,
+                volumen: numero(b, &["v", "volumen", "volume"]).map(|v| v as i64),
+                operaciones: numero(b, &["o", "operaciones", "trades"]).map(|o| o as i32),
+            }),
+            _ => None,
+        };
+        if let Some(barra) = barra {
+            barras.push(barra);
+        }
+    }
+}
+
+async fn descargar(
+    emisora: &str,
+    serie: &str,
+    inicio: NaiveDate,
+    fin: NaiveDate,
+) -> Result<Vec<BarraDiaria>, Box<dyn std::error::Error + Send + Sync>> {
+    let api_key = get_data::get_api_key()?;
+    let mut barras = Vec::new();
+    let mut desde = inicio;
+    while desde <= fin {
+        let hasta = (desde + Duration::days(DIAS_POR_TRAMO - 1)).min(fin);
+        let url = format!(
+            "https://api.databursatil.com/v2/historicos?token={}&emisora_serie={}{}&bolsa=BMV&inicio={}&final={}",
+            api_key, emisora, serie, desde, hasta
+        );
+        let response = provider().get_text(&url).await?;
+        let json: serde_json::Value = serde_json::from_str(&response)?;
+        parse_barras(emisora, serie, &json, &mut barras);
+        desde = hasta + Duration::days(1);
+    }
+    barras.sort_by_key(|b| b.fecha);
+    barras.dedup_by_key(|b| b.fecha);
+    Ok(barras)
+}
+
+async fn guardar_barras(pool: &PgPool, barras: &[BarraDiaria]) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for b in barras {
+        sqlx::query(
+            "INSERT INTO precios_diarios (emisoras, serie, fecha, apertura, maximo, minimo, cierre, volumen, operaciones)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT (emisoras, serie, fecha) DO UPDATE SET
+                apertura = EXCLUDED.apertura, maximo = EXCLUDED.maximo, minimo = EXCLUDED.minimo,
+                cierre = EXCLUDED.cierre, volumen = EXCLUDED.volumen, operaciones = EXCLUDED.operaciones,
+                actualizado = now()",
+        )
+        .bind(&b.emisora)
+        .bind(&b.serie)
+        .bind(b.fecha)
+        .bind(b.apertura)
+        .bind(b.maximo)
+        .bind(b.minimo)
+        .bind(b.cierre)
+        .bind(b.volumen)
+        .bind(b.operaciones)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(barras.len() as u64)
+}
+
+/// Primera y última fecha ya guardadas para la emisora.
+async fn rango_guardado(pool: &PgPool, emisora: &str, serie: &str) -> Result<Option<(NaiveDate, NaiveDate)>, sqlx::Error> {
+    let row = sqlx::query("SELECT MIN(fecha), MAX(fecha) FROM precios_diarios WHERE emisoras = $1 AND serie = $2")
+        .bind(emisora)
+        .bind(serie)
+        .fetch_one(pool)
+        .await?;
+    let min: Option<NaiveDate> = row.try_get(0)?;
+    let max: Option<NaiveDate> = row.try_get(1)?;
+    Ok(min.zip(max))
+}
+
+/// Días hábiles de `[inicio, fin]` sin barra guardada, agrupados en tramos. Los
+/// huecos cercanos van en el mismo tramo mientras quepan en una petición: pedir
+/// de nuevo días ya guardados sólo los reescribe.
+async fn tramos_faltantes(
+    pool: &PgPool,
+    emisora: &str,
+    serie: &str,
+    inicio: NaiveDate,
+    fin: NaiveDate,
+) -> Result<Vec<(NaiveDate, NaiveDate)>, sqlx::Error> {
+    let guardadas: HashSet<NaiveDate> = sqlx::query(
+        "SELECT fecha FROM precios_diarios WHERE emisoras = $1 AND serie = $2 AND fecha BETWEEN $3 AND $4",
+    )
+    .bind(emisora)
+    .bind(serie)
+    .bind(inicio)
+    .bind(fin)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| row.try_get(0))
+    .collect::<Result<_, sqlx::Error>>()?;
+
+    let mut tramos: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for dia in calendario::trading_days_between(inicio, fin) {
+        if guardadas.contains(&dia) {
+            continue;
+        }
+        match tramos.last_mut() {
+            Some((desde, hasta)) if (dia - *desde).num_days() < DIAS_POR_TRAMO => *hasta = dia,
+            _ => tramos.push((dia, dia)),
+        }
+    }
+    Ok(tramos)
+}
+
+async fn descargar_y_guardar(
+    pool: &PgPool,
+    emisora: &str,
+    serie: &str,
+    tramos: Vec<(NaiveDate, NaiveDate)>,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let mut total = 0;
+    for (inicio, fin) in tramos {
+        // Un tramo sin días hábiles (fin de semana, feriado) no amerita petición
+        if calendario::trading_days_between(inicio, fin).is_empty() {
+            continue;
+        }
+        let barras = descargar(emisora, serie, inicio, fin).await?;
+        total += guardar_barras(pool, &barras).await?;
+    }
+    Ok(total)
+}
+
+/// Última sesión cuya barra diaria ya es definitiva.
+fn ultima_sesion_cerrada() -> NaiveDate {
+    let vigente = calendario::sesion_vigente(Utc::now());
+    if calendario::is_open(Utc::now()) {
+        calendario::previous_trading_day(vigente)
+    } else {
+        vigente
+    }
+}
+
+/// Descarga los días del rango histórico de la emisora que no están guardados.
+pub async fn backfill_emisora(pool: &PgPool, emisora: &str, serie: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let hoy = ultima_sesion_cerrada();
+    let rango: Option<String> = sqlx::query("SELECT rangos_historicos FROM emisoras WHERE emisoras = $1 AND serie = $2")
+        .bind(emisora)
+        .bind(serie)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| format!("No existe la emisora {} serie {}", emisora, serie))?
+        .try_get(0)?;
+    let inicio = rango
+        .as_deref()
+        .and_then(parse_rango)
+        .map(|(inicio, _)| inicio)
+        .unwrap_or(hoy - Duration::days(365 * ANIOS_POR_DEFECTO));
+
+    let tramos = tramos_faltantes(pool, emisora, serie, inicio, hoy).await?;
+    let n = descargar_y_guardar(pool, emisora, serie, tramos).await?;
+    println!("[historicos] {}{}: {} barras guardadas (backfill)", emisora, serie, n);
+    Ok(n)
+}
+
+/// Pide los días posteriores a la última barra guardada y los que falten en las
+/// últimas `DIAS_REVISION` jornadas. Si la emisora no tiene nada guardado hace
+/// el backfill completo.
+pub async fn actualizar_emisora(pool: &PgPool, emisora: &str, serie: &str) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let hoy = ultima_sesion_cerrada();
+    let (primera, ultima) = match rango_guardado(pool, emisora, serie).await? {
+        Some(rango) => rango,
+        None => return backfill_emisora(pool, emisora, serie).await,
+    };
+    let inicio = (hoy - Duration::days(DIAS_REVISION)).min(ultima + Duration::days(1)).max(primera);
+    let tramos = tramos_faltantes(pool, emisora, serie, inicio, hoy).await?;
+    let n = descargar_y_guardar(pool, emisora, serie, tramos).await?;
+    if n > 0 {
+        println!("[historicos] {}{}: {} barras nuevas", emisora, serie, n);
+    }
+    Ok(n)
+}
+
+/// Descarga (o vuelve a descargar) un rango explícito, sin ver lo ya guardado.
+pub async fn backfill_rango(
+    pool: &PgPool,
+    emisora: &str,
+    serie: &str,
+    inicio: NaiveDate,
+    fin: NaiveDate,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    descargar_y_guardar(pool, emisora, serie, vec![(inicio, fin.min(ultima_sesion_cerrada()))]).await
+}
+
+#[command]
+pub async fn backfill_historicos(emisora: String, serie: String) -> Result<ResumenHistoricos, String> {
+    let pool = db::pool().await?;
+    let barras = backfill_emisora(pool, &emisora, &serie).await.map_err(|e| e.to_string())?;
+    Ok(ResumenHistoricos { emisoras: 1, barras, errores: Vec::new() })
+}
+
+/// Actualización diaria de todas las emisoras que ya tienen históricos guardados.
+#[command]
+pub async fn actualizar_historicos() -> Result<ResumenHistoricos, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query("SELECT DISTINCT emisoras, serie FROM precios_diarios ORDER BY emisoras, serie")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut resumen = ResumenHistoricos::default();
+    for row in rows {
+        let emisora: String = row.try_get(0).map_err(|e| e.to_string())?;
+        let serie: String = row.try_get(1).map_err(|e| e.to_string())?;
+        match actualizar_emisora(pool, &emisora, &serie).await {
+            Ok(n) => resumen.barras += n,
+            Err(e) => resumen.errores.push(format!("{}{}: {}", emisora, serie, e)),
+        }
+        resumen.emisoras += 1;
+    }
+    Ok(resumen)
+}
+
+#[command]
+pub async fn get_precios_diarios(
+    emisora: String,
+    serie: String,
+    inicio: Option<NaiveDate>,
+    fin: Option<NaiveDate>,
+) -> Result<Vec<BarraDiaria>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT fecha, apertura, maximo, minimo, cierre, volumen, operaciones
+         FROM precios_diarios
+         WHERE emisoras = $1 AND serie = $2
+           AND ($3::date IS NULL OR fecha >= $3)
+           AND ($4::date IS NULL OR fecha <= $4)
+         ORDER BY fecha",
+    )
+    .bind(&emisora)
+    .bind(&serie)
+    .bind(inicio)
+    .bind(fin)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.into_iter()
+        .map(|row| Ok(BarraDiaria {
+            emisora: emisora.clone(),
+            serie: serie.clone(),
+            fecha: row.try_get("fecha")?,
+            apertura: row.try_get("apertura")?,
+            maximo: row.try_get("maximo")?,
+            minimo: row.try_get("minimo")?,
+            cierre: row.try_get("cierre")?,
+            volumen: row.try_get("volumen")?,
+            operaciones: row.try_get("operaciones")?,
+        }))
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
+}
@@ -1,5 +1,5 @@
 use chrono::{Utc, TimeZone};
-use postgres::Client;
+use sqlx::{PgPool, Row};
 use crate::portfolio;
 
 /*
@@ -9,16 +9,16 @@ use crate::portfolio;
 
 */
 
-pub fn ejemplo_agregar_gmexicob(client: &mut Client, portafolio_id: i32) {
+pub async fn ejemplo_agregar_gmexicob(pool: &PgPool, portafolio_id: i32) {
     let ticker = "ANAUN";
     let emisoras = "ANAU";
     let serie = "N";
     let fecha_alta_utc = Utc.with_ymd_and_hms(2025, 6, 24, 12, 0, 0).unwrap();
-    let portafolio_ticker_id = match portfolio::add_ticker(client, portafolio_id, ticker, emisoras, serie, Some(fecha_alta_utc)) {
+    let portafolio_ticker_id = match portfolio::add_ticker(pool, portafolio_id, ticker, emisoras, serie, Some(fecha_alta_utc)).await {
         Ok(id) => id,
         Err(e) => {
             eprintln!("[ERROR SQL] No se pudo insertar el ticker '{}': {}", ticker, e);
-            let tickers = portfolio::list_tickers(client, portafolio_id).unwrap_or_default();
+            let tickers = portfolio::list_tickers(pool, portafolio_id).await.unwrap_or_default();
             match tickers.iter().find(|(_, t, _, _)| t == ticker).map(|(id, _, _, _)| *id) {
                 Some(id) => id,
                 None => {
@@ -28,17 +28,17 @@ pub fn ejemplo_agregar_gmexicob(client: &mut Client, portafolio_id: i32) {
             }
         }
     };
-    if let Err(e) = portfolio::add_transaction(client, portafolio_ticker_id, "compra", 1, 122.97) {
+    if let Err(e) = portfolio::add_transaction(pool, portafolio_ticker_id, "compra", 1, 122.97).await {
         eprintln!("[ADVERTENCIA] No se pudo agregar compra 1: {}", e);
     }
 }
 
-pub fn listar_holdings(client: &mut Client, portafolio_id: i32) {
-    let tickers = portfolio::list_tickers(client, portafolio_id).unwrap_or_default();
+pub async fn listar_holdings(pool: &PgPool, portafolio_id: i32) {
+    let tickers = portfolio::list_tickers(pool, portafolio_id).await.unwrap_or_default();
     println!("\nSlots (holds) actuales en el portafolio (id: {}):", portafolio_id);
     for (id, ticker, emisoras, serie) in &tickers {
         // Consultar transacciones para este portafolio_ticker_id
-        let trans = portfolio::list_transactions(client, *id).unwrap_or_default();
+        let trans = portfolio::list_transactions(pool, *id).await.unwrap_or_default();
         let mut cantidad_total = 0;
         let mut monto_total = 0.0;
         let mut ventas_total = 0;
@@ -68,24 +68,26 @@ pub fn listar_holdings(client: &mut Client, portafolio_id: i32) {
     }
 }
 
-pub fn agregar_usuario_y_portafolio(client: &mut Client, usuario_id: i32, nombre_usuario: &str, nombre_portafolio: &str) -> Result<String, Box<dyn std::error::Error>> {
+pub async fn agregar_usuario_y_portafolio(pool: &PgPool, usuario_id: i32, nombre_usuario: &str, nombre_portafolio: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     // 1. Agregar usuario (si no existe)
-    client.execute(
-        "INSERT INTO usuarios (id, nombre) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
-        &[&usuario_id, &nombre_usuario],
-    )?;
+    sqlx::query("INSERT INTO usuarios (id, nombre) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING")
+        .bind(usuario_id)
+        .bind(nombre_usuario)
+        .execute(pool)
+        .await?;
     // 2. Verificar si el portafolio ya existe para ese usuario
-    let row = client.query_opt(
-        "SELECT id_hex FROM portafolios WHERE usuario_id = $1 AND nombre = $2",
-        &[&usuario_id, &nombre_portafolio],
-    )?;
+    let row = sqlx::query("SELECT id_hex FROM portafolios WHERE usuario_id = $1 AND nombre = $2")
+        .bind(usuario_id)
+        .bind(nombre_portafolio)
+        .fetch_optional(pool)
+        .await?;
     if let Some(row) = row {
-        let id_hex: String = row.get(0);
+        let id_hex: String = row.try_get(0)?;
         println!("Usuario '{}' (id: {}) y portafolio '{}' ya existen. id_hex: {}", nombre_usuario, usuario_id, nombre_portafolio, id_hex);
         return Ok(id_hex);
     }
     // 3. Crear portafolio para ese usuario si no existe
-    let id_hex = portfolio::add_portfolio(client, usuario_id, nombre_portafolio)?;
+    let id_hex = portfolio::add_portfolio(pool, usuario_id, nombre_portafolio).await?;
     println!("Usuario '{}' (id: {}) y portafolio '{}' creados/vinculados.", nombre_usuario, usuario_id, nombre_portafolio);
     Ok(id_hex)
 }
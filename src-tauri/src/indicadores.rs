@@ -0,0 +1,289 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::command;
+use crate::db;
+use crate::velas::{self, Temporalidad, Vela};
+
+// --- Indicadores técnicos ---
+// Cada indicador regresa una serie del mismo largo que las velas de entrada,
+// con `None` mientras no hay suficientes datos (periodo de calentamiento), para
+// que el frontend las pinte alineadas a los mismos tiempos. Las velas de relleno
+// (minutos sin operaciones, ver `velas`) no entran al cálculo y quedan en `None`.
+
+pub type Serie = Vec<Option<f64>>;
+
+pub fn sma(valores: &[f64], periodo: usize) -> Serie {
+    let mut out = vec![None; valores.len()];
+    if periodo == 0 {
+        return out;
+    }
+    let mut suma = 0.0;
+    for i in 0..valores.len() {
+        suma += valores[i];
+        if i >= periodo {
+            suma -= valores[i - periodo];
+        }
+        if i + 1 >= periodo {
+            out[i] = Some(suma / periodo as f64);
+        }
+    }
+    out
+}
+
+/// EMA sembrada con la SMA de los primeros `periodo` valores.
+pub fn ema(valores: &[f64], periodo: usize) -> Serie {
+    suavizar(valores, periodo, 2.0 / (periodo as f64 + 1.0))
+}
+
+/// Media de Wilder (alpha = 1/periodo), usada por RSI y ATR.
+fn wilder(valores: &[f64], periodo: usize) -> Serie {
+    suavizar(valores, periodo, 1.0 / periodo as f64)
+}
+
+fn suavizar(valores: &[f64], periodo: usize, alpha: f64) -> Serie {
+    let mut out = vec![None; valores.len()];
+    if periodo == 0 || valores.len() < periodo {
+        return out;
+    }
+    let mut prev = valores[..periodo].iter().sum::<f64>() / periodo as f64;
+    out[periodo - 1] = Some(prev);
+    for i in periodo..valores.len() {
+        prev = alpha * valores[i] + (1.0 - alpha) * prev;
+        out[i] = Some(prev);
+    }
+    out
+}
+
+pub fn rsi(cierres: &[f64], periodo: usize) -> Serie {
+    let mut out = vec![None; cierres.len()];
+    if cierres.len() < 2 {
+        return out;
+    }
+    let (ganancias, perdidas): (Vec<f64>, Vec<f64>) = cierres
+        .windows(2)
+        .map(|w| {
+            let d = w[1] - w[0];
+            (d.max(0.0), (-d).max(0.0))
+        })
+        .unzip();
+    let g = wilder(&ganancias, periodo);
+    let p = wilder(&perdidas, periodo);
+    for i in 0..ganancias.len() {
+        if let (Some(g), Some(p)) = (g[i], p[i]) {
+            out[i + 1] = Some(if p == 0.0 { 100.0 } else { 100.0 - 100.0 / (1.0 + g / p) });
+        }
+    }
+    out
+}
+
+pub struct Macd {
+    pub macd: Serie,
+    pub senal: Serie,
+    pub histograma: Serie,
+}
+
+pub fn macd(cierres: &[f64], rapida: usize, lenta: usize, senal: usize) -> Macd {
+    let r = ema(cierres, rapida);
+    let l = ema(cierres, lenta);
+    let linea: Serie = r.iter().zip(&l).map(|(r, l)| Some((*r)? - (*l)?)).collect();
+    // La señal se calcula sólo sobre la parte definida de la línea MACD
+    let inicio = linea.iter().position(|v| v.is_some()).unwrap_or(linea.len());
+    let definidos: Vec<f64> = linea[inicio..].iter().map(|v| v.unwrap_or(0.0)).collect();
+    let mut senal_serie = vec![None; inicio];
+    senal_serie.extend(ema(&definidos, senal));
+    let histograma = linea.iter().zip(&senal_serie).map(|(m, s)| Some((*m)? - (*s)?)).collect();
+    Macd { macd: linea, senal: senal_serie, histograma }
+}
+
+pub struct Bollinger {
+    pub media: Serie,
+    pub superior: Serie,
+    pub inferior: Serie,
+}
+
+pub fn bollinger(cierres: &[f64], periodo: usize, desviaciones: f64) -> Bollinger {
+    let media = sma(cierres, periodo);
+    let mut superior = vec![None; cierres.len()];
+    let mut inferior = vec![None; cierres.len()];
+    for i in 0..cierres.len() {
+        if let Some(m) = media[i] {
+            let ventana = &cierres[i + 1 - periodo..=i];
+            let var = ventana.iter().map(|x| (x - m).powi(2)).sum::<f64>() / periodo as f64;
+            let banda = desviaciones * var.sqrt();
+            superior[i] = Some(m + banda);
+            inferior[i] = Some(m - banda);
+        }
+    }
+    Bollinger { media, superior, inferior }
+}
+
+pub fn atr(velas: &[Vela], periodo: usize) -> Serie {
+    let rangos: Vec<f64> = velas
+        .iter()
+        .enumerate()
+        .map(|(i, v)| match i.checked_sub(1).map(|j| velas[j].cierre) {
+            Some(prev) => (v.maximo - v.minimo).max((v.maximo - prev).abs()).max((v.minimo - prev).abs()),
+            None => v.maximo - v.minimo,
+        })
+        .collect();
+    wilder(&rangos, periodo)
+}
+
+/// VWAP acumulado por sesión (se reinicia cada día). Sin volumen no hay VWAP.
+pub fn vwap(velas: &[Vela]) -> Serie {
+    let mut out = vec![None; velas.len()];
+    let mut dia = None;
+    let (mut pv, mut vol) = (0.0, 0.0);
+    for (i, v) in velas.iter().enumerate() {
+        if dia != Some(v.fecha_hora.date()) {
+            dia = Some(v.fecha_hora.date());
+            pv = 0.0;
+            vol = 0.0;
+        }
+        if let Some(volumen) = v.volumen {
+            let tipico = (v.maximo + v.minimo + v.cierre) / 3.0;
+            pv += tipico * volumen;
+            vol += volumen;
+        }
+        if vol > 0.0 {
+            out[i] = Some(pv / vol);
+        }
+    }
+    out
+}
+
+pub fn obv(velas: &[Vela]) -> Serie {
+    let mut out = vec![None; velas.len()];
+    let mut acumulado = 0.0;
+    for (i, v) in velas.iter().enumerate() {
+        let volumen = match v.volumen {
+            Some(vol) => vol,
+            None => continue,
+        };
+        if i > 0 {
+            let prev = velas[i - 1].cierre;
+            if v.cierre > prev {
+                acumulado += volumen;
+            } else if v.cierre < prev {
+                acumulado -= volumen;
+            }
+        }
+        out[i] = Some(acumulado);
+    }
+    out
+}
+
+/// Máximo (o mínimo) de las últimas `periodo` velas.
+pub fn extremo_movil(valores: &[f64], periodo: usize, maximo: bool) -> Serie {
+    (0..valores.len())
+        .map(|i| {
+            if periodo == 0 || i + 1 < periodo {
+                return None;
+            }
+            let ventana = valores[i + 1 - periodo..=i].iter().copied();
+            if maximo { ventana.reduce(f64::max) } else { ventana.reduce(f64::min) }
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IndicadoresResponse {
+    pub tiempos: Vec<i64>,
+    pub velas: Vec<Vela>,
+    /// Nombre de la serie (p. ej. "sma_20", "macd_senal") → valores alineados a `tiempos`.
+    pub series: BTreeMap<String, Serie>,
+}
+
+fn parametros(spec: &str, defaults: &[f64]) -> Result<Vec<f64>, String> {
+    let mut valores = defaults.to_vec();
+    if let Some((_, args)) = spec.split_once(':') {
+        for (i, arg) in args.split(',').enumerate().take(defaults.len()) {
+            valores[i] = arg
+                .trim()
+                .parse()
+                .map_err(|_| format!("Parámetro inválido '{}' en '{}'", arg, spec))?;
+        }
+    }
+    Ok(valores)
+}
+
+/// Calcula los indicadores pedidos. Cada spec es `nombre[:param,...]`, p. ej.
+/// `sma:20`, `ema:50`, `rsi:14`, `macd:12,26,9`, `bollinger:20,2`, `atr:14`,
+/// `vwap`, `obv`, `max:20`, `min:20`.
+pub fn calcular(todas: &[Vela], specs: &[String]) -> Result<BTreeMap<String, Serie>, String> {
+    let reales: Vec<Vela> = todas.iter().filter(|v| !v.relleno).cloned().collect();
+    let velas = reales.as_slice();
+    let cierres: Vec<f64> = velas.iter().map(|v| v.cierre).collect();
+    let maximos: Vec<f64> = velas.iter().map(|v| v.maximo).collect();
+    let minimos: Vec<f64> = velas.iter().map(|v| v.minimo).collect();
+    let mut series = BTreeMap::new();
+    for spec in specs {
+        let nombre = spec.split(':').next().unwrap_or("").trim().to_lowercase();
+        match nombre.as_str() {
+            "sma" | "ema" | "rsi" | "atr" | "max" | "min" => {
+                let defecto = if nombre == "rsi" || nombre == "atr" { 14.0 } else { 20.0 };
+                let n = parametros(spec, &[defecto])?[0] as usize;
+                let serie = match nombre.as_str() {
+                    "sma" => sma(&cierres, n),
+                    "ema" => ema(&cierres, n),
+                    "rsi" => rsi(&cierres, n),
+                    "atr" => atr(velas, n),
+                    "max" => extremo_movil(&maximos, n, true),
+                    _ => extremo_movil(&minimos, n, false),
+                };
+                series.insert(format!("{}_{}", nombre, n), serie);
+            }
+            "macd" => {
+                let p = parametros(spec, &[12.0, 26.0, 9.0])?;
+                let m = macd(&cierres, p[0] as usize, p[1] as usize, p[2] as usize);
+                series.insert("macd".to_string(), m.macd);
+                series.insert("macd_senal".to_string(), m.senal);
+                series.insert("macd_histograma".to_string(), m.histograma);
+            }
+            "bollinger" => {
+                let p = parametros(spec, &[20.0, 2.0])?;
+                let b = bollinger(&cierres, p[0] as usize, p[1]);
+                let n = p[0] as usize;
+                series.insert(format!("bollinger_media_{}", n), b.media);
+                series.insert(format!("bollinger_superior_{}", n), b.superior);
+                series.insert(format!("bollinger_inferior_{}", n), b.inferior);
+            }
+            "vwap" => {
+                series.insert("vwap".to_string(), vwap(velas));
+            }
+            "obv" => {
+                series.insert("obv".to_string(), obv(velas));
+            }
+            otro => return Err(format!("Indicador desconocido '{}'", otro)),
+        }
+    }
+    Ok(series.into_iter().map(|(nombre, serie)| (nombre, alinear(todas, serie))).collect())
+}
+
+/// Lleva una serie calculada sobre las velas reales a todas las velas.
+fn alinear(todas: &[Vela], serie: Serie) -> Serie {
+    let mut valores = serie.into_iter();
+    todas.iter().map(|v| if v.relleno { None } else { valores.next().flatten() }).collect()
+}
+
+#[command]
+pub async fn get_indicators(
+    ticker: String,
+    temporalidad: String,
+    indicadores: Vec<String>,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+) -> Result<IndicadoresResponse, String> {
+    let temporalidad = Temporalidad::parse(&temporalidad)?;
+    let pool = db::pool().await?;
+    let velas = velas::velas(pool, &ticker, temporalidad, inicio, fin)
+        .await
+        .map_err(|e| e.to_string())?;
+    let series = calcular(&velas, &indicadores)?;
+    Ok(IndicadoresResponse {
+        tiempos: velas.iter().map(|v| v.tiempo).collect(),
+        velas,
+        series,
+    })
+}
@@ -0,0 +1,291 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::command;
+use crate::db;
+use crate::get_data;
+use crate::periodos::Trimestre;
+use crate::scheduler::{self, Notificador, ProgresoJob};
+
+// --- Ingesta masiva de estados financieros ---
+// Recorre `rangos_financieros` de cada emisora activa y pide los tres estados de
+// cada trimestre del rango. Va de los trimestres más recientes a los más viejos,
+// todas las emisoras a la vez, para que lo reciente esté primero. Cada respuesta
+// trae además periodos comparativos que se guardan de paso, así que antes de
+// pedir un trimestre se revisa si ya está en las tres tablas.
+// El avance queda en `ingesta_financieros`: si se cancela o se cierra la app, la
+// siguiente ejecución retoma lo pendiente. Los trimestres con error o sin datos
+// se reintentan hasta `MAX_INTENTOS` veces; el último trimestre del rango, que
+// suele llegar vacío mientras la emisora no reporta, se sigue reintentando una
+// vez cada `ESPERA_ULTIMO_DIAS` aunque haya agotado los intentos. El progreso
+// sale por el mismo notificador que los jobs del programador.
+
+pub const JOB: &str = "ingesta_financieros";
+const MAX_INTENTOS: i32 = 3;
+const ESPERA_ULTIMO_DIAS: i32 = 1;
+
+type IngestaError = Box<dyn std::error::Error + Send + Sync>;
+
+static EN_CURSO: AtomicBool = AtomicBool::new(false);
+static CANCELAR: AtomicBool = AtomicBool::new(false);
+
+struct Candado;
+
+impl Candado {
+    fn tomar() -> Option<Candado> {
+        EN_CURSO.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).ok()?;
+        CANCELAR.store(false, Ordering::SeqCst);
+        Some(Candado)
+    }
+}
+
+impl Drop for Candado {
+    fn drop(&mut self) {
+        EN_CURSO.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ResumenIngesta {
+    /// Trimestres pendientes al arrancar
+    pub total: usize,
+    pub procesados: usize,
+    /// Periodos guardados, contando los comparativos de cada respuesta
+    pub periodos: usize,
+    pub vacios: usize,
+    pub errores: usize,
+    pub cancelada: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EstadoIngesta {
+    pub en_curso: bool,
+    pub completos: i64,
+    /// Sin datos y todavía con intentos
+    pub vacios: i64,
+    /// Con error y todavía con intentos
+    pub con_error: i64,
+    /// Sin datos o con error y sin intentos restantes; sólo se vuelve a pedir
+    /// el último trimestre de cada rango
+    pub agotados: i64,
+    pub pendientes: usize,
+    pub ultima_actualizacion: Option<DateTime<Utc>>,
+}
+
+/// Trimestres que cubre un `rangos_financieros`, del primero al último, con
+/// etiquetas en el formato del proveedor.
+pub fn trimestres_del_rango(rango: &str) -> Vec<String> {
+    let periodos = scheduler::periodos_rango(rango);
+    let (primero, ultimo) = match (periodos.first(), periodos.last()) {
+        (Some(p), Some(u)) => (*p, *u),
+        _ => return Vec::new(),
+    };
+    let (mut t, fin) = match (Trimestre::parse(primero), Trimestre::parse(ultimo)) {
+        (Some(inicio), Some(fin)) => (inicio, fin),
+        _ => return Vec::new(),
+    };
+    let mut etiquetas = Vec::new();
+    while t <= fin {
+        etiquetas.push(t.etiqueta_como(ultimo));
+        t = t.siguiente();
+    }
+    etiquetas
+}
+
+/// Emisora/trimestre por pedir, del trimestre más reciente al más viejo.
+/// `emisoras` limita la ingesta; sin ella se usan todas las emisoras activas.
+async fn pendientes(pool: &PgPool, emisoras: Option<Vec<String>>) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let emisoras = emisoras.map(|lista| lista.into_iter().map(|e| e.to_uppercase()).collect::<Vec<_>>());
+    // Los estados son por emisora; de sus series se toma el rango más largo
+    let rows = sqlx::query(
+        "SELECT DISTINCT ON (emisoras) emisoras, rangos_financieros
+         FROM emisoras
+         WHERE estatus = 'ACTIVA' AND rangos_financieros IS NOT NULL
+           AND ($1::text[] IS NULL OR emisoras = ANY($1))
+         ORDER BY emisoras, length(rangos_financieros) DESC",
+    )
+    .bind(emisoras)
+    .fetch_all(pool)
+    .await?;
+    // Terminados: completos o sin intentos. El valor dice si también lo están
+    // como último trimestre del rango, que se reintenta pasada la espera.
+    let hechos: HashMap<(String, String), bool> = sqlx::query(
+        "SELECT emisora, LOWER(trimestre),
+                estado = 'ok' OR actualizado > now() - make_interval(days => $2)
+         FROM ingesta_financieros
+         WHERE estado = 'ok' OR intentos >= $1",
+    )
+    .bind(MAX_INTENTOS)
+    .bind(ESPERA_ULTIMO_DIAS)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| Ok(((row.try_get(0)?, row.try_get(1)?), row.try_get(2)?)))
+    .collect::<Result<_, sqlx::Error>>()?;
+
+    let mut pendientes = Vec::new();
+    for row in rows {
+        let emisora: String = row.try_get(0)?;
+        let rango: String = row.try_get(1)?;
+        let trimestres = trimestres_del_rango(&rango);
+        let ultimo = trimestres.len().saturating_sub(1);
+        for (i, trimestre) in trimestres.into_iter().enumerate() {
+            let terminado = match hechos.get(&(emisora.clone(), trimestre.to_lowercase())) {
+                Some(&aun_como_ultimo) => i != ultimo || aun_como_ultimo,
+                None => false,
+            };
+            if !terminado {
+                pendientes.push((emisora.clone(), trimestre));
+            }
+        }
+    }
+    pendientes.sort_by(|(e1, t1), (e2, t2)| Trimestre::parse(t2).cmp(&Trimestre::parse(t1)).then_with(|| e1.cmp(e2)));
+    Ok(pendientes)
+}
+
+/// ¿Ya están los tres estados del trimestre (p. ej. como comparativos de otra respuesta)?
+async fn completo(pool: &PgPool, emisora: &str, trimestre: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT
+            EXISTS (SELECT 1 FROM estado_flujos WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2))
+            AND EXISTS (SELECT 1 FROM estado_resultado_trimestral WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2))
+            AND EXISTS (SELECT 1 FROM estado_posicion WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2))",
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .fetch_one(pool)
+    .await?;
+    row.try_get(0)
+}
+
+/// Pide los tres estados de un trimestre. `None` si ya estaban guardados; si
+/// no, cuántos periodos se guardaron y si entre ellos quedó completo el pedido
+/// (la respuesta puede traer sólo comparativos).
+async fn descargar(pool: &PgPool, emisora: &str, trimestre: &str) -> Result<Option<(usize, bool)>, IngestaError> {
+    if completo(pool, emisora, trimestre).await? {
+        return Ok(None);
+    }
+    let flujos = get_data::get_flujos_financieros(pool, emisora, trimestre).await?;
+    let resultados = get_data::get_estado_resultado_trimestral(pool, emisora, trimestre).await?;
+    let posicion = get_data::get_posicion_financiera(pool, emisora, trimestre).await?;
+    Ok(Some((flujos + resultados + posicion, completo(pool, emisora, trimestre).await?)))
+}
+
+async fn registrar(
+    pool: &PgPool,
+    emisora: &str,
+    trimestre: &str,
+    estado: &str,
+    periodos: usize,
+    mensaje: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO ingesta_financieros (emisora, trimestre, estado, periodos, mensaje)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (emisora, trimestre) DO UPDATE SET
+            estado = EXCLUDED.estado, intentos = ingesta_financieros.intentos + 1,
+            periodos = EXCLUDED.periodos, mensaje = EXCLUDED.mensaje, actualizado = now()",
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .bind(estado)
+    .bind(periodos as i32)
+    .bind(mensaje)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Descarga los trimestres pendientes de `emisoras` (o de todas las activas),
+/// avisando el avance. Se detiene entre trimestres si se pide cancelar.
+pub async fn ingerir(pool: &PgPool, emisoras: Option<Vec<String>>, notificar: &Notificador) -> Result<ResumenIngesta, String> {
+    let _candado = Candado::tomar().ok_or("La ingesta de estados financieros ya está en ejecución")?;
+    let avisar = |estado: &str, actual: usize, total: usize, mensaje: Option<String>| {
+        notificar(ProgresoJob { job: JOB.to_string(), estado: estado.to_string(), actual, total, mensaje });
+    };
+    let pendientes = pendientes(pool, emisoras).await.map_err(|e| e.to_string())?;
+    let mut resumen = ResumenIngesta { total: pendientes.len(), ..Default::default() };
+    avisar("iniciado", 0, resumen.total, None);
+
+    for (i, (emisora, trimestre)) in pendientes.iter().enumerate() {
+        if CANCELAR.load(Ordering::SeqCst) {
+            resumen.cancelada = true;
+            break;
+        }
+        avisar("progreso", i, resumen.total, Some(format!("{} {}", emisora, trimestre)));
+        let registro = match descargar(pool, emisora, trimestre).await {
+            Ok(None) => registrar(pool, emisora, trimestre, "ok", 0, Some("Ya estaba guardado".to_string())).await,
+            Ok(Some((n, true))) => {
+                resumen.periodos += n;
+                registrar(pool, emisora, trimestre, "ok", n, None).await
+            }
+            Ok(Some((n, false))) => {
+                resumen.periodos += n;
+                resumen.vacios += 1;
+                let mensaje = (n > 0).then(|| "La respuesta sólo trae periodos comparativos".to_string());
+                registrar(pool, emisora, trimestre, "vacio", n, mensaje).await
+            }
+            Err(e) => {
+                eprintln!("[ingesta] {} {}: {}", emisora, trimestre, e);
+                resumen.errores += 1;
+                registrar(pool, emisora, trimestre, "error", 0, Some(e.to_string())).await
+            }
+        };
+        registro.map_err(|e| e.to_string())?;
+        resumen.procesados += 1;
+    }
+
+    let mensaje = format!(
+        "{} de {} trimestres, {} periodos guardados, {} sin datos, {} con error",
+        resumen.procesados, resumen.total, resumen.periodos, resumen.vacios, resumen.errores
+    );
+    avisar(if resumen.cancelada { "cancelado" } else { "exito" }, resumen.procesados, resumen.total, Some(mensaje));
+    Ok(resumen)
+}
+
+/// Ingesta masiva de estados financieros; retoma lo pendiente de la anterior.
+#[command]
+pub async fn run_bulk_ingestion(app: tauri::AppHandle, emisoras: Option<Vec<String>>) -> Result<ResumenIngesta, String> {
+    let pool = db::pool().await?;
+    ingerir(pool, emisoras, &scheduler::notificador_app(app)).await
+}
+
+/// Pide detener la ingesta en curso tras el trimestre actual. Regresa si había una.
+#[command]
+pub fn cancel_bulk_ingestion() -> bool {
+    let en_curso = EN_CURSO.load(Ordering::SeqCst);
+    if en_curso {
+        CANCELAR.store(true, Ordering::SeqCst);
+    }
+    en_curso
+}
+
+#[command]
+pub async fn get_bulk_ingestion_status() -> Result<EstadoIngesta, String> {
+    let pool = db::pool().await?;
+    let row = sqlx::query(
+        "SELECT
+            COUNT(*) FILTER (WHERE estado = 'ok') AS completos,
+            COUNT(*) FILTER (WHERE estado = 'vacio' AND intentos < $1) AS vacios,
+            COUNT(*) FILTER (WHERE estado = 'error' AND intentos < $1) AS con_error,
+            COUNT(*) FILTER (WHERE estado <> 'ok' AND intentos >= $1) AS agotados,
+            MAX(actualizado) AS ultima_actualizacion
+         FROM ingesta_financieros",
+    )
+    .bind(MAX_INTENTOS)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let pendientes = pendientes(pool, None).await.map_err(|e| e.to_string())?;
+    Ok(EstadoIngesta {
+        en_curso: EN_CURSO.load(Ordering::SeqCst),
+        completos: row.try_get("completos").map_err(|e| e.to_string())?,
+        vacios: row.try_get("vacios").map_err(|e| e.to_string())?,
+        con_error: row.try_get("con_error").map_err(|e| e.to_string())?,
+        agotados: row.try_get("agotados").map_err(|e| e.to_string())?,
+        pendientes: pendientes.len(),
+        ultima_actualizacion: row.try_get("ultima_actualizacion").map_err(|e| e.to_string())?,
+    })
+}
@@ -1,13 +1,156 @@
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
+// Biblioteca compartida por la app de escritorio (`main.rs`) y la línea de
+// comandos de administración (`bin/dalia-cli.rs`).
+
+pub mod activos;
+pub mod alertas;
+pub mod analisis;
+pub mod api;
+pub mod cache;
+pub mod calendario;
+pub mod calidad_datos;
+pub mod clean_emisoras;
+pub mod db;
+pub mod dcf;
+pub mod get_data;
+pub mod historicos;
+pub mod holdings;
+pub mod indicadores;
+pub mod ingesta;
+pub mod mapeo_xbrl;
+pub mod migraciones;
+pub mod multiplos;
+pub mod periodos;
+pub mod portfolio;
+pub mod portfolio_management;
+pub mod portfolio_members;
+pub mod provider_client;
+pub mod puntajes;
+pub mod ratios;
+pub mod scheduler;
+pub mod screener;
+pub mod settings;
+pub mod ticker_tape;
+pub mod velas;
+pub mod watchlists;
 
+/// Arranca la app de escritorio.
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            // Cierres extraordinarios y medias sesiones del calendario de la BMV
+            tauri::async_runtime::spawn(async {
+                match db::pool().await {
+                    Ok(pool) => {
+                        if let Err(e) = calendario::cargar_excepciones(pool).await {
+                            eprintln!("[WARN] No se pudieron cargar las excepciones del calendario: {}", e);
+                        }
+                    }
+                    Err(e) => eprintln!("[WARN] Calendario sin excepciones: {}", e),
+                }
+            });
+            // Sincronización periódica de catálogo, precios, estados financieros y tasas
+            scheduler::iniciar(scheduler::notificador_app(app.handle().clone()));
+            // Alertas de precio y fundamentales del usuario
+            alertas::iniciar(alertas::notificador_app(app.handle().clone()));
+            // Cinta de cotizaciones empujada al frontend
+            ticker_tape::iniciar(ticker_tape::notificador_app(app.handle().clone()));
+            // API HTTP local para notebooks y scripts, sólo si se configuró un puerto
+            if let Some(puerto) = api::puerto_configurado() {
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = api::iniciar(puerto).await {
+                        eprintln!("[WARN] No se pudo iniciar la API local: {}", e);
+                    }
+                });
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            settings::get_provider_status,
+            settings::set_api_key,
+            settings::delete_api_key,
+            get_data::get_provider_diagnostics,
+            get_data::get_indices_tauri,
+            get_data::get_forex_tauri,
+            get_data::get_top_tauri,
+            get_data::buscar_emisoras,
+            activos::get_emisora_query,
+            activos::get_emisora_info,
+            activos::get_trimestres_disponibles,
+            activos::get_asset_details,
+            ratios::get_ratios,
+            multiplos::get_multiples,
+            screener::get_screen_fields,
+            screener::run_screen,
+            screener::get_saved_screens,
+            screener::save_screen,
+            screener::delete_saved_screen,
+            screener::run_saved_screen,
+            dcf::get_dcf_history,
+            dcf::get_dcf_assumptions,
+            dcf::save_dcf_assumptions,
+            dcf::delete_dcf_assumptions,
+            dcf::run_dcf,
+            puntajes::get_scores,
+            analisis::get_analysis,
+            calidad_datos::get_data_quality,
+            calidad_datos::validate_statements,
+            mapeo_xbrl::get_xbrl_mapping,
+            mapeo_xbrl::get_unmapped_concepts,
+            mapeo_xbrl::get_unmapped_summary,
+            periodos::get_periodos,
+            ticker_tape::get_ticker_data,
+            ticker_tape::get_ticker_tape_config,
+            ticker_tape::set_ticker_tape_config,
+            historicos::backfill_historicos,
+            historicos::actualizar_historicos,
+            historicos::get_precios_diarios,
+            velas::get_velas,
+            indicadores::get_indicators,
+            calendario::get_market_status,
+            scheduler::get_jobs,
+            scheduler::run_job,
+            ingesta::run_bulk_ingestion,
+            ingesta::cancel_bulk_ingestion,
+            ingesta::get_bulk_ingestion_status,
+            api::get_api_status,
+            api::start_api_server,
+            api::stop_api_server,
+            api::get_api_token,
+            api::regenerate_api_token,
+            portfolio::get_users,
+            portfolio::create_user,
+            portfolio::get_portfolios,
+            portfolio::create_portfolio,
+            portfolio_members::share_portfolio,
+            portfolio_members::remove_portfolio_member,
+            portfolio_members::get_portfolio_members,
+            watchlists::get_watchlists,
+            watchlists::create_watchlist,
+            watchlists::rename_watchlist,
+            watchlists::delete_watchlist,
+            watchlists::add_watchlist_item,
+            watchlists::remove_watchlist_item,
+            watchlists::get_watchlist_quotes,
+            alertas::get_alert_rules,
+            alertas::create_alert_rule,
+            alertas::set_alert_rule_active,
+            alertas::delete_alert_rule,
+            alertas::get_alert_history,
+            alertas::mark_alerts_read,
+            alertas::evaluate_alerts,
+            // --- Portfolio Management ---
+            portfolio_management::add_cash_movement,
+            portfolio_management::get_cash_balance,
+            portfolio_management::get_cash_flow_history,
+            portfolio_management::add_asset_transaction,
+            portfolio_management::get_asset_transactions,
+            portfolio_management::delete_asset_transaction,
+            portfolio_management::get_portfolio_slots,
+            portfolio_management::calculate_portfolio_pl,
+            portfolio_management::register_dividend_as_cash,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
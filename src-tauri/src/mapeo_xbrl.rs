@@ -0,0 +1,360 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+use tauri::command;
+use crate::activos::{COLUMNAS_FLUJO, COLUMNAS_POSICION, COLUMNAS_RESULTADO};
+use crate::db;
+use crate::settings;
+
+// --- Mapeo de conceptos XBRL a columnas ---
+// `/financieros` regresa cada periodo como un objeto de conceptos IFRS en
+// minúsculas (`cashflowsfromusedinoperatingactivities`: [descripción, valor]).
+// `mapeo_xbrl.json` dice de qué conceptos sale cada columna de los estados:
+// - `conceptos`: en orden de preferencia; se usa el primero que traiga número.
+//   Un concepto puede ir como `{ "concepto": ..., "invertir": true }` cuando su
+//   signo es el contrario de la columna (p. ej. aumento contra disminución);
+// - `signo` (opcional): `reportado`, el de omisión, lo deja como viene;
+//   `positivo` y `negativo` fuerzan el signo. El mapeo embebido no fuerza
+//   ninguno, así se guarda lo mismo que antes del mapeo; forzarlo en un mapeo
+//   propio deja trimestres con signos distintos hasta volver a bajarlos todos.
+// El mapeo va embebido en la app; un `mapeo_xbrl.json` en el directorio de
+// configuración lo reemplaza completo. Los conceptos del periodo que no llenaron
+// ninguna columna se guardan en `conceptos_no_mapeados`.
+
+const ARCHIVO: &str = "mapeo_xbrl.json";
+const MAPEO_EMBEBIDO: &str = include_str!("../mapeo_xbrl.json");
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoXbrl {
+    Flujos,
+    Resultados,
+    Posicion,
+}
+
+impl EstadoXbrl {
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            EstadoXbrl::Flujos => "flujos",
+            EstadoXbrl::Resultados => "resultados",
+            EstadoXbrl::Posicion => "posicion",
+        }
+    }
+
+    fn tabla(&self) -> &'static str {
+        match self {
+            EstadoXbrl::Flujos => "estado_flujos",
+            EstadoXbrl::Resultados => "estado_resultado_trimestral",
+            EstadoXbrl::Posicion => "estado_posicion",
+        }
+    }
+
+    /// Columnas de la tabla que se pueden llenar desde el mapeo.
+    fn admite(&self, columna: &str) -> bool {
+        match self {
+            EstadoXbrl::Flujos => COLUMNAS_FLUJO.contains(&columna),
+            EstadoXbrl::Resultados => COLUMNAS_RESULTADO.contains(&columna),
+            EstadoXbrl::Posicion => COLUMNAS_POSICION.contains(&columna) || columna == "assets",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Signo {
+    #[default]
+    Reportado,
+    Positivo,
+    Negativo,
+}
+
+impl Signo {
+    fn aplicar(&self, valor: f64) -> f64 {
+        match self {
+            Signo::Reportado => valor,
+            Signo::Positivo => valor.abs(),
+            Signo::Negativo => -valor.abs(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Concepto {
+    Clave(String),
+    ConSigno {
+        concepto: String,
+        #[serde(default)]
+        invertir: bool,
+    },
+}
+
+impl Concepto {
+    fn clave(&self) -> &str {
+        match self {
+            Concepto::Clave(c) | Concepto::ConSigno { concepto: c, .. } => c,
+        }
+    }
+
+    fn invertir(&self) -> bool {
+        matches!(self, Concepto::ConSigno { invertir: true, .. })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Campo {
+    pub conceptos: Vec<Concepto>,
+    #[serde(default)]
+    pub signo: Signo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mapeo {
+    pub flujos: BTreeMap<String, Campo>,
+    pub resultados: BTreeMap<String, Campo>,
+    pub posicion: BTreeMap<String, Campo>,
+}
+
+impl Mapeo {
+    fn parse(texto: &str) -> Result<Mapeo, String> {
+        let mapeo: Mapeo = serde_json::from_str(texto).map_err(|e| e.to_string())?;
+        for estado in [EstadoXbrl::Flujos, EstadoXbrl::Resultados, EstadoXbrl::Posicion] {
+            if mapeo.campos(estado).is_empty() {
+                return Err(format!("El mapeo de {} no tiene columnas", estado.nombre()));
+            }
+            for (columna, campo) in mapeo.campos(estado) {
+                if !estado.admite(columna) {
+                    return Err(format!("La columna '{}' no existe en {}", columna, estado.tabla()));
+                }
+                if campo.conceptos.is_empty() {
+                    return Err(format!("La columna '{}' de {} no tiene conceptos", columna, estado.nombre()));
+                }
+            }
+        }
+        Ok(mapeo)
+    }
+
+    pub fn campos(&self, estado: EstadoXbrl) -> &BTreeMap<String, Campo> {
+        match estado {
+            EstadoXbrl::Flujos => &self.flujos,
+            EstadoXbrl::Resultados => &self.resultados,
+            EstadoXbrl::Posicion => &self.posicion,
+        }
+    }
+}
+
+/// Mapeo vigente: el del directorio de configuración si existe y es válido; si
+/// no, el embebido. Se lee una vez por proceso.
+pub fn mapeo() -> &'static Mapeo {
+    static MAPEO: OnceLock<Mapeo> = OnceLock::new();
+    MAPEO.get_or_init(|| {
+        let propio = settings::config_dir()
+            .map(|dir| dir.join(ARCHIVO))
+            .ok()
+            .filter(|ruta| ruta.exists());
+        if let Some(ruta) = propio {
+            match std::fs::read_to_string(&ruta).map_err(|e| e.to_string()).and_then(|t| Mapeo::parse(&t)) {
+                Ok(mapeo) => return mapeo,
+                Err(e) => eprintln!("[WARN] Se ignora {}: {}", ruta.display(), e),
+            }
+        }
+        Mapeo::parse(MAPEO_EMBEBIDO).expect("mapeo_xbrl.json embebido válido")
+    })
+}
+
+fn numero(dato: &serde_json::Value) -> Option<f64> {
+    dato.get(1).and_then(|v| v.as_f64())
+}
+
+/// Valores de las columnas y conceptos sobrantes de un periodo.
+pub struct Extraccion {
+    pub valores: Vec<(String, Option<f64>)>,
+    /// (concepto, valor, descripción)
+    pub no_mapeados: Vec<(String, Option<f64>, Option<String>)>,
+}
+
+pub fn extraer(estado: EstadoXbrl, datos: &serde_json::Map<String, serde_json::Value>) -> Extraccion {
+    let mut usados = HashSet::new();
+    let valores = mapeo()
+        .campos(estado)
+        .iter()
+        .map(|(columna, campo)| {
+            let valor = campo.conceptos.iter().find_map(|c| {
+                let v = numero(datos.get(c.clave())?)?;
+                usados.insert(c.clave());
+                Some(if c.invertir() { -v } else { v })
+            });
+            (columna.clone(), valor.map(|v| campo.signo.aplicar(v)))
+        })
+        .collect();
+    let no_mapeados = datos
+        .iter()
+        .filter(|(concepto, _)| !usados.contains(concepto.as_str()))
+        .map(|(concepto, dato)| {
+            let descripcion = dato.get(0).and_then(|v| v.as_str()).map(|s| s.to_string());
+            (concepto.clone(), numero(dato), descripcion)
+        })
+        .collect();
+    Extraccion { valores, no_mapeados }
+}
+
+/// Guarda un periodo de un estado: la fila de la tabla del estado y sus
+/// conceptos no mapeados, en una sola transacción. Regresa `false` si el
+/// periodo no trae un objeto de conceptos.
+pub async fn guardar(
+    pool: &PgPool,
+    estado: EstadoXbrl,
+    emisora: &str,
+    trimestre: &str,
+    fecha: Option<NaiveDate>,
+    datos: &serde_json::Value,
+) -> Result<bool, sqlx::Error> {
+    let datos = match datos.as_object() {
+        Some(d) => d,
+        None => return Ok(false),
+    };
+    let Extraccion { valores, no_mapeados } = extraer(estado, datos);
+    let columnas: Vec<&str> = valores.iter().map(|(c, _)| c.as_str()).collect();
+
+    let mut tx = pool.begin().await?;
+    // `Mapeo::parse` no deja estados sin columnas; sin ellas no hay fila que guardar.
+    if !columnas.is_empty() {
+        let sql = format!(
+            "INSERT INTO public.{} (emisora, trimestre, fecha, {}) VALUES ({})
+             ON CONFLICT (emisora, trimestre) DO UPDATE SET fecha = EXCLUDED.fecha, {}",
+            estado.tabla(),
+            columnas.join(", "),
+            (1..=columnas.len() + 3).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", "),
+            columnas.iter().map(|c| format!("{c} = EXCLUDED.{c}")).collect::<Vec<_>>().join(", ")
+        );
+        let mut query = sqlx::query(&sql).bind(emisora).bind(trimestre).bind(fecha);
+        for (_, valor) in &valores {
+            query = query.bind(*valor);
+        }
+        query.execute(&mut *tx).await?;
+    }
+
+    sqlx::query(
+        "DELETE FROM conceptos_no_mapeados
+         WHERE LOWER(emisora) = LOWER($1) AND LOWER(trimestre) = LOWER($2) AND estado = $3",
+    )
+    .bind(emisora)
+    .bind(trimestre)
+    .bind(estado.nombre())
+    .execute(&mut *tx)
+    .await?;
+    if !no_mapeados.is_empty() {
+        let mut conceptos = Vec::new();
+        let mut montos = Vec::new();
+        let mut descripciones = Vec::new();
+        for (concepto, valor, descripcion) in no_mapeados {
+            conceptos.push(concepto);
+            montos.push(valor);
+            descripciones.push(descripcion);
+        }
+        sqlx::query(
+            "INSERT INTO conceptos_no_mapeados (emisora, trimestre, estado, concepto, valor, descripcion)
+             SELECT $1, $2, $3, c.concepto, c.valor, c.descripcion
+             FROM UNNEST($4::text[], $5::float8[], $6::text[]) AS c(concepto, valor, descripcion)",
+        )
+        .bind(emisora)
+        .bind(trimestre)
+        .bind(estado.nombre())
+        .bind(conceptos)
+        .bind(montos)
+        .bind(descripciones)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConceptoNoMapeado {
+    pub emisora: String,
+    pub trimestre: String,
+    pub estado: String,
+    pub concepto: String,
+    pub valor: Option<f64>,
+    pub descripcion: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FrecuenciaConcepto {
+    pub estado: String,
+    pub concepto: String,
+    pub emisoras: i64,
+    pub periodos: i64,
+    /// Una descripción con la que lo reporta el proveedor
+    pub descripcion: Option<String>,
+}
+
+/// Mapeo vigente, para revisarlo o copiarlo como base de uno propio.
+#[command]
+pub fn get_xbrl_mapping() -> Mapeo {
+    mapeo().clone()
+}
+
+/// Conceptos sin columna de una emisora, opcionalmente de un trimestre.
+#[command]
+pub async fn get_unmapped_concepts(emisora: String, trimestre: Option<String>) -> Result<Vec<ConceptoNoMapeado>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT emisora, trimestre, estado, concepto, valor, descripcion
+         FROM conceptos_no_mapeados
+         WHERE LOWER(emisora) = LOWER($1) AND ($2::text IS NULL OR LOWER(trimestre) = LOWER($2))
+         ORDER BY trimestre, estado, concepto",
+    )
+    .bind(&emisora)
+    .bind(trimestre)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter()
+        .map(|row| {
+            Ok(ConceptoNoMapeado {
+                emisora: row.try_get("emisora")?,
+                trimestre: row.try_get("trimestre")?,
+                estado: row.try_get("estado")?,
+                concepto: row.try_get("concepto")?,
+                valor: row.try_get("valor")?,
+                descripcion: row.try_get("descripcion")?,
+            })
+        })
+        .collect::<Result<_, sqlx::Error>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Conceptos sin columna más comunes entre emisoras: candidatos a agregar al mapeo.
+#[command]
+pub async fn get_unmapped_summary(limite: Option<i64>) -> Result<Vec<FrecuenciaConcepto>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT estado, concepto, COUNT(DISTINCT emisora) AS emisoras, COUNT(*) AS periodos,
+                MAX(descripcion) AS descripcion
+         FROM conceptos_no_mapeados
+         WHERE valor IS NOT NULL
+         GROUP BY estado, concepto
+         ORDER BY emisoras DESC, periodos DESC, concepto
+         LIMIT $1",
+    )
+    .bind(limite.unwrap_or(100))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter()
+        .map(|row| {
+            Ok(FrecuenciaConcepto {
+                estado: row.try_get("estado")?,
+                concepto: row.try_get("concepto")?,
+                emisoras: row.try_get("emisoras")?,
+                periodos: row.try_get("periodos")?,
+                descripcion: row.try_get("descripcion")?,
+            })
+        })
+        .collect::<Result<_, sqlx::Error>>()
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,92 @@
+use sqlx::{Executor, PgPool, Row};
+use std::collections::HashSet;
+
+// --- Migraciones del esquema ---
+// Los scripts de `sql/` van embebidos en el binario y se aplican en este orden
+// (las llaves foráneas mandan). Cada uno se registra en `schema_migrations` y no
+// se vuelve a correr. Los scripts usan `IF NOT EXISTS`, así que aplicarlos sobre
+// una base creada a mano desde pgAdmin sólo agrega lo que falte.
+// Para un cambio de esquema nuevo se agrega un script al final; nunca se edita
+// uno ya aplicado. La excepción es un script que no corresponde a lo que usa el
+// código (así pasó con cashflow y portfolio_transactions): se corrige y se agrega
+// al final otro que lleve las bases ya migradas al esquema corregido.
+
+pub const MIGRACIONES: &[(&str, &str)] = &[
+    ("001_secuencias", include_str!("../../sql/secuencias.sql")),
+    ("002_usuarios", include_str!("../../sql/usuarios.sql")),
+    ("003_portafolios", include_str!("../../sql/portfolios.sql")),
+    ("004_emisoras", include_str!("../../sql/emisoras.sql")),
+    ("005_portafolio_ticker", include_str!("../../sql/portfolio_ticker.sql")),
+    ("006_transacciones", include_str!("../../sql/transacciones.sql")),
+    ("007_cashflow", include_str!("../../sql/cashflow.sql")),
+    ("008_dividendos", include_str!("../../sql/dividendos.sql")),
+    ("009_intradia", include_str!("../../sql/intradia.sql")),
+    ("010_estado_flujo", include_str!("../../sql/estado_flujo.sql")),
+    ("011_estado_posicion", include_str!("../../sql/estado_posicion.sql")),
+    ("012_estado_resultado", include_str!("../../sql/estado_resultado.sql")),
+    ("013_portfolio_transactions", include_str!("../../sql/portfolio_transactions.sql")),
+    ("014_portfolio_members", include_str!("../../sql/portfolio_members.sql")),
+    ("015_api_cache", include_str!("../../sql/api_cache.sql")),
+    ("016_precios_diarios", include_str!("../../sql/precios_diarios.sql")),
+    ("017_calendario_excepciones", include_str!("../../sql/calendario_excepciones.sql")),
+    ("018_job_runs", include_str!("../../sql/job_runs.sql")),
+    ("019_financieros_sincronizados", include_str!("../../sql/financieros_sincronizados.sql")),
+    ("020_tasas_diarias", include_str!("../../sql/tasas_diarias.sql")),
+    ("021_divisas_diarias", include_str!("../../sql/divisas_diarias.sql")),
+    ("022_watchlists", include_str!("../../sql/watchlists.sql")),
+    ("023_alertas", include_str!("../../sql/alertas.sql")),
+    ("024_saved_screens", include_str!("../../sql/saved_screens.sql")),
+    ("025_dcf_supuestos", include_str!("../../sql/dcf_supuestos.sql")),
+    ("026_estado_posicion_activos", include_str!("../../sql/estado_posicion_activos.sql")),
+    ("027_calidad_datos", include_str!("../../sql/calidad_datos.sql")),
+    ("028_ingesta_financieros", include_str!("../../sql/ingesta_financieros.sql")),
+    ("029_conceptos_no_mapeados", include_str!("../../sql/conceptos_no_mapeados.sql")),
+    ("030_portafolio_columnas", include_str!("../../sql/portafolio_columnas.sql")),
+];
+
+const CREAR_TABLA: &str = "CREATE TABLE IF NOT EXISTS public.schema_migrations
+(
+    version text NOT NULL PRIMARY KEY,
+    aplicada timestamp with time zone NOT NULL DEFAULT now()
+)";
+
+async fn aplicadas(pool: &PgPool) -> Result<HashSet<String>, sqlx::Error> {
+    pool.execute(CREAR_TABLA).await?;
+    let rows = sqlx::query("SELECT version FROM schema_migrations").fetch_all(pool).await?;
+    rows.iter().map(|row| row.try_get(0)).collect()
+}
+
+/// Versiones que faltan por aplicar, en orden.
+pub async fn pendientes(pool: &PgPool) -> Result<Vec<&'static str>, sqlx::Error> {
+    let hechas = aplicadas(pool).await?;
+    Ok(MIGRACIONES
+        .iter()
+        .map(|(version, _)| *version)
+        .filter(|v| !hechas.contains(*v))
+        .collect())
+}
+
+/// Aplica las migraciones pendientes, cada una en su propia transacción.
+/// Regresa las versiones aplicadas.
+pub async fn migrar(pool: &PgPool) -> Result<Vec<&'static str>, Box<dyn std::error::Error + Send + Sync>> {
+    let hechas = aplicadas(pool).await?;
+    let mut nuevas = Vec::new();
+    for (version, sql) in MIGRACIONES {
+        if hechas.contains(*version) {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        // Sin parámetros: protocolo simple, que admite varias sentencias por script
+        (&mut *tx)
+            .execute(*sql)
+            .await
+            .map_err(|e| format!("Migración {} falló: {}", version, e))?;
+        sqlx::query("INSERT INTO schema_migrations (version) VALUES ($1)")
+            .bind(*version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        nuevas.push(*version);
+    }
+    Ok(nuevas)
+}
@@ -0,0 +1,313 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use tauri::command;
+use crate::periodos;
+use crate::ratios::{self, dividir_o, ratio, Categoria, Estados, Ratio};
+use crate::{calendario, db, get_data};
+
+// --- Múltiplos de valuación ---
+// Combinan el precio de mercado con los estados financieros: capitalización
+// (precio por acciones en circulación), valor empresa (capitalización más deuda
+// financiera e interés minoritario, menos efectivo) y las cifras de doce meses
+// (TTM, ver `periodos`). Sin cuatro trimestres no hay múltiplos: un P/U sobre un
+// solo trimestre saldría cuatro veces más alto.
+// Las acciones son las de todas las series de la emisora según el catálogo
+// (`emisoras.acciones_circulacion`); no hay historia de acciones, así que los
+// múltiplos históricos usan las actuales y el precio de cierre de la serie
+// indicada al fin de cada trimestre.
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MultiplosTrimestre {
+    pub trimestre: String,
+    /// Fecha del precio: hoy para los actuales, el último cierre del trimestre para los históricos
+    pub fecha: NaiveDate,
+    pub precio: f64,
+    pub multiplos: Vec<Ratio>,
+}
+
+/// Dónde está el múltiplo actual contra su propia historia.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RangoHistorico {
+    pub clave: String,
+    pub minimo: f64,
+    pub mediana: f64,
+    pub maximo: f64,
+    /// Porcentaje de trimestres con un múltiplo menor o igual al actual
+    pub percentil: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ValuacionEmisora {
+    pub emisora: String,
+    pub serie: String,
+    pub acciones: i64,
+    pub actual: Option<MultiplosTrimestre>,
+    pub historico: Vec<MultiplosTrimestre>,
+    pub rangos: Vec<RangoHistorico>,
+}
+
+/// Múltiplos de un periodo con cifras de doce meses.
+pub fn calcular(e: &Estados, precio: f64, acciones: i64) -> Vec<Ratio> {
+    let acciones = acciones as f64;
+    let capitalizacion = precio * acciones;
+    let deuda_financiera = e.deuda_financiera();
+    // Sin interés minoritario reportado se toma como cero
+    let minoritario = e.p("noncontrollinginterests").unwrap_or(0.0);
+    let efectivo = e.p("cashandcashequivalents");
+    let valor_empresa = deuda_financiera.zip(efectivo).map(|(d, x)| capitalizacion + d + minoritario - x);
+
+    let utilidad_controladora = e.r("profitlossattributabletoownersofparent");
+    let capital_controladora = e.p("equityattributabletoownersofparent");
+    let ingresos = e.r("revenue");
+    let utilidad_operativa = e.r("profitlossfromoperatingactivities");
+    // La depreciación viene en resultados y en flujos; se prefiere la de resultados
+    let depreciacion = e.r("depreciacion").filter(|d| *d != 0.0).or(e.f("depreciacion")).map(f64::abs);
+    let ebitda = utilidad_operativa.zip(depreciacion).map(|(u, d)| u + d);
+    let dividendos = e.f("dividendos_pagados").map(f64::abs);
+    let fcf = e.flujo_libre();
+
+    let ev = [
+        ("capitalizacion", Some(capitalizacion)),
+        ("othernoncurrentfinancialliabilities", e.p("othernoncurrentfinancialliabilities")),
+        ("noncurrentleaseliabilities", e.p("noncurrentleaseliabilities")),
+        ("noncontrollinginterests", Some(minoritario)),
+        ("cashandcashequivalents", efectivo),
+    ];
+    let con_ev = |extra: &[(&'static str, Option<f64>)]| ev.iter().chain(extra.iter()).copied().collect::<Vec<_>>();
+
+    use Categoria::Valuacion;
+    vec![
+        ratio(
+            "capitalizacion",
+            "Capitalización de mercado",
+            Valuacion,
+            "precio * acciones",
+            &[("precio", Some(precio)), ("acciones", Some(acciones))],
+            Some(capitalizacion),
+        ),
+        ratio(
+            "valor_empresa",
+            "Valor empresa",
+            Valuacion,
+            "capitalizacion + othernoncurrentfinancialliabilities + noncurrentleaseliabilities + noncontrollinginterests - cashandcashequivalents",
+            &ev,
+            valor_empresa,
+        ),
+        ratio(
+            "pe",
+            "Precio / utilidad",
+            Valuacion,
+            "capitalizacion / profitlossattributabletoownersofparent",
+            &[("capitalizacion", Some(capitalizacion)), ("profitlossattributabletoownersofparent", utilidad_controladora)],
+            dividir_o(Some(capitalizacion), utilidad_controladora).filter(|v| *v > 0.0),
+        ),
+        ratio(
+            "pb",
+            "Precio / valor en libros",
+            Valuacion,
+            "capitalizacion / equityattributabletoownersofparent",
+            &[("capitalizacion", Some(capitalizacion)), ("equityattributabletoownersofparent", capital_controladora)],
+            dividir_o(Some(capitalizacion), capital_controladora).filter(|v| *v > 0.0),
+        ),
+        ratio(
+            "ev_ebitda",
+            "Valor empresa / EBITDA",
+            Valuacion,
+            "valor_empresa / (profitlossfromoperatingactivities + |depreciacion|)",
+            &con_ev(&[("profitlossfromoperatingactivities", utilidad_operativa), ("depreciacion", depreciacion)]),
+            dividir_o(valor_empresa, ebitda).filter(|v| *v > 0.0),
+        ),
+        ratio(
+            "ev_ventas",
+            "Valor empresa / ventas",
+            Valuacion,
+            "valor_empresa / revenue",
+            &con_ev(&[("revenue", ingresos)]),
+            dividir_o(valor_empresa, ingresos),
+        ),
+        ratio(
+            "dividend_yield",
+            "Rendimiento por dividendo",
+            Valuacion,
+            "|dividendos_pagados| / capitalizacion",
+            &[("dividendos_pagados", dividendos), ("capitalizacion", Some(capitalizacion))],
+            dividir_o(dividendos, Some(capitalizacion)),
+        ),
+        ratio(
+            "fcf_yield",
+            "Rendimiento de flujo libre",
+            Valuacion,
+            "(flujo_operacion - |capex|) / capitalizacion",
+            &[
+                ("flujo_operacion", e.f("flujo_operacion")),
+                ("capex", e.f("capex").map(f64::abs)),
+                ("capitalizacion", Some(capitalizacion)),
+            ],
+            dividir_o(fcf, Some(capitalizacion)),
+        ),
+    ]
+}
+
+/// Serie a usar y acciones en circulación de toda la emisora. Sin serie se toma
+/// la que tiene más acciones.
+pub(crate) async fn acciones(pool: &PgPool, emisora: &str, serie: Option<&str>) -> Result<(String, String, i64), String> {
+    let rows = sqlx::query(
+        "SELECT emisoras, serie, acciones_circulacion FROM emisoras
+         WHERE UPPER(emisoras) = UPPER($1)
+         ORDER BY acciones_circulacion DESC NULLS LAST, serie",
+    )
+    .bind(emisora.trim())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Err(format!("No se encontró la emisora {} en el catálogo", emisora.trim()));
+    }
+    let mut total = 0i64;
+    let mut elegida = None;
+    for row in &rows {
+        let emisoras: String = row.try_get("emisoras").map_err(|e| e.to_string())?;
+        let s: String = row.try_get("serie").map_err(|e| e.to_string())?;
+        total += row.try_get::<Option<i64>, _>("acciones_circulacion").map_err(|e| e.to_string())?.unwrap_or(0);
+        let coincide = serie.map(|x| x.trim().eq_ignore_ascii_case(&s)).unwrap_or(elegida.is_none());
+        if coincide && elegida.is_none() {
+            elegida = Some((emisoras, s));
+        }
+    }
+    let (emisoras, serie) = elegida.ok_or_else(|| {
+        format!("No se encontró la emisora {} serie {} en el catálogo", emisora.trim(), serie.unwrap_or_default())
+    })?;
+    if total <= 0 {
+        return Err(format!("La emisora {} no tiene acciones en circulación en el catálogo", emisoras));
+    }
+    Ok((emisoras, serie, total))
+}
+
+/// Último precio: el del proveedor y, si no responde, el último cierre guardado.
+pub(crate) async fn precio_actual(pool: &PgPool, emisora: &str, serie: &str) -> Result<Option<(NaiveDate, f64)>, sqlx::Error> {
+    let cotizacion = get_data::get_cotizaciones(&format!("{}{}", emisora, serie)).await.ok().flatten();
+    if let Some(precio) = cotizacion.and_then(|c| c.ultimo_precio).filter(|p| *p > 0.0) {
+        return Ok(Some((calendario::sesion_vigente(Utc::now()), precio)));
+    }
+    let row = sqlx::query(
+        "SELECT fecha, cierre FROM precios_diarios WHERE emisoras = $1 AND serie = $2 ORDER BY fecha DESC LIMIT 1",
+    )
+    .bind(emisora)
+    .bind(serie)
+    .fetch_optional(pool)
+    .await?;
+    row.map(|row| Ok((row.try_get(0)?, row.try_get(1)?))).transpose()
+}
+
+/// Último cierre en o antes de cada fecha.
+pub(crate) async fn cierres(pool: &PgPool, emisora: &str, serie: &str, fechas: &[NaiveDate]) -> Result<Vec<Option<(NaiveDate, f64)>>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT c.fecha, c.cierre
+         FROM unnest($3::date[]) WITH ORDINALITY AS f(fecha, orden)
+         LEFT JOIN LATERAL (
+             SELECT fecha, cierre FROM precios_diarios p
+             WHERE p.emisoras = $1 AND p.serie = $2 AND p.fecha <= f.fecha
+             ORDER BY p.fecha DESC
+             LIMIT 1
+         ) c ON true
+         ORDER BY f.orden",
+    )
+    .bind(emisora)
+    .bind(serie)
+    .bind(fechas)
+    .fetch_all(pool)
+    .await?;
+    rows.iter()
+        .map(|row| {
+            let fecha: Option<NaiveDate> = row.try_get(0)?;
+            let cierre: Option<f64> = row.try_get(1)?;
+            Ok(fecha.zip(cierre))
+        })
+        .collect()
+}
+
+/// Múltiplos al cierre de cada trimestre con doce meses completos y posición guardada.
+async fn historico(pool: &PgPool, emisora: &str, serie: &str, acciones: i64) -> Result<Vec<MultiplosTrimestre>, Box<dyn std::error::Error + Send + Sync>> {
+    let candidatos = periodos::historia_ttm(pool, emisora).await?;
+    let fechas: Vec<NaiveDate> = candidatos.iter().map(|(_, t, _)| t.fin()).collect();
+    let precios = cierres(pool, emisora, serie, &fechas).await?;
+    Ok(candidatos
+        .into_iter()
+        .zip(precios)
+        .filter_map(|((trimestre, _, estados), precio)| {
+            let (fecha, precio) = precio?;
+            Some(MultiplosTrimestre {
+                trimestre,
+                fecha,
+                precio,
+                multiplos: calcular(&estados, precio, acciones),
+            })
+        })
+        .collect())
+}
+
+fn rangos(actual: &MultiplosTrimestre, historico: &[MultiplosTrimestre]) -> Vec<RangoHistorico> {
+    let mut por_clave: HashMap<&str, Vec<f64>> = HashMap::new();
+    for m in historico.iter().flat_map(|h| h.multiplos.iter()) {
+        if let Some(v) = m.valor {
+            por_clave.entry(m.clave.as_str()).or_default().push(v);
+        }
+    }
+    actual
+        .multiplos
+        .iter()
+        .filter_map(|m| {
+            let mut valores = por_clave.remove(m.clave.as_str())?;
+            valores.sort_by(|a, b| a.total_cmp(b));
+            let n = valores.len();
+            let mediana = if n % 2 == 1 { valores[n / 2] } else { (valores[n / 2 - 1] + valores[n / 2]) / 2.0 };
+            Some(RangoHistorico {
+                clave: m.clave.clone(),
+                minimo: valores[0],
+                mediana,
+                maximo: valores[n - 1],
+                percentil: m.valor.map(|v| valores.iter().filter(|x| **x <= v).count() as f64 / n as f64 * 100.0),
+            })
+        })
+        .collect()
+}
+
+/// Múltiplos actuales (último precio contra los doce meses más recientes) e
+/// históricos al cierre de cada trimestre, con el rango de cada múltiplo.
+#[command]
+pub async fn get_multiples(emisora: String, serie: Option<String>) -> Result<ValuacionEmisora, String> {
+    let pool = db::pool().await?;
+    let (emisora, serie, acciones) = acciones(pool, &emisora, serie.as_deref()).await?;
+    let ultimo = ratios::trimestres_recientes(pool, &emisora, 1).await.map_err(|e| e.to_string())?.pop();
+
+    let actual = async {
+        let trimestre = match ultimo {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let (estados, precio) = tokio::try_join!(
+            periodos::estados_ttm(pool, &emisora, &trimestre),
+            async { precio_actual(pool, &emisora, &serie).await.map_err(|e| e.into()) },
+        )?;
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(estados.zip(precio).map(|(estados, (fecha, precio))| MultiplosTrimestre {
+            trimestre,
+            fecha,
+            precio,
+            multiplos: calcular(&estados, precio, acciones),
+        }))
+    };
+    let (actual, historico) = tokio::try_join!(actual, historico(pool, &emisora, &serie, acciones))
+        .map_err(|e| format!("Error calculando múltiplos de {}: {}", emisora, e))?;
+    let rangos = actual.as_ref().map(|a| rangos(a, &historico)).unwrap_or_default();
+
+    Ok(ValuacionEmisora {
+        emisora,
+        serie,
+        acciones,
+        actual,
+        historico,
+        rangos,
+    })
+}
@@ -0,0 +1,574 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+use crate::activos::{get_finantial_position, COLUMNAS_FLUJO, COLUMNAS_POSICION, COLUMNAS_RESULTADO};
+use crate::db;
+use crate::ratios::Estados;
+
+// --- Normalización de periodos de los estados financieros ---
+// Los estados se guardan una fila por `trimestre`, pero no todas las emisoras
+// reportan igual: unas traen cifras del trimestre (discretas) y otras el
+// acumulado del año a la fecha (el flujo de efectivo casi siempre es acumulado).
+// Aquí se detecta el modo de cada estado, se derivan los trimestres discretos y
+// a partir de ellos los doce meses (TTM) y los totales por ejercicio, para que
+// razones y crecimientos comparen periodos equivalentes.
+// La detección compara trimestres consecutivos del mismo año en una partida que
+// siempre se acumula (ingresos, depreciación): en un reporte acumulado crece
+// como 1, 2, 3, 4 trimestres; en uno discreto se mantiene parecida.
+
+/// Partidas del flujo que son saldos, no movimientos del periodo: no se restan ni se suman.
+const SALDOS_FLUJO: [&str; 1] = ["efectivo_final"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Estado {
+    Resultados,
+    Flujos,
+}
+
+impl Estado {
+    fn tabla(&self) -> &'static str {
+        match self {
+            Estado::Resultados => "estado_resultado_trimestral",
+            Estado::Flujos => "estado_flujos",
+        }
+    }
+
+    fn columnas(&self) -> &'static [&'static str] {
+        match self {
+            Estado::Resultados => &COLUMNAS_RESULTADO,
+            Estado::Flujos => &COLUMNAS_FLUJO,
+        }
+    }
+
+    /// Partidas que siempre se acumulan a lo largo del año, en orden de preferencia.
+    fn partidas_testigo(&self) -> &'static [&'static str] {
+        match self {
+            Estado::Resultados => &["revenue", "costofsales"],
+            Estado::Flujos => &["depreciacion", "capex"],
+        }
+    }
+
+    /// Modo que se asume cuando no hay trimestres suficientes para decidir.
+    fn modo_por_defecto(&self) -> Modo {
+        match self {
+            Estado::Resultados => Modo::Discreto,
+            Estado::Flujos => Modo::Acumulado,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Modo {
+    Acumulado,
+    Discreto,
+}
+
+/// Año y trimestre (1 a 4) de un periodo.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Trimestre {
+    pub anio: i32,
+    pub q: u32,
+}
+
+impl Trimestre {
+    /// Entiende las formas en que el proveedor nombra los trimestres: `2024Q4`,
+    /// `2024-4T`, `4T_2024`, `Q4 2024`...
+    pub fn parse(s: &str) -> Option<Trimestre> {
+        let s = s.trim().to_uppercase();
+        let digitos: Vec<(usize, String)> = {
+            let mut grupos = Vec::new();
+            let mut actual = String::new();
+            let mut inicio = 0;
+            for (i, c) in s.char_indices() {
+                if c.is_ascii_digit() {
+                    if actual.is_empty() {
+                        inicio = i;
+                    }
+                    actual.push(c);
+                } else if !actual.is_empty() {
+                    grupos.push((inicio, std::mem::take(&mut actual)));
+                }
+            }
+            if !actual.is_empty() {
+                grupos.push((inicio, actual));
+            }
+            grupos
+        };
+        let anio = digitos.iter().find(|(_, g)| g.len() == 4)?.1.parse().ok()?;
+        let q = digitos
+            .iter()
+            .find(|(i, g)| {
+                g.len() == 1
+                    && (s[..*i].ends_with('Q') || s[i + 1..].starts_with('T') || s[i + 1..].starts_with('Q'))
+            })
+            .or_else(|| digitos.iter().find(|(_, g)| g.len() == 1))?
+            .1
+            .parse()
+            .ok()?;
+        (1..=4).contains(&q).then_some(Trimestre { anio, q })
+    }
+
+    pub fn anterior(&self) -> Trimestre {
+        if self.q == 1 {
+            Trimestre { anio: self.anio - 1, q: 4 }
+        } else {
+            Trimestre { anio: self.anio, q: self.q - 1 }
+        }
+    }
+
+    pub fn siguiente(&self) -> Trimestre {
+        if self.q == 4 {
+            Trimestre { anio: self.anio + 1, q: 1 }
+        } else {
+            Trimestre { anio: self.anio, q: self.q + 1 }
+        }
+    }
+
+    pub fn etiqueta(&self) -> String {
+        format!("{}Q{}", self.anio, self.q)
+    }
+
+    /// Etiqueta con el mismo formato que `modelo`, otra etiqueta del proveedor
+    /// (`4T_2024` con 2023Q1 da `1T_2023`). Si `modelo` no se entiende, `etiqueta()`.
+    pub fn etiqueta_como(&self, modelo: &str) -> String {
+        let base = match Trimestre::parse(modelo) {
+            Some(t) => t,
+            None => return self.etiqueta(),
+        };
+        let (anio, q) = (base.anio.to_string(), base.q.to_string());
+        let (mut anio_puesto, mut q_puesto) = (false, false);
+        let mut etiqueta = String::new();
+        let mut grupo = String::new();
+        let mut cerrar = |grupo: &mut String, etiqueta: &mut String| {
+            if !anio_puesto && *grupo == anio {
+                etiqueta.push_str(&self.anio.to_string());
+                anio_puesto = true;
+            } else if !q_puesto && *grupo == q {
+                etiqueta.push_str(&self.q.to_string());
+                q_puesto = true;
+            } else {
+                etiqueta.push_str(grupo);
+            }
+            grupo.clear();
+        };
+        for c in modelo.chars() {
+            if c.is_ascii_digit() {
+                grupo.push(c);
+            } else {
+                if !grupo.is_empty() {
+                    cerrar(&mut grupo, &mut etiqueta);
+                }
+                etiqueta.push(c);
+            }
+        }
+        if !grupo.is_empty() {
+            cerrar(&mut grupo, &mut etiqueta);
+        }
+        etiqueta
+    }
+
+    /// Último día natural del trimestre.
+    pub fn fin(&self) -> NaiveDate {
+        let (anio, mes) = if self.q == 4 { (self.anio + 1, 1) } else { (self.anio, self.q * 3 + 1) };
+        NaiveDate::from_ymd_opt(anio, mes, 1).expect("fecha de trimestre válida") - chrono::Duration::days(1)
+    }
+}
+
+/// Un periodo con sus partidas; `trimestre` es la etiqueta tal como está guardada.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Periodo {
+    pub trimestre: String,
+    pub periodo: Trimestre,
+    pub valores: HashMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Deteccion {
+    pub estado: Estado,
+    pub modo: Modo,
+    /// Partida con la que se decidió; vacía si se usó el modo por defecto
+    pub partida: Option<String>,
+    pub votos_acumulado: usize,
+    pub votos_discreto: usize,
+}
+
+async fn filas(pool: &PgPool, emisora: &str, tabla: &str, columnas: &[&str]) -> Result<Vec<Periodo>, sqlx::Error> {
+    let sql = format!(
+        "SELECT trimestre, {} FROM public.{} WHERE LOWER(emisora) = LOWER($1)",
+        columnas.join(", "),
+        tabla
+    );
+    let rows = sqlx::query(&sql).bind(emisora).fetch_all(pool).await?;
+    let mut periodos = Vec::new();
+    for row in rows {
+        let trimestre: String = row.try_get(0)?;
+        let periodo = match Trimestre::parse(&trimestre) {
+            Some(p) => p,
+            None => continue,
+        };
+        let mut valores = HashMap::new();
+        for (i, col) in columnas.iter().enumerate() {
+            if let Some(v) = row.try_get::<Option<f64>, _>(i + 1)? {
+                valores.insert(col.to_string(), v);
+            }
+        }
+        periodos.push(Periodo { trimestre, periodo, valores });
+    }
+    periodos.sort_by_key(|p| p.periodo);
+    periodos.dedup_by_key(|p| p.periodo);
+    Ok(periodos)
+}
+
+/// Filas guardadas de un estado, ordenadas por periodo. Las etiquetas que no se
+/// entienden se omiten.
+pub async fn serie(pool: &PgPool, emisora: &str, estado: Estado) -> Result<Vec<Periodo>, sqlx::Error> {
+    filas(pool, emisora, estado.tabla(), estado.columnas()).await
+}
+
+/// Posiciones financieras guardadas (saldos al cierre de cada trimestre). No se
+/// piden al proveedor las que falten.
+pub async fn posiciones(pool: &PgPool, emisora: &str) -> Result<Vec<Periodo>, sqlx::Error> {
+    filas(pool, emisora, "estado_posicion", &COLUMNAS_POSICION).await
+}
+
+/// Decide si la serie está acumulada o es discreta. Para cada par de trimestres
+/// consecutivos del mismo año compara el cociente observado contra el esperado
+/// en cada modo (q+1)/q contra 1, en escala logarítmica.
+pub fn detectar_modo(estado: Estado, serie: &[Periodo]) -> Deteccion {
+    let por_periodo: BTreeMap<Trimestre, &Periodo> = serie.iter().map(|p| (p.periodo, p)).collect();
+    for partida in estado.partidas_testigo() {
+        let (mut acumulado, mut discreto) = (0, 0);
+        for (t, p) in &por_periodo {
+            if t.q == 1 {
+                continue;
+            }
+            let previo = match por_periodo.get(&t.anterior()) {
+                Some(previo) => previo,
+                None => continue,
+            };
+            let (actual, anterior) = match (p.valores.get(*partida), previo.valores.get(*partida)) {
+                (Some(a), Some(b)) if *a != 0.0 && *b != 0.0 => (a.abs(), b.abs()),
+                _ => continue,
+            };
+            let observado = (actual / anterior).ln();
+            let esperado_acumulado = (t.q as f64 / (t.q - 1) as f64).ln();
+            if (observado - esperado_acumulado).abs() < observado.abs() {
+                acumulado += 1;
+            } else {
+                discreto += 1;
+            }
+        }
+        if acumulado + discreto > 0 {
+            return Deteccion {
+                estado,
+                modo: if acumulado > discreto { Modo::Acumulado } else { Modo::Discreto },
+                partida: Some(partida.to_string()),
+                votos_acumulado: acumulado,
+                votos_discreto: discreto,
+            };
+        }
+    }
+    Deteccion {
+        estado,
+        modo: estado.modo_por_defecto(),
+        partida: None,
+        votos_acumulado: 0,
+        votos_discreto: 0,
+    }
+}
+
+fn es_saldo(estado: Estado, columna: &str) -> bool {
+    estado == Estado::Flujos && SALDOS_FLUJO.contains(&columna)
+}
+
+/// Trimestres discretos. En modo acumulado, el trimestre n es el acumulado de n
+/// menos el de n-1 del mismo año; si falta el anterior, ese trimestre se omite.
+pub fn discretos(estado: Estado, serie: &[Periodo], modo: Modo) -> Vec<Periodo> {
+    if modo == Modo::Discreto {
+        return serie.to_vec();
+    }
+    let por_periodo: BTreeMap<Trimestre, &Periodo> = serie.iter().map(|p| (p.periodo, p)).collect();
+    let mut resultado = Vec::new();
+    for (t, p) in &por_periodo {
+        if t.q == 1 {
+            resultado.push((*p).clone());
+            continue;
+        }
+        let previo = match por_periodo.get(&t.anterior()) {
+            Some(previo) => previo,
+            None => continue,
+        };
+        let valores = p
+            .valores
+            .iter()
+            .map(|(col, v)| {
+                let valor = if es_saldo(estado, col) { *v } else { v - previo.valores.get(col).copied().unwrap_or(0.0) };
+                (col.clone(), valor)
+            })
+            .collect();
+        resultado.push(Periodo { trimestre: p.trimestre.clone(), periodo: *t, valores });
+    }
+    resultado
+}
+
+/// Suma de cuatro trimestres discretos consecutivos. Los saldos toman el valor del último.
+fn sumar(estado: Estado, trimestres: &[&Periodo]) -> HashMap<String, f64> {
+    let mut total: HashMap<String, f64> = HashMap::new();
+    for p in trimestres {
+        for (col, v) in &p.valores {
+            if es_saldo(estado, col) {
+                continue;
+            }
+            *total.entry(col.clone()).or_insert(0.0) += v;
+        }
+    }
+    if let Some(ultimo) = trimestres.last() {
+        for (col, v) in &ultimo.valores {
+            if es_saldo(estado, col) {
+                total.insert(col.clone(), *v);
+            }
+        }
+    }
+    total
+}
+
+/// Doce meses que terminan en `hasta`, o `None` si falta alguno de los cuatro trimestres.
+pub fn ttm(estado: Estado, discretos: &[Periodo], hasta: Trimestre) -> Option<HashMap<String, f64>> {
+    let por_periodo: HashMap<Trimestre, &Periodo> = discretos.iter().map(|p| (p.periodo, p)).collect();
+    let mut trimestres = Vec::with_capacity(4);
+    let mut t = hasta;
+    for _ in 0..4 {
+        trimestres.push(*por_periodo.get(&t)?);
+        t = t.anterior();
+    }
+    trimestres.reverse();
+    Some(sumar(estado, &trimestres))
+}
+
+/// Totales por ejercicio (sólo años con los cuatro trimestres).
+pub fn anuales(estado: Estado, discretos: &[Periodo]) -> BTreeMap<i32, HashMap<String, f64>> {
+    let anios: std::collections::BTreeSet<i32> = discretos.iter().map(|p| p.periodo.anio).collect();
+    anios
+        .into_iter()
+        .filter_map(|anio| ttm(estado, discretos, Trimestre { anio, q: 4 }).map(|total| (anio, total)))
+        .collect()
+}
+
+/// Serie discreta de un estado junto con la detección de su modo.
+pub async fn serie_discreta(
+    pool: &PgPool,
+    emisora: &str,
+    estado: Estado,
+) -> Result<(Deteccion, Vec<Periodo>), sqlx::Error> {
+    let original = serie(pool, emisora, estado).await?;
+    let deteccion = detectar_modo(estado, &original);
+    let discretos = discretos(estado, &original, deteccion.modo);
+    Ok((deteccion, discretos))
+}
+
+/// Estados de doce meses al cierre de `trimestre`: resultados y flujos TTM y la
+/// posición financiera de ese trimestre. `None` si no hay cuatro trimestres.
+pub async fn estados_ttm(
+    pool: &PgPool,
+    emisora: &str,
+    trimestre: &str,
+) -> Result<Option<Estados>, Box<dyn std::error::Error + Send + Sync>> {
+    let hasta = match Trimestre::parse(trimestre) {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let ((_, resultados), (_, flujos)) = tokio::try_join!(
+        serie_discreta(pool, emisora, Estado::Resultados),
+        serie_discreta(pool, emisora, Estado::Flujos),
+    )?;
+    let (resultado, flujo) = match (ttm(Estado::Resultados, &resultados, hasta), ttm(Estado::Flujos, &flujos, hasta)) {
+        (Some(r), Some(f)) => (r, f),
+        _ => return Ok(None),
+    };
+    let posicion = get_finantial_position(pool, emisora, trimestre).await?;
+    Ok(Some(Estados { flujo, posicion, resultado }))
+}
+
+/// Estados de doce meses de cada trimestre guardado con posición y cuatro
+/// trimestres de resultados y flujos, del más antiguo al más reciente. Sólo lee
+/// lo guardado; no pide nada al proveedor.
+pub async fn historia_ttm(pool: &PgPool, emisora: &str) -> Result<Vec<(String, Trimestre, Estados)>, sqlx::Error> {
+    let ((_, resultados), (_, flujos), posiciones) = tokio::try_join!(
+        serie_discreta(pool, emisora, Estado::Resultados),
+        serie_discreta(pool, emisora, Estado::Flujos),
+        posiciones(pool, emisora),
+    )?;
+    Ok(posiciones
+        .into_iter()
+        .filter_map(|posicion| {
+            let t = posicion.periodo;
+            let resultado = ttm(Estado::Resultados, &resultados, t)?;
+            let flujo = ttm(Estado::Flujos, &flujos, t)?;
+            Some((posicion.trimestre, t, Estados { flujo, posicion: posicion.valores, resultado }))
+        })
+        .collect())
+}
+
+/// Estados por ejercicio completo: resultados y flujos de los cuatro trimestres y
+/// la posición al cierre del cuarto. Sólo años con los tres estados.
+pub async fn ejercicios(pool: &PgPool, emisora: &str) -> Result<BTreeMap<i32, Estados>, sqlx::Error> {
+    let ((_, resultados), (_, flujos), posiciones) = tokio::try_join!(
+        serie_discreta(pool, emisora, Estado::Resultados),
+        serie_discreta(pool, emisora, Estado::Flujos),
+        posiciones(pool, emisora),
+    )?;
+    let mut resultados = anuales(Estado::Resultados, &resultados);
+    let mut flujos = anuales(Estado::Flujos, &flujos);
+    Ok(posiciones
+        .into_iter()
+        .filter(|p| p.periodo.q == 4)
+        .filter_map(|posicion| {
+            let anio = posicion.periodo.anio;
+            let resultado = resultados.remove(&anio)?;
+            let flujo = flujos.remove(&anio)?;
+            Some((anio, Estados { flujo, posicion: posicion.valores, resultado }))
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PeriodoNormalizado {
+    pub trimestre: String,
+    pub resultado: Option<HashMap<String, f64>>,
+    pub flujo: Option<HashMap<String, f64>>,
+    pub resultado_ttm: Option<HashMap<String, f64>>,
+    pub flujo_ttm: Option<HashMap<String, f64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Ejercicio {
+    pub anio: i32,
+    pub resultado: Option<HashMap<String, f64>>,
+    pub flujo: Option<HashMap<String, f64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PeriodosEmisora {
+    pub emisora: String,
+    pub resultados: Deteccion,
+    pub flujos: Deteccion,
+    pub trimestres: Vec<PeriodoNormalizado>,
+    pub ejercicios: Vec<Ejercicio>,
+}
+
+/// Trimestres discretos, TTM y ejercicios de una emisora, con el modo detectado.
+#[command]
+pub async fn get_periodos(emisora: String) -> Result<PeriodosEmisora, String> {
+    let pool = db::pool().await?;
+    let ((det_resultados, resultados), (det_flujos, flujos)) = tokio::try_join!(
+        serie_discreta(pool, &emisora, Estado::Resultados),
+        serie_discreta(pool, &emisora, Estado::Flujos),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut etiquetas: BTreeMap<Trimestre, String> = BTreeMap::new();
+    for p in resultados.iter().chain(flujos.iter()) {
+        etiquetas.entry(p.periodo).or_insert_with(|| p.trimestre.clone());
+    }
+    let buscar = |serie: &[Periodo], t: Trimestre| serie.iter().find(|p| p.periodo == t).map(|p| p.valores.clone());
+    let trimestres = etiquetas
+        .into_iter()
+        .map(|(t, trimestre)| PeriodoNormalizado {
+            trimestre,
+            resultado: buscar(&resultados, t),
+            flujo: buscar(&flujos, t),
+            resultado_ttm: ttm(Estado::Resultados, &resultados, t),
+            flujo_ttm: ttm(Estado::Flujos, &flujos, t),
+        })
+        .collect();
+
+    let mut anuales_resultado = anuales(Estado::Resultados, &resultados);
+    let mut anuales_flujo = anuales(Estado::Flujos, &flujos);
+    let anios: std::collections::BTreeSet<i32> = anuales_resultado.keys().chain(anuales_flujo.keys()).copied().collect();
+    let ejercicios = anios
+        .into_iter()
+        .map(|anio| Ejercicio {
+            anio,
+            resultado: anuales_resultado.remove(&anio),
+            flujo: anuales_flujo.remove(&anio),
+        })
+        .collect();
+
+    Ok(PeriodosEmisora {
+        emisora,
+        resultados: det_resultados,
+        flujos: det_flujos,
+        trimestres,
+        ejercicios,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn periodo(anio: i32, q: u32, revenue: f64) -> Periodo {
+        Periodo {
+            trimestre: format!("{}Q{}", anio, q),
+            periodo: Trimestre { anio, q },
+            valores: HashMap::from([("revenue".to_string(), revenue)]),
+        }
+    }
+
+    #[test]
+    fn parse_entiende_las_etiquetas_del_proveedor() {
+        let t = Some(Trimestre { anio: 2024, q: 4 });
+        assert_eq!(Trimestre::parse("2024Q4"), t);
+        assert_eq!(Trimestre::parse("4T_2024"), t);
+        assert_eq!(Trimestre::parse("Q4 2024"), t);
+        assert_eq!(Trimestre::parse("2024-4T"), t);
+        assert_eq!(Trimestre::parse(" 2024q4 "), t);
+    }
+
+    #[test]
+    fn parse_rechaza_etiquetas_incompletas() {
+        assert_eq!(Trimestre::parse("2024"), None);
+        assert_eq!(Trimestre::parse("5T_2024"), None);
+        assert_eq!(Trimestre::parse("Q4 24"), None);
+        assert_eq!(Trimestre::parse(""), None);
+    }
+
+    #[test]
+    fn detecta_serie_acumulada() {
+        let serie: Vec<Periodo> = [100.0, 200.0, 300.0, 400.0]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| periodo(2024, i as u32 + 1, *v))
+            .collect();
+        let deteccion = detectar_modo(Estado::Resultados, &serie);
+        assert_eq!(deteccion.modo, Modo::Acumulado);
+        assert_eq!(deteccion.partida.as_deref(), Some("revenue"));
+        assert_eq!((deteccion.votos_acumulado, deteccion.votos_discreto), (3, 0));
+    }
+
+    #[test]
+    fn detecta_serie_discreta() {
+        let serie: Vec<Periodo> = [100.0, 105.0, 98.0, 110.0]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| periodo(2024, i as u32 + 1, *v))
+            .collect();
+        let deteccion = detectar_modo(Estado::Resultados, &serie);
+        assert_eq!(deteccion.modo, Modo::Discreto);
+        assert_eq!((deteccion.votos_acumulado, deteccion.votos_discreto), (0, 3));
+    }
+
+    #[test]
+    fn sin_trimestres_consecutivos_usa_el_modo_por_defecto() {
+        // Q1 no vota y Q3 no tiene a Q2 para comparar
+        let serie = vec![periodo(2024, 1, 100.0), periodo(2024, 3, 300.0)];
+        let deteccion = detectar_modo(Estado::Resultados, &serie);
+        assert_eq!(deteccion.modo, Modo::Discreto);
+        assert_eq!(deteccion.partida, None);
+        assert_eq!(detectar_modo(Estado::Flujos, &[]).modo, Modo::Acumulado);
+    }
+}
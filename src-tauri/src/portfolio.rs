@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
-use postgres::{Client, NoTls, Error};
+use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
-use crate::get_data;
+use crate::db;
+use crate::portfolio_members::{self, Rol};
 
 // --- DDL de referencia para la tabla intradia_data ---
 /*
@@ -43,7 +44,8 @@ pub struct PortfolioSummary {
 // }
 
 #[tauri::command]
-pub fn add_portfolio_transaction(
+pub async fn add_portfolio_transaction(
+    portfolio_id: i32,
     user_id: i32,
     ticker: String,
     transaction_type: String,
@@ -51,30 +53,48 @@ pub fn add_portfolio_transaction(
     price: f64,
     transaction_date: DateTime<Utc>
 ) -> Result<(), String> {
-    let mut client = connect_db()?;
-    let mut db_transaction = client.transaction().map_err(|e| e.to_string())?;
+    let pool = db::pool().await?;
+    let mut db_transaction = pool.begin().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut db_transaction, portfolio_id, user_id, Rol::Editor).await?;
 
     let total_amount = quantity * price;
 
-    db_transaction.execute(
-        "INSERT INTO portfolio_transactions (user_id, ticker, transaction_type, quantity, price, transaction_date, total_amount) VALUES ($1, $2, $3, $4, $5, $6, $7)",
-        &[&user_id, &ticker, &transaction_type, &quantity, &price, &transaction_date, &total_amount],
-    ).map_err(|e| e.to_string())?;
-
-    db_transaction.commit().map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO portfolio_transactions (portfolio_id, user_id, ticker, transaction_type, quantity, price, transaction_date, total_amount) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(portfolio_id)
+    .bind(user_id)
+    .bind(&ticker)
+    .bind(&transaction_type)
+    .bind(quantity)
+    .bind(price)
+    .bind(transaction_date)
+    .bind(total_amount)
+    .execute(&mut *db_transaction)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    db_transaction.commit().await.map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_portfolio_summary(user_id: i32) -> Result<PortfolioSummary, String> {
-    let mut client = connect_db()?;
+pub async fn get_portfolio_summary(portfolio_id: i32, user_id: i32) -> Result<PortfolioSummary, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, user_id, Rol::Viewer).await?;
 
     let mut holdings_map: HashMap<String, (f64, f64)> = HashMap::new();
-    for row in client.query("SELECT ticker, quantity, total_amount FROM portfolio_transactions WHERE user_id = $1 AND transaction_type IN ('BUY', 'SELL')", &[&user_id]).map_err(|e| e.to_string())? {
-        let ticker: String = row.get("ticker");
-        let quantity: f64 = row.get("quantity");
-        let total_amount: f64 = row.get("total_amount");
+    let rows = sqlx::query("SELECT ticker, quantity, total_amount FROM portfolio_transactions WHERE portfolio_id = $1 AND transaction_type IN ('BUY', 'SELL')")
+        .bind(portfolio_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let ticker: String = row.try_get("ticker").map_err(|e| e.to_string())?;
+        let quantity: f64 = row.try_get("quantity").map_err(|e| e.to_string())?;
+        let total_amount: f64 = row.try_get("total_amount").map_err(|e| e.to_string())?;
 
         let entry = holdings_map.entry(ticker).or_insert((0.0, 0.0));
         entry.0 += quantity;
@@ -87,7 +107,7 @@ pub fn get_portfolio_summary(user_id: i32) -> Result<PortfolioSummary, String> {
 
     for (ticker, (quantity, total_cost)) in holdings_map.iter() {
         if *quantity > 0.0 {
-            let market_price = get_market_price(ticker).unwrap_or(0.0);
+            let market_price = get_market_price(ticker).await.unwrap_or(0.0);
             let market_value = quantity * market_price;
             let average_cost = total_cost / quantity;
             let unrealized_pnl = market_value - total_cost;
@@ -121,114 +141,162 @@ pub fn get_portfolio_summary(user_id: i32) -> Result<PortfolioSummary, String> {
 // --- Ticker Tape ---
 // Mover a ticker_tape.rs
 
-fn connect_db() -> Result<Client, String> {
-    Client::connect("host=localhost user=garden_admin password=password dbname=dalia_db", NoTls)
-        .map_err(|e| format!("DB connection error: {}", e))
-}
-
-fn get_market_price(ticker: &str) -> Result<f64, Error> {
+async fn get_market_price(ticker: &str) -> Result<f64, String> {
     println!("Fetching market price for {}", ticker);
     Ok(150.75)
 }
 
 // --- FUNCIONES LEGACY RESTAURADAS PARA COMPATIBILIDAD ---
 
-pub fn add_ticker(
-    pg_client: &mut Client,
+pub async fn add_ticker(
+    pool: &PgPool,
     portafolio_id: i32,
     ticker: &str,
     emisoras: &str,
     serie: &str,
     fecha: Option<chrono::DateTime<chrono::Utc>>,
-) -> Result<i32, Error> {
-    if let Some(fecha) = fecha {
-        let row = pg_client.query_one(
-            "INSERT INTO portafolio_ticker (portafolio_id, ticker, emisoras, serie, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING id",
-            &[&portafolio_id, &ticker, &emisoras, &serie, &fecha],
-        )?;
-        let id: i32 = row.get(0);
-        Ok(id)
+) -> Result<i32, sqlx::Error> {
+    let row = if let Some(fecha) = fecha {
+        sqlx::query("INSERT INTO portafolio_ticker (portafolio_id, ticker, emisoras, serie, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING id")
+            .bind(portafolio_id)
+            .bind(ticker)
+            .bind(emisoras)
+            .bind(serie)
+            .bind(fecha)
+            .fetch_one(pool)
+            .await?
     } else {
-        let row = pg_client.query_one(
-            "INSERT INTO portafolio_ticker (portafolio_id, ticker, emisoras, serie) VALUES ($1, $2, $3, $4) RETURNING id",
-            &[&portafolio_id, &ticker, &emisoras, &serie],
-        )?;
-        let id: i32 = row.get(0);
-        Ok(id)
-    }
+        sqlx::query("INSERT INTO portafolio_ticker (portafolio_id, ticker, emisoras, serie) VALUES ($1, $2, $3, $4) RETURNING id")
+            .bind(portafolio_id)
+            .bind(ticker)
+            .bind(emisoras)
+            .bind(serie)
+            .fetch_one(pool)
+            .await?
+    };
+    row.try_get(0)
 }
 
-pub fn list_tickers(
-    pg_client: &mut Client,
+pub async fn list_tickers(
+    pool: &PgPool,
     portafolio_id: i32
-) -> Result<Vec<(i32, String, String, String)>, Error> {
-    let rows = pg_client.query(
-        "SELECT id, ticker, emisoras, serie FROM portafolio_ticker WHERE portafolio_id = $1 ORDER BY id",
-        &[&portafolio_id],
-    )?;
+) -> Result<Vec<(i32, String, String, String)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, ticker, emisoras, serie FROM portafolio_ticker WHERE portafolio_id = $1 ORDER BY id")
+        .bind(portafolio_id)
+        .fetch_all(pool)
+        .await?;
     let mut tickers = Vec::new();
     for row in rows {
-        let id: i32 = row.get(0);
-        let ticker: String = row.get(1);
-        let emisoras: String = row.get(2);
-        let serie: String = row.get(3);
+        let id: i32 = row.try_get(0)?;
+        let ticker: String = row.try_get(1)?;
+        let emisoras: String = row.try_get(2)?;
+        let serie: String = row.try_get(3)?;
         tickers.push((id, ticker, emisoras, serie));
     }
     Ok(tickers)
 }
 
-pub fn add_transaction(
-    pg_client: &mut Client,
+pub async fn add_transaction(
+    pool: &PgPool,
     portafolio_ticker_id: i32,
     tipo: &str,
     cantidad: i32,
     precio: f64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    pg_client.execute(
-        "INSERT INTO transacciones (portafolio_ticker_id, tipo, cantidad, precio) VALUES ($1, $2, $3, $4)",
-        &[&portafolio_ticker_id, &tipo, &cantidad, &precio],
-    )?;
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    sqlx::query("INSERT INTO transacciones (portafolio_ticker_id, tipo, cantidad, precio) VALUES ($1, $2, $3, $4)")
+        .bind(portafolio_ticker_id)
+        .bind(tipo)
+        .bind(cantidad)
+        .bind(precio)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
-pub fn list_transactions(
-    pg_client: &mut Client,
+pub async fn list_transactions(
+    pool: &PgPool,
     portfolio_ticker_id: i32
-) -> Result<Vec<(i32, String, i32, f64, chrono::NaiveDateTime)>, Box<dyn std::error::Error>> {
-    let rows = pg_client.query(
-        "SELECT id, tipo, cantidad, precio, fecha FROM transacciones WHERE portafolio_ticker_id = $1 ORDER BY fecha DESC",
-        &[&portfolio_ticker_id],
-    )?;
+) -> Result<Vec<(i32, String, i32, f64, chrono::NaiveDateTime)>, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = sqlx::query("SELECT id, tipo, cantidad, precio, fecha FROM transacciones WHERE portafolio_ticker_id = $1 ORDER BY fecha DESC")
+        .bind(portfolio_ticker_id)
+        .fetch_all(pool)
+        .await?;
     let mut transactions = Vec::new();
     for row in rows {
-        let id: i32 = row.get(0);
-        let tipo: String = row.get(1);
-        let cantidad: i32 = row.get(2);
-        let precio: f64 = row.get(3);
-        let fecha: chrono::NaiveDateTime = row.get(4);
+        let id: i32 = row.try_get(0)?;
+        let tipo: String = row.try_get(1)?;
+        let cantidad: i32 = row.try_get(2)?;
+        let precio: f64 = row.try_get(3)?;
+        let fecha: chrono::NaiveDateTime = row.try_get(4)?;
         transactions.push((id, tipo, cantidad, precio, fecha));
     }
     Ok(transactions)
 }
 
-pub fn remove_ticker(pg_client: &mut Client, id: i32) -> Result<(), Box<dyn std::error::Error>> {
-    let _ = pg_client.execute(
-        "DELETE FROM portafolio_ticker WHERE id = $1",
-        &[&id],
-    )?;
+pub async fn remove_ticker(pool: &PgPool, id: i32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    sqlx::query("DELETE FROM portafolio_ticker WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
-pub fn add_portfolio(
-    pg_client: &mut Client,
+pub async fn ensure_user_exists(pool: &PgPool, usuario_id: i32, nombre: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let rows = sqlx::query("INSERT INTO usuarios (id, nombre) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING")
+        .bind(usuario_id)
+        .bind(nombre)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    if rows == 0 {
+        println!("Usuario '{}' (id: {}) ya existe. Iniciando sesión...", nombre, usuario_id);
+    } else {
+        println!("Usuario '{}' (id: {}) creado exitosamente.", nombre, usuario_id);
+    }
+    Ok(())
+}
+
+/// Borra todos los tickers de un portafolio; sus transacciones y dividendos se
+/// van en cascada. No revisa permisos: es para la línea de comandos de
+/// administración, no se expone como comando de la app. Devuelve cuántos
+/// tickers se borraron.
+pub async fn borrar_todos_los_tickers_y_transacciones(pool: &PgPool, portafolio_id: i32) -> Result<usize, sqlx::Error> {
+    let tickers = list_tickers(pool, portafolio_id).await?;
+    let mut tx = pool.begin().await?;
+    for (id, _, _, _) in &tickers {
+        sqlx::query("DELETE FROM portafolio_ticker WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    for (_, ticker, emisoras, serie) in &tickers {
+        println!("Ticker '{}' ({}/{}) eliminado.", ticker, emisoras, serie);
+    }
+    Ok(tickers.len())
+}
+
+pub async fn add_portfolio(
+    pool: &PgPool,
     usuario_id: i32,
     nombre: &str,
-) -> Result<String, Error> {
+) -> Result<String, sqlx::Error> {
     let id = format!("{:09x}", rand::random::<u32>());
-    pg_client.execute(
-        "INSERT INTO portafolios (id_hex, usuario_id, nombre) VALUES ($1, $2, $3)",
-        &[&id, &usuario_id, &nombre],
-    )?;
+    // Portafolio y owner juntos: sin la fila de owner nadie podría abrirlo
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query("INSERT INTO portafolios (id_hex, usuario_id, nombre) VALUES ($1, $2, $3) RETURNING id")
+        .bind(&id)
+        .bind(usuario_id)
+        .bind(nombre)
+        .fetch_one(&mut *tx)
+        .await?;
+    let portafolio_id: i32 = row.try_get(0)?;
+    sqlx::query("INSERT INTO portfolio_members (portafolio_id, usuario_id, rol) VALUES ($1, $2, 'owner') ON CONFLICT DO NOTHING")
+        .bind(portafolio_id)
+        .bind(usuario_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
     Ok(id)
 }
 
@@ -238,60 +306,69 @@ pub struct Portfolio {
     pub id: i32,
     pub nombre: String,
     pub id_hex: String,
+    pub rol: Rol,
 }
 
 #[tauri::command]
-pub fn get_portfolios() -> Result<Vec<Portfolio>, String> {
-    let mut client = connect_db()?;
-    // Buscar usuario MAKIMA
-    let row = client.query_opt("SELECT id FROM usuarios WHERE nombre = $1", &[&"MAKIMA"]).map_err(|e| e.to_string())?;
-    let usuario_id = if let Some(row) = row {
-        row.get::<_, i32>(0)
-    } else {
-        // Si no existe, lo crea
-        let row = client.query_one("INSERT INTO usuarios (nombre) VALUES ($1) RETURNING id", &[&"MAKIMA"]).map_err(|e| e.to_string())?;
-        row.get::<_, i32>(0)
-    };
-    let rows = client.query(
-        "SELECT id, nombre, id_hex FROM portafolios WHERE usuario_id = $1 ORDER BY id",
-        &[&usuario_id]
-    ).map_err(|e| format!("Error fetching portfolios: {}", e))?;
-    let portfolios = rows.into_iter().map(|row| Portfolio {
-        id: row.get("id"),
-        nombre: row.get("nombre"),
-        id_hex: row.get("id_hex"),
-    }).collect();
+pub async fn get_portfolios(usuario_id: i32) -> Result<Vec<Portfolio>, String> {
+    let pool = db::pool().await?;
+    // Portafolios propios y compartidos con el usuario
+    let rows = sqlx::query(
+        "SELECT p.id, p.nombre, p.id_hex, m.rol
+         FROM portafolios p
+         JOIN portfolio_members m ON m.portafolio_id = p.id
+         WHERE m.usuario_id = $1
+         ORDER BY p.id",
+    )
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Error fetching portfolios: {}", e))?;
+    let mut portfolios = Vec::new();
+    for row in rows {
+        let rol: String = row.try_get("rol").map_err(|e| e.to_string())?;
+        portfolios.push(Portfolio {
+            id: row.try_get("id").map_err(|e| e.to_string())?,
+            nombre: row.try_get("nombre").map_err(|e| e.to_string())?,
+            id_hex: row.try_get("id_hex").map_err(|e| e.to_string())?,
+            rol: Rol::parse(&rol)?,
+        });
+    }
     Ok(portfolios)
 }
 
 #[tauri::command]
-pub fn create_portfolio(nombre: String) -> Result<Portfolio, String> {
-    println!("[create_portfolio] nombre: {} (usuario fijo: MAKIMA)", nombre);
-    let mut client = connect_db()?;
-    // Buscar usuario MAKIMA
-    let row = client.query_opt("SELECT id FROM usuarios WHERE nombre = $1", &[&"MAKIMA"]).map_err(|e| e.to_string())?;
-    let usuario_id = if let Some(row) = row {
-        row.get::<_, i32>(0)
-    } else {
-        // Si no existe, lo crea
-        let row = client.query_one("INSERT INTO usuarios (nombre) VALUES ($1) RETURNING id", &[&"MAKIMA"]).map_err(|e| e.to_string())?;
-        row.get::<_, i32>(0)
-    };
-    println!("[create_portfolio] usuario_id de MAKIMA: {}", usuario_id);
+pub async fn create_portfolio(usuario_id: i32, nombre: String) -> Result<Portfolio, String> {
+    println!("[create_portfolio] nombre: {} usuario_id: {}", nombre, usuario_id);
+    let pool = db::pool().await?;
+    let existe_usuario = sqlx::query("SELECT 1 FROM usuarios WHERE id = $1")
+        .bind(usuario_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if existe_usuario.is_none() {
+        return Err(format!("No existe el usuario con id {}", usuario_id));
+    }
     // Verifica si ya existe un portafolio con ese nombre para el usuario
-    let exists = client.query_one(
-        "SELECT 1 FROM portafolios WHERE usuario_id = $1 AND nombre = $2",
-        &[&usuario_id, &nombre]
-    );
-    if let Ok(_) = exists {
+    let exists = sqlx::query("SELECT 1 FROM portafolios WHERE usuario_id = $1 AND nombre = $2")
+        .bind(usuario_id)
+        .bind(&nombre)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists.is_some() {
         println!("[create_portfolio] Ya existe un portafolio con ese nombre para este usuario.");
         return Err("Ya existe un portafolio con ese nombre. Por favor, elige otro.".to_string());
     }
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
     let id_hex = format!("{:09x}", rand::random::<u32>());
-    let row = match client.query_one(
-        "INSERT INTO portafolios (id_hex, usuario_id, nombre) VALUES ($1, $2, $3) RETURNING id, nombre, id_hex",
-        &[&id_hex, &usuario_id, &nombre]
-    ) {
+    let row = match sqlx::query("INSERT INTO portafolios (id_hex, usuario_id, nombre) VALUES ($1, $2, $3) RETURNING id, nombre, id_hex")
+        .bind(&id_hex)
+        .bind(usuario_id)
+        .bind(&nombre)
+        .fetch_one(&mut *tx)
+        .await
+    {
         Ok(row) => {
             println!("[create_portfolio] Portafolio creado correctamente");
             row
@@ -301,10 +378,15 @@ pub fn create_portfolio(nombre: String) -> Result<Portfolio, String> {
             return Err(e.to_string());
         }
     };
+    let portfolio_id: i32 = row.try_get("id").map_err(|e| e.to_string())?;
+    // El creador queda como owner del portafolio
+    portfolio_members::upsert_member(&mut tx, portfolio_id, usuario_id, Rol::Owner).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(Portfolio {
-        id: row.get("id"),
-        nombre: row.get("nombre"),
-        id_hex: row.get("id_hex"),
+        id: portfolio_id,
+        nombre: row.try_get("nombre").map_err(|e| e.to_string())?,
+        id_hex: row.try_get("id_hex").map_err(|e| e.to_string())?,
+        rol: Rol::Owner,
     })
 }
 
@@ -317,32 +399,35 @@ pub struct Usuario {
 }
 
 #[tauri::command]
-pub fn get_users() -> Result<Vec<Usuario>, String> {
-    let mut client = connect_db()?;
-    let rows = client.query(
-        "SELECT id, nombre, email FROM usuarios ORDER BY id",
-        &[]
-    ).map_err(|e| format!("Error fetching users: {}", e))?;
-
-    let usuarios = rows.into_iter().map(|row| Usuario {
-        id: row.get("id"),
-        nombre: row.get("nombre"),
-        email: row.get("email"),
-    }).collect();
-
-    Ok(usuarios)
+pub async fn get_users() -> Result<Vec<Usuario>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query("SELECT id, nombre, email FROM usuarios ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Error fetching users: {}", e))?;
+
+    rows.into_iter()
+        .map(|row| Ok(Usuario {
+            id: row.try_get("id")?,
+            nombre: row.try_get("nombre")?,
+            email: row.try_get("email")?,
+        }))
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn create_user(nombre: String, email: String) -> Result<Usuario, String> {
-    let mut client = connect_db()?;
-    let row = client.query_one(
-        "INSERT INTO usuarios (nombre, email) VALUES ($1, $2) RETURNING id, nombre, email",
-        &[&nombre, &email]
-    ).map_err(|e| e.to_string())?;
+pub async fn create_user(nombre: String, email: String) -> Result<Usuario, String> {
+    let pool = db::pool().await?;
+    let row = sqlx::query("INSERT INTO usuarios (nombre, email) VALUES ($1, $2) RETURNING id, nombre, email")
+        .bind(&nombre)
+        .bind(&email)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
     Ok(Usuario {
-        id: row.get("id"),
-        nombre: row.get("nombre"),
-        email: row.get("email"),
+        id: row.try_get("id").map_err(|e| e.to_string())?,
+        nombre: row.try_get("nombre").map_err(|e| e.to_string())?,
+        email: row.try_get("email").map_err(|e| e.to_string())?,
     })
-}
\ No newline at end of file
+}
@@ -1,7 +1,10 @@
 use serde::{Serialize, Deserialize};
 use chrono::NaiveDate;
-use postgres::{Client, NoTls, Transaction};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, Row};
 use std::collections::HashMap;
+use crate::db;
+use crate::portfolio_members::{self, Rol};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AssetTransaction {
@@ -14,6 +17,18 @@ pub struct AssetTransaction {
     pub transaction_date: NaiveDate,
 }
 
+/// Compra o venta por registrar con `add_asset_transaction`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NuevaTransaccion {
+    pub ticker: String,
+    pub transaction_type: String, // "buy" o "sell"
+    pub quantity: f64,
+    pub price: f64,
+    pub transaction_date: NaiveDate,
+    #[serde(default)]
+    pub use_cash_from_portfolio: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CashFlow {
     pub id: i32,
@@ -48,14 +63,22 @@ pub struct Dividend {
     pub dividend_date: NaiveDate,
 }
 
-fn connect_db() -> Result<Client, String> {
-    Client::connect("host=localhost user=garden_admin password=password dbname=dalia_db", NoTls)
-        .map_err(|e| format!("DB connection error: {}", e))
+fn cash_flow_de_fila(row: &PgRow) -> Result<CashFlow, String> {
+    let campo = |e: sqlx::Error| e.to_string();
+    Ok(CashFlow {
+        id: row.try_get("id").map_err(campo)?,
+        portfolio_id: row.try_get("portfolio_id").map_err(campo)?,
+        flow_type: row.try_get("flow_type").map_err(campo)?,
+        amount: row.try_get("amount").map_err(campo)?,
+        flow_date: row.try_get("flow_date").map_err(campo)?,
+        description: row.try_get("description").map_err(campo)?,
+    })
 }
 
 #[tauri::command]
-pub fn add_cash_movement(
+pub async fn add_cash_movement(
     portfolio_id: i32,
+    usuario_id: i32,
     flow_type: String,
     amount: f64,
     flow_date: NaiveDate,
@@ -64,90 +87,81 @@ pub fn add_cash_movement(
     if flow_type != "deposit" && flow_type != "withdrawal" {
         return Err("flow_type debe ser 'deposit' o 'withdrawal'".to_string());
     }
-    let mut client = connect_db()?;
-    let row = client.query_one(
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Editor).await?;
+    let row = sqlx::query(
         "INSERT INTO cashflow (portfolio_id, flow_type, amount, flow_date, description) VALUES ($1, $2, $3, $4, $5) RETURNING id, portfolio_id, flow_type, amount, flow_date, description",
-        &[&portfolio_id, &flow_type, &amount, &flow_date, &Some(description.clone())]
-    ).map_err(|e| e.to_string())?;
-    Ok(CashFlow {
-        id: row.get("id"),
-        portfolio_id: row.get("portfolio_id"),
-        flow_type: row.get("flow_type"),
-        amount: row.get("amount"),
-        flow_date: row.get("flow_date"),
-        description: row.get("description"),
-    })
+    )
+    .bind(portfolio_id)
+    .bind(&flow_type)
+    .bind(amount)
+    .bind(flow_date)
+    .bind(Some(description))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    cash_flow_de_fila(&row)
 }
 
 #[tauri::command]
-pub fn get_cash_balance(portfolio_id: i32) -> Result<f64, String> {
-    let mut client = connect_db()?;
-    let rows = client.query(
-        "SELECT flow_type, amount FROM cashflow WHERE portfolio_id = $1",
-        &[&portfolio_id]
-    ).map_err(|e| e.to_string())?;
-
-    let mut balance = 0.0;
-    for row in rows {
-        let flow_type: String = row.get("flow_type");
-        let amount: f64 = row.get("amount");
-        match flow_type.as_str() {
-            "deposit" | "sell_proceeds" | "dividend" => balance += amount,
-            "withdrawal" | "buy_cost" => balance += amount, // buy_cost y withdrawal deben ser negativos
-            _ => {}
-        }
-    }
-    Ok(balance)
+pub async fn get_cash_balance(portfolio_id: i32, usuario_id: i32) -> Result<f64, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    get_cash_balance_tx(&mut conn, portfolio_id).await
 }
 
 #[tauri::command]
-pub fn get_cash_flow_history(portfolio_id: i32) -> Result<Vec<CashFlow>, String> {
-    let mut client = connect_db()?;
-    let rows = client.query(
+pub async fn get_cash_flow_history(portfolio_id: i32, usuario_id: i32) -> Result<Vec<CashFlow>, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    let rows = sqlx::query(
         "SELECT id, portfolio_id, flow_type, amount, flow_date, description FROM cashflow WHERE portfolio_id = $1 ORDER BY flow_date",
-        &[&portfolio_id]
-    ).map_err(|e| e.to_string())?;
-
-    let history = rows.into_iter().map(|row| CashFlow {
-        id: row.get("id"),
-        portfolio_id: row.get("portfolio_id"),
-        flow_type: row.get("flow_type"),
-        amount: row.get("amount"),
-        flow_date: row.get("flow_date"),
-        description: row.get("description"),
-    }).collect();
+    )
+    .bind(portfolio_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
 
-    Ok(history)
+    rows.iter().map(cash_flow_de_fila).collect()
 }
 
 #[tauri::command]
-pub fn add_asset_transaction(
+pub async fn add_asset_transaction(
     portfolio_id: i32,
-    ticker: String,
-    transaction_type: String, // "buy" o "sell"
-    quantity: f64,
-    price: f64,
-    transaction_date: NaiveDate,
-    use_cash_from_portfolio: bool,
+    usuario_id: i32,
+    transaccion: NuevaTransaccion,
 ) -> Result<AssetTransaction, String> {
+    let NuevaTransaccion { ticker, transaction_type, quantity, price, transaction_date, use_cash_from_portfolio } = transaccion;
     if transaction_type != "buy" && transaction_type != "sell" {
         return Err("transaction_type debe ser 'buy' o 'sell'".to_string());
     }
-    let mut client = connect_db()?;
-    let mut tx = client.transaction().map_err(|e| e.to_string())?;
+    let pool = db::pool().await?;
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut tx, portfolio_id, usuario_id, Rol::Editor).await?;
     let total_cost = quantity * price;
 
     if use_cash_from_portfolio {
-        let balance = get_cash_balance_tx(&mut tx, portfolio_id)?;
+        let balance = get_cash_balance_tx(&mut tx, portfolio_id).await?;
         if transaction_type == "buy" && balance < total_cost {
             return Err("Saldo insuficiente para realizar la compra".to_string());
         }
     }
 
-    let row = tx.query_one(
+    let row = sqlx::query(
         "INSERT INTO portfolio_transactions (portfolio_id, ticker, transaction_type, quantity, price, transaction_date) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, portfolio_id, ticker, transaction_type, quantity, price, transaction_date",
-        &[&portfolio_id, &ticker, &transaction_type, &quantity, &price, &transaction_date]
-    ).map_err(|e| e.to_string())?;
+    )
+    .bind(portfolio_id)
+    .bind(&ticker)
+    .bind(&transaction_type)
+    .bind(quantity)
+    .bind(price)
+    .bind(transaction_date)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
 
     if use_cash_from_portfolio {
         let (flow_type, amount) = match transaction_type.as_str() {
@@ -155,38 +169,47 @@ pub fn add_asset_transaction(
             "sell" => ("sell_proceeds", total_cost),
             _ => unreachable!(),
         };
-        tx.execute(
+        sqlx::query(
             "INSERT INTO cashflow (portfolio_id, flow_type, amount, flow_date, description) VALUES ($1, $2, $3, $4, $5)",
-            &[&portfolio_id, &flow_type, &amount, &transaction_date, &Some(format!("{} {}", transaction_type, ticker))]
-        ).map_err(|e| e.to_string())?;
+        )
+        .bind(portfolio_id)
+        .bind(flow_type)
+        .bind(amount)
+        .bind(transaction_date)
+        .bind(Some(format!("{} {}", transaction_type, ticker)))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
     }
 
-    tx.commit().map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
 
+    let campo = |e: sqlx::Error| e.to_string();
     Ok(AssetTransaction {
-        id: row.get("id"),
-        portfolio_id: row.get("portfolio_id"),
-        ticker: row.get("ticker"),
-        transaction_type: row.get("transaction_type"),
-        quantity: row.get("quantity"),
-        price: row.get("price"),
-        transaction_date: row.get("transaction_date"),
+        id: row.try_get("id").map_err(campo)?,
+        portfolio_id: row.try_get("portfolio_id").map_err(campo)?,
+        ticker: row.try_get("ticker").map_err(campo)?,
+        transaction_type: row.try_get("transaction_type").map_err(campo)?,
+        quantity: row.try_get("quantity").map_err(campo)?,
+        price: row.try_get("price").map_err(campo)?,
+        transaction_date: row.try_get("transaction_date").map_err(campo)?,
     })
 }
 
-fn get_cash_balance_tx(tx: &mut Transaction, portfolio_id: i32) -> Result<f64, String> {
-    let rows = tx.query(
-        "SELECT flow_type, amount FROM cashflow WHERE portfolio_id = $1",
-        &[&portfolio_id]
-    ).map_err(|e| e.to_string())?;
+async fn get_cash_balance_tx(conn: &mut PgConnection, portfolio_id: i32) -> Result<f64, String> {
+    let rows = sqlx::query("SELECT flow_type, amount FROM cashflow WHERE portfolio_id = $1")
+        .bind(portfolio_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
 
     let mut balance = 0.0;
     for row in rows {
-        let flow_type: String = row.get("flow_type");
-        let amount: f64 = row.get("amount");
+        let flow_type: String = row.try_get("flow_type").map_err(|e| e.to_string())?;
+        let amount: f64 = row.try_get("amount").map_err(|e| e.to_string())?;
         match flow_type.as_str() {
             "deposit" | "sell_proceeds" | "dividend" => balance += amount,
-            "withdrawal" | "buy_cost" => balance += amount,
+            "withdrawal" | "buy_cost" => balance += amount, // buy_cost y withdrawal deben ser negativos
             _ => {}
         }
     }
@@ -194,12 +217,25 @@ fn get_cash_balance_tx(tx: &mut Transaction, portfolio_id: i32) -> Result<f64, S
 }
 
 #[tauri::command]
-pub fn delete_asset_transaction(transaction_id: i32) -> Result<String, String> {
-    let mut client = connect_db()?;
-    let n = client.execute(
-        "DELETE FROM portfolio_transactions WHERE id = $1",
-        &[&transaction_id]
-    ).map_err(|e| e.to_string())?;
+pub async fn delete_asset_transaction(transaction_id: i32, usuario_id: i32) -> Result<String, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let row = sqlx::query("SELECT portfolio_id FROM portfolio_transactions WHERE id = $1")
+        .bind(transaction_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let portfolio_id: i32 = match row {
+        Some(row) => row.try_get("portfolio_id").map_err(|e| e.to_string())?,
+        None => return Err("No se encontró la transacción para eliminar.".to_string()),
+    };
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Editor).await?;
+    let n = sqlx::query("DELETE FROM portfolio_transactions WHERE id = $1")
+        .bind(transaction_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
     if n == 1 {
         Ok("Transacción eliminada correctamente. Recuerda ajustar el cashflow manualmente si es necesario.".to_string())
     } else {
@@ -208,19 +244,28 @@ pub fn delete_asset_transaction(transaction_id: i32) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub fn get_portfolio_slots(portfolio_id: i32) -> Result<Vec<PositionSlot>, String> {
-    let mut client = connect_db()?;
-    let rows = client.query(
+pub async fn get_portfolio_slots(portfolio_id: i32, usuario_id: i32) -> Result<Vec<PositionSlot>, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    posiciones(&mut conn, portfolio_id).await
+}
+
+pub(crate) async fn posiciones(conn: &mut PgConnection, portfolio_id: i32) -> Result<Vec<PositionSlot>, String> {
+    let rows = sqlx::query(
         "SELECT ticker, transaction_type, quantity, price FROM portfolio_transactions WHERE portfolio_id = $1",
-        &[&portfolio_id]
-    ).map_err(|e| e.to_string())?;
+    )
+    .bind(portfolio_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
 
     let mut positions: HashMap<String, (f64, f64)> = HashMap::new();
     for row in rows {
-        let ticker: String = row.get("ticker");
-        let transaction_type: String = row.get("transaction_type");
-        let quantity: f64 = row.get("quantity");
-        let price: f64 = row.get("price");
+        let ticker: String = row.try_get("ticker").map_err(|e| e.to_string())?;
+        let transaction_type: String = row.try_get("transaction_type").map_err(|e| e.to_string())?;
+        let quantity: f64 = row.try_get("quantity").map_err(|e| e.to_string())?;
+        let price: f64 = row.try_get("price").map_err(|e| e.to_string())?;
         let entry = positions.entry(ticker.clone()).or_insert((0.0, 0.0));
         match transaction_type.as_str() {
             "buy" => {
@@ -250,9 +295,87 @@ pub fn get_portfolio_slots(portfolio_id: i32) -> Result<Vec<PositionSlot>, Strin
     Ok(slots)
 }
 
+async fn transacciones(conn: &mut PgConnection, portfolio_id: i32) -> Result<Vec<AssetTransaction>, String> {
+    let campo = |e: sqlx::Error| e.to_string();
+    sqlx::query(
+        "SELECT id, portfolio_id, ticker, transaction_type, quantity, price, transaction_date
+         FROM portfolio_transactions WHERE portfolio_id = $1 ORDER BY transaction_date, id",
+    )
+    .bind(portfolio_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(campo)?
+    .iter()
+    .map(|row| {
+        Ok(AssetTransaction {
+            id: row.try_get("id").map_err(campo)?,
+            portfolio_id: row.try_get("portfolio_id").map_err(campo)?,
+            ticker: row.try_get("ticker").map_err(campo)?,
+            transaction_type: row.try_get("transaction_type").map_err(campo)?,
+            quantity: row.try_get("quantity").map_err(campo)?,
+            price: row.try_get("price").map_err(campo)?,
+            transaction_date: row.try_get("transaction_date").map_err(campo)?,
+        })
+    })
+    .collect()
+}
+
 #[tauri::command]
-pub fn calculate_portfolio_pl(portfolio_id: i32) -> Result<Vec<ProfitLoss>, String> {
-    let slots = get_portfolio_slots(portfolio_id)?;
+pub async fn get_asset_transactions(portfolio_id: i32, usuario_id: i32) -> Result<Vec<AssetTransaction>, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    transacciones(&mut conn, portfolio_id).await
+}
+
+/// Todo lo registrado en un portafolio, para respaldos y scripts de operación.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PortfolioExport {
+    pub portfolio_id: i32,
+    pub nombre: String,
+    pub transacciones: Vec<AssetTransaction>,
+    pub flujos: Vec<CashFlow>,
+    pub posiciones: Vec<PositionSlot>,
+    pub saldo_efectivo: f64,
+}
+
+/// Exporta un portafolio sin revisar permisos: es para la línea de comandos de
+/// administración, no se expone como comando de la app.
+pub async fn exportar_portafolio(conn: &mut PgConnection, portfolio_id: i32) -> Result<PortfolioExport, String> {
+    let row = sqlx::query("SELECT nombre FROM portafolios WHERE id = $1")
+        .bind(portfolio_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No existe el portafolio {}", portfolio_id))?;
+    let nombre: String = row.try_get("nombre").map_err(|e| e.to_string())?;
+
+    let campo = |e: sqlx::Error| e.to_string();
+    let transacciones = transacciones(conn, portfolio_id).await?;
+    let flujos = sqlx::query(
+        "SELECT id, portfolio_id, flow_type, amount, flow_date, description FROM cashflow WHERE portfolio_id = $1 ORDER BY flow_date, id",
+    )
+    .bind(portfolio_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(campo)?
+    .iter()
+    .map(cash_flow_de_fila)
+    .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(PortfolioExport {
+        portfolio_id,
+        nombre,
+        transacciones,
+        flujos,
+        posiciones: posiciones(conn, portfolio_id).await?,
+        saldo_efectivo: get_cash_balance_tx(conn, portfolio_id).await?,
+    })
+}
+
+#[tauri::command]
+pub async fn calculate_portfolio_pl(portfolio_id: i32, usuario_id: i32) -> Result<Vec<ProfitLoss>, String> {
+    let slots = get_portfolio_slots(portfolio_id, usuario_id).await?;
     let mut result = Vec::new();
     for slot in slots {
         let current_price = get_current_price(&slot.ticker);
@@ -275,25 +398,28 @@ pub fn calculate_portfolio_pl(portfolio_id: i32) -> Result<Vec<ProfitLoss>, Stri
 }
 
 #[tauri::command]
-pub fn register_dividend_as_cash(
+pub async fn register_dividend_as_cash(
     portfolio_id: i32,
+    usuario_id: i32,
     ticker: String,
     total_dividend_amount: f64,
     dividend_date: NaiveDate,
 ) -> Result<CashFlow, String> {
-    let mut client = connect_db()?;
-    let row = client.query_one(
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Editor).await?;
+    let row = sqlx::query(
         "INSERT INTO cashflow (portfolio_id, flow_type, amount, flow_date, description) VALUES ($1, $2, $3, $4, $5) RETURNING id, portfolio_id, flow_type, amount, flow_date, description",
-        &[&portfolio_id, &"dividend", &total_dividend_amount, &dividend_date, &Some(format!("Dividendo de {}", ticker))]
-    ).map_err(|e| e.to_string())?;
-    Ok(CashFlow {
-        id: row.get("id"),
-        portfolio_id: row.get("portfolio_id"),
-        flow_type: row.get("flow_type"),
-        amount: row.get("amount"),
-        flow_date: row.get("flow_date"),
-        description: row.get("description"),
-    })
+    )
+    .bind(portfolio_id)
+    .bind("dividend")
+    .bind(total_dividend_amount)
+    .bind(dividend_date)
+    .bind(Some(format!("Dividendo de {}", ticker)))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    cash_flow_de_fila(&row)
 }
 
 fn get_current_price(_ticker: &str) -> f64 {
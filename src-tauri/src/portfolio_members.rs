@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, Row};
+use crate::db;
+
+// --- Roles de un usuario dentro de un portafolio ---
+// viewer: ve holdings y reportes
+// editor: además registra transacciones y movimientos de efectivo
+// owner: además comparte el portafolio y administra a sus miembros
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Rol {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl Rol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rol::Viewer => "viewer",
+            Rol::Editor => "editor",
+            Rol::Owner => "owner",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Rol, String> {
+        match s.trim().to_lowercase().as_str() {
+            "viewer" => Ok(Rol::Viewer),
+            "editor" => Ok(Rol::Editor),
+            "owner" => Ok(Rol::Owner),
+            otro => Err(format!("Rol inválido '{}'. Usa 'viewer', 'editor' u 'owner'", otro)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PortfolioMember {
+    pub portfolio_id: i32,
+    pub usuario_id: i32,
+    pub nombre: String,
+    pub email: Option<String>,
+    pub rol: Rol,
+}
+
+/// Rol del usuario en el portafolio, o `None` si no es miembro.
+pub async fn get_role(
+    conn: &mut PgConnection,
+    portfolio_id: i32,
+    usuario_id: i32,
+) -> Result<Option<Rol>, String> {
+    let row = sqlx::query("SELECT rol FROM portfolio_members WHERE portafolio_id = $1 AND usuario_id = $2")
+        .bind(portfolio_id)
+        .bind(usuario_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(row) => {
+            let rol: String = row.try_get(0).map_err(|e| e.to_string())?;
+            Rol::parse(&rol).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Verifica que el usuario tenga al menos el rol `minimo` en el portafolio.
+///
+/// Regresa el rol real del usuario, o un error legible para el frontend si no
+/// es miembro o su rol no alcanza.
+pub async fn require_role(
+    conn: &mut PgConnection,
+    portfolio_id: i32,
+    usuario_id: i32,
+    minimo: Rol,
+) -> Result<Rol, String> {
+    match get_role(conn, portfolio_id, usuario_id).await? {
+        Some(rol) if rol >= minimo => Ok(rol),
+        Some(rol) => Err(format!(
+            "Permiso denegado: tu rol en este portafolio es '{}' y se requiere '{}'",
+            rol.as_str(), minimo.as_str()
+        )),
+        None => Err("Permiso denegado: no eres miembro de este portafolio".to_string()),
+    }
+}
+
+/// Da de alta (o actualiza) a un usuario como miembro del portafolio.
+pub async fn upsert_member(
+    conn: &mut PgConnection,
+    portfolio_id: i32,
+    usuario_id: i32,
+    rol: Rol,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO portfolio_members (portafolio_id, usuario_id, rol) VALUES ($1, $2, $3)
+         ON CONFLICT (portafolio_id, usuario_id) DO UPDATE SET rol = EXCLUDED.rol",
+    )
+    .bind(portfolio_id)
+    .bind(usuario_id)
+    .bind(rol.as_str())
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Bloquea las filas de los owners hasta el fin de la transacción, para que dos
+/// degradaciones simultáneas no dejen el portafolio sin owner.
+async fn count_owners(conn: &mut PgConnection, portfolio_id: i32) -> Result<usize, String> {
+    let rows = sqlx::query("SELECT usuario_id FROM portfolio_members WHERE portafolio_id = $1 AND rol = 'owner' FOR UPDATE")
+        .bind(portfolio_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.len())
+}
+
+#[tauri::command]
+pub async fn share_portfolio(
+    portfolio_id: i32,
+    usuario_id: i32,
+    invitado_id: i32,
+    rol: String,
+) -> Result<PortfolioMember, String> {
+    let rol = Rol::parse(&rol)?;
+    let pool = db::pool().await?;
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    require_role(&mut tx, portfolio_id, usuario_id, Rol::Owner).await?;
+
+    // No dejar el portafolio sin owner al degradar al último
+    if invitado_id == usuario_id && rol != Rol::Owner && count_owners(&mut tx, portfolio_id).await? <= 1 {
+        return Err("El portafolio debe conservar al menos un owner".to_string());
+    }
+
+    let usuario = sqlx::query("SELECT nombre, email FROM usuarios WHERE id = $1")
+        .bind(invitado_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No existe el usuario con id {}", invitado_id))?;
+
+    upsert_member(&mut tx, portfolio_id, invitado_id, rol).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(PortfolioMember {
+        portfolio_id,
+        usuario_id: invitado_id,
+        nombre: usuario.try_get("nombre").map_err(|e| e.to_string())?,
+        email: usuario.try_get("email").map_err(|e| e.to_string())?,
+        rol,
+    })
+}
+
+#[tauri::command]
+pub async fn remove_portfolio_member(
+    portfolio_id: i32,
+    usuario_id: i32,
+    miembro_id: i32,
+) -> Result<String, String> {
+    let pool = db::pool().await?;
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    // Cualquier miembro puede salirse; sólo un owner puede quitar a otros
+    if miembro_id != usuario_id {
+        require_role(&mut tx, portfolio_id, usuario_id, Rol::Owner).await?;
+    }
+    if get_role(&mut tx, portfolio_id, miembro_id).await? == Some(Rol::Owner)
+        && count_owners(&mut tx, portfolio_id).await? <= 1
+    {
+        return Err("El portafolio debe conservar al menos un owner".to_string());
+    }
+    let n = sqlx::query("DELETE FROM portfolio_members WHERE portafolio_id = $1 AND usuario_id = $2")
+        .bind(portfolio_id)
+        .bind(miembro_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    tx.commit().await.map_err(|e| e.to_string())?;
+    if n == 1 {
+        Ok("Miembro eliminado del portafolio.".to_string())
+    } else {
+        Err("El usuario no es miembro de este portafolio.".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn get_portfolio_members(portfolio_id: i32, usuario_id: i32) -> Result<Vec<PortfolioMember>, String> {
+    let pool = db::pool().await?;
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    let rows = sqlx::query(
+        "SELECT m.usuario_id, u.nombre, u.email, m.rol
+         FROM portfolio_members m
+         JOIN usuarios u ON u.id = m.usuario_id
+         WHERE m.portafolio_id = $1
+         ORDER BY m.created_at, u.nombre",
+    )
+    .bind(portfolio_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Error fetching members: {}", e))?;
+
+    let mut miembros = Vec::new();
+    for row in rows {
+        let rol: String = row.try_get("rol").map_err(|e| e.to_string())?;
+        miembros.push(PortfolioMember {
+            portfolio_id,
+            usuario_id: row.try_get("usuario_id").map_err(|e| e.to_string())?,
+            nombre: row.try_get("nombre").map_err(|e| e.to_string())?,
+            email: row.try_get("email").map_err(|e| e.to_string())?,
+            rol: Rol::parse(&rol)?,
+        });
+    }
+    Ok(miembros)
+}
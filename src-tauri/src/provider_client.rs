@@ -0,0 +1,301 @@
+use chrono::{Local, NaiveDate};
+use rand::Rng;
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// --- Cliente HTTP compartido para DataBursatil ---
+// Una sola instancia (ver `get_data::provider`) concentra:
+// - limitador token bucket para no rebasar la cuota del plan
+// - reintentos con backoff exponencial en 5xx, 429 y timeouts
+// - circuit breaker que deja de llamar a la API mientras está caída
+// - métricas que expone el comando `get_provider_diagnostics`
+
+const MAX_REINTENTOS: u32 = 3;
+const BACKOFF_BASE_MS: u64 = 500;
+const FALLAS_PARA_ABRIR: u32 = 5;
+const ENFRIAMIENTO_CIRCUITO: Duration = Duration::from_secs(60);
+// Más que lo que tarda una llamada con todos sus reintentos (4 × 20 s + backoff)
+const PLAZO_PRUEBA: Duration = Duration::from_secs(120);
+
+/// Llamadas por minuto y cuota diaria del plan; se pueden ajustar con
+/// `DATABURSATIL_RATE_PER_MINUTE` y `DATABURSATIL_DAILY_QUOTA`.
+const RATE_POR_MINUTO_DEFAULT: f64 = 60.0;
+const CUOTA_DIARIA_DEFAULT: u64 = 10_000;
+
+fn env_o<T: std::str::FromStr>(nombre: &str, default: T) -> T {
+    std::env::var(nombre).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+struct TokenBucket {
+    capacidad: f64,
+    tokens: f64,
+    recarga_por_seg: f64,
+    ultimo: Instant,
+}
+
+impl TokenBucket {
+    fn new(por_minuto: f64) -> Self {
+        // Con 0, negativos, NaN o infinito `reservar` calcularía una espera inválida
+        let por_minuto = if por_minuto.is_finite() && por_minuto > 0.0 {
+            por_minuto
+        } else {
+            eprintln!("DATABURSATIL_RATE_PER_MINUTE inválido ({}); se usa {}", por_minuto, RATE_POR_MINUTO_DEFAULT);
+            RATE_POR_MINUTO_DEFAULT
+        };
+        TokenBucket {
+            capacidad: por_minuto,
+            tokens: por_minuto,
+            recarga_por_seg: por_minuto / 60.0,
+            ultimo: Instant::now(),
+        }
+    }
+
+    fn recargar(&mut self) {
+        let ahora = Instant::now();
+        let transcurrido = ahora.duration_since(self.ultimo).as_secs_f64();
+        self.tokens = (self.tokens + transcurrido * self.recarga_por_seg).min(self.capacidad);
+        self.ultimo = ahora;
+    }
+
+    /// Aparta un token y regresa cuánto hay que esperar antes de usarlo.
+    fn reservar(&mut self) -> Duration {
+        self.recargar();
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.recarga_por_seg)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EstadoCircuito {
+    Cerrado,
+    Abierto(Instant),
+    /// Desde cuándo está en curso la llamada de prueba
+    SemiAbierto(Instant),
+}
+
+struct CircuitBreaker {
+    estado: EstadoCircuito,
+    fallas_consecutivas: u32,
+}
+
+impl CircuitBreaker {
+    /// Si se permite hacer la llamada. Tras el enfriamiento deja pasar una de prueba.
+    fn permitir(&mut self) -> bool {
+        match self.estado {
+            EstadoCircuito::Cerrado => true,
+            EstadoCircuito::Abierto(desde) if desde.elapsed() >= ENFRIAMIENTO_CIRCUITO => {
+                self.estado = EstadoCircuito::SemiAbierto(Instant::now());
+                true
+            }
+            EstadoCircuito::Abierto(_) => false,
+            // La prueba no avisó a tiempo (p. ej. se soltó su future): cuenta como
+            // falla y se espera otro enfriamiento antes de la siguiente
+            EstadoCircuito::SemiAbierto(desde) if desde.elapsed() >= PLAZO_PRUEBA => {
+                self.estado = EstadoCircuito::Abierto(Instant::now());
+                false
+            }
+            // Ya hay una llamada de prueba en curso
+            EstadoCircuito::SemiAbierto(_) => false,
+        }
+    }
+
+    fn exito(&mut self) {
+        self.estado = EstadoCircuito::Cerrado;
+        self.fallas_consecutivas = 0;
+    }
+
+    fn falla(&mut self) {
+        self.fallas_consecutivas += 1;
+        if matches!(self.estado, EstadoCircuito::SemiAbierto(_)) || self.fallas_consecutivas >= FALLAS_PARA_ABRIR {
+            self.estado = EstadoCircuito::Abierto(Instant::now());
+        }
+    }
+
+    fn nombre(&self) -> &'static str {
+        match self.estado {
+            EstadoCircuito::Cerrado => "cerrado",
+            EstadoCircuito::Abierto(_) => "abierto",
+            EstadoCircuito::SemiAbierto(_) => "semiabierto",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Metricas {
+    llamadas: AtomicU64,
+    errores: AtomicU64,
+    reintentos: AtomicU64,
+    rechazadas_por_circuito: AtomicU64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProviderDiagnostics {
+    pub llamadas: u64,
+    pub errores: u64,
+    pub reintentos: u64,
+    pub rechazadas_por_circuito: u64,
+    pub cache_hits: u64,
+    pub cache_stale_hits: u64,
+    pub cache_misses: u64,
+    pub circuito: String,
+    pub tokens_disponibles: f64,
+    pub cuota_diaria: u64,
+    pub llamadas_hoy: u64,
+    pub cuota_restante: u64,
+}
+
+pub struct ProviderClient {
+    http: HttpClient,
+    limitador: Mutex<TokenBucket>,
+    circuito: Mutex<CircuitBreaker>,
+    metricas: Metricas,
+    cuota_diaria: u64,
+    /// (día, llamadas hechas ese día, cuota restante reportada por el proveedor)
+    uso_diario: Mutex<(NaiveDate, u64, Option<u64>)>,
+}
+
+enum Intento {
+    Ok(String),
+    Reintentable(String),
+    Fatal(String),
+}
+
+impl Default for ProviderClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderClient {
+    pub fn new() -> Self {
+        let http = HttpClient::builder()
+            .timeout(Duration::from_secs(20))
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64)")
+            .build()
+            .unwrap_or_else(|_| HttpClient::new());
+        ProviderClient {
+            http,
+            limitador: Mutex::new(TokenBucket::new(env_o("DATABURSATIL_RATE_PER_MINUTE", RATE_POR_MINUTO_DEFAULT))),
+            circuito: Mutex::new(CircuitBreaker { estado: EstadoCircuito::Cerrado, fallas_consecutivas: 0 }),
+            metricas: Metricas::default(),
+            cuota_diaria: env_o("DATABURSATIL_DAILY_QUOTA", CUOTA_DIARIA_DEFAULT),
+            uso_diario: Mutex::new((Local::now().date_naive(), 0, None)),
+        }
+    }
+
+    fn registrar_uso(&self, restante_reportado: Option<u64>) {
+        let hoy = Local::now().date_naive();
+        let mut uso = self.uso_diario.lock().unwrap();
+        if uso.0 != hoy {
+            *uso = (hoy, 0, None);
+        }
+        uso.1 += 1;
+        if restante_reportado.is_some() {
+            uso.2 = restante_reportado;
+        }
+    }
+
+    async fn intentar(&self, url: &str) -> Intento {
+        let espera = self.limitador.lock().unwrap().reservar();
+        if !espera.is_zero() {
+            tokio::time::sleep(espera).await;
+        }
+        self.metricas.llamadas.fetch_add(1, Ordering::Relaxed);
+
+        let response = match self.http.get(url).send().await {
+            Ok(r) => r,
+            Err(e) if e.is_timeout() || e.is_connect() => return Intento::Reintentable(e.to_string()),
+            Err(e) => return Intento::Fatal(e.to_string()),
+        };
+        let restante = response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        self.registrar_uso(restante);
+
+        let status = response.status();
+        if status.is_server_error() || status.as_u16() == 429 {
+            return Intento::Reintentable(format!("HTTP {}", status));
+        }
+        if !status.is_success() {
+            return Intento::Fatal(format!("HTTP {}", status));
+        }
+        match response.text().await {
+            Ok(text) => Intento::Ok(text),
+            Err(e) if e.is_timeout() => Intento::Reintentable(e.to_string()),
+            Err(e) => Intento::Fatal(e.to_string()),
+        }
+    }
+
+    /// GET a `url` con limitador, reintentos y circuit breaker.
+    pub async fn get_text(&self, url: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.circuito.lock().unwrap().permitir() {
+            self.metricas.rechazadas_por_circuito.fetch_add(1, Ordering::Relaxed);
+            return Err("Proveedor de datos no disponible: demasiadas fallas seguidas, se reintentará en un minuto".into());
+        }
+
+        let mut intento = 0;
+        loop {
+            match self.intentar(url).await {
+                Intento::Ok(text) => {
+                    self.circuito.lock().unwrap().exito();
+                    return Ok(text);
+                }
+                Intento::Fatal(e) => {
+                    // Un 4xx es culpa de la petición, no de la disponibilidad del proveedor
+                    self.metricas.errores.fetch_add(1, Ordering::Relaxed);
+                    self.circuito.lock().unwrap().exito();
+                    return Err(e.into());
+                }
+                Intento::Reintentable(e) if intento >= MAX_REINTENTOS => {
+                    self.metricas.errores.fetch_add(1, Ordering::Relaxed);
+                    self.circuito.lock().unwrap().falla();
+                    return Err(format!("Proveedor de datos no respondió tras {} intentos: {}", intento + 1, e).into());
+                }
+                Intento::Reintentable(e) => {
+                    self.metricas.reintentos.fetch_add(1, Ordering::Relaxed);
+                    let jitter = rand::thread_rng().gen_range(0..BACKOFF_BASE_MS);
+                    let backoff = Duration::from_millis(BACKOFF_BASE_MS * 2u64.pow(intento) + jitter);
+                    println!("[provider] {} — reintento {} en {:?}", e, intento + 1, backoff);
+                    tokio::time::sleep(backoff).await;
+                    intento += 1;
+                }
+            }
+        }
+    }
+
+    pub fn diagnostics(&self) -> ProviderDiagnostics {
+        let (cache_hits, cache_stale_hits, cache_misses) = crate::cache::estadisticas();
+        let tokens_disponibles = {
+            let mut limitador = self.limitador.lock().unwrap();
+            limitador.recargar();
+            limitador.tokens.max(0.0)
+        };
+        let (llamadas_hoy, restante_reportado) = {
+            let uso = self.uso_diario.lock().unwrap();
+            if uso.0 == Local::now().date_naive() { (uso.1, uso.2) } else { (0, None) }
+        };
+        ProviderDiagnostics {
+            llamadas: self.metricas.llamadas.load(Ordering::Relaxed),
+            errores: self.metricas.errores.load(Ordering::Relaxed),
+            reintentos: self.metricas.reintentos.load(Ordering::Relaxed),
+            rechazadas_por_circuito: self.metricas.rechazadas_por_circuito.load(Ordering::Relaxed),
+            cache_hits,
+            cache_stale_hits,
+            cache_misses,
+            circuito: self.circuito.lock().unwrap().nombre().to_string(),
+            tokens_disponibles,
+            cuota_diaria: self.cuota_diaria,
+            llamadas_hoy,
+            cuota_restante: restante_reportado.unwrap_or(self.cuota_diaria.saturating_sub(llamadas_hoy)),
+        }
+    }
+}
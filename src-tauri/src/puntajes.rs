@@ -0,0 +1,550 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+use crate::ratios::{dividir, Estados};
+use crate::{db, multiplos, periodos};
+
+// --- Puntajes de calidad: Piotroski, Altman y Beneish ---
+// Se calculan por ejercicio con los estados anuales de `periodos::ejercicios`
+// (resultados y flujos de los cuatro trimestres, posición al cierre del año);
+// Piotroski y Beneish comparan contra el ejercicio anterior. Cada puntaje trae
+// sus componentes con la fórmula y las entradas, y la lista de lo que no se
+// pudo calcular:
+// - Piotroski suma sólo las señales evaluadas; el total indica cuántas fueron.
+// - Altman necesita todos sus componentes. Se dan la versión de manufactura
+//   (con capitalización de mercado al cierre del año; sin precio se usa la Z' con
+//   capital contable) y la de no manufactura (Z''); el catálogo no trae sector.
+// - En Beneish un índice que no se puede calcular toma su valor neutral (1, o 0
+//   para TATA), se reporta como omitido y la interpretación queda "incompleto".
+//   DSRI, GMI, AQI y SGI sí son necesarios: sin ejercicio anterior no hay puntaje.
+// Los activos totales son pasivo más capital, y la deuda de largo plazo es la
+// deuda financiera no circulante más los arrendamientos, como en `ratios`.
+
+const UMBRAL_BENEISH: f64 = -1.78;
+/// Índices de Beneish sin los cuales no se calcula el puntaje.
+const BENEISH_ESENCIALES: [&str; 4] = ["dsri", "gmi", "aqi", "sgi"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Componente {
+    pub clave: String,
+    pub nombre: String,
+    pub formula: String,
+    pub valor: Option<f64>,
+    /// Peso del componente en el puntaje; vacío en las señales de Piotroski
+    pub coeficiente: Option<f64>,
+    pub entradas: BTreeMap<String, f64>,
+    /// Por qué no se calculó
+    pub omitido: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Puntaje {
+    pub modelo: String,
+    pub nombre: String,
+    pub valor: Option<f64>,
+    pub interpretacion: Option<String>,
+    pub componentes: Vec<Componente>,
+    pub omitidos: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PuntajesEjercicio {
+    pub anio: i32,
+    pub piotroski: Puntaje,
+    pub altman_manufactura: Puntaje,
+    pub altman_no_manufactura: Puntaje,
+    pub beneish: Puntaje,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PuntajesEmisora {
+    pub emisora: String,
+    pub ejercicios: Vec<PuntajesEjercicio>,
+}
+
+fn tomar(mapa: &HashMap<String, f64>, columna: &str) -> Option<f64> {
+    mapa.get(columna).copied().filter(|v| v.is_finite())
+}
+
+fn r(e: &Estados, columna: &str) -> Option<f64> {
+    tomar(&e.resultado, columna)
+}
+
+fn f(e: &Estados, columna: &str) -> Option<f64> {
+    tomar(&e.flujo, columna)
+}
+
+fn p(e: &Estados, columna: &str) -> Option<f64> {
+    tomar(&e.posicion, columna)
+}
+
+fn activos(e: &Estados) -> Option<f64> {
+    Some(p(e, "liabilities")? + p(e, "equity")?)
+}
+
+/// Deuda de largo plazo; basta con una de las dos partidas.
+fn deuda_largo_plazo(e: &Estados) -> Option<f64> {
+    match (p(e, "othernoncurrentfinancialliabilities"), p(e, "noncurrentleaseliabilities")) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+    }
+}
+
+/// Gastos de venta y administración.
+fn gastos_generales(e: &Estados) -> Option<f64> {
+    match (r(e, "distributioncosts"), r(e, "administrativeexpense")) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0.0).abs() + b.unwrap_or(0.0).abs()),
+    }
+}
+
+/// Componente a partir de sus entradas. Si falta alguna, o `calculo` no da
+/// valor (un denominador en cero), queda omitido.
+fn componente(
+    clave: &str,
+    nombre: &str,
+    formula: &str,
+    coeficiente: Option<f64>,
+    entradas: &[(&str, Option<f64>)],
+    calculo: impl Fn(&[f64]) -> Option<f64>,
+) -> Componente {
+    let faltantes: Vec<&str> = entradas.iter().filter(|(_, v)| v.is_none()).map(|(k, _)| *k).collect();
+    let valores: Vec<f64> = entradas.iter().filter_map(|(_, v)| *v).collect();
+    let (valor, omitido) = if !faltantes.is_empty() {
+        (None, Some(format!("falta {}", faltantes.join(", "))))
+    } else {
+        match calculo(&valores) {
+            Some(v) => (Some(v), None),
+            None => (None, Some("denominador en cero".to_string())),
+        }
+    };
+    Componente {
+        clave: clave.to_string(),
+        nombre: nombre.to_string(),
+        formula: formula.to_string(),
+        valor,
+        coeficiente,
+        entradas: entradas.iter().filter_map(|(k, v)| v.map(|v| (k.to_string(), v))).collect(),
+        omitido,
+    }
+}
+
+fn senal(condicion: bool) -> f64 {
+    if condicion { 1.0 } else { 0.0 }
+}
+
+fn omitidos(componentes: &[Componente]) -> Vec<String> {
+    componentes
+        .iter()
+        .filter_map(|c| c.omitido.as_ref().map(|m| format!("{}: {}", c.clave, m)))
+        .collect()
+}
+
+/// Las nueve señales de Piotroski; `b` es el ejercicio anterior.
+pub fn piotroski(a: &Estados, b: Option<&Estados>) -> Puntaje {
+    let vacio = Estados::default();
+    let b = b.unwrap_or(&vacio);
+    let componentes = vec![
+        componente(
+            "roa_positivo",
+            "ROA positivo",
+            "profitloss / activos > 0",
+            None,
+            &[("profitloss", r(a, "profitloss")), ("activos", activos(a))],
+            |v| Some(senal(dividir(v[0], v[1])? > 0.0)),
+        ),
+        componente(
+            "flujo_operacion_positivo",
+            "Flujo de operación positivo",
+            "flujo_operacion > 0",
+            None,
+            &[("flujo_operacion", f(a, "flujo_operacion"))],
+            |v| Some(senal(v[0] > 0.0)),
+        ),
+        componente(
+            "roa_creciente",
+            "ROA mayor que el año anterior",
+            "profitloss / activos > profitloss_previo / activos_previo",
+            None,
+            &[
+                ("profitloss", r(a, "profitloss")),
+                ("activos", activos(a)),
+                ("profitloss_previo", r(b, "profitloss")),
+                ("activos_previo", activos(b)),
+            ],
+            |v| Some(senal(dividir(v[0], v[1])? > dividir(v[2], v[3])?)),
+        ),
+        componente(
+            "calidad_utilidad",
+            "Flujo de operación mayor que la utilidad",
+            "flujo_operacion / activos > profitloss / activos",
+            None,
+            &[
+                ("flujo_operacion", f(a, "flujo_operacion")),
+                ("profitloss", r(a, "profitloss")),
+                ("activos", activos(a)),
+            ],
+            |v| Some(senal(dividir(v[0], v[2])? > dividir(v[1], v[2])?)),
+        ),
+        componente(
+            "apalancamiento_menor",
+            "Deuda de largo plazo / activos no aumentó",
+            "deuda_lp / activos <= deuda_lp_previo / activos_previo",
+            None,
+            &[
+                ("deuda_lp", deuda_largo_plazo(a)),
+                ("activos", activos(a)),
+                ("deuda_lp_previo", deuda_largo_plazo(b)),
+                ("activos_previo", activos(b)),
+            ],
+            |v| Some(senal(dividir(v[0], v[1])? <= dividir(v[2], v[3])?)),
+        ),
+        componente(
+            "liquidez_mayor",
+            "Razón circulante mayor",
+            "currentassets / currentliabilities > currentassets_previo / currentliabilities_previo",
+            None,
+            &[
+                ("currentassets", p(a, "currentassets")),
+                ("currentliabilities", p(a, "currentliabilities")),
+                ("currentassets_previo", p(b, "currentassets")),
+                ("currentliabilities_previo", p(b, "currentliabilities")),
+            ],
+            |v| Some(senal(dividir(v[0], v[1])? > dividir(v[2], v[3])?)),
+        ),
+        componente(
+            "sin_emision",
+            "Sin emisión de acciones",
+            "issuedcapital <= issuedcapital_previo",
+            None,
+            &[("issuedcapital", p(a, "issuedcapital")), ("issuedcapital_previo", p(b, "issuedcapital"))],
+            |v| Some(senal(v[0] <= v[1])),
+        ),
+        componente(
+            "margen_bruto_mayor",
+            "Margen bruto mayor",
+            "grossprofit / revenue > grossprofit_previo / revenue_previo",
+            None,
+            &[
+                ("grossprofit", r(a, "grossprofit")),
+                ("revenue", r(a, "revenue")),
+                ("grossprofit_previo", r(b, "grossprofit")),
+                ("revenue_previo", r(b, "revenue")),
+            ],
+            |v| Some(senal(dividir(v[0], v[1])? > dividir(v[2], v[3])?)),
+        ),
+        componente(
+            "rotacion_mayor",
+            "Rotación de activos mayor",
+            "revenue / activos > revenue_previo / activos_previo",
+            None,
+            &[
+                ("revenue", r(a, "revenue")),
+                ("activos", activos(a)),
+                ("revenue_previo", r(b, "revenue")),
+                ("activos_previo", activos(b)),
+            ],
+            |v| Some(senal(dividir(v[0], v[1])? > dividir(v[2], v[3])?)),
+        ),
+    ];
+    let evaluadas: Vec<f64> = componentes.iter().filter_map(|c| c.valor).collect();
+    let valor = (!evaluadas.is_empty()).then(|| evaluadas.iter().sum::<f64>());
+    let interpretacion = valor.map(|v| {
+        let nivel = if evaluadas.len() < componentes.len() {
+            "incompleto"
+        } else if v >= 8.0 {
+            "fuerte"
+        } else if v <= 2.0 {
+            "débil"
+        } else {
+            "intermedio"
+        };
+        format!("{} de {} señales evaluadas ({})", v, evaluadas.len(), nivel)
+    });
+    Puntaje {
+        modelo: "piotroski".to_string(),
+        nombre: "Piotroski F-Score".to_string(),
+        valor,
+        interpretacion,
+        omitidos: omitidos(&componentes),
+        componentes,
+    }
+}
+
+/// Z de Altman. En manufactura X4 usa la capitalización de mercado; si no hay,
+/// se usa la Z' (empresas privadas) con capital contable.
+pub fn altman(a: &Estados, capitalizacion: Option<f64>, manufactura: bool) -> Puntaje {
+    let activos = activos(a);
+    let (modelo, nombre, coef, zonas) = match (manufactura, capitalizacion.is_some()) {
+        (true, true) => ("altman_z", "Altman Z (manufactura)", [1.2, 1.4, 3.3, 0.6, 1.0], (2.99, 1.81)),
+        (true, false) => ("altman_z_privada", "Altman Z' (manufactura, capital contable)", [0.717, 0.847, 3.107, 0.420, 0.998], (2.9, 1.23)),
+        (false, _) => ("altman_z2", "Altman Z'' (no manufactura)", [6.56, 3.26, 6.72, 1.05, 0.0], (2.6, 1.1)),
+    };
+    let mut componentes = vec![
+        componente(
+            "x1",
+            "Capital de trabajo / activos",
+            "(currentassets - currentliabilities) / activos",
+            Some(coef[0]),
+            &[("currentassets", p(a, "currentassets")), ("currentliabilities", p(a, "currentliabilities")), ("activos", activos)],
+            |v| dividir(v[0] - v[1], v[2]),
+        ),
+        componente(
+            "x2",
+            "Utilidades retenidas / activos",
+            "retainedearnings / activos",
+            Some(coef[1]),
+            &[("retainedearnings", p(a, "retainedearnings")), ("activos", activos)],
+            |v| dividir(v[0], v[1]),
+        ),
+        componente(
+            "x3",
+            "Utilidad de operación / activos",
+            "profitlossfromoperatingactivities / activos",
+            Some(coef[2]),
+            &[("profitlossfromoperatingactivities", r(a, "profitlossfromoperatingactivities")), ("activos", activos)],
+            |v| dividir(v[0], v[1]),
+        ),
+        match (manufactura, capitalizacion) {
+            (true, Some(capitalizacion)) => componente(
+                "x4",
+                "Capitalización / pasivo total",
+                "capitalizacion / liabilities",
+                Some(coef[3]),
+                &[("capitalizacion", Some(capitalizacion)), ("liabilities", p(a, "liabilities"))],
+                |v| dividir(v[0], v[1]),
+            ),
+            _ => componente(
+                "x4",
+                "Capital contable / pasivo total",
+                "equity / liabilities",
+                Some(coef[3]),
+                &[("equity", p(a, "equity")), ("liabilities", p(a, "liabilities"))],
+                |v| dividir(v[0], v[1]),
+            ),
+        },
+    ];
+    // Z'' no usa la rotación de activos, que depende mucho de la industria
+    if manufactura {
+        componentes.push(componente(
+            "x5",
+            "Ventas / activos",
+            "revenue / activos",
+            Some(coef[4]),
+            &[("revenue", r(a, "revenue")), ("activos", activos)],
+            |v| dividir(v[0], v[1]),
+        ));
+    }
+    let valor = componentes
+        .iter()
+        .map(|c| Some(c.valor? * c.coeficiente?))
+        .sum::<Option<f64>>();
+    let interpretacion = valor.map(|v| {
+        let (segura, riesgo) = zonas;
+        if v > segura {
+            "zona segura"
+        } else if v < riesgo {
+            "zona de riesgo"
+        } else {
+            "zona gris"
+        }
+        .to_string()
+    });
+    let mut omitidos = omitidos(&componentes);
+    if manufactura && capitalizacion.is_none() {
+        omitidos.push("capitalizacion: sin precio al cierre del ejercicio; se usó la Z' con capital contable".to_string());
+    }
+    Puntaje {
+        modelo: modelo.to_string(),
+        nombre: nombre.to_string(),
+        valor,
+        interpretacion,
+        componentes,
+        omitidos,
+    }
+}
+
+/// M de Beneish (ocho variables); `b` es el ejercicio anterior. Sin DSRI, GMI,
+/// AQI o SGI no hay puntaje.
+pub fn beneish(a: &Estados, b: Option<&Estados>) -> Puntaje {
+    let vacio = Estados::default();
+    let b = b.unwrap_or(&vacio);
+    let utilidad_continua = |e: &Estados| Some(r(e, "profitloss")? - r(e, "profitlossfromdiscontinuedoperations").unwrap_or(0.0));
+    let componentes = vec![
+        componente(
+            "dsri",
+            "Días de cuentas por cobrar",
+            "(tradeandothercurrentreceivables / revenue) / (tradeandothercurrentreceivables_previo / revenue_previo)",
+            Some(0.920),
+            &[
+                ("tradeandothercurrentreceivables", p(a, "tradeandothercurrentreceivables")),
+                ("revenue", r(a, "revenue")),
+                ("tradeandothercurrentreceivables_previo", p(b, "tradeandothercurrentreceivables")),
+                ("revenue_previo", r(b, "revenue")),
+            ],
+            |v| dividir(dividir(v[0], v[1])?, dividir(v[2], v[3])?),
+        ),
+        componente(
+            "gmi",
+            "Margen bruto",
+            "(grossprofit_previo / revenue_previo) / (grossprofit / revenue)",
+            Some(0.528),
+            &[
+                ("grossprofit", r(a, "grossprofit")),
+                ("revenue", r(a, "revenue")),
+                ("grossprofit_previo", r(b, "grossprofit")),
+                ("revenue_previo", r(b, "revenue")),
+            ],
+            |v| dividir(dividir(v[2], v[3])?, dividir(v[0], v[1])?),
+        ),
+        componente(
+            "aqi",
+            "Calidad de activos",
+            "(1 - (currentassets + propertyplantandequipment) / activos) / (1 - (currentassets_previo + propertyplantandequipment_previo) / activos_previo)",
+            Some(0.404),
+            &[
+                ("currentassets", p(a, "currentassets")),
+                ("propertyplantandequipment", p(a, "propertyplantandequipment")),
+                ("activos", activos(a)),
+                ("currentassets_previo", p(b, "currentassets")),
+                ("propertyplantandequipment_previo", p(b, "propertyplantandequipment")),
+                ("activos_previo", activos(b)),
+            ],
+            |v| dividir(1.0 - dividir(v[0] + v[1], v[2])?, 1.0 - dividir(v[3] + v[4], v[5])?),
+        ),
+        componente(
+            "sgi",
+            "Crecimiento en ventas",
+            "revenue / revenue_previo",
+            Some(0.892),
+            &[("revenue", r(a, "revenue")), ("revenue_previo", r(b, "revenue"))],
+            |v| dividir(v[0], v[1]),
+        ),
+        componente(
+            "depi",
+            "Depreciación",
+            "(depreciacion_previo / (depreciacion_previo + propertyplantandequipment_previo)) / (depreciacion / (depreciacion + propertyplantandequipment))",
+            Some(0.115),
+            &[
+                ("depreciacion", r(a, "depreciacion").or_else(|| f(a, "depreciacion")).map(f64::abs)),
+                ("propertyplantandequipment", p(a, "propertyplantandequipment")),
+                ("depreciacion_previo", r(b, "depreciacion").or_else(|| f(b, "depreciacion")).map(f64::abs)),
+                ("propertyplantandequipment_previo", p(b, "propertyplantandequipment")),
+            ],
+            |v| dividir(dividir(v[2], v[2] + v[3])?, dividir(v[0], v[0] + v[1])?),
+        ),
+        componente(
+            "sgai",
+            "Gastos de venta y administración",
+            "(gastos_generales / revenue) / (gastos_generales_previo / revenue_previo)",
+            Some(-0.172),
+            &[
+                ("gastos_generales", gastos_generales(a)),
+                ("revenue", r(a, "revenue")),
+                ("gastos_generales_previo", gastos_generales(b)),
+                ("revenue_previo", r(b, "revenue")),
+            ],
+            |v| dividir(dividir(v[0], v[1])?, dividir(v[2], v[3])?),
+        ),
+        componente(
+            "lvgi",
+            "Apalancamiento",
+            "((currentliabilities + deuda_lp) / activos) / ((currentliabilities_previo + deuda_lp_previo) / activos_previo)",
+            Some(-0.327),
+            &[
+                ("currentliabilities", p(a, "currentliabilities")),
+                ("deuda_lp", deuda_largo_plazo(a)),
+                ("activos", activos(a)),
+                ("currentliabilities_previo", p(b, "currentliabilities")),
+                ("deuda_lp_previo", deuda_largo_plazo(b)),
+                ("activos_previo", activos(b)),
+            ],
+            |v| dividir(dividir(v[0] + v[1], v[2])?, dividir(v[3] + v[4], v[5])?),
+        ),
+        componente(
+            "tata",
+            "Devengos totales / activos",
+            "(utilidad_continua - flujo_operacion) / activos",
+            Some(4.679),
+            &[
+                ("utilidad_continua", utilidad_continua(a)),
+                ("flujo_operacion", f(a, "flujo_operacion")),
+                ("activos", activos(a)),
+            ],
+            |v| dividir(v[0] - v[1], v[2]),
+        ),
+    ];
+    let esenciales = componentes
+        .iter()
+        .filter(|c| BENEISH_ESENCIALES.contains(&c.clave.as_str()))
+        .all(|c| c.valor.is_some());
+    let evaluados = componentes.iter().filter(|c| c.valor.is_some()).count();
+    let valor = esenciales.then(|| {
+        -4.84
+            + componentes
+                .iter()
+                .map(|c| {
+                    let neutral = if c.clave == "tata" { 0.0 } else { 1.0 };
+                    c.valor.unwrap_or(neutral) * c.coeficiente.unwrap_or(0.0)
+                })
+                .sum::<f64>()
+    });
+    let interpretacion = valor.map(|v| {
+        if evaluados < componentes.len() {
+            format!("incompleto: {} de {} variables evaluadas", evaluados, componentes.len())
+        } else if v > UMBRAL_BENEISH {
+            "probable manipulación de utilidades".to_string()
+        } else {
+            "sin señales de manipulación".to_string()
+        }
+    });
+    Puntaje {
+        modelo: "beneish".to_string(),
+        nombre: "Beneish M-Score".to_string(),
+        valor,
+        interpretacion,
+        omitidos: omitidos(&componentes),
+        componentes,
+    }
+}
+
+/// Capitalización al cierre de cada ejercicio: último cierre del año por las
+/// acciones actuales del catálogo. Vacío si la emisora no está en el catálogo.
+async fn capitalizaciones(pool: &sqlx::PgPool, emisora: &str, anios: &[i32]) -> Result<HashMap<i32, f64>, String> {
+    let (emisora, serie, acciones) = match multiplos::acciones(pool, emisora, None).await {
+        Ok(datos) => datos,
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let fechas: Vec<NaiveDate> = anios.iter().filter_map(|a| NaiveDate::from_ymd_opt(*a, 12, 31)).collect();
+    let cierres = multiplos::cierres(pool, &emisora, &serie, &fechas).await.map_err(|e| e.to_string())?;
+    Ok(anios
+        .iter()
+        .zip(cierres)
+        .filter_map(|(anio, cierre)| cierre.map(|(_, precio)| (*anio, precio * acciones as f64)))
+        .collect())
+}
+
+/// Piotroski, Altman y Beneish de cada ejercicio completo de la emisora.
+#[command]
+pub async fn get_scores(emisora: String) -> Result<PuntajesEmisora, String> {
+    let pool = db::pool().await?;
+    let ejercicios = periodos::ejercicios(pool, &emisora).await.map_err(|e| e.to_string())?;
+    let anios: Vec<i32> = ejercicios.keys().copied().collect();
+    let capitalizaciones = capitalizaciones(pool, &emisora, &anios).await?;
+    let ejercicios = ejercicios
+        .iter()
+        .map(|(anio, actual)| {
+            let previo = ejercicios.get(&(anio - 1));
+            let capitalizacion = capitalizaciones.get(anio).copied();
+            PuntajesEjercicio {
+                anio: *anio,
+                piotroski: piotroski(actual, previo),
+                altman_manufactura: altman(actual, capitalizacion, true),
+                altman_no_manufactura: altman(actual, capitalizacion, false),
+                beneish: beneish(actual, previo),
+            }
+        })
+        .collect();
+    Ok(PuntajesEmisora { emisora, ejercicios })
+}
@@ -0,0 +1,472 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashMap};
+use tauri::command;
+use crate::activos::{get_finantial_flow, get_finantial_position, get_quarterly_income_statement};
+use crate::{db, periodos};
+
+// --- Razones financieras ---
+// Se calculan por trimestre a partir de los tres estados guardados (flujos,
+// posición financiera y resultados). Cada razón lleva su fórmula y los valores
+// con los que se calculó, para poder auditarla contra los estados. Una razón
+// cuyo denominador es cero queda sin valor en lugar de dar infinito.
+// Los activos totales no vienen en `estado_posicion`; se obtienen como pasivo
+// total más capital contable.
+// Por defecto las cifras de resultados y flujos son de doce meses (TTM, ver
+// `periodos`) para que márgenes y rendimientos no dependan de si la emisora
+// reporta acumulado; si no hay cuatro trimestres se usan las del trimestre.
+
+/// Días del periodo para las razones de días (cobro, pago).
+const DIAS_TRIMESTRE: f64 = 91.0;
+const DIAS_TTM: f64 = 365.0;
+/// Tasa de impuestos que se usa para el NOPAT cuando la efectiva no tiene sentido.
+const TASA_IMPUESTOS_POR_DEFECTO: f64 = 0.30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Categoria {
+    Liquidez,
+    Apalancamiento,
+    Rentabilidad,
+    Eficiencia,
+    FlujoEfectivo,
+    Valuacion,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ratio {
+    pub clave: String,
+    pub nombre: String,
+    pub categoria: Categoria,
+    pub valor: Option<f64>,
+    pub formula: String,
+    /// Valores de entrada usados en la fórmula
+    pub entradas: BTreeMap<String, f64>,
+}
+
+/// Periodo de las cifras de resultados y flujos.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Base {
+    Trimestre,
+    Ttm,
+}
+
+impl Base {
+    pub fn parse(s: &str) -> Result<Base, String> {
+        match s.trim().to_lowercase().as_str() {
+            "trimestre" => Ok(Base::Trimestre),
+            "ttm" => Ok(Base::Ttm),
+            otro => Err(format!("Base inválida '{}'. Usa 'trimestre' o 'ttm'", otro)),
+        }
+    }
+
+    pub fn dias(&self) -> f64 {
+        match self {
+            Base::Trimestre => DIAS_TRIMESTRE,
+            Base::Ttm => DIAS_TTM,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RatiosTrimestre {
+    pub emisora: String,
+    pub trimestre: String,
+    pub base: Base,
+    pub ratios: Vec<Ratio>,
+}
+
+/// Los tres estados de un periodo, con las columnas de `activos::get_finantial_*`.
+#[derive(Debug, Clone, Default)]
+pub struct Estados {
+    pub flujo: HashMap<String, f64>,
+    pub posicion: HashMap<String, f64>,
+    pub resultado: HashMap<String, f64>,
+}
+
+impl Estados {
+    /// Partida del flujo; `None` si la emisora no la reportó.
+    pub(crate) fn f(&self, columna: &str) -> Option<f64> {
+        self.flujo.get(columna).copied().filter(|v| v.is_finite())
+    }
+
+    pub(crate) fn p(&self, columna: &str) -> Option<f64> {
+        self.posicion.get(columna).copied().filter(|v| v.is_finite())
+    }
+
+    pub(crate) fn r(&self, columna: &str) -> Option<f64> {
+        self.resultado.get(columna).copied().filter(|v| v.is_finite())
+    }
+
+    pub fn activos_totales(&self) -> Option<f64> {
+        Some(self.p("liabilities")? + self.p("equity")?)
+    }
+
+    /// Deuda financiera de largo plazo; basta con una de las dos partidas.
+    pub fn deuda_financiera(&self) -> Option<f64> {
+        match (self.p("othernoncurrentfinancialliabilities"), self.p("noncurrentleaseliabilities")) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        }
+    }
+
+    /// Flujo libre: flujo de operación menos inversión en activo fijo. El signo del
+    /// capex varía entre emisoras, así que se resta su valor absoluto.
+    pub fn flujo_libre(&self) -> Option<f64> {
+        Some(self.f("flujo_operacion")? - self.f("capex")?.abs())
+    }
+}
+
+pub async fn estados(
+    pool: &PgPool,
+    emisora: &str,
+    trimestre: &str,
+) -> Result<Estados, Box<dyn std::error::Error + Send + Sync>> {
+    let (flujo, posicion, resultado) = tokio::try_join!(
+        get_finantial_flow(pool, emisora, trimestre),
+        get_finantial_position(pool, emisora, trimestre),
+        get_quarterly_income_statement(pool, emisora, trimestre),
+    )?;
+    Ok(Estados { flujo, posicion, resultado })
+}
+
+pub(crate) fn dividir(numerador: f64, denominador: f64) -> Option<f64> {
+    if denominador == 0.0 || !numerador.is_finite() || !denominador.is_finite() {
+        None
+    } else {
+        Some(numerador / denominador)
+    }
+}
+
+/// Como `dividir`, para entradas que pueden faltar.
+pub(crate) fn dividir_o(numerador: Option<f64>, denominador: Option<f64>) -> Option<f64> {
+    dividir(numerador?, denominador?)
+}
+
+/// Razón con sus entradas; las que faltan no aparecen en `entradas`.
+pub(crate) fn ratio(
+    clave: &str,
+    nombre: &str,
+    categoria: Categoria,
+    formula: &str,
+    entradas: &[(&str, Option<f64>)],
+    valor: Option<f64>,
+) -> Ratio {
+    Ratio {
+        clave: clave.to_string(),
+        nombre: nombre.to_string(),
+        categoria,
+        valor,
+        formula: formula.to_string(),
+        entradas: entradas.iter().filter_map(|(k, v)| Some((k.to_string(), (*v)?))).collect(),
+    }
+}
+
+/// Tasa efectiva de impuestos, acotada a [0, 1]; si la utilidad antes de
+/// impuestos no es positiva se usa `TASA_IMPUESTOS_POR_DEFECTO`.
+pub fn tasa_impuestos(e: &Estados) -> f64 {
+    match (e.r("profitlossbeforetax"), e.r("incometaxexpensecontinuingoperations")) {
+        (Some(antes), Some(impuestos)) if antes > 0.0 => (impuestos / antes).clamp(0.0, 1.0),
+        _ => TASA_IMPUESTOS_POR_DEFECTO,
+    }
+}
+
+/// Razones de un periodo. Las de resultados y flujos usan las cifras del periodo
+/// tal como vienen en `Estados` (un trimestre, o doce meses si se agregaron antes);
+/// `base` sólo fija los días de las razones de cobro y pago. Si falta una
+/// partida, las razones que la usan quedan sin valor.
+pub fn calcular(e: &Estados, base: Base) -> Vec<Ratio> {
+    use Categoria::*;
+    let activo_circulante = e.p("currentassets");
+    let pasivo_circulante = e.p("currentliabilities");
+    let efectivo = e.p("cashandcashequivalents");
+    let inventarios = e.p("inventories");
+    let cxc = e.p("tradeandothercurrentreceivables");
+    let cxp = e.p("tradeandothercurrentpayables");
+    let capital = e.p("equity");
+    let capital_controladora = e.p("equityattributabletoownersofparent");
+    let pasivo = e.p("liabilities");
+    let deuda_financiera = e.deuda_financiera();
+    let activos = e.activos_totales();
+
+    let ingresos = e.r("revenue");
+    let utilidad_bruta = e.r("grossprofit");
+    let utilidad_operativa = e.r("profitlossfromoperatingactivities");
+    let utilidad_neta = e.r("profitloss");
+    let utilidad_controladora = e.r("profitlossattributabletoownersofparent");
+    let costo_ventas = e.r("costofsales").map(f64::abs);
+    let gastos_financieros = e.r("financecosts").map(f64::abs);
+
+    let flujo_operacion = e.f("flujo_operacion");
+    let capex = e.f("capex").map(f64::abs);
+    let fcf = e.flujo_libre();
+
+    let tasa = tasa_impuestos(e);
+    let nopat = utilidad_operativa.map(|u| u * (1.0 - tasa));
+    let capital_invertido = capital.zip(deuda_financiera).zip(efectivo).map(|((c, d), x)| c + d - x);
+    let dias = base.dias();
+
+    vec![
+        // Liquidez
+        ratio(
+            "razon_circulante",
+            "Razón circulante",
+            Liquidez,
+            "currentassets / currentliabilities",
+            &[("currentassets", activo_circulante), ("currentliabilities", pasivo_circulante)],
+            dividir_o(activo_circulante, pasivo_circulante),
+        ),
+        ratio(
+            "prueba_acida",
+            "Prueba ácida",
+            Liquidez,
+            "(currentassets - inventories) / currentliabilities",
+            &[("currentassets", activo_circulante), ("inventories", inventarios), ("currentliabilities", pasivo_circulante)],
+            dividir_o(activo_circulante.zip(inventarios).map(|(a, i)| a - i), pasivo_circulante),
+        ),
+        ratio(
+            "razon_efectivo",
+            "Razón de efectivo",
+            Liquidez,
+            "cashandcashequivalents / currentliabilities",
+            &[("cashandcashequivalents", efectivo), ("currentliabilities", pasivo_circulante)],
+            dividir_o(efectivo, pasivo_circulante),
+        ),
+        ratio(
+            "capital_trabajo",
+            "Capital de trabajo",
+            Liquidez,
+            "currentassets - currentliabilities",
+            &[("currentassets", activo_circulante), ("currentliabilities", pasivo_circulante)],
+            activo_circulante.zip(pasivo_circulante).map(|(a, p)| a - p),
+        ),
+        // Apalancamiento
+        ratio(
+            "deuda_capital",
+            "Pasivo total / capital",
+            Apalancamiento,
+            "liabilities / equity",
+            &[("liabilities", pasivo), ("equity", capital)],
+            dividir_o(pasivo, capital),
+        ),
+        ratio(
+            "deuda_financiera_capital",
+            "Deuda financiera / capital",
+            Apalancamiento,
+            "(othernoncurrentfinancialliabilities + noncurrentleaseliabilities) / equity",
+            &[
+                ("othernoncurrentfinancialliabilities", e.p("othernoncurrentfinancialliabilities")),
+                ("noncurrentleaseliabilities", e.p("noncurrentleaseliabilities")),
+                ("equity", capital),
+            ],
+            dividir_o(deuda_financiera, capital),
+        ),
+        ratio(
+            "pasivo_activo",
+            "Pasivo / activo",
+            Apalancamiento,
+            "liabilities / (liabilities + equity)",
+            &[("liabilities", pasivo), ("equity", capital)],
+            dividir_o(pasivo, activos),
+        ),
+        ratio(
+            "multiplicador_capital",
+            "Multiplicador de capital",
+            Apalancamiento,
+            "(liabilities + equity) / equity",
+            &[("liabilities", pasivo), ("equity", capital)],
+            dividir_o(activos, capital),
+        ),
+        ratio(
+            "cobertura_intereses",
+            "Cobertura de intereses",
+            Apalancamiento,
+            "profitlossfromoperatingactivities / |financecosts|",
+            &[("profitlossfromoperatingactivities", utilidad_operativa), ("financecosts", gastos_financieros)],
+            dividir_o(utilidad_operativa, gastos_financieros),
+        ),
+        // Rentabilidad
+        ratio(
+            "margen_bruto",
+            "Margen bruto",
+            Rentabilidad,
+            "grossprofit / revenue",
+            &[("grossprofit", utilidad_bruta), ("revenue", ingresos)],
+            dividir_o(utilidad_bruta, ingresos),
+        ),
+        ratio(
+            "margen_operativo",
+            "Margen operativo",
+            Rentabilidad,
+            "profitlossfromoperatingactivities / revenue",
+            &[("profitlossfromoperatingactivities", utilidad_operativa), ("revenue", ingresos)],
+            dividir_o(utilidad_operativa, ingresos),
+        ),
+        ratio(
+            "margen_neto",
+            "Margen neto",
+            Rentabilidad,
+            "profitloss / revenue",
+            &[("profitloss", utilidad_neta), ("revenue", ingresos)],
+            dividir_o(utilidad_neta, ingresos),
+        ),
+        ratio(
+            "roe",
+            "ROE",
+            Rentabilidad,
+            "profitlossattributabletoownersofparent / equityattributabletoownersofparent",
+            &[
+                ("profitlossattributabletoownersofparent", utilidad_controladora),
+                ("equityattributabletoownersofparent", capital_controladora),
+            ],
+            dividir_o(utilidad_controladora, capital_controladora),
+        ),
+        ratio(
+            "roa",
+            "ROA",
+            Rentabilidad,
+            "profitloss / (liabilities + equity)",
+            &[("profitloss", utilidad_neta), ("liabilities", pasivo), ("equity", capital)],
+            dividir_o(utilidad_neta, activos),
+        ),
+        ratio(
+            "roic",
+            "ROIC",
+            Rentabilidad,
+            "profitlossfromoperatingactivities * (1 - tasa_impuestos) / (equity + othernoncurrentfinancialliabilities + noncurrentleaseliabilities - cashandcashequivalents)",
+            &[
+                ("profitlossfromoperatingactivities", utilidad_operativa),
+                ("tasa_impuestos", Some(tasa)),
+                ("equity", capital),
+                ("othernoncurrentfinancialliabilities", e.p("othernoncurrentfinancialliabilities")),
+                ("noncurrentleaseliabilities", e.p("noncurrentleaseliabilities")),
+                ("cashandcashequivalents", efectivo),
+            ],
+            dividir_o(nopat, capital_invertido),
+        ),
+        // Eficiencia
+        ratio(
+            "rotacion_activos",
+            "Rotación de activos",
+            Eficiencia,
+            "revenue / (liabilities + equity)",
+            &[("revenue", ingresos), ("liabilities", pasivo), ("equity", capital)],
+            dividir_o(ingresos, activos),
+        ),
+        ratio(
+            "rotacion_inventarios",
+            "Rotación de inventarios",
+            Eficiencia,
+            "|costofsales| / inventories",
+            &[("costofsales", costo_ventas), ("inventories", inventarios)],
+            dividir_o(costo_ventas, inventarios),
+        ),
+        ratio(
+            "dias_cobro",
+            "Días de cobro",
+            Eficiencia,
+            "tradeandothercurrentreceivables / revenue * dias",
+            &[("tradeandothercurrentreceivables", cxc), ("revenue", ingresos), ("dias", Some(dias))],
+            dividir_o(cxc, ingresos).map(|v| v * dias),
+        ),
+        ratio(
+            "dias_pago",
+            "Días de pago",
+            Eficiencia,
+            "tradeandothercurrentpayables / |costofsales| * dias",
+            &[("tradeandothercurrentpayables", cxp), ("costofsales", costo_ventas), ("dias", Some(dias))],
+            dividir_o(cxp, costo_ventas).map(|v| v * dias),
+        ),
+        // Flujo de efectivo
+        ratio(
+            "fcf",
+            "Flujo libre de efectivo",
+            FlujoEfectivo,
+            "flujo_operacion - |capex|",
+            &[("flujo_operacion", flujo_operacion), ("capex", capex)],
+            fcf,
+        ),
+        ratio(
+            "margen_fcf",
+            "Margen de flujo libre",
+            FlujoEfectivo,
+            "(flujo_operacion - |capex|) / revenue",
+            &[("flujo_operacion", flujo_operacion), ("capex", capex), ("revenue", ingresos)],
+            dividir_o(fcf, ingresos),
+        ),
+        ratio(
+            "conversion_efectivo",
+            "Conversión de utilidad a efectivo",
+            FlujoEfectivo,
+            "flujo_operacion / profitloss",
+            &[("flujo_operacion", flujo_operacion), ("profitloss", utilidad_neta)],
+            dividir_o(flujo_operacion, utilidad_neta),
+        ),
+        ratio(
+            "capex_ingresos",
+            "Capex / ingresos",
+            FlujoEfectivo,
+            "|capex| / revenue",
+            &[("capex", capex), ("revenue", ingresos)],
+            dividir_o(capex, ingresos),
+        ),
+    ]
+}
+
+/// Trimestres con estados guardados, del más antiguo al más reciente (los últimos `limite`).
+pub async fn trimestres_recientes(pool: &PgPool, emisora: &str, limite: i64) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT DISTINCT trimestre FROM public.estado_flujos WHERE LOWER(emisora) = LOWER($1) ORDER BY trimestre DESC LIMIT $2",
+    )
+    .bind(emisora)
+    .bind(limite)
+    .fetch_all(pool)
+    .await?;
+    let mut trimestres = rows.iter().map(|row| row.try_get(0)).collect::<Result<Vec<String>, _>>()?;
+    trimestres.sort();
+    Ok(trimestres)
+}
+
+/// Estados para calcular razones al cierre de `trimestre`: TTM si se pide y hay
+/// cuatro trimestres, si no los del trimestre. Regresa la base que se usó.
+pub async fn estados_base(
+    pool: &PgPool,
+    emisora: &str,
+    trimestre: &str,
+    base: Base,
+) -> Result<(Estados, Base), Box<dyn std::error::Error + Send + Sync>> {
+    if base == Base::Ttm {
+        if let Some(estados) = periodos::estados_ttm(pool, emisora, trimestre).await? {
+            return Ok((estados, Base::Ttm));
+        }
+    }
+    Ok((estados(pool, emisora, trimestre).await?, Base::Trimestre))
+}
+
+/// Razones por trimestre: el indicado o, si no hay, los 4 más recientes.
+/// `base` es "ttm" (por defecto) o "trimestre".
+#[command]
+pub async fn get_ratios(emisora: String, trimestre: Option<String>, base: Option<String>) -> Result<Vec<RatiosTrimestre>, String> {
+    let base = base.as_deref().map(Base::parse).transpose()?.unwrap_or(Base::Ttm);
+    let pool = db::pool().await?;
+    let trimestres = match trimestre {
+        Some(t) => vec![t],
+        None => trimestres_recientes(pool, &emisora, 4).await.map_err(|e| e.to_string())?,
+    };
+    let consultas = trimestres.into_iter().map(|t| {
+        let emisora = &emisora;
+        async move {
+            let (estados, base) = estados_base(pool, emisora, &t, base)
+                .await
+                .map_err(|e| format!("Error obteniendo estados de {} {}: {}", emisora, t, e))?;
+            Ok::<_, String>(RatiosTrimestre {
+                emisora: emisora.clone(),
+                trimestre: t,
+                base,
+                ratios: calcular(&estados, base),
+            })
+        }
+    });
+    futures::future::try_join_all(consultas).await
+}
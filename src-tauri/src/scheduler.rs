@@ -0,0 +1,514 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::America::Mexico_City;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::command;
+use crate::calendario;
+use crate::db;
+use crate::get_data::{self, TasaItem};
+use crate::historicos;
+
+// --- Programador de tareas en segundo plano ---
+// Revisa cada minuto qué jobs tocan y los ejecuta uno por uno. Un job "toca"
+// cuando ya pasó su hora programada más reciente y no hay una ejecución exitosa
+// posterior en `job_runs`, así que un job que no corrió porque la app estaba
+// cerrada se pone al corriente al abrirla. Cada ejecución reintenta con espera
+// creciente y avisa su avance por el `Notificador` (en la app, un evento de Tauri).
+// El ciclo no depende de Tauri para poder correr también como demonio.
+
+const REVISION_SEGUNDOS: u64 = 60;
+const MAX_INTENTOS: i32 = 3;
+/// Espera antes del primer reintento; se duplica en cada intento.
+const ESPERA_REINTENTO_SEGUNDOS: u64 = 30;
+/// Tras agotar los reintentos, no se vuelve a intentar antes de este tiempo.
+const ESPERA_TRAS_ERROR_MINUTOS: i64 = 30;
+
+type JobError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Job {
+    CatalogoEmisoras,
+    PreciosCierre,
+    EstadosFinancieros,
+    TasasDivisas,
+}
+
+/// Cuándo corre un job (hora de la Ciudad de México).
+#[derive(Debug, Clone, Copy)]
+enum Programa {
+    /// Todos los días a la hora indicada.
+    Diario(u32, u32),
+    /// Cada día hábil, tantos minutos después del cierre de la sesión.
+    TrasCierre(i64),
+}
+
+impl Job {
+    pub const TODOS: [Job; 4] = [Job::CatalogoEmisoras, Job::PreciosCierre, Job::EstadosFinancieros, Job::TasasDivisas];
+
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            Job::CatalogoEmisoras => "catalogo_emisoras",
+            Job::PreciosCierre => "precios_cierre",
+            Job::EstadosFinancieros => "estados_financieros",
+            Job::TasasDivisas => "tasas_divisas",
+        }
+    }
+
+    pub fn parse(nombre: &str) -> Result<Job, String> {
+        Job::TODOS
+            .into_iter()
+            .find(|j| j.nombre() == nombre)
+            .ok_or_else(|| format!("Job desconocido '{}'", nombre))
+    }
+
+    fn programa(&self) -> Programa {
+        match self {
+            Job::CatalogoEmisoras => Programa::Diario(6, 0),
+            // Después del catálogo, que es de donde sale `rangos_financieros`
+            Job::EstadosFinancieros => Programa::Diario(6, 30),
+            Job::PreciosCierre => Programa::TrasCierre(30),
+            Job::TasasDivisas => Programa::TrasCierre(45),
+        }
+    }
+}
+
+impl Programa {
+    /// Momento programado más reciente que no sea posterior a `ahora`.
+    fn ultima_hora(&self, ahora: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let hoy = ahora.with_timezone(&Mexico_City).date_naive();
+        (0..10)
+            .map(|d| hoy - Duration::days(d))
+            .filter_map(|dia| self.hora_en(dia))
+            .find(|momento| *momento <= ahora)
+    }
+
+    fn hora_en(&self, dia: NaiveDate) -> Option<DateTime<Utc>> {
+        let local: NaiveDateTime = match *self {
+            Programa::Diario(h, m) => dia.and_time(NaiveTime::from_hms_opt(h, m, 0)?),
+            Programa::TrasCierre(minutos) => {
+                let (_, cierre) = calendario::sesion(dia)?;
+                dia.and_time(cierre) + Duration::minutes(minutos)
+            }
+        };
+        Mexico_City.from_local_datetime(&local).earliest().map(|m| m.with_timezone(&Utc))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProgresoJob {
+    pub job: String,
+    /// "iniciado", "progreso", "reintento", "exito" o "error"; la ingesta masiva
+    /// también avisa "cancelado".
+    pub estado: String,
+    pub actual: usize,
+    pub total: usize,
+    pub mensaje: Option<String>,
+}
+
+/// Recibe el avance de los jobs (en la app se reenvía al frontend como evento).
+pub type Notificador = Arc<dyn Fn(ProgresoJob) + Send + Sync>;
+
+struct Contexto<'a> {
+    job: Job,
+    notificar: &'a Notificador,
+}
+
+impl Contexto<'_> {
+    fn avisar(&self, estado: &str, actual: usize, total: usize, mensaje: Option<String>) {
+        (self.notificar)(ProgresoJob {
+            job: self.job.nombre().to_string(),
+            estado: estado.to_string(),
+            actual,
+            total,
+            mensaje,
+        });
+    }
+}
+
+/// Jobs en ejecución; evita que el ciclo y una ejecución manual se pisen.
+fn en_curso() -> &'static Mutex<HashSet<&'static str>> {
+    static EN_CURSO: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    EN_CURSO.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+struct Candado(&'static str);
+
+impl Candado {
+    fn tomar(job: Job) -> Option<Candado> {
+        en_curso().lock().unwrap().insert(job.nombre()).then_some(Candado(job.nombre()))
+    }
+}
+
+impl Drop for Candado {
+    fn drop(&mut self) {
+        en_curso().lock().unwrap().remove(self.0);
+    }
+}
+
+/// Emisoras/series que el programador mantiene al día: las que están en algún
+/// portafolio o en alguna lista de seguimiento.
+pub async fn emisoras_seguidas(pool: &PgPool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT emisoras, serie FROM portafolio_ticker
+         WHERE emisoras IS NOT NULL AND serie IS NOT NULL
+         UNION
+         SELECT emisoras, serie FROM watchlist_items
+         ORDER BY emisoras, serie",
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.iter().map(|row| Ok((row.try_get(0)?, row.try_get(1)?))).collect()
+}
+
+/// Resume un paso por emisora: si todas fallaron, el job falla (y se reintenta).
+fn resumen(procesadas: usize, errores: Vec<String>) -> Result<String, JobError> {
+    if procesadas > 0 && errores.len() == procesadas {
+        return Err(errores.join("; ").into());
+    }
+    let mut mensaje = format!("{} procesadas", procesadas);
+    if !errores.is_empty() {
+        mensaje.push_str(&format!(", {} con error: {}", errores.len(), errores.join("; ")));
+    }
+    Ok(mensaje)
+}
+
+async fn catalogo_emisoras(pool: &PgPool) -> Result<String, JobError> {
+    get_data::get_ticker(pool).await?;
+    let row = sqlx::query("SELECT COUNT(*) FROM emisoras").fetch_one(pool).await?;
+    let total: i64 = row.try_get(0)?;
+    Ok(format!("{} emisoras en catálogo", total))
+}
+
+/// Barras diarias y precios intradía de la última sesión para las emisoras seguidas.
+async fn precios_cierre(pool: &PgPool, ctx: &Contexto<'_>) -> Result<String, JobError> {
+    let emisoras = emisoras_seguidas(pool).await?;
+    let sesion = calendario::last_trading_day_on_or_before(calendario::hoy()).to_string();
+    let mut errores = Vec::new();
+    for (i, (emisora, serie)) in emisoras.iter().enumerate() {
+        ctx.avisar("progreso", i, emisoras.len(), Some(format!("{}{}", emisora, serie)));
+        let ticker = format!("{}{}", emisora, serie);
+        let resultado = async {
+            historicos::actualizar_emisora(pool, emisora, serie).await?;
+            get_data::get_intradia(&[ticker.as_str()], &sesion, &sesion, "1m", pool).await
+        }
+        .await;
+        if let Err(e) = resultado {
+            errores.push(format!("{}: {}", ticker, e));
+        }
+    }
+    resumen(emisoras.len(), errores)
+}
+
+/// Periodos que nombra un `rangos_financieros`, en el orden en que vienen y tal
+/// como los nombra el proveedor.
+pub(crate) fn periodos_rango(rango: &str) -> Vec<&str> {
+    rango
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '|' | ';'))
+        .filter(|t| t.chars().any(|c| c.is_ascii_digit()))
+        .collect()
+}
+
+/// Último periodo de un `rangos_financieros` (el token final del rango).
+pub(crate) fn ultimo_periodo(rango: &str) -> Option<String> {
+    periodos_rango(rango).last().map(|t| t.to_string())
+}
+
+/// Pide los estados del trimestre nuevo cuando avanza `rangos_financieros`.
+async fn estados_financieros(pool: &PgPool, ctx: &Contexto<'_>) -> Result<String, JobError> {
+    let rows = sqlx::query(
+        "SELECT e.emisoras, e.serie, e.rangos_financieros
+         FROM emisoras e
+         JOIN (SELECT DISTINCT emisoras, serie FROM portafolio_ticker) p
+           ON p.emisoras = e.emisoras AND p.serie = e.serie
+         LEFT JOIN financieros_sincronizados s
+           ON s.emisoras = e.emisoras AND s.serie = e.serie
+         WHERE e.rangos_financieros IS NOT NULL
+           AND s.rango IS DISTINCT FROM e.rangos_financieros
+         ORDER BY e.emisoras, e.serie",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut errores = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let emisora: String = row.try_get(0)?;
+        let serie: String = row.try_get(1)?;
+        let rango: String = row.try_get(2)?;
+        let trimestre = match ultimo_periodo(&rango) {
+            Some(t) => t,
+            None => continue,
+        };
+        ctx.avisar("progreso", i, rows.len(), Some(format!("{} {}", emisora, trimestre)));
+        let resultado = async {
+            get_data::get_flujos_financieros(pool, &emisora, &trimestre).await?;
+            get_data::get_estado_resultado_trimestral(pool, &emisora, &trimestre).await?;
+            get_data::get_posicion_financiera(pool, &emisora, &trimestre).await?;
+            sqlx::query(
+                "INSERT INTO financieros_sincronizados (emisoras, serie, rango, trimestre)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (emisoras, serie) DO UPDATE SET
+                    rango = EXCLUDED.rango, trimestre = EXCLUDED.trimestre, actualizado = now()",
+            )
+            .bind(&emisora)
+            .bind(&serie)
+            .bind(&rango)
+            .bind(&trimestre)
+            .execute(pool)
+            .await?;
+            Ok::<(), JobError>(())
+        }
+        .await;
+        if let Err(e) = resultado {
+            errores.push(format!("{} {}: {}", emisora, trimestre, e));
+        }
+    }
+    resumen(rows.len(), errores)
+}
+
+/// Foto del día de tasas de referencia y tipos de cambio.
+async fn tasas_divisas(pool: &PgPool) -> Result<String, JobError> {
+    let (tasas, forex) = tokio::try_join!(get_data::get_tasas_struct(), get_data::get_forex())?;
+    let sesion = calendario::last_trading_day_on_or_before(calendario::hoy());
+    let lista: [(&str, &Option<TasaItem>); 9] = [
+        ("CETE28", &tasas.CETE28),
+        ("CETE91", &tasas.CETE_91),
+        ("CETE182", &tasas.CETE182),
+        ("CETE364", &tasas.CETE364),
+        ("TIIE28", &tasas.TIIE28),
+        ("TIIE91", &tasas.TIIE91),
+        ("TIIE182", &tasas.TIIE182),
+        ("TIIEFB", &tasas.TIIEFB),
+        ("Tasa_Objetivo", &tasas.Tasa_Objetivo),
+    ];
+    let mut tx = pool.begin().await?;
+    let mut guardadas = 0;
+    for (nombre, item) in lista {
+        let item = match item {
+            Some(item) => item,
+            None => continue,
+        };
+        // La tasa trae su propia fecha de publicación; si no se entiende, se usa la sesión
+        let fecha = NaiveDate::parse_from_str(item.f.get(0..10).unwrap_or(&item.f), "%Y-%m-%d").unwrap_or(sesion);
+        sqlx::query(
+            "INSERT INTO tasas_diarias (tasa, fecha, valor) VALUES ($1, $2, $3)
+             ON CONFLICT (tasa, fecha) DO UPDATE SET valor = EXCLUDED.valor, guardado = now()",
+        )
+        .bind(nombre)
+        .bind(fecha)
+        .bind(item.t)
+        .execute(&mut *tx)
+        .await?;
+        guardadas += 1;
+    }
+    for (par, item) in [("USDMXN", &forex.USDMXN), ("EURMXN", &forex.EURMXN)] {
+        if let Some(item) = item {
+            sqlx::query(
+                "INSERT INTO divisas_diarias (par, fecha, ultimo, cambio_pct) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (par, fecha) DO UPDATE SET
+                    ultimo = EXCLUDED.ultimo, cambio_pct = EXCLUDED.cambio_pct, guardado = now()",
+            )
+            .bind(par)
+            .bind(sesion)
+            .bind(item.u)
+            .bind(item.c)
+            .execute(&mut *tx)
+            .await?;
+            guardadas += 1;
+        }
+    }
+    tx.commit().await?;
+    Ok(format!("{} valores guardados para {}", guardadas, sesion))
+}
+
+async fn correr_job(pool: &PgPool, ctx: &Contexto<'_>) -> Result<String, JobError> {
+    match ctx.job {
+        Job::CatalogoEmisoras => catalogo_emisoras(pool).await,
+        Job::PreciosCierre => precios_cierre(pool, ctx).await,
+        Job::EstadosFinancieros => estados_financieros(pool, ctx).await,
+        Job::TasasDivisas => tasas_divisas(pool).await,
+    }
+}
+
+/// Ejecuta un job con reintentos y deja constancia en `job_runs`.
+pub async fn ejecutar(pool: &PgPool, job: Job, notificar: &Notificador) -> Result<String, String> {
+    let _candado = Candado::tomar(job).ok_or_else(|| format!("El job '{}' ya está en ejecución", job.nombre()))?;
+    let ctx = Contexto { job, notificar };
+    let row = sqlx::query("INSERT INTO job_runs (job) VALUES ($1) RETURNING id")
+        .bind(job.nombre())
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let run_id: i32 = row.try_get(0).map_err(|e| e.to_string())?;
+    ctx.avisar("iniciado", 0, 0, None);
+
+    let mut intento = 1;
+    let resultado = loop {
+        match correr_job(pool, &ctx).await {
+            Ok(mensaje) => break Ok(mensaje),
+            Err(e) if intento < MAX_INTENTOS => {
+                let espera = ESPERA_REINTENTO_SEGUNDOS * 2u64.pow((intento - 1) as u32);
+                eprintln!("[scheduler] {} falló (intento {}): {}. Reintentando en {}s", job.nombre(), intento, e, espera);
+                ctx.avisar("reintento", 0, 0, Some(e.to_string()));
+                tokio::time::sleep(std::time::Duration::from_secs(espera)).await;
+                intento += 1;
+                let _ = sqlx::query("UPDATE job_runs SET intentos = $2 WHERE id = $1")
+                    .bind(run_id)
+                    .bind(intento)
+                    .execute(pool)
+                    .await;
+            }
+            Err(e) => break Err(e.to_string()),
+        }
+    };
+
+    let (estado, mensaje) = match &resultado {
+        Ok(m) => ("exito", m.clone()),
+        Err(e) => ("error", e.clone()),
+    };
+    sqlx::query("UPDATE job_runs SET fin = now(), estado = $2, mensaje = $3 WHERE id = $1")
+        .bind(run_id)
+        .bind(estado)
+        .bind(&mensaje)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    ctx.avisar(estado, 0, 0, Some(mensaje));
+    resultado
+}
+
+/// ¿Toca correr `job` en `ahora`?
+async fn le_toca(pool: &PgPool, job: Job, ahora: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+    let programado = match job.programa().ultima_hora(ahora) {
+        Some(m) => m,
+        None => return Ok(false),
+    };
+    let row = sqlx::query(
+        "SELECT
+            bool_or(estado = 'exito' AND inicio >= $2) AS hecho,
+            bool_or(estado = 'error' AND fin >= $3) AS fallo_reciente
+         FROM job_runs WHERE job = $1",
+    )
+    .bind(job.nombre())
+    .bind(programado)
+    .bind(ahora - Duration::minutes(ESPERA_TRAS_ERROR_MINUTOS))
+    .fetch_one(pool)
+    .await?;
+    let hecho: Option<bool> = row.try_get("hecho")?;
+    let fallo_reciente: Option<bool> = row.try_get("fallo_reciente")?;
+    Ok(!hecho.unwrap_or(false) && !fallo_reciente.unwrap_or(false))
+}
+
+/// Ciclo del programador. No regresa; pensado para `tokio::spawn` o para un demonio.
+pub async fn correr(notificar: Notificador) {
+    // Ejecuciones que quedaron a medias si la app se cerró durante un job
+    if let Ok(pool) = db::pool().await {
+        let _ = sqlx::query(
+            "UPDATE job_runs SET estado = 'error', fin = now(), mensaje = 'Interrumpido' WHERE estado = 'en_curso'",
+        )
+        .execute(pool)
+        .await;
+    }
+    let mut intervalo = tokio::time::interval(std::time::Duration::from_secs(REVISION_SEGUNDOS));
+    loop {
+        intervalo.tick().await;
+        // Sin API key no hay nada que sincronizar
+        if get_data::get_api_key().is_err() {
+            continue;
+        }
+        let pool = match db::pool().await {
+            Ok(pool) => pool,
+            Err(e) => {
+                eprintln!("[scheduler] {}", e);
+                continue;
+            }
+        };
+        for job in Job::TODOS {
+            match le_toca(pool, job, Utc::now()).await {
+                Ok(true) => {
+                    let _ = ejecutar(pool, job, &notificar).await;
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!("[scheduler] No se pudo revisar {}: {}", job.nombre(), e),
+            }
+        }
+    }
+}
+
+/// Arranca el programador en segundo plano.
+pub fn iniciar(notificar: Notificador) {
+    tauri::async_runtime::spawn(correr(notificar));
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EstadoJob {
+    pub job: String,
+    pub en_curso: bool,
+    pub ultimo_inicio: Option<DateTime<Utc>>,
+    pub ultimo_fin: Option<DateTime<Utc>>,
+    pub ultimo_estado: Option<String>,
+    pub intentos: Option<i32>,
+    pub mensaje: Option<String>,
+    pub ultimo_exito: Option<DateTime<Utc>>,
+}
+
+#[command]
+pub async fn get_jobs() -> Result<Vec<EstadoJob>, String> {
+    let pool = db::pool().await?;
+    let mut estados = Vec::new();
+    for job in Job::TODOS {
+        let ultima = sqlx::query(
+            "SELECT inicio, fin, estado, intentos, mensaje,
+                    (SELECT MAX(fin) FROM job_runs WHERE job = $1 AND estado = 'exito') AS ultimo_exito
+             FROM job_runs WHERE job = $1
+             ORDER BY inicio DESC LIMIT 1",
+        )
+        .bind(job.nombre())
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let en_curso = en_curso().lock().unwrap().contains(job.nombre());
+        let estado = match ultima {
+            Some(row) => EstadoJob {
+                job: job.nombre().to_string(),
+                en_curso,
+                ultimo_inicio: row.try_get("inicio").map_err(|e| e.to_string())?,
+                ultimo_fin: row.try_get("fin").map_err(|e| e.to_string())?,
+                ultimo_estado: row.try_get("estado").map_err(|e| e.to_string())?,
+                intentos: row.try_get("intentos").map_err(|e| e.to_string())?,
+                mensaje: row.try_get("mensaje").map_err(|e| e.to_string())?,
+                ultimo_exito: row.try_get("ultimo_exito").map_err(|e| e.to_string())?,
+            },
+            None => EstadoJob {
+                job: job.nombre().to_string(),
+                en_curso,
+                ultimo_inicio: None,
+                ultimo_fin: None,
+                ultimo_estado: None,
+                intentos: None,
+                mensaje: None,
+                ultimo_exito: None,
+            },
+        };
+        estados.push(estado);
+    }
+    Ok(estados)
+}
+
+/// Ejecuta un job en este momento, sin esperar su hora programada.
+#[command]
+pub async fn run_job(app: tauri::AppHandle, job: String) -> Result<String, String> {
+    let job = Job::parse(&job)?;
+    let pool = db::pool().await?;
+    ejecutar(pool, job, &notificador_app(app)).await
+}
+
+pub const EVENTO_PROGRESO: &str = "scheduler-progress";
+
+/// Notificador que reenvía el avance al frontend.
+pub fn notificador_app(app: tauri::AppHandle) -> Notificador {
+    use tauri::Emitter;
+    Arc::new(move |progreso| {
+        let _ = app.emit(EVENTO_PROGRESO, progreso);
+    })
+}
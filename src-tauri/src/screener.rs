@@ -0,0 +1,771 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::command;
+use crate::ratios::{self, Base, Estados};
+use crate::{db, multiplos, periodos};
+
+// --- Screener ---
+// Filtra el universo de `emisoras` con datos guardados (precios diarios o
+// estados financieros) con una expresión como
+//     roe > 15 AND pe < 12 AND tipo_valor = 'ACCIONES'
+// Los campos son los del catálogo (tipo_valor, bolsa, estatus; el catálogo no
+// trae sector), las razones de `ratios` y los múltiplos de `multiplos` con cifras
+// de doce meses del último trimestre guardado, y rendimiento y volatilidad de
+// `precios_diarios`. Los múltiplos usan el último cierre guardado, no la
+// cotización en vivo. Márgenes, rendimientos y volatilidad van en porcentaje.
+// Una comparación contra un campo sin valor es falsa.
+// El universo se arma una vez y se reutiliza por `VIGENCIA_UNIVERSO`.
+
+const VIGENCIA_UNIVERSO: Duration = Duration::from_secs(300);
+const CONSULTAS_SIMULTANEAS: usize = 8;
+const LIMITE_POR_DEFECTO: usize = 200;
+const ORDEN_POR_DEFECTO: &str = "capitalizacion";
+/// Columnas que siempre se muestran, además de las que usa el filtro.
+const COLUMNAS_BASE: [&str; 5] = ["precio", "capitalizacion", "pe", "roe", "rendimiento_1a"];
+/// Campos de texto del catálogo.
+const CAMPOS_TEXTO: [&str; 7] = ["emisora", "serie", "razon_social", "tipo_valor", "bolsa", "estatus", "trimestre"];
+/// Campos de precio, en el orden en que se listan.
+const CAMPOS_PRECIO: [&str; 8] = [
+    "precio", "volumen_promedio", "acciones", "rendimiento_1m", "rendimiento_3m", "rendimiento_6m",
+    "rendimiento_1a", "volatilidad",
+];
+/// Razones y múltiplos que vienen como fracción y se muestran en porcentaje.
+const EN_PORCENTAJE: [&str; 11] = [
+    "margen_bruto", "margen_operativo", "margen_neto", "roe", "roa", "roic", "margen_fcf", "capex_ingresos",
+    "pasivo_activo", "dividend_yield", "fcf_yield",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Valor {
+    Numero(f64),
+    Texto(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoCampo {
+    Numero,
+    Texto,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CampoScreener {
+    pub clave: String,
+    pub tipo: TipoCampo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilaScreener {
+    pub emisora: String,
+    pub serie: String,
+    pub razon_social: Option<String>,
+    pub valores: BTreeMap<String, Valor>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResultadoScreener {
+    pub expresion: String,
+    pub columnas: Vec<String>,
+    /// Emisoras que pasan el filtro, antes de aplicar el límite
+    pub total: usize,
+    pub filas: Vec<FilaScreener>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedScreen {
+    pub id: i32,
+    pub nombre: String,
+    pub expresion: String,
+    pub orden: Option<String>,
+    pub descendente: bool,
+}
+
+/// Campos disponibles y su tipo. Las claves de razones y múltiplos salen de sus
+/// propias funciones para no duplicar la lista.
+fn campos() -> BTreeMap<String, TipoCampo> {
+    let vacio = Estados::default();
+    let mut campos: BTreeMap<String, TipoCampo> = CAMPOS_TEXTO.iter().map(|c| (c.to_string(), TipoCampo::Texto)).collect();
+    let numericos = CAMPOS_PRECIO
+        .iter()
+        .map(|c| c.to_string())
+        .chain(ratios::calcular(&vacio, Base::Ttm).into_iter().map(|r| r.clave))
+        .chain(multiplos::calcular(&vacio, 0.0, 0).into_iter().map(|r| r.clave));
+    for clave in numericos {
+        campos.insert(clave, TipoCampo::Numero);
+    }
+    campos
+}
+
+// --- Expresiones ---
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Campo(String),
+    Numero(f64),
+    Texto(String),
+    Operador(Operador),
+    Y,
+    O,
+    No,
+    Abre,
+    Cierra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operador {
+    Igual,
+    Distinto,
+    Menor,
+    MenorIgual,
+    Mayor,
+    MayorIgual,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Y(Box<Expr>, Box<Expr>),
+    O(Box<Expr>, Box<Expr>),
+    No(Box<Expr>),
+    Comparacion { campo: String, operador: Operador, valor: Valor },
+}
+
+fn tokens(expresion: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expresion.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::Abre);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Cierra);
+                i += 1;
+            }
+            '\'' => {
+                let inicio = i + 1;
+                let fin = chars[inicio..]
+                    .iter()
+                    .position(|c| *c == '\'')
+                    .map(|p| inicio + p)
+                    .ok_or_else(|| "Falta cerrar una comilla en la expresión".to_string())?;
+                tokens.push(Token::Texto(chars[inicio..fin].iter().collect()));
+                i = fin + 1;
+            }
+            '=' | '!' | '<' | '>' => {
+                let siguiente = chars.get(i + 1).copied();
+                let (operador, largo) = match (c, siguiente) {
+                    ('=', _) => (Operador::Igual, 1),
+                    ('!', Some('=')) => (Operador::Distinto, 2),
+                    ('<', Some('>')) => (Operador::Distinto, 2),
+                    ('<', Some('=')) => (Operador::MenorIgual, 2),
+                    ('<', _) => (Operador::Menor, 1),
+                    ('>', Some('=')) => (Operador::MayorIgual, 2),
+                    ('>', _) => (Operador::Mayor, 1),
+                    _ => return Err(format!("Operador inválido en la posición {}", i + 1)),
+                };
+                tokens.push(Token::Operador(operador));
+                i += largo;
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let inicio = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let texto: String = chars[inicio..i].iter().collect();
+                let numero = texto.parse().map_err(|_| format!("Número inválido '{}'", texto))?;
+                tokens.push(Token::Numero(numero));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let inicio = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let palabra: String = chars[inicio..i].iter().collect();
+                tokens.push(match palabra.to_uppercase().as_str() {
+                    "AND" | "Y" => Token::Y,
+                    "OR" | "O" => Token::O,
+                    "NOT" | "NO" => Token::No,
+                    _ => Token::Campo(palabra.to_lowercase()),
+                });
+            }
+            otro => return Err(format!("Carácter inesperado '{}' en la posición {}", otro, i + 1)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Analizador descendente: OR < AND < NOT < comparación o paréntesis.
+struct Analizador<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    campos: &'a BTreeMap<String, TipoCampo>,
+}
+
+impl Analizador<'_> {
+    fn siguiente(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn ver(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn o(&mut self) -> Result<Expr, String> {
+        let mut izquierda = self.y()?;
+        while self.ver() == Some(&Token::O) {
+            self.pos += 1;
+            izquierda = Expr::O(Box::new(izquierda), Box::new(self.y()?));
+        }
+        Ok(izquierda)
+    }
+
+    fn y(&mut self) -> Result<Expr, String> {
+        let mut izquierda = self.no()?;
+        while self.ver() == Some(&Token::Y) {
+            self.pos += 1;
+            izquierda = Expr::Y(Box::new(izquierda), Box::new(self.no()?));
+        }
+        Ok(izquierda)
+    }
+
+    fn no(&mut self) -> Result<Expr, String> {
+        if self.ver() == Some(&Token::No) {
+            self.pos += 1;
+            return Ok(Expr::No(Box::new(self.no()?)));
+        }
+        self.atomo()
+    }
+
+    fn atomo(&mut self) -> Result<Expr, String> {
+        match self.siguiente() {
+            Some(Token::Abre) => {
+                let expr = self.o()?;
+                match self.siguiente() {
+                    Some(Token::Cierra) => Ok(expr),
+                    _ => Err("Falta cerrar un paréntesis".to_string()),
+                }
+            }
+            Some(Token::Campo(campo)) => {
+                let tipo = *self.campos.get(&campo).ok_or_else(|| format!("Campo desconocido '{}'", campo))?;
+                let operador = match self.siguiente() {
+                    Some(Token::Operador(op)) => op,
+                    _ => return Err(format!("Se esperaba un operador después de '{}'", campo)),
+                };
+                let valor = match (self.siguiente(), tipo) {
+                    (Some(Token::Numero(n)), TipoCampo::Numero) => Valor::Numero(n),
+                    (Some(Token::Texto(t)), TipoCampo::Texto) => {
+                        if !matches!(operador, Operador::Igual | Operador::Distinto) {
+                            return Err(format!("'{}' es texto; sólo admite = y !=", campo));
+                        }
+                        Valor::Texto(t)
+                    }
+                    (Some(Token::Numero(_)), TipoCampo::Texto) => {
+                        return Err(format!("'{}' es texto; el valor va entre comillas", campo))
+                    }
+                    (Some(Token::Texto(_)), TipoCampo::Numero) => return Err(format!("'{}' es numérico", campo)),
+                    _ => return Err(format!("Se esperaba un valor para '{}'", campo)),
+                };
+                Ok(Expr::Comparacion { campo, operador, valor })
+            }
+            Some(otro) => Err(format!("Token inesperado {:?}", otro)),
+            None => Err("La expresión está incompleta".to_string()),
+        }
+    }
+}
+
+fn analizar(expresion: &str, campos: &BTreeMap<String, TipoCampo>) -> Result<Expr, String> {
+    let tokens = tokens(expresion)?;
+    if tokens.is_empty() {
+        return Err("La expresión del filtro está vacía".to_string());
+    }
+    let mut analizador = Analizador { tokens, pos: 0, campos };
+    let expr = analizador.o()?;
+    if analizador.pos < analizador.tokens.len() {
+        return Err(format!("Sobra texto después de {:?}", analizador.tokens[analizador.pos - 1]));
+    }
+    Ok(expr)
+}
+
+/// Campos de la expresión, en el orden en que aparecen.
+fn campos_usados(expr: &Expr, usados: &mut Vec<String>) {
+    match expr {
+        Expr::Y(a, b) | Expr::O(a, b) => {
+            campos_usados(a, usados);
+            campos_usados(b, usados);
+        }
+        Expr::No(a) => campos_usados(a, usados),
+        Expr::Comparacion { campo, .. } => {
+            if !usados.contains(campo) {
+                usados.push(campo.clone());
+            }
+        }
+    }
+}
+
+fn evaluar(expr: &Expr, valores: &HashMap<String, Valor>) -> bool {
+    match expr {
+        Expr::Y(a, b) => evaluar(a, valores) && evaluar(b, valores),
+        Expr::O(a, b) => evaluar(a, valores) || evaluar(b, valores),
+        Expr::No(a) => !evaluar(a, valores),
+        Expr::Comparacion { campo, operador, valor } => match (valores.get(campo), valor) {
+            (Some(Valor::Numero(x)), Valor::Numero(y)) => match operador {
+                Operador::Igual => x == y,
+                Operador::Distinto => x != y,
+                Operador::Menor => x < y,
+                Operador::MenorIgual => x <= y,
+                Operador::Mayor => x > y,
+                Operador::MayorIgual => x >= y,
+            },
+            (Some(Valor::Texto(x)), Valor::Texto(y)) => match operador {
+                Operador::Distinto => !x.trim().eq_ignore_ascii_case(y.trim()),
+                _ => x.trim().eq_ignore_ascii_case(y.trim()),
+            },
+            _ => false,
+        },
+    }
+}
+
+// --- Universo ---
+
+/// Una emisora/serie con todos sus campos.
+#[derive(Debug, Clone)]
+struct Candidato {
+    emisora: String,
+    serie: String,
+    razon_social: Option<String>,
+    valores: HashMap<String, Valor>,
+}
+
+/// Universo armado y cuándo se armó.
+type Universo = Option<(Instant, Arc<Vec<Candidato>>)>;
+
+fn universo_cache() -> &'static Mutex<Universo> {
+    static UNIVERSO: OnceLock<Mutex<Universo>> = OnceLock::new();
+    UNIVERSO.get_or_init(|| Mutex::new(None))
+}
+
+/// Razones y múltiplos TTM del último trimestre guardado de cada emisora.
+async fn fundamentales(pool: &PgPool) -> Result<HashMap<String, (String, Estados)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT DISTINCT UPPER(emisora) FROM public.estado_posicion WHERE emisora IS NOT NULL")
+        .fetch_all(pool)
+        .await?;
+    let emisoras = rows.iter().map(|row| row.try_get(0)).collect::<Result<Vec<String>, _>>()?;
+    let resultados: Vec<_> = futures::stream::iter(emisoras)
+        .map(|emisora| async move {
+            let historia = periodos::historia_ttm(pool, &emisora).await;
+            (emisora, historia)
+        })
+        .buffer_unordered(CONSULTAS_SIMULTANEAS)
+        .collect()
+        .await;
+    let mut fundamentales = HashMap::new();
+    for (emisora, historia) in resultados {
+        if let Some((trimestre, _, estados)) = historia?.pop() {
+            fundamentales.insert(emisora, (trimestre, estados));
+        }
+    }
+    Ok(fundamentales)
+}
+
+/// Emisoras del catálogo con precios o estados guardados, con sus métricas de precio.
+async fn armar_universo(pool: &PgPool) -> Result<Vec<Candidato>, sqlx::Error> {
+    let rows = sqlx::query(
+        "WITH ultimo AS (
+             SELECT DISTINCT ON (emisoras, serie) emisoras, serie, fecha, cierre
+             FROM precios_diarios
+             ORDER BY emisoras, serie, fecha DESC
+         )
+         SELECT e.emisoras, e.serie, e.razon_social, e.tipo_valor, e.bolsa, e.estatus,
+                (SUM(e.acciones_circulacion) OVER (PARTITION BY UPPER(e.emisoras)))::bigint AS acciones,
+                u.cierre,
+                (SELECT cierre FROM precios_diarios p WHERE p.emisoras = u.emisoras AND p.serie = u.serie
+                  AND p.fecha <= u.fecha - 30 ORDER BY p.fecha DESC LIMIT 1) AS cierre_1m,
+                (SELECT cierre FROM precios_diarios p WHERE p.emisoras = u.emisoras AND p.serie = u.serie
+                  AND p.fecha <= u.fecha - 91 ORDER BY p.fecha DESC LIMIT 1) AS cierre_3m,
+                (SELECT cierre FROM precios_diarios p WHERE p.emisoras = u.emisoras AND p.serie = u.serie
+                  AND p.fecha <= u.fecha - 182 ORDER BY p.fecha DESC LIMIT 1) AS cierre_6m,
+                (SELECT cierre FROM precios_diarios p WHERE p.emisoras = u.emisoras AND p.serie = u.serie
+                  AND p.fecha <= u.fecha - 365 ORDER BY p.fecha DESC LIMIT 1) AS cierre_1a,
+                v.volatilidad, v.volumen_promedio
+         FROM emisoras e
+         LEFT JOIN ultimo u ON u.emisoras = e.emisoras AND u.serie = e.serie
+         LEFT JOIN LATERAL (
+             SELECT stddev_samp(r) * sqrt(252) AS volatilidad, AVG(volumen)::double precision AS volumen_promedio
+             FROM (
+                 SELECT ln(cierre / lag(cierre) OVER (ORDER BY fecha)) AS r, volumen
+                 FROM precios_diarios p
+                 WHERE p.emisoras = u.emisoras AND p.serie = u.serie AND p.fecha > u.fecha - 365 AND p.cierre > 0
+             ) x
+         ) v ON true
+         WHERE e.emisoras IS NOT NULL AND e.serie IS NOT NULL
+           AND (u.cierre IS NOT NULL
+                OR EXISTS (SELECT 1 FROM estado_posicion ep WHERE UPPER(ep.emisora) = UPPER(e.emisoras)))
+         ORDER BY e.emisoras, e.serie",
+    )
+    .fetch_all(pool)
+    .await?;
+    let fundamentales = fundamentales(pool).await?;
+
+    let mut universo = Vec::with_capacity(rows.len());
+    for row in rows {
+        let emisora: String = row.try_get("emisoras")?;
+        let serie: String = row.try_get("serie")?;
+        let razon_social: Option<String> = row.try_get("razon_social")?;
+        let acciones: Option<i64> = row.try_get("acciones")?;
+        let precio: Option<f64> = row.try_get("cierre")?;
+
+        let mut valores: HashMap<String, Valor> = HashMap::new();
+        let mut texto = |clave: &str, valor: Option<String>| {
+            if let Some(v) = valor {
+                valores.insert(clave.to_string(), Valor::Texto(v));
+            }
+        };
+        texto("emisora", Some(emisora.clone()));
+        texto("serie", Some(serie.clone()));
+        texto("razon_social", razon_social.clone());
+        texto("tipo_valor", row.try_get("tipo_valor")?);
+        texto("bolsa", row.try_get("bolsa")?);
+        texto("estatus", row.try_get("estatus")?);
+
+        let mut numero = |clave: &str, valor: Option<f64>| {
+            if let Some(v) = valor.filter(|v| v.is_finite()) {
+                valores.insert(clave.to_string(), Valor::Numero(v));
+            }
+        };
+        numero("precio", precio);
+        numero("acciones", acciones.map(|a| a as f64));
+        numero("volumen_promedio", row.try_get("volumen_promedio")?);
+        numero("volatilidad", row.try_get::<Option<f64>, _>("volatilidad")?.map(|v| v * 100.0));
+        for (clave, columna) in [
+            ("rendimiento_1m", "cierre_1m"),
+            ("rendimiento_3m", "cierre_3m"),
+            ("rendimiento_6m", "cierre_6m"),
+            ("rendimiento_1a", "cierre_1a"),
+        ] {
+            let anterior: Option<f64> = row.try_get(columna)?;
+            numero(
+                clave,
+                precio.zip(anterior).and_then(|(p, a)| ratios::dividir(p - a, a)).map(|r| r * 100.0),
+            );
+        }
+
+        if let Some((trimestre, estados)) = fundamentales.get(&emisora.to_uppercase()) {
+            valores.insert("trimestre".to_string(), Valor::Texto(trimestre.clone()));
+            let mut calculados = ratios::calcular(estados, Base::Ttm);
+            if let (Some(precio), Some(acciones)) = (precio, acciones.filter(|a| *a > 0)) {
+                calculados.extend(multiplos::calcular(estados, precio, acciones));
+            }
+            for r in calculados {
+                if let Some(v) = r.valor.filter(|v| v.is_finite()) {
+                    let v = if EN_PORCENTAJE.contains(&r.clave.as_str()) { v * 100.0 } else { v };
+                    valores.insert(r.clave, Valor::Numero(v));
+                }
+            }
+        }
+        universo.push(Candidato { emisora, serie, razon_social, valores });
+    }
+    Ok(universo)
+}
+
+async fn universo(pool: &PgPool) -> Result<Arc<Vec<Candidato>>, String> {
+    if let Some((creado, universo)) = universo_cache().lock().unwrap().as_ref() {
+        if creado.elapsed() < VIGENCIA_UNIVERSO {
+            return Ok(universo.clone());
+        }
+    }
+    let universo = Arc::new(armar_universo(pool).await.map_err(|e| format!("Error armando el universo del screener: {}", e))?);
+    *universo_cache().lock().unwrap() = Some((Instant::now(), universo.clone()));
+    Ok(universo)
+}
+
+/// Corre un filtro contra el universo y arma la tabla ordenada.
+pub async fn correr(
+    pool: &PgPool,
+    expresion: &str,
+    orden: Option<&str>,
+    descendente: bool,
+    limite: usize,
+) -> Result<ResultadoScreener, String> {
+    let campos = campos();
+    let expr = analizar(expresion, &campos)?;
+    let orden = orden.map(|o| o.trim().to_lowercase()).unwrap_or_else(|| ORDEN_POR_DEFECTO.to_string());
+    if !campos.contains_key(&orden) {
+        return Err(format!("No se puede ordenar por '{}': no es un campo del screener", orden));
+    }
+    let mut columnas: Vec<String> = COLUMNAS_BASE.iter().map(|c| c.to_string()).collect();
+    campos_usados(&expr, &mut columnas);
+    if !columnas.contains(&orden) {
+        columnas.push(orden.clone());
+    }
+
+    let universo = universo(pool).await?;
+    let mut pasan: Vec<&Candidato> = universo.iter().filter(|c| evaluar(&expr, &c.valores)).collect();
+    // Los que no tienen valor en la columna de orden van al final
+    pasan.sort_by(|a, b| {
+        let (x, y) = (a.valores.get(&orden), b.valores.get(&orden));
+        let comparacion = match (x, y) {
+            (Some(Valor::Numero(x)), Some(Valor::Numero(y))) => x.total_cmp(y),
+            (Some(Valor::Texto(x)), Some(Valor::Texto(y))) => x.cmp(y),
+            (Some(_), None) => return std::cmp::Ordering::Less,
+            (None, Some(_)) => return std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        };
+        if descendente { comparacion.reverse() } else { comparacion }
+    });
+    let total = pasan.len();
+    let filas = pasan
+        .into_iter()
+        .take(limite)
+        .map(|c| FilaScreener {
+            emisora: c.emisora.clone(),
+            serie: c.serie.clone(),
+            razon_social: c.razon_social.clone(),
+            valores: columnas
+                .iter()
+                .filter_map(|col| c.valores.get(col).map(|v| (col.clone(), v.clone())))
+                .collect(),
+        })
+        .collect();
+    Ok(ResultadoScreener {
+        expresion: expresion.trim().to_string(),
+        columnas,
+        total,
+        filas,
+    })
+}
+
+/// Campos que se pueden usar en un filtro.
+#[command]
+pub fn get_screen_fields() -> Vec<CampoScreener> {
+    campos().into_iter().map(|(clave, tipo)| CampoScreener { clave, tipo }).collect()
+}
+
+/// Corre un filtro. `orden` es un campo (por defecto la capitalización),
+/// descendente salvo que se indique lo contrario.
+#[command]
+pub async fn run_screen(
+    expresion: String,
+    orden: Option<String>,
+    descendente: Option<bool>,
+    limite: Option<usize>,
+) -> Result<ResultadoScreener, String> {
+    let pool = db::pool().await?;
+    correr(pool, &expresion, orden.as_deref(), descendente.unwrap_or(true), limite.unwrap_or(LIMITE_POR_DEFECTO)).await
+}
+
+// --- Filtros guardados ---
+
+fn desde_fila(row: &sqlx::postgres::PgRow) -> Result<SavedScreen, sqlx::Error> {
+    Ok(SavedScreen {
+        id: row.try_get("id")?,
+        nombre: row.try_get("nombre")?,
+        expresion: row.try_get("expresion")?,
+        orden: row.try_get("orden")?,
+        descendente: row.try_get("descendente")?,
+    })
+}
+
+async fn filtro_del_usuario(pool: &PgPool, screen_id: i32, usuario_id: i32) -> Result<SavedScreen, String> {
+    let row = sqlx::query(
+        "SELECT id, nombre, expresion, orden, descendente FROM saved_screens WHERE id = $1 AND usuario_id = $2",
+    )
+    .bind(screen_id)
+    .bind(usuario_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No se encontró el filtro guardado {}", screen_id))?;
+    desde_fila(&row).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_saved_screens(usuario_id: i32) -> Result<Vec<SavedScreen>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT id, nombre, expresion, orden, descendente FROM saved_screens WHERE usuario_id = $1 ORDER BY nombre",
+    )
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter().map(desde_fila).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Guarda un filtro; si el usuario ya tiene uno con ese nombre, lo reemplaza.
+#[command]
+pub async fn save_screen(
+    usuario_id: i32,
+    nombre: String,
+    expresion: String,
+    orden: Option<String>,
+    descendente: Option<bool>,
+) -> Result<SavedScreen, String> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre del filtro no puede estar vacío".to_string());
+    }
+    let campos = campos();
+    analizar(&expresion, &campos)?;
+    let orden = orden.map(|o| o.trim().to_lowercase()).filter(|o| !o.is_empty());
+    if let Some(o) = &orden {
+        if !campos.contains_key(o) {
+            return Err(format!("No se puede ordenar por '{}': no es un campo del screener", o));
+        }
+    }
+    let pool = db::pool().await?;
+    let row = sqlx::query(
+        "INSERT INTO saved_screens (usuario_id, nombre, expresion, orden, descendente)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (usuario_id, nombre) DO UPDATE
+             SET expresion = EXCLUDED.expresion, orden = EXCLUDED.orden,
+                 descendente = EXCLUDED.descendente, updated_at = now()
+         RETURNING id, nombre, expresion, orden, descendente",
+    )
+    .bind(usuario_id)
+    .bind(nombre)
+    .bind(expresion.trim())
+    .bind(&orden)
+    .bind(descendente.unwrap_or(true))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    desde_fila(&row).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn delete_saved_screen(screen_id: i32, usuario_id: i32) -> Result<(), String> {
+    let pool = db::pool().await?;
+    filtro_del_usuario(pool, screen_id, usuario_id).await?;
+    sqlx::query("DELETE FROM saved_screens WHERE id = $1")
+        .bind(screen_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[command]
+pub async fn run_saved_screen(screen_id: i32, usuario_id: i32, limite: Option<usize>) -> Result<ResultadoScreener, String> {
+    let pool = db::pool().await?;
+    let filtro = filtro_del_usuario(pool, screen_id, usuario_id).await?;
+    correr(
+        pool,
+        &filtro.expresion,
+        filtro.orden.as_deref(),
+        filtro.descendente,
+        limite.unwrap_or(LIMITE_POR_DEFECTO),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campos_prueba() -> BTreeMap<String, TipoCampo> {
+        BTreeMap::from([
+            ("precio".to_string(), TipoCampo::Numero),
+            ("roe".to_string(), TipoCampo::Numero),
+            ("emisora".to_string(), TipoCampo::Texto),
+        ])
+    }
+
+    fn error(expresion: &str) -> String {
+        analizar(expresion, &campos_prueba()).unwrap_err()
+    }
+
+    #[test]
+    fn tokens_de_una_expresion() {
+        assert_eq!(
+            tokens("(Precio >= 10.5 and emisora <> 'WALMEX') OR NOT roe < -0.1").unwrap(),
+            vec![
+                Token::Abre,
+                Token::Campo("precio".to_string()),
+                Token::Operador(Operador::MayorIgual),
+                Token::Numero(10.5),
+                Token::Y,
+                Token::Campo("emisora".to_string()),
+                Token::Operador(Operador::Distinto),
+                Token::Texto("WALMEX".to_string()),
+                Token::Cierra,
+                Token::O,
+                Token::No,
+                Token::Campo("roe".to_string()),
+                Token::Operador(Operador::Menor),
+                Token::Numero(-0.1),
+            ]
+        );
+        assert_eq!(
+            tokens("a != 1 y b <= 2 o c > 3").unwrap()[1..3],
+            [Token::Operador(Operador::Distinto), Token::Numero(1.0)]
+        );
+    }
+
+    #[test]
+    fn tokens_rechaza_entradas_invalidas() {
+        assert!(tokens("emisora = 'WALMEX").unwrap_err().contains("comilla"));
+        assert!(tokens("precio ! 3").unwrap_err().contains("Operador inválido en la posición 8"));
+        assert!(tokens("precio # 3").unwrap_err().contains("Carácter inesperado '#'"));
+        assert!(tokens("precio > 1.2.3").unwrap_err().contains("Número inválido '1.2.3'"));
+    }
+
+    #[test]
+    fn and_tiene_precedencia_sobre_or() {
+        let expr = analizar("precio > 1 OR precio < 5 AND NOT emisora = 'X'", &campos_prueba()).unwrap();
+        match expr {
+            Expr::O(izquierda, derecha) => {
+                assert!(matches!(*izquierda, Expr::Comparacion { operador: Operador::Mayor, .. }));
+                match *derecha {
+                    Expr::Y(a, b) => {
+                        assert!(matches!(*a, Expr::Comparacion { operador: Operador::Menor, .. }));
+                        assert!(matches!(*b, Expr::No(_)));
+                    }
+                    otro => panic!("se esperaba AND, no {:?}", otro),
+                }
+            }
+            otro => panic!("se esperaba OR, no {:?}", otro),
+        }
+    }
+
+    #[test]
+    fn evalua_la_expresion_analizada() {
+        let expr = analizar("(precio > 10 OR roe >= 0.2) AND emisora != 'amx'", &campos_prueba()).unwrap();
+        let valores = |precio: f64, emisora: &str| {
+            HashMap::from([
+                ("precio".to_string(), Valor::Numero(precio)),
+                ("emisora".to_string(), Valor::Texto(emisora.to_string())),
+            ])
+        };
+        assert!(evaluar(&expr, &valores(12.0, "WALMEX")));
+        assert!(!evaluar(&expr, &valores(12.0, "AMX")));
+        // Sin roe la comparación es falsa
+        assert!(!evaluar(&expr, &valores(8.0, "WALMEX")));
+    }
+
+    #[test]
+    fn errores_de_gramatica() {
+        assert_eq!(error("  "), "La expresión del filtro está vacía");
+        assert_eq!(error("volumen > 3"), "Campo desconocido 'volumen'");
+        assert_eq!(error("precio 3"), "Se esperaba un operador después de 'precio'");
+        assert_eq!(error("precio >"), "Se esperaba un valor para 'precio'");
+        assert_eq!(error("precio = 'x'"), "'precio' es numérico");
+        assert_eq!(error("emisora = 3"), "'emisora' es texto; el valor va entre comillas");
+        assert_eq!(error("emisora > 'X'"), "'emisora' es texto; sólo admite = y !=");
+        assert_eq!(error("(precio > 1"), "Falta cerrar un paréntesis");
+        assert_eq!(error("precio > 1 AND"), "La expresión está incompleta");
+        assert!(error("precio > 1 roe").starts_with("Sobra texto"));
+        assert!(error("> 1").starts_with("Token inesperado"));
+    }
+}
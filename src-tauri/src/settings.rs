@@ -0,0 +1,239 @@
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::command;
+
+// --- Almacenamiento local de secretos ---
+// Los secretos (p. ej. la API key de DataBursatil) se guardan como un JSON cifrado
+// con AES-256-GCM en `secrets.enc`. La llave se genera al primer uso y vive en el
+// llavero del sistema (Keychain, Credential Manager o Secret Service). Si no hay
+// llavero (p. ej. el daemon en un servidor sin sesión) se guarda en `secrets.key`,
+// con permisos 0600, junto a `secrets.enc`: eso sólo ofusca, no protege.
+
+const SECRETS_FILE: &str = "secrets.enc";
+const KEY_FILE: &str = "secrets.key";
+const KEYRING_SERVICE: &str = "dalia-trac";
+const KEYRING_USER: &str = "secrets-key";
+const NONCE_LEN: usize = 12;
+
+pub const API_KEY_SECRET: &str = "databursatil_api_key";
+pub const PROVIDER_NOT_CONFIGURED: &str =
+    "Proveedor de datos no configurado: registra tu API key de DataBursatil en Configuración";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProviderStatus {
+    pub configurado: bool,
+    /// "secrets" si viene del archivo cifrado, "env" si viene de `API_KEY` en `.env`
+    pub origen: Option<String>,
+    /// Aviso de la última operación, p. ej. que los secretos se reiniciaron
+    pub aviso: Option<String>,
+}
+
+/// Directorio de configuración de la app. Se puede forzar con `DALIA_CONFIG_DIR`.
+pub fn config_dir() -> Result<PathBuf, String> {
+    if let Ok(dir) = std::env::var("DALIA_CONFIG_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    dirs::config_dir()
+        .map(|d| d.join("dalia-trac"))
+        .ok_or_else(|| "No se pudo determinar el directorio de configuración".to_string())
+}
+
+fn write_private(path: &PathBuf, data: &[u8]) -> Result<(), String> {
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts.open(path).map_err(|e| format!("Error abriendo {}: {}", path.display(), e))?;
+    file.write_all(data).map_err(|e| format!("Error escribiendo {}: {}", path.display(), e))
+}
+
+/// Llave guardada en el llavero del sistema. La primera vez adopta la de
+/// `secrets.key`, si la hay, para no perder los secretos ya cifrados, y borra
+/// el archivo.
+fn llave_del_llavero(archivo: &Path) -> Result<[u8; 32], keyring::Error> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+    match entry.get_secret() {
+        Ok(bytes) => return bytes.try_into().map_err(keyring::Error::BadEncoding),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(e),
+    }
+    let key: [u8; 32] = match fs::read(archivo).ok().and_then(|b| b.try_into().ok()) {
+        Some(key) => key,
+        None => rand::random(),
+    };
+    entry.set_secret(&key)?;
+    if archivo.exists() {
+        if let Err(e) = fs::remove_file(archivo) {
+            eprintln!("[settings] No se pudo borrar {}: {}", archivo.display(), e);
+        }
+    }
+    Ok(key)
+}
+
+fn load_or_create_key() -> Result<[u8; 32], String> {
+    let dir = config_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Error creando {}: {}", dir.display(), e))?;
+    let path = dir.join(KEY_FILE);
+    match llave_del_llavero(&path) {
+        Ok(key) => return Ok(key),
+        Err(e) => eprintln!("[settings] Llavero del sistema no disponible, se usa {}: {}", path.display(), e),
+    }
+    if let Ok(bytes) = fs::read(&path) {
+        return bytes
+            .try_into()
+            .map_err(|_| format!("Llave de secretos corrupta en {}", path.display()));
+    }
+    // Sin archivo, la llave de los secretos existentes está en el llavero: una
+    // nueva los dejaría ilegibles
+    if dir.join(SECRETS_FILE).exists() {
+        return Err("La llave de los secretos está en el llavero del sistema, que no está disponible".to_string());
+    }
+    let key: [u8; 32] = rand::random();
+    write_private(&path, &key)?;
+    Ok(key)
+}
+
+fn read_secrets() -> Result<HashMap<String, String>, String> {
+    let path = config_dir()?.join(SECRETS_FILE);
+    let data = match fs::read(&path) {
+        Ok(d) => d,
+        Err(_) => return Ok(HashMap::new()),
+    };
+    let key = load_or_create_key()?;
+    descifrar(&data, &key).map_err(|e| format!("{}: {}", e, path.display()))
+}
+
+fn descifrar(data: &[u8], key: &[u8; 32]) -> Result<HashMap<String, String>, String> {
+    if data.len() <= NONCE_LEN {
+        return Err("Archivo de secretos corrupto".to_string());
+    }
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "No se pudo descifrar el archivo de secretos".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Secretos inválidos ({})", e))
+}
+
+/// Secretos a modificar. Si el archivo no se puede descifrar o leer, se mueve a
+/// `secrets.enc.corrupto` y se empieza de cero, con un aviso para el usuario.
+/// Un llavero no disponible sí es error: el archivo puede estar bien.
+fn secrets_para_escribir() -> Result<(HashMap<String, String>, Option<String>), String> {
+    let path = config_dir()?.join(SECRETS_FILE);
+    let data = match fs::read(&path) {
+        Ok(d) => d,
+        Err(_) => return Ok((HashMap::new(), None)),
+    };
+    let key = load_or_create_key()?;
+    match descifrar(&data, &key) {
+        Ok(secrets) => Ok((secrets, None)),
+        Err(e) => {
+            let apartado = path.with_extension("enc.corrupto");
+            fs::rename(&path, &apartado)
+                .map_err(|er| format!("Error moviendo {}: {}", path.display(), er))?;
+            let aviso = format!(
+                "{}; se movió a {} y los secretos guardados se reiniciaron",
+                e,
+                apartado.display()
+            );
+            eprintln!("[settings] {}", aviso);
+            Ok((HashMap::new(), Some(aviso)))
+        }
+    }
+}
+
+fn write_secrets(secrets: &HashMap<String, String>) -> Result<(), String> {
+    let key = load_or_create_key()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+        .map_err(|_| "No se pudo cifrar el archivo de secretos".to_string())?;
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    write_private(&config_dir()?.join(SECRETS_FILE), &data)
+}
+
+pub fn get_secret(nombre: &str) -> Result<Option<String>, String> {
+    Ok(read_secrets()?.get(nombre).cloned())
+}
+
+/// Regresa un aviso si el archivo de secretos estaba corrupto y se reinició.
+pub fn set_secret(nombre: &str, valor: &str) -> Result<Option<String>, String> {
+    let (mut secrets, aviso) = secrets_para_escribir()?;
+    secrets.insert(nombre.to_string(), valor.to_string());
+    write_secrets(&secrets)?;
+    Ok(aviso)
+}
+
+/// Regresa un aviso si el archivo de secretos estaba corrupto y se reinició.
+pub fn delete_secret(nombre: &str) -> Result<Option<String>, String> {
+    let (mut secrets, aviso) = secrets_para_escribir()?;
+    if secrets.remove(nombre).is_some() {
+        write_secrets(&secrets)?;
+    }
+    Ok(aviso)
+}
+
+/// API key del proveedor: primero el archivo cifrado, después `API_KEY` en `.env`.
+pub fn load_api_key() -> Option<(String, &'static str)> {
+    match get_secret(API_KEY_SECRET) {
+        Ok(Some(key)) if !key.trim().is_empty() => return Some((key, "secrets")),
+        Ok(_) => {}
+        Err(e) => eprintln!("[settings] {}", e),
+    }
+    dotenv::dotenv().ok();
+    match std::env::var("API_KEY") {
+        Ok(key) if !key.trim().is_empty() => Some((key, "env")),
+        _ => None,
+    }
+}
+
+/// Hace una llamada de prueba al proveedor con la key dada.
+async fn validar_api_key(api_key: &str) -> Result<(), String> {
+    let url = format!("https://api.databursatil.com/v2/tasas?token={}", api_key);
+    let response = crate::get_data::provider()
+        .get_text(&url)
+        .await
+        .map_err(|e| format!("El proveedor rechazó la API key: {}", e))?;
+    let body: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|e| format!("Respuesta inválida del proveedor: {}", e))?;
+    match body.as_object() {
+        Some(obj) if !obj.is_empty() && !obj.contains_key("error") => Ok(()),
+        _ => Err(format!("El proveedor rechazó la API key: {}", body)),
+    }
+}
+
+#[command]
+pub fn get_provider_status() -> ProviderStatus {
+    match load_api_key() {
+        Some((_, origen)) => ProviderStatus { configurado: true, origen: Some(origen.to_string()), aviso: None },
+        None => ProviderStatus { configurado: false, origen: None, aviso: None },
+    }
+}
+
+#[command]
+pub async fn set_api_key(api_key: String) -> Result<ProviderStatus, String> {
+    let api_key = api_key.trim().to_string();
+    if api_key.is_empty() {
+        return Err("La API key no puede estar vacía".to_string());
+    }
+    validar_api_key(&api_key).await?;
+    let aviso = set_secret(API_KEY_SECRET, &api_key)?;
+    Ok(ProviderStatus { aviso, ..get_provider_status() })
+}
+
+#[command]
+pub fn delete_api_key() -> Result<ProviderStatus, String> {
+    let aviso = delete_secret(API_KEY_SECRET)?;
+    Ok(ProviderStatus { aviso, ..get_provider_status() })
+}
@@ -1,83 +1,344 @@
-use serde::Serialize;
-use crate::get_data;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::command;
+use tokio::sync::Notify;
+use crate::portfolio_members::{self, Rol};
+use crate::{calendario, db, get_data, portfolio_management, settings, watchlists};
 
-#[derive(Serialize)]
+// --- Cinta de cotizaciones ---
+// Una tarea en segundo plano arma la cinta cada `intervalo_segundos` (diez veces
+// menos seguido con el mercado cerrado) y la empuja al frontend con el evento
+// `ticker-tape-update`. `get_ticker_data` sólo regresa la última foto. Qué se
+// muestra (índices, divisas, tasas, top, una lista de seguimiento, las posiciones
+// de un portafolio) se configura en `ticker_tape.json`. La lista y el portafolio
+// se leen con los permisos del usuario que guardó la configuración, revisados en
+// cada vuelta por si los pierde después.
+
+const CONFIG_FILE: &str = "ticker_tape.json";
+const FACTOR_MERCADO_CERRADO: u64 = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TickerData {
     pub symbol: String,
     pub price: f64,
+    /// Cambio absoluto contra el cierre anterior
     pub change: f64,
     pub change_percent: f64,
+    /// indice, divisa, tasa, top, lista o posicion
+    pub categoria: String,
 }
 
-#[tauri::command]
-pub fn get_ticker_data() -> Result<Vec<TickerData>, String> {
-    // --- Indices ---
-    let indices = match get_data::get_indices() {
-        Ok(i) => i,
-        Err(e) => return Err(format!("Error obteniendo índices: {}", e)),
-    };
-    // --- Forex ---
-    let forex = match get_data::get_forex() {
-        Ok(f) => f,
-        Err(e) => return Err(format!("Error obteniendo forex: {}", e)),
-    };
-    // --- Top movers ---
-    let top = match get_data::get_top() {
-        Ok(t) => t,
-        Err(e) => return Err(format!("Error obteniendo top movers: {}", e)),
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ConfigCinta {
+    pub indices: bool,
+    pub divisas: bool,
+    pub tasas: bool,
+    pub top: bool,
+    pub watchlist_id: Option<i32>,
+    pub portfolio_id: Option<i32>,
+    pub intervalo_segundos: u64,
+    /// Quién guardó la configuración; lo pone `set_ticker_tape_config`
+    pub usuario_id: Option<i32>,
+}
+
+impl Default for ConfigCinta {
+    fn default() -> Self {
+        ConfigCinta {
+            indices: true,
+            divisas: true,
+            tasas: false,
+            top: true,
+            watchlist_id: None,
+            portfolio_id: None,
+            intervalo_segundos: 30,
+            usuario_id: None,
+        }
+    }
+}
+
+pub type Notificador = Arc<dyn Fn(&[TickerData]) + Send + Sync>;
+
+fn ultima_foto() -> &'static Mutex<Vec<TickerData>> {
+    static FOTO: OnceLock<Mutex<Vec<TickerData>>> = OnceLock::new();
+    FOTO.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Despierta a la tarea cuando cambia la configuración.
+fn refrescar() -> &'static Notify {
+    static REFRESCAR: OnceLock<Notify> = OnceLock::new();
+    REFRESCAR.get_or_init(Notify::new)
+}
+
+pub fn cargar_config() -> ConfigCinta {
+    let path = match settings::config_dir() {
+        Ok(dir) => dir.join(CONFIG_FILE),
+        Err(_) => return ConfigCinta::default(),
     };
-    let mut data = Vec::new();
-    // Indices principales
-    if let Some(ipc) = indices.IPC {
-        data.push(TickerData {
-            symbol: "IPC".to_string(),
-            price: ipc.u,
-            change: 0.0,
-            change_percent: ipc.c, // c es el porcentaje ya calculado
-        });
+    match std::fs::read(&path) {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+            eprintln!("[ticker_tape] {} inválido, se usa la configuración por defecto: {}", path.display(), e);
+            ConfigCinta::default()
+        }),
+        Err(_) => ConfigCinta::default(),
     }
-    if let Some(sp500) = indices.SP500 {
-        data.push(TickerData {
-            symbol: "S&P 500".to_string(),
-            price: sp500.u,
-            change: 0.0,
-            change_percent: sp500.c, // c es el porcentaje ya calculado
-        });
+}
+
+fn guardar_config(config: &ConfigCinta) -> Result<(), String> {
+    let dir = settings::config_dir()?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Error creando {}: {}", dir.display(), e))?;
+    let data = serde_json::to_vec_pretty(config).map_err(|e| e.to_string())?;
+    let path = dir.join(CONFIG_FILE);
+    std::fs::write(&path, data).map_err(|e| format!("Error escribiendo {}: {}", path.display(), e))
+}
+
+/// Precio anterior a partir del último y del cambio porcentual que reporta el proveedor.
+fn desde_porcentaje(symbol: &str, precio: f64, cambio_pct: f64, categoria: &str) -> TickerData {
+    let anterior = precio / (1.0 + cambio_pct / 100.0);
+    TickerData {
+        symbol: symbol.to_string(),
+        price: precio,
+        change: if anterior.is_finite() { precio - anterior } else { 0.0 },
+        change_percent: cambio_pct,
+        categoria: categoria.to_string(),
     }
-    // Forex
-    if let Some(usd) = forex.USDMXN {
-        data.push(TickerData {
-            symbol: "USD/MXN".to_string(),
-            price: usd.u,
-            change: 0.0,
-            change_percent: usd.c, // c es el porcentaje ya calculado
-        });
+}
+
+async fn indices() -> Result<Vec<TickerData>, Box<dyn std::error::Error + Send + Sync>> {
+    let indices = get_data::get_indices().await?;
+    let mut data = Vec::new();
+    for (symbol, item) in [("IPC", &indices.IPC), ("FTSE BIVA", &indices.FTSEBIVA), ("S&P 500", &indices.SP500), ("DJIA", &indices.DJIA)] {
+        if let Some(item) = item {
+            data.push(desde_porcentaje(symbol, item.u, item.c, "indice"));
+        }
     }
-    if let Some(eur) = forex.EURMXN {
-        data.push(TickerData {
-            symbol: "EUR/MXN".to_string(),
-            price: eur.u,
-            change: 0.0,
-            change_percent: eur.c, // c es el porcentaje ya calculado
-        });
+    Ok(data)
+}
+
+async fn divisas() -> Result<Vec<TickerData>, Box<dyn std::error::Error + Send + Sync>> {
+    let forex = get_data::get_forex().await?;
+    let mut data = Vec::new();
+    for (symbol, item) in [("USD/MXN", &forex.USDMXN), ("EUR/MXN", &forex.EURMXN)] {
+        if let Some(item) = item {
+            data.push(desde_porcentaje(symbol, item.u, item.c, "divisa"));
+        }
     }
-    // Top suben
-    for sube in top.suben.iter().take(5) {
+    Ok(data)
+}
+
+/// Tasas de referencia; el cambio es contra la foto anterior en `tasas_diarias`.
+async fn tasas(pool: &PgPool) -> Result<Vec<TickerData>, Box<dyn std::error::Error + Send + Sync>> {
+    let tasas = get_data::get_tasas_struct().await?;
+    let mut data = Vec::new();
+    for (nombre, symbol, item) in [
+        ("Tasa_Objetivo", "Objetivo Banxico", &tasas.Tasa_Objetivo),
+        ("TIIEFB", "TIIE de fondeo", &tasas.TIIEFB),
+        ("CETE28", "CETE 28", &tasas.CETE28),
+    ] {
+        let item = match item {
+            Some(item) => item,
+            None => continue,
+        };
+        let fecha = NaiveDate::parse_from_str(item.f.get(0..10).unwrap_or(&item.f), "%Y-%m-%d").unwrap_or_else(|_| calendario::hoy());
+        let anterior: Option<f64> = sqlx::query(
+            "SELECT valor FROM tasas_diarias WHERE tasa = $1 AND fecha < $2 ORDER BY fecha DESC LIMIT 1",
+        )
+        .bind(nombre)
+        .bind(fecha)
+        .fetch_optional(pool)
+        .await?
+        .map(|row| row.try_get(0))
+        .transpose()?;
+        let change = anterior.map(|a| item.t - a).unwrap_or(0.0);
         data.push(TickerData {
-            symbol: sube.e.clone(),
-            price: sube.u,
-            change: 0.0,
-            change_percent: sube.c, // c es el porcentaje ya calculado
+            symbol: symbol.to_string(),
+            price: item.t,
+            change,
+            change_percent: match anterior {
+                Some(a) if a != 0.0 => change / a * 100.0,
+                _ => 0.0,
+            },
+            categoria: "tasa".to_string(),
         });
     }
-    // Top bajan
-    for baja in top.bajan.iter().take(5) {
-        data.push(TickerData {
-            symbol: baja.e.clone(),
-            price: baja.u,
-            change: 0.0,
-            change_percent: baja.c, // c es el porcentaje ya calculado
-        });
+    Ok(data)
+}
+
+async fn top() -> Result<Vec<TickerData>, Box<dyn std::error::Error + Send + Sync>> {
+    let top = get_data::get_top().await?;
+    Ok(top
+        .suben
+        .iter()
+        .take(5)
+        .chain(top.bajan.iter().take(5))
+        .map(|t| desde_porcentaje(&t.e, t.u, t.c, "top"))
+        .collect())
+}
+
+fn desde_cotizaciones(cotizaciones: Vec<watchlists::CotizacionLista>, categoria: &str) -> Vec<TickerData> {
+    cotizaciones
+        .into_iter()
+        .filter_map(|c| {
+            Some(TickerData {
+                symbol: format!("{}{}", c.emisora, c.serie),
+                price: c.ultimo?,
+                change: c.cambio.unwrap_or(0.0),
+                change_percent: c.cambio_pct.unwrap_or(0.0),
+                categoria: categoria.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn usuario(config: &ConfigCinta) -> Result<i32, String> {
+    config
+        .usuario_id
+        .ok_or_else(|| "La configuración no tiene usuario; vuelve a guardarla".to_string())
+}
+
+/// Emisoras con posición abierta en el portafolio, las mismas que muestra
+/// `get_portfolio_slots`. Los tickers se buscan como emisora + serie.
+async fn posiciones(pool: &PgPool, usuario_id: i32, portfolio_id: i32, sesion: NaiveDate) -> Result<Vec<TickerData>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut conn = pool.acquire().await?;
+    portfolio_members::require_role(&mut conn, portfolio_id, usuario_id, Rol::Viewer).await?;
+    let tickers: Vec<String> = portfolio_management::posiciones(&mut conn, portfolio_id)
+        .await?
+        .into_iter()
+        .map(|p| p.ticker.to_uppercase())
+        .collect();
+    let rows = sqlx::query(
+        "SELECT DISTINCT emisoras, serie FROM emisoras
+         WHERE UPPER(emisoras || serie) = ANY($1)
+         ORDER BY emisoras, serie",
+    )
+    .bind(&tickers)
+    .fetch_all(&mut *conn)
+    .await?;
+    let pares = rows
+        .iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect::<Result<Vec<(String, String)>, sqlx::Error>>()?;
+    Ok(desde_cotizaciones(watchlists::cotizar(pool, &pares, sesion).await?, "posicion"))
+}
+
+/// Arma la cinta según la configuración. Una fuente que falla se omite.
+pub async fn armar(config: &ConfigCinta) -> Result<Vec<TickerData>, String> {
+    let pool = db::pool().await?;
+    let sesion = calendario::sesion_vigente(Utc::now());
+    let vacio = || async { Ok(Vec::new()) };
+    let (indices, divisas, tasas, top, lista, posiciones) = tokio::join!(
+        async { if config.indices { indices().await } else { vacio().await } },
+        async { if config.divisas { divisas().await } else { vacio().await } },
+        async { if config.tasas { tasas(pool).await } else { vacio().await } },
+        async { if config.top { top().await } else { vacio().await } },
+        async {
+            match config.watchlist_id {
+                Some(id) => async {
+                    watchlists::lista_del_usuario(pool, id, usuario(config)?).await?;
+                    let cotizaciones = watchlists::cotizaciones_lista(pool, id, sesion).await?;
+                    Ok(desde_cotizaciones(cotizaciones, "lista"))
+                }
+                .await,
+                None => vacio().await,
+            }
+        },
+        async {
+            match config.portfolio_id {
+                Some(id) => match usuario(config) {
+                    Ok(usuario_id) => posiciones(pool, usuario_id, id, sesion).await,
+                    Err(e) => Err(e.into()),
+                },
+                None => vacio().await,
+            }
+        },
+    );
+    let mut data = Vec::new();
+    for (fuente, resultado) in [
+        ("índices", indices),
+        ("divisas", divisas),
+        ("tasas", tasas),
+        ("top", top),
+        ("lista", lista),
+        ("posiciones", posiciones),
+    ] {
+        match resultado {
+            Ok(items) => data.extend(items),
+            Err(e) => eprintln!("[ticker_tape] Error obteniendo {}: {}", fuente, e),
+        }
     }
     Ok(data)
 }
+
+/// Ciclo de la cinta. No regresa; pensado para `tokio::spawn`.
+pub async fn correr(notificar: Notificador) {
+    loop {
+        let config = cargar_config();
+        if get_data::get_api_key().is_ok() {
+            match armar(&config).await {
+                Ok(data) => {
+                    notificar(&data);
+                    *ultima_foto().lock().unwrap() = data;
+                }
+                Err(e) => eprintln!("[ticker_tape] {}", e),
+            }
+        }
+        let mut espera = config.intervalo_segundos.max(5);
+        if !calendario::is_open(Utc::now()) {
+            espera *= FACTOR_MERCADO_CERRADO;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(espera)) => {}
+            _ = refrescar().notified() => {}
+        }
+    }
+}
+
+/// Arranca la cinta en segundo plano.
+pub fn iniciar(notificar: Notificador) {
+    tauri::async_runtime::spawn(correr(notificar));
+}
+
+pub const EVENTO_CINTA: &str = "ticker-tape-update";
+
+/// Notificador que empuja cada foto de la cinta al frontend.
+pub fn notificador_app(app: tauri::AppHandle) -> Notificador {
+    use tauri::Emitter;
+    Arc::new(move |data| {
+        let _ = app.emit(EVENTO_CINTA, data.to_vec());
+    })
+}
+
+/// Última foto de la cinta; si la tarea todavía no produce una, se arma en este momento.
+#[command]
+pub async fn get_ticker_data() -> Result<Vec<TickerData>, String> {
+    let foto = ultima_foto().lock().unwrap().clone();
+    if !foto.is_empty() {
+        return Ok(foto);
+    }
+    armar(&cargar_config()).await
+}
+
+#[command]
+pub fn get_ticker_tape_config() -> ConfigCinta {
+    cargar_config()
+}
+
+/// Guarda la configuración a nombre de `usuario_id`, que debe poder ver el
+/// portafolio y ser dueño de la lista elegidos.
+#[command]
+pub async fn set_ticker_tape_config(usuario_id: i32, mut config: ConfigCinta) -> Result<ConfigCinta, String> {
+    let pool = db::pool().await?;
+    if let Some(id) = config.watchlist_id {
+        watchlists::lista_del_usuario(pool, id, usuario_id).await?;
+    }
+    if let Some(id) = config.portfolio_id {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        portfolio_members::require_role(&mut conn, id, usuario_id, Rol::Viewer).await?;
+    }
+    config.usuario_id = Some(usuario_id);
+    guardar_config(&config)?;
+    refrescar().notify_one();
+    Ok(config)
+}
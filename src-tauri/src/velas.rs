@@ -0,0 +1,272 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tauri::command;
+use crate::calendario;
+use crate::db;
+
+// --- Velas OHLC a partir de precios guardados ---
+// Agrega los puntos de `intradia_data` (y las barras de `precios_diarios` para
+// temporalidades de un día o más) en velas de 1m/5m/15m/1h/1d/1w/1M.
+// - Una vela intradía nunca cruza de un día a otro: la primera vela de cada
+//   sesión abre con el primer precio de esa sesión, no con el cierre anterior.
+// - Dentro de una sesión, los huecos entre ticks se rellenan con velas planas
+//   al último cierre (`relleno = true`); entre sesiones no se rellena nada.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Temporalidad {
+    M1,
+    M5,
+    M15,
+    H1,
+    D1,
+    W1,
+    Mes,
+}
+
+impl Temporalidad {
+    pub fn parse(s: &str) -> Result<Temporalidad, String> {
+        match s.trim() {
+            "1m" => Ok(Temporalidad::M1),
+            "5m" => Ok(Temporalidad::M5),
+            "15m" => Ok(Temporalidad::M15),
+            "1h" | "1H" => Ok(Temporalidad::H1),
+            "1d" | "1D" => Ok(Temporalidad::D1),
+            "1w" | "1W" => Ok(Temporalidad::W1),
+            "1M" => Ok(Temporalidad::Mes),
+            otro => Err(format!("Temporalidad inválida '{}'. Usa 1m, 5m, 15m, 1h, 1d, 1w o 1M", otro)),
+        }
+    }
+
+    /// Minutos por vela en las temporalidades intradía.
+    fn minutos(&self) -> Option<u32> {
+        match self {
+            Temporalidad::M1 => Some(1),
+            Temporalidad::M5 => Some(5),
+            Temporalidad::M15 => Some(15),
+            Temporalidad::H1 => Some(60),
+            _ => None,
+        }
+    }
+
+    pub fn es_intradia(&self) -> bool {
+        self.minutos().is_some()
+    }
+
+    /// Inicio de la vela que contiene `momento`.
+    pub fn inicio_vela(&self, momento: NaiveDateTime) -> NaiveDateTime {
+        let dia = momento.date();
+        match self.minutos() {
+            Some(m) => {
+                let minuto_del_dia = momento.hour() * 60 + momento.minute();
+                let inicio = minuto_del_dia - minuto_del_dia % m;
+                dia.and_hms_opt(inicio / 60, inicio % 60, 0).expect("hora válida")
+            }
+            None => {
+                let fecha = match self {
+                    Temporalidad::W1 => dia - Duration::days(dia.weekday().num_days_from_monday() as i64),
+                    Temporalidad::Mes => NaiveDate::from_ymd_opt(dia.year(), dia.month(), 1).expect("fecha válida"),
+                    _ => dia,
+                };
+                fecha.and_hms_opt(0, 0, 0).expect("medianoche válida")
+            }
+        }
+    }
+}
+
+/// Precio observado: un tick (OHLC iguales) o una barra ya agregada.
+#[derive(Debug, Clone)]
+pub struct Punto {
+    pub momento: NaiveDateTime,
+    pub apertura: f64,
+    pub maximo: f64,
+    pub minimo: f64,
+    pub cierre: f64,
+    pub volumen: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Vela {
+    /// Segundos desde epoch tomando la hora local como UTC (lo que espera lightweight-charts).
+    pub tiempo: i64,
+    pub fecha_hora: NaiveDateTime,
+    pub apertura: f64,
+    pub maximo: f64,
+    pub minimo: f64,
+    pub cierre: f64,
+    pub volumen: Option<f64>,
+    pub puntos: u32,
+    pub relleno: bool,
+}
+
+impl Vela {
+    fn nueva(inicio: NaiveDateTime, p: &Punto) -> Vela {
+        Vela {
+            tiempo: inicio.and_utc().timestamp(),
+            fecha_hora: inicio,
+            apertura: p.apertura,
+            maximo: p.maximo,
+            minimo: p.minimo,
+            cierre: p.cierre,
+            volumen: p.volumen,
+            puntos: 1,
+            relleno: false,
+        }
+    }
+
+    fn plana(inicio: NaiveDateTime, precio: f64) -> Vela {
+        Vela {
+            tiempo: inicio.and_utc().timestamp(),
+            fecha_hora: inicio,
+            apertura: precio,
+            maximo: precio,
+            minimo: precio,
+            cierre: precio,
+            volumen: None,
+            puntos: 0,
+            relleno: true,
+        }
+    }
+
+    fn agregar(&mut self, p: &Punto) {
+        self.maximo = self.maximo.max(p.maximo);
+        self.minimo = self.minimo.min(p.minimo);
+        self.cierre = p.cierre;
+        self.volumen = match (self.volumen, p.volumen) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.puntos += 1;
+    }
+}
+
+/// Agrega `puntos` (ordenados por momento) en velas de la temporalidad dada.
+pub fn agregar_velas(puntos: &[Punto], temporalidad: Temporalidad) -> Vec<Vela> {
+    let mut velas: Vec<Vela> = Vec::new();
+    for p in puntos {
+        let inicio = temporalidad.inicio_vela(p.momento);
+        let anterior = velas.last().map(|v| (v.fecha_hora, v.cierre));
+        match anterior {
+            Some((inicio_anterior, _)) if inicio_anterior == inicio => {
+                if let Some(v) = velas.last_mut() {
+                    v.agregar(p);
+                }
+            }
+            Some((inicio_anterior, cierre)) => {
+                // Rellenar huecos sólo dentro de la misma sesión y en horario de mercado
+                if let Some(m) = temporalidad.minutos() {
+                    let sesion = calendario::sesion(inicio.date());
+                    if let (true, Some((_, cierre_sesion))) = (inicio_anterior.date() == inicio.date(), sesion) {
+                        let paso = Duration::minutes(m as i64);
+                        let mut siguiente = inicio_anterior + paso;
+                        while siguiente < inicio && siguiente.time() < cierre_sesion {
+                            velas.push(Vela::plana(siguiente, cierre));
+                            siguiente += paso;
+                        }
+                    }
+                }
+                velas.push(Vela::nueva(inicio, p));
+            }
+            None => velas.push(Vela::nueva(inicio, p)),
+        }
+    }
+    velas
+}
+
+async fn puntos_intradia(
+    pool: &PgPool,
+    ticker: &str,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+) -> Result<Vec<Punto>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT fecha_hora, precio FROM intradia_data
+         WHERE emisora = $1
+           AND ($2::timestamp IS NULL OR fecha_hora >= $2)
+           AND ($3::timestamp IS NULL OR fecha_hora <= $3)
+         ORDER BY fecha_hora",
+    )
+    .bind(ticker)
+    .bind(inicio)
+    .bind(fin)
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            let precio: f64 = row.try_get("precio")?;
+            Ok(Punto {
+                momento: row.try_get("fecha_hora")?,
+                apertura: precio,
+                maximo: precio,
+                minimo: precio,
+                cierre: precio,
+                volumen: None,
+            })
+        })
+        .collect()
+}
+
+async fn puntos_diarios(
+    pool: &PgPool,
+    ticker: &str,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+) -> Result<Vec<Punto>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT fecha, apertura, maximo, minimo, cierre, volumen FROM precios_diarios
+         WHERE emisoras || serie = $1
+           AND ($2::date IS NULL OR fecha >= $2)
+           AND ($3::date IS NULL OR fecha <= $3)
+         ORDER BY fecha",
+    )
+    .bind(ticker)
+    .bind(inicio.map(|i| i.date()))
+    .bind(fin.map(|f| f.date()))
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            let fecha: NaiveDate = row.try_get("fecha")?;
+            let cierre: f64 = row.try_get("cierre")?;
+            Ok(Punto {
+                momento: fecha.and_hms_opt(0, 0, 0).expect("medianoche válida"),
+                apertura: row.try_get::<Option<f64>, _>("apertura")?.unwrap_or(cierre),
+                maximo: row.try_get::<Option<f64>, _>("maximo")?.unwrap_or(cierre),
+                minimo: row.try_get::<Option<f64>, _>("minimo")?.unwrap_or(cierre),
+                cierre,
+                volumen: row.try_get::<Option<i64>, _>("volumen")?.map(|v| v as f64),
+            })
+        })
+        .collect()
+}
+
+/// Velas de `ticker` (emisora + serie, p. ej. "AMXB") en la temporalidad dada.
+/// Para 1d/1w/1M se usan las barras diarias si existen; si no, los precios intradía.
+pub async fn velas(
+    pool: &PgPool,
+    ticker: &str,
+    temporalidad: Temporalidad,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+) -> Result<Vec<Vela>, sqlx::Error> {
+    let mut puntos = Vec::new();
+    if !temporalidad.es_intradia() {
+        puntos = puntos_diarios(pool, ticker, inicio, fin).await?;
+    }
+    if puntos.is_empty() {
+        puntos = puntos_intradia(pool, ticker, inicio, fin).await?;
+    }
+    Ok(agregar_velas(&puntos, temporalidad))
+}
+
+#[command]
+pub async fn get_velas(
+    ticker: String,
+    temporalidad: String,
+    inicio: Option<NaiveDateTime>,
+    fin: Option<NaiveDateTime>,
+) -> Result<Vec<Vela>, String> {
+    let temporalidad = Temporalidad::parse(&temporalidad)?;
+    let pool = db::pool().await?;
+    velas(pool, &ticker, temporalidad, inicio, fin).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,293 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tauri::command;
+use crate::{calendario, db, get_data};
+
+// --- Listas de seguimiento ---
+// Cada usuario arma sus listas de emisoras sin tener que comprarlas. Las
+// emisoras se validan contra el catálogo (`emisoras`) y también se descargan en
+// la actualización diaria de precios (ver `scheduler::emisoras_seguidas`).
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Watchlist {
+    pub id: i32,
+    pub nombre: String,
+    pub emisoras: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CotizacionLista {
+    pub emisora: String,
+    pub serie: String,
+    pub razon_social: Option<String>,
+    pub ultimo: Option<f64>,
+    pub cambio: Option<f64>,
+    pub cambio_pct: Option<f64>,
+    pub volumen: Option<f64>,
+    pub maximo_52s: Option<f64>,
+    pub minimo_52s: Option<f64>,
+    /// Fecha de la cotización según el proveedor; vacía si se usó el último cierre guardado
+    pub fecha: Option<String>,
+}
+
+fn nombre_valido(nombre: &str) -> Result<String, String> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre de la lista no puede estar vacío".to_string());
+    }
+    Ok(nombre.to_string())
+}
+
+fn error_nombre(e: sqlx::Error) -> String {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            "Ya existe una lista con ese nombre. Por favor, elige otro.".to_string()
+        }
+        _ => e.to_string(),
+    }
+}
+
+/// Verifica que la lista exista y sea del usuario.
+pub(crate) async fn lista_del_usuario(pool: &PgPool, watchlist_id: i32, usuario_id: i32) -> Result<(), String> {
+    let row = sqlx::query("SELECT 1 FROM watchlists WHERE id = $1 AND usuario_id = $2")
+        .bind(watchlist_id)
+        .bind(usuario_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(_) => Ok(()),
+        None => Err(format!("No se encontró la lista de seguimiento {}", watchlist_id)),
+    }
+}
+
+#[command]
+pub async fn get_watchlists(usuario_id: i32) -> Result<Vec<Watchlist>, String> {
+    let pool = db::pool().await?;
+    let rows = sqlx::query(
+        "SELECT w.id, w.nombre, COUNT(i.emisoras) AS emisoras
+         FROM watchlists w
+         LEFT JOIN watchlist_items i ON i.watchlist_id = w.id
+         WHERE w.usuario_id = $1
+         GROUP BY w.id, w.nombre
+         ORDER BY w.nombre",
+    )
+    .bind(usuario_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    rows.iter()
+        .map(|row| {
+            Ok(Watchlist {
+                id: row.try_get("id")?,
+                nombre: row.try_get("nombre")?,
+                emisoras: row.try_get("emisoras")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn create_watchlist(usuario_id: i32, nombre: String) -> Result<Watchlist, String> {
+    let nombre = nombre_valido(&nombre)?;
+    let pool = db::pool().await?;
+    let row = sqlx::query("INSERT INTO watchlists (usuario_id, nombre) VALUES ($1, $2) RETURNING id")
+        .bind(usuario_id)
+        .bind(&nombre)
+        .fetch_one(pool)
+        .await
+        .map_err(error_nombre)?;
+    Ok(Watchlist {
+        id: row.try_get("id").map_err(|e| e.to_string())?,
+        nombre,
+        emisoras: 0,
+    })
+}
+
+#[command]
+pub async fn rename_watchlist(watchlist_id: i32, usuario_id: i32, nombre: String) -> Result<(), String> {
+    let nombre = nombre_valido(&nombre)?;
+    let pool = db::pool().await?;
+    lista_del_usuario(pool, watchlist_id, usuario_id).await?;
+    sqlx::query("UPDATE watchlists SET nombre = $1 WHERE id = $2")
+        .bind(&nombre)
+        .bind(watchlist_id)
+        .execute(pool)
+        .await
+        .map_err(error_nombre)?;
+    Ok(())
+}
+
+#[command]
+pub async fn delete_watchlist(watchlist_id: i32, usuario_id: i32) -> Result<(), String> {
+    let pool = db::pool().await?;
+    lista_del_usuario(pool, watchlist_id, usuario_id).await?;
+    // Las emisoras de la lista se borran en cascada
+    sqlx::query("DELETE FROM watchlists WHERE id = $1")
+        .bind(watchlist_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[command]
+pub async fn add_watchlist_item(watchlist_id: i32, usuario_id: i32, emisora: String, serie: String) -> Result<(), String> {
+    let pool = db::pool().await?;
+    lista_del_usuario(pool, watchlist_id, usuario_id).await?;
+    // Se guarda la emisora/serie tal como está en el catálogo
+    let row = sqlx::query(
+        "SELECT emisoras, serie FROM emisoras
+         WHERE UPPER(emisoras) = UPPER($1) AND UPPER(serie) = UPPER($2)
+         LIMIT 1",
+    )
+    .bind(emisora.trim())
+    .bind(serie.trim())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No existe la emisora {} serie {} en el catálogo", emisora.trim(), serie.trim()))?;
+    let emisora: String = row.try_get("emisoras").map_err(|e| e.to_string())?;
+    let serie: String = row.try_get("serie").map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO watchlist_items (watchlist_id, emisoras, serie) VALUES ($1, $2, $3)
+         ON CONFLICT (watchlist_id, emisoras, serie) DO NOTHING",
+    )
+    .bind(watchlist_id)
+    .bind(&emisora)
+    .bind(&serie)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[command]
+pub async fn remove_watchlist_item(watchlist_id: i32, usuario_id: i32, emisora: String, serie: String) -> Result<(), String> {
+    let pool = db::pool().await?;
+    lista_del_usuario(pool, watchlist_id, usuario_id).await?;
+    sqlx::query("DELETE FROM watchlist_items WHERE watchlist_id = $1 AND emisoras = $2 AND serie = $3")
+        .bind(watchlist_id)
+        .bind(&emisora)
+        .bind(&serie)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Cotizaciones de varias emisoras: último precio y volumen del proveedor (por
+/// la caché de cotizaciones), cambio contra el cierre anterior y rango de 52
+/// semanas de `precios_diarios`. Se regresan en el orden de `pares`.
+pub async fn cotizar(
+    pool: &PgPool,
+    pares: &[(String, String)],
+    sesion: NaiveDate,
+) -> Result<Vec<CotizacionLista>, Box<dyn std::error::Error + Send + Sync>> {
+    let (emisoras, series): (Vec<String>, Vec<String>) = pares.iter().cloned().unzip();
+    let rows = sqlx::query(
+        "SELECT i.emisoras, i.serie, e.razon_social, ant.cierre AS cierre_anterior, r.maximo, r.minimo
+         FROM unnest($1::text[], $2::text[]) WITH ORDINALITY AS i(emisoras, serie, orden)
+         LEFT JOIN emisoras e ON e.emisoras = i.emisoras AND e.serie = i.serie
+         LEFT JOIN LATERAL (
+             SELECT cierre FROM precios_diarios p
+             WHERE p.emisoras = i.emisoras AND p.serie = i.serie AND p.fecha < $3
+             ORDER BY p.fecha DESC
+             LIMIT 1
+         ) ant ON true
+         LEFT JOIN LATERAL (
+             SELECT MAX(maximo) AS maximo, MIN(minimo) AS minimo FROM precios_diarios p
+             WHERE p.emisoras = i.emisoras AND p.serie = i.serie AND p.fecha > $3 - 364
+         ) r ON true
+         ORDER BY i.orden",
+    )
+    .bind(&emisoras)
+    .bind(&series)
+    .bind(sesion)
+    .fetch_all(pool)
+    .await?;
+
+    let mut base = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let emisora: String = row.try_get("emisoras")?;
+        let serie: String = row.try_get("serie")?;
+        let razon_social: Option<String> = row.try_get("razon_social")?;
+        let cierre_anterior: Option<f64> = row.try_get("cierre_anterior")?;
+        let maximo: Option<f64> = row.try_get("maximo")?;
+        let minimo: Option<f64> = row.try_get("minimo")?;
+        base.push((emisora, serie, razon_social, cierre_anterior, maximo, minimo));
+    }
+
+    // Una cotización por emisora, en paralelo; si el proveedor falla queda el último cierre
+    let cotizaciones = futures::future::join_all(
+        base.iter()
+            .map(|(emisora, serie, ..)| async move { get_data::get_cotizaciones(&format!("{}{}", emisora, serie)).await }),
+    )
+    .await;
+
+    let mut tabla = Vec::with_capacity(base.len());
+    for ((emisora, serie, razon_social, cierre_anterior, maximo, minimo), cotizacion) in base.into_iter().zip(cotizaciones) {
+        let cotizacion = cotizacion.ok().flatten();
+        let precio = cotizacion.as_ref().and_then(|c| c.ultimo_precio).filter(|p| *p > 0.0);
+        let ultimo = precio.or(cierre_anterior);
+        let cambio = match (precio, cierre_anterior) {
+            (Some(p), Some(ant)) if ant > 0.0 => Some(p - ant),
+            _ => None,
+        };
+        let cambio_pct = match (cambio, cierre_anterior) {
+            (Some(c), Some(ant)) => Some(c / ant * 100.0),
+            _ => None,
+        };
+        // El precio de hoy puede marcar un nuevo extremo antes de que exista su barra diaria
+        let maximo_52s = match (maximo, precio) {
+            (Some(m), Some(p)) => Some(m.max(p)),
+            (m, p) => m.or(p),
+        };
+        let minimo_52s = match (minimo, precio) {
+            (Some(m), Some(p)) => Some(m.min(p)),
+            (m, p) => m.or(p),
+        };
+        tabla.push(CotizacionLista {
+            emisora,
+            serie,
+            razon_social,
+            ultimo,
+            cambio,
+            cambio_pct,
+            volumen: cotizacion.as_ref().and_then(|c| c.volumen),
+            maximo_52s,
+            minimo_52s,
+            fecha: cotizacion.and_then(|c| c.fecha),
+        });
+    }
+    Ok(tabla)
+}
+
+/// Cotizaciones de las emisoras de una lista, en orden alfabético.
+pub async fn cotizaciones_lista(
+    pool: &PgPool,
+    watchlist_id: i32,
+    sesion: NaiveDate,
+) -> Result<Vec<CotizacionLista>, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = sqlx::query(
+        "SELECT emisoras, serie FROM watchlist_items WHERE watchlist_id = $1 ORDER BY emisoras, serie",
+    )
+    .bind(watchlist_id)
+    .fetch_all(pool)
+    .await?;
+    let pares = rows
+        .iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect::<Result<Vec<(String, String)>, sqlx::Error>>()?;
+    cotizar(pool, &pares, sesion).await
+}
+
+#[command]
+pub async fn get_watchlist_quotes(watchlist_id: i32, usuario_id: i32) -> Result<Vec<CotizacionLista>, String> {
+    let pool = db::pool().await?;
+    lista_del_usuario(pool, watchlist_id, usuario_id).await?;
+    let sesion = calendario::sesion_vigente(Utc::now());
+    cotizaciones_lista(pool, watchlist_id, sesion).await.map_err(|e| e.to_string())
+}
@@ -9,6 +9,7 @@ import AssetViewPage from './components/AssetViewPage';
 import SearchBar from './components/SearchBar';
 import UserSelectorPage from './components/UserSelectorPage';
 import PortfolioSelectorPage from './components/PortfolioSelectorPage';
+import SettingsPage from './components/SettingsPage';
 
 type View = 'search' | 'portfolio' | 'settings';
 
@@ -70,9 +71,9 @@ function App() {
   const renderMainContent = () => {
     switch (currentView) {
       case 'portfolio':
-        return <PortfolioPage portfolio_id={portfolio_id} />;
+        return <PortfolioPage portfolio_id={portfolio_id} usuario_id={selectedUser.id} />;
       case 'settings':
-        return <div style={{padding:'2rem'}}><h1>Configuración</h1><p>Próximamente...</p></div>;
+        return <SettingsPage />;
       case 'search':
       default:
         if (selectedEmisora) {
@@ -1,14 +1,79 @@
-import { useEffect, useRef } from "react";
-import { createChart, IChartApi, LineData } from "lightweight-charts";
+import { useEffect, useRef, useState } from "react";
+import { createChart, IChartApi, LineData, CandlestickData, UTCTimestamp } from "lightweight-charts";
+import { invoke } from "@tauri-apps/api/core";
+
+export type Temporalidad = '1m' | '5m' | '15m' | '1h' | '1d' | '1w' | '1M';
+
+interface Vela {
+  tiempo: number;
+  apertura: number;
+  maximo: number;
+  minimo: number;
+  cierre: number;
+  relleno: boolean;
+}
+
+interface IndicadoresResponse {
+  tiempos: number[];
+  velas: Vela[];
+  series: Record<string, (number | null)[]>;
+}
+
+// Indicadores que comparten escala con el precio y se dibujan sobre las velas
+const OVERLAYS = ['sma_', 'ema_', 'bollinger_', 'vwap', 'max_', 'min_'];
 
 interface Props {
   symbol: string;
-  data: LineData[];
+  data?: LineData[];
+  // Si se indica, las velas (e indicadores) se piden al backend en lugar de usar `data`
+  timeframe?: Temporalidad;
+  from?: string;
+  to?: string;
+  // Specs para get_indicators, p. ej. ['sma:20', 'bollinger:20,2']
+  indicators?: string[];
 }
 
-export default function LightweightChart({ symbol, data }: Props) {
+export default function LightweightChart({ symbol, data, timeframe, from, to, indicators }: Props) {
   const chartRef = useRef<HTMLDivElement>(null);
   const chartInstance = useRef<IChartApi | null>(null);
+  const [velas, setVelas] = useState<CandlestickData[] | null>(null);
+  const [overlays, setOverlays] = useState<Record<string, LineData[]>>({});
+
+  useEffect(() => {
+    if (!timeframe) {
+      setVelas(null);
+      setOverlays({});
+      return;
+    }
+    invoke<IndicadoresResponse>('get_indicators', {
+      ticker: symbol,
+      temporalidad: timeframe,
+      indicadores: indicators ?? [],
+      inicio: from ?? null,
+      fin: to ?? null,
+    })
+      .then(result => {
+        setVelas(result.velas.map(v => ({
+          time: v.tiempo as UTCTimestamp,
+          open: v.apertura,
+          high: v.maximo,
+          low: v.minimo,
+          close: v.cierre,
+        })));
+        const lineas: Record<string, LineData[]> = {};
+        for (const [nombre, valores] of Object.entries(result.series)) {
+          if (!OVERLAYS.some(p => nombre.startsWith(p))) continue;
+          lineas[nombre] = valores.flatMap((valor, i) =>
+            valor == null ? [] : [{ time: result.tiempos[i] as UTCTimestamp, value: valor }]);
+        }
+        setOverlays(lineas);
+      })
+      .catch(e => {
+        console.error('Error obteniendo velas:', e);
+        setVelas([]);
+        setOverlays({});
+      });
+  }, [symbol, timeframe, from, to, indicators?.join('|')]);
 
   useEffect(() => {
     if (!chartRef.current) return;
@@ -23,13 +88,23 @@ export default function LightweightChart({ symbol, data }: Props) {
       timeScale: { timeVisible: true, secondsVisible: false },
     });
     chartInstance.current = chart;
-    // const series = chart.addLineSeries();
     // Para lightweight-charts v4+:
-    // @ts-ignore
-    const series = (chart as any).addLineSeries ? (chart as any).addLineSeries() : chart.addSeries({ isBuiltIn: true, type: 'Line', defaultOptions: {} });
-    series.setData(data);
+    if (timeframe) {
+      // @ts-ignore
+      const series = (chart as any).addCandlestickSeries ? (chart as any).addCandlestickSeries() : chart.addSeries({ isBuiltIn: true, type: 'Candlestick', defaultOptions: {} });
+      series.setData(velas ?? []);
+      for (const lineas of Object.values(overlays)) {
+        // @ts-ignore
+        const linea = (chart as any).addLineSeries ? (chart as any).addLineSeries({ lineWidth: 1 }) : chart.addSeries({ isBuiltIn: true, type: 'Line', defaultOptions: {} });
+        linea.setData(lineas);
+      }
+    } else {
+      // @ts-ignore
+      const series = (chart as any).addLineSeries ? (chart as any).addLineSeries() : chart.addSeries({ isBuiltIn: true, type: 'Line', defaultOptions: {} });
+      series.setData(data ?? []);
+    }
     return () => chart.remove();
-  }, [symbol, data]);
+  }, [symbol, data, timeframe, velas, overlays]);
 
   return <div ref={chartRef} style={{ width: '100%', height: 320 }} />;
 }
@@ -20,11 +20,70 @@ interface FinancialDataByQuarter {
   income: Record<string, number>;
 }
 
+interface Hallazgo {
+  trimestre: string;
+  estado: 'flujos' | 'resultados' | 'posicion';
+  regla: string;
+  partida: string | null;
+  severidad: 'advertencia' | 'error';
+  mensaje: string;
+}
+
+const ESTADO_HALLAZGO: Record<'cashflow' | 'income' | 'position', Hallazgo['estado']> = {
+  cashflow: 'flujos',
+  income: 'resultados',
+  position: 'posicion',
+};
+
+interface DupontTrimestre {
+  trimestre: string;
+  roe: number | null;
+  tres_pasos: { margen_neto: number | null; rotacion_activos: number | null; multiplicador_capital: number | null };
+  cinco_pasos: {
+    carga_fiscal: number | null;
+    carga_intereses: number | null;
+    margen_operativo: number | null;
+    rotacion_activos: number | null;
+    multiplicador_capital: number | null;
+  };
+}
+
+interface Crecimiento {
+  valor: number | null;
+  qoq: number | null;
+  yoy: number | null;
+}
+
+interface AnalisisEmisora {
+  dupont: DupontTrimestre[];
+  crecimiento: { trimestre: string; metricas: Record<string, Crecimiento> }[];
+  cagr: { metrica: string; anios: number; desde: number; hasta: number; valor: number | null }[];
+}
+
+const METRICAS_CRECIMIENTO: Record<string, string> = {
+  ingresos: "Ingresos",
+  utilidad_operativa: "Utilidad Op.",
+  utilidad_neta: "Utilidad Neta",
+  upa: "UPA Básica",
+  flujo_operacion: "Flujo Op.",
+};
+
+const formatoPct = (v: number | null | undefined) =>
+  v === null || v === undefined ? '-' : (v * 100).toLocaleString('es-MX', { maximumFractionDigits: 1 }) + '%';
+
+const formatoVeces = (v: number | null | undefined) =>
+  v === null || v === undefined ? '-' : v.toLocaleString('es-MX', { maximumFractionDigits: 2 }) + 'x';
+
+const colorSigno = (v: number | null | undefined) =>
+  v === null || v === undefined ? 'var(--color-text)' : v > 0 ? 'var(--color-positive)' : v < 0 ? 'var(--color-negative)' : 'var(--color-text)';
+
 const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
   const [financialData, setFinancialData] = useState<FinancialDataByQuarter[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
   const [trimestresDisponibles, setTrimestresDisponibles] = useState<string[]>([]);
+  const [analisis, setAnalisis] = useState<AnalisisEmisora | null>(null);
+  const [hallazgos, setHallazgos] = useState<Record<string, Hallazgo[]>>({});
 
   useEffect(() => {
     // Consulta los trimestres disponibles para la emisora
@@ -50,6 +109,28 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
       .finally(() => setLoading(false));
   }, [emisora]);
 
+  useEffect(() => {
+    // DuPont y crecimientos de los últimos 8 trimestres
+    invoke<AnalisisEmisora>("get_analysis", { emisora: emisora.emisoras, trimestres: 8 })
+      .then(setAnalisis)
+      .catch(() => setAnalisis(null));
+  }, [emisora]);
+
+  useEffect(() => {
+    // Advertencias de calidad de datos, agrupadas por estado, trimestre y partida
+    invoke<Hallazgo[]>("get_data_quality", { emisora: emisora.emisoras, trimestre: null })
+      .then((lista) => {
+        const agrupados: Record<string, Hallazgo[]> = {};
+        for (const h of lista) {
+          if (!h.partida) continue;
+          const clave = `${h.estado}|${h.trimestre}|${h.partida}`;
+          (agrupados[clave] ||= []).push(h);
+        }
+        setHallazgos(agrupados);
+      })
+      .catch(() => setHallazgos({}));
+  }, [emisora]);
+
   // Mapeo de nombres cortos para conceptos clave
   const CONCEPTOS_CLAVE: Record<string, string> = {
     // Cashflow
@@ -58,9 +139,11 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
     depreciacion: "Depreciación",
     flujo_inversion: "Flujo Inv.",
     flujo_financiamiento: "Flujo Fin.",
+    cambio_efectivo: "Cambio Efectivo",
     efectivo_final: "Efectivo Fin.",
     // Income
     revenue: "Ingresos",
+    costofsales: "Costo de Ventas",
     grossprofit: "Utilidad Bruta",
     profitloss: "Utilidad Neta",
     profitlossbeforetax: "Utilidad Antes Imp.",
@@ -69,19 +152,21 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
     currentassets: "Activos Corr.",
     currentliabilities: "Pasivos Corr.",
     cashandcashequivalents: "Efectivo",
+    inventories: "Inventarios",
     equity: "Capital",
     liabilities: "Pasivos",
     propertyplantandequipment: "Prop. Planta Eq.",
+    goodwill: "Crédito Mercantil",
     retainedearnings: "Utilidades Ret.",
   };
 
   const CONCEPTOS_ORDEN = [
     // Cashflow
-    "flujo_operacion", "utilidad_neta", "depreciacion", "flujo_inversion", "flujo_financiamiento", "efectivo_final",
+    "flujo_operacion", "utilidad_neta", "depreciacion", "flujo_inversion", "flujo_financiamiento", "cambio_efectivo", "efectivo_final",
     // Income
-    "revenue", "grossprofit", "profitloss", "profitlossbeforetax", "basicearningslosspershare",
+    "revenue", "costofsales", "grossprofit", "profitloss", "profitlossbeforetax", "basicearningslosspershare",
     // Position
-    "currentassets", "currentliabilities", "cashandcashequivalents", "equity", "liabilities", "propertyplantandequipment", "retainedearnings"
+    "currentassets", "currentliabilities", "cashandcashequivalents", "inventories", "equity", "liabilities", "propertyplantandequipment", "goodwill", "retainedearnings"
   ];
 
   const renderComparativeTable = (title: string, key: 'cashflow' | 'income' | 'position') => {
@@ -117,8 +202,15 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
                         else if (val < prev) color = 'var(--color-negative)';
                       }
                     }
+                    const avisos = hallazgos[`${ESTADO_HALLAZGO[key]}|${q.trimestre}|${concepto}`];
                     return (
                       <td key={q.trimestre + concepto} style={{color, textAlign:'right', fontVariantNumeric:'tabular-nums', fontWeight:600, background: idx%2===0 ? 'rgba(28,33,39,0.95)' : 'rgba(16,20,25,0.95)'}}>
+                        {avisos && (
+                          <span
+                            title={avisos.map(h => h.mensaje).join('\n')}
+                            style={{marginRight: 4, cursor: 'help', color: avisos.some(h => h.severidad === 'error') ? 'var(--color-negative)' : '#e0a800'}}
+                          >⚠</span>
+                        )}
                         {val !== undefined ? val.toLocaleString('es-MX', { style: 'currency', currency: 'MXN', maximumFractionDigits: 0 }) : '-'}
                       </td>
                     );
@@ -132,6 +224,99 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
     );
   };
 
+  const renderDupont = () => {
+    if (!analisis || !analisis.dupont.length) return null;
+    const filas: [string, (d: DupontTrimestre) => string][] = [
+      ["ROE", d => formatoPct(d.roe)],
+      ["Margen Neto", d => formatoPct(d.tres_pasos.margen_neto)],
+      ["Rotación Activos", d => formatoVeces(d.tres_pasos.rotacion_activos)],
+      ["Multiplicador Capital", d => formatoVeces(d.tres_pasos.multiplicador_capital)],
+      ["Carga Fiscal", d => formatoPct(d.cinco_pasos.carga_fiscal)],
+      ["Carga Intereses", d => formatoPct(d.cinco_pasos.carga_intereses)],
+      ["Margen Op.", d => formatoPct(d.cinco_pasos.margen_operativo)],
+    ];
+    return (
+      <div className="financial-section">
+        <h3>DuPont (12 meses)</h3>
+        <div style={{overflowX: 'auto'}}>
+          <table className="estado-table tradingview-style-table">
+            <thead>
+              <tr>
+                <th>Factor</th>
+                {analisis.dupont.map(d => <th key={d.trimestre} style={{minWidth:100, textAlign:'center'}}>{d.trimestre}</th>)}
+              </tr>
+            </thead>
+            <tbody>
+              {filas.map(([nombre, valor]) => (
+                <tr key={nombre}>
+                  <td style={{color:'var(--color-text-secondary)', fontWeight:500}}>{nombre}</td>
+                  {analisis.dupont.map(d => (
+                    <td key={d.trimestre + nombre} style={{textAlign:'right', fontVariantNumeric:'tabular-nums'}}>{valor(d)}</td>
+                  ))}
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        </div>
+      </div>
+    );
+  };
+
+  const renderCrecimiento = () => {
+    if (!analisis || !analisis.crecimiento.length) return null;
+    return (
+      <div className="financial-section">
+        <h3>Crecimiento (YoY / QoQ)</h3>
+        <div style={{overflowX: 'auto'}}>
+          <table className="estado-table tradingview-style-table">
+            <thead>
+              <tr>
+                <th>Concepto</th>
+                {analisis.crecimiento.map(c => <th key={c.trimestre} style={{minWidth:100, textAlign:'center'}}>{c.trimestre}</th>)}
+              </tr>
+            </thead>
+            <tbody>
+              {Object.entries(METRICAS_CRECIMIENTO).map(([clave, nombre]) => (
+                <tr key={clave}>
+                  <td style={{color:'var(--color-text-secondary)', fontWeight:500}}>{nombre}</td>
+                  {analisis.crecimiento.map(c => {
+                    const m = c.metricas[clave];
+                    return (
+                      <td key={c.trimestre + clave} style={{textAlign:'right', fontVariantNumeric:'tabular-nums'}}>
+                        <span style={{color: colorSigno(m?.yoy), fontWeight:600}}>{formatoPct(m?.yoy)}</span>
+                        <span style={{color: 'var(--color-text-secondary)'}}> / {formatoPct(m?.qoq)}</span>
+                      </td>
+                    );
+                  })}
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        </div>
+        {analisis.cagr.length > 0 && (
+          <table className="estado-table tradingview-style-table" style={{marginTop: '1rem'}}>
+            <thead>
+              <tr>
+                <th>CAGR</th>
+                <th>Periodo</th>
+                <th style={{textAlign:'right'}}>Tasa</th>
+              </tr>
+            </thead>
+            <tbody>
+              {analisis.cagr.map(c => (
+                <tr key={c.metrica + c.anios}>
+                  <td>{METRICAS_CRECIMIENTO[c.metrica] || c.metrica} ({c.anios}a)</td>
+                  <td>{c.desde}–{c.hasta}</td>
+                  <td style={{textAlign:'right', color: colorSigno(c.valor), fontWeight:600}}>{formatoPct(c.valor)}</td>
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        )}
+      </div>
+    );
+  };
+
   if (loading) return <div className="emisora-page"> <button onClick={onBack}>← Volver</button> Cargando datos financieros... </div>;
   if (error) return <div className="emisora-page"> <button onClick={onBack}>← Volver</button> <p>{error}</p></div>;
 
@@ -148,6 +333,8 @@ const EmisoraPage: React.FC<EmisoraPageProps> = ({ emisora, onBack }) => {
           {renderComparativeTable("Flujos Financieros", 'cashflow')}
           {renderComparativeTable("Resultados Trimestrales", 'income')}
           {renderComparativeTable("Posición Financiera", 'position')}
+          {renderDupont()}
+          {renderCrecimiento()}
         </div>
       )}
     </div>
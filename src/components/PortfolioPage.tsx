@@ -26,7 +26,7 @@ interface CashFlow {
   description?: string;
 }
 
-const PortfolioPage: React.FC<{ portfolio_id: number }> = ({ portfolio_id }) => {
+const PortfolioPage: React.FC<{ portfolio_id: number; usuario_id: number }> = ({ portfolio_id, usuario_id }) => {
   const [cashBalance, setCashBalance] = useState<number>(0);
   const [cashHistory, setCashHistory] = useState<CashFlow[]>([]);
   const [positions, setPositions] = useState<PositionSlot[]>([]);
@@ -42,10 +42,10 @@ const PortfolioPage: React.FC<{ portfolio_id: number }> = ({ portfolio_id }) =>
     setError(null);
     try {
       const [balance, history, slots, plData] = await Promise.all([
-        invoke<number>('get_cash_balance', { portfolio_id }),
-        invoke<CashFlow[]>('get_cash_flow_history', { portfolio_id }),
-        invoke<PositionSlot[]>('get_portfolio_slots', { portfolio_id }),
-        invoke<ProfitLoss[]>('calculate_portfolio_pl', { portfolio_id })
+        invoke<number>('get_cash_balance', { portfolio_id, usuario_id }),
+        invoke<CashFlow[]>('get_cash_flow_history', { portfolio_id, usuario_id }),
+        invoke<PositionSlot[]>('get_portfolio_slots', { portfolio_id, usuario_id }),
+        invoke<ProfitLoss[]>('calculate_portfolio_pl', { portfolio_id, usuario_id })
       ]);
       setCashBalance(balance);
       setCashHistory(history);
@@ -60,7 +60,7 @@ const PortfolioPage: React.FC<{ portfolio_id: number }> = ({ portfolio_id }) =>
   useEffect(() => {
     fetchAll();
     // eslint-disable-next-line
-  }, [portfolio_id]);
+  }, [portfolio_id, usuario_id]);
 
   // --- UI para agregar movimiento de efectivo ---
   const [cashType, setCashType] = useState<'deposit'|'withdrawal'>('deposit');
@@ -76,6 +76,7 @@ const PortfolioPage: React.FC<{ portfolio_id: number }> = ({ portfolio_id }) =>
       }
       await invoke('add_cash_movement', {
         portfolio_id: Number(portfolio_id),
+        usuario_id,
         flow_type: cashType,
         amount: parseFloat(cashAmount),
         flow_date: cashDate,
@@ -102,12 +103,15 @@ const PortfolioPage: React.FC<{ portfolio_id: number }> = ({ portfolio_id }) =>
     try {
       await invoke('add_asset_transaction', {
         portfolio_id,
-        ticker: assetTicker,
-        transaction_type: assetType,
-        quantity: parseFloat(assetQty),
-        price: parseFloat(assetPrice),
-        transaction_date: assetDate,
-        use_cash_from_portfolio: assetUseCash
+        usuario_id,
+        transaccion: {
+          ticker: assetTicker,
+          transaction_type: assetType,
+          quantity: parseFloat(assetQty),
+          price: parseFloat(assetPrice),
+          transaction_date: assetDate,
+          use_cash_from_portfolio: assetUseCash
+        }
       });
       setShowAssetModal(false);
       setAssetTicker('');
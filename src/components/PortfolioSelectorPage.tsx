@@ -5,6 +5,7 @@ interface Portfolio {
   id: number;
   nombre: string;
   id_hex: string;
+  rol: 'viewer' | 'editor' | 'owner';
 }
 
 interface PortfolioSelectorPageProps {
@@ -22,8 +23,8 @@ const PortfolioSelectorPage: React.FC<PortfolioSelectorPageProps> = ({ userId, o
     setLoading(true);
     setError(null);
     try {
-      // No se requiere userId, el backend asume MAKIMA
-      const result = await invoke<Portfolio[]>('get_portfolios');
+      // Portafolios propios y compartidos con el usuario
+      const result = await invoke<Portfolio[]>('get_portfolios', { usuario_id: userId });
       setPortfolios(result);
     } catch (e: any) {
       setError('Error al cargar portafolios');
@@ -47,6 +48,7 @@ const PortfolioSelectorPage: React.FC<PortfolioSelectorPageProps> = ({ userId, o
       // Log para depuración
       console.log('[PortfolioSelectorPage] Enviando a backend:', { nombre: newPortfolioName });
       const portfolio = await invoke<Portfolio>('create_portfolio', {
+        usuario_id: userId,
         nombre: newPortfolioName,
       });
       setNewPortfolioName('');
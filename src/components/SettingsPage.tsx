@@ -0,0 +1,73 @@
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+interface ProviderStatus {
+  configurado: boolean;
+  origen?: 'secrets' | 'env' | null;
+  aviso?: string | null;
+}
+
+const SettingsPage: React.FC = () => {
+  const [status, setStatus] = useState<ProviderStatus | null>(null);
+  const [apiKey, setApiKey] = useState('');
+  const [saving, setSaving] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    invoke<ProviderStatus>('get_provider_status')
+      .then(setStatus)
+      .catch(e => setError(e?.toString() || 'Error al consultar el proveedor'));
+  }, []);
+
+  const handleSave = async () => {
+    if (!apiKey.trim()) return;
+    setSaving(true);
+    setError(null);
+    try {
+      // El backend valida la key contra el proveedor antes de guardarla
+      const result = await invoke<ProviderStatus>('set_api_key', { api_key: apiKey });
+      setStatus(result);
+      setApiKey('');
+    } catch (e: any) {
+      setError(e?.toString() || 'No se pudo guardar la API key');
+    }
+    setSaving(false);
+  };
+
+  const handleDelete = async () => {
+    setError(null);
+    try {
+      setStatus(await invoke<ProviderStatus>('delete_api_key'));
+    } catch (e: any) {
+      setError(e?.toString() || 'No se pudo borrar la API key');
+    }
+  };
+
+  return (
+    <div style={{ padding: '2rem', maxWidth: 600 }}>
+      <h1>Configuración</h1>
+      <h3>Proveedor de datos (DataBursatil)</h3>
+      <p>
+        Estado:{' '}
+        {status == null ? 'Cargando...' : status.configurado
+          ? <b>configurado {status.origen === 'env' ? '(desde .env)' : '(almacenamiento cifrado)'}</b>
+          : <b>no configurado</b>}
+      </p>
+      <div style={{ display: 'flex', gap: 8 }}>
+        <input
+          type="password"
+          placeholder="API key"
+          value={apiKey}
+          onChange={e => setApiKey(e.target.value)}
+          style={{ flex: 1 }}
+        />
+        <button onClick={handleSave} disabled={saving}>{saving ? 'Validando...' : 'Guardar'}</button>
+        {status?.origen === 'secrets' && <button onClick={handleDelete}>Borrar</button>}
+      </div>
+      {status?.aviso && <p style={{ color: 'orange' }}>{status.aviso}</p>}
+      {error && <p style={{ color: 'red' }}>{error}</p>}
+    </div>
+  );
+};
+
+export default SettingsPage;
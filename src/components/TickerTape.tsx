@@ -1,5 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import './TickerTape.css';
 
 interface TickerItem {
@@ -7,6 +8,7 @@ interface TickerItem {
   price: number;
   change: number;
   change_percent: number;
+  categoria?: string;
 }
 
 interface TickerTapeProps {
@@ -34,6 +36,14 @@ const TickerTape: React.FC<TickerTapeProps> = ({ onTickerClick }) => {
       }
     };
     fetchTickerData();
+    // El backend empuja cada actualización de la cinta
+    const unlisten = listen<TickerItem[]>('ticker-tape-update', event => {
+      setItems(event.payload);
+      setLoading(false);
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
   }, []);
 
   const renderItem = (item: TickerItem, isClone = false) => {
@@ -50,7 +60,9 @@ const TickerTape: React.FC<TickerTapeProps> = ({ onTickerClick }) => {
       >
         <span className="ticker__symbol">{item.symbol}</span>
         <span className="ticker__price">{item.price.toFixed(2)}</span>
-        <span className={`ticker__change ${changeClass}`}>{item.change_percent.toFixed(2)}% {arrow}</span>
+        <span className={`ticker__change ${changeClass}`}>
+          {item.change >= 0 ? '+' : ''}{item.change.toFixed(2)} ({item.change_percent.toFixed(2)}%) {arrow}
+        </span>
       </div>
     );
   };